@@ -354,6 +354,24 @@ async fn main() -> anyhow::Result<()> {
                                 .unwrap_or_else(|| did.to_string());
                             writeln!(stdout, "> {username} has been updated ")?;
                         }
+                        warp::multipass::MultiPassEventKind::DeviceLinked { did } => {
+                            writeln!(stdout, "> Device {did} has been linked")?;
+                        }
+                        warp::multipass::MultiPassEventKind::DeviceRevoked { did } => {
+                            writeln!(stdout, "> Device {did} has been revoked")?;
+                        }
+                        warp::multipass::MultiPassEventKind::IdentitySynced { .. } => {
+                            writeln!(stdout, "> Identity has been synced from another device")?;
+                        }
+                        warp::multipass::MultiPassEventKind::IdentityRotated { old, new } => {
+                            let username = account
+                                .get_identity(Identifier::did_key(new.clone())).await
+                                .ok()
+                                .and_then(|list| list.first().cloned())
+                                .map(|ident| ident.username())
+                                .unwrap_or_else(|| new.to_string());
+                            writeln!(stdout, "> {old} has rotated their identity to {username}")?;
+                        }
                     }
                 }
             }
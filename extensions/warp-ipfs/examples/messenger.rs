@@ -851,6 +851,17 @@ async fn message_event_handle(
                             }
                         }
                         MessageType::Event => {}
+                        MessageType::Poll => {
+                            let Some(poll) = message.poll() else {
+                                return Ok(());
+                            };
+
+                            writeln!(stdout, "[{}] @> Poll: {}", username, poll.question())?;
+
+                            for (index, option) in poll.options().iter().enumerate() {
+                                writeln!(stdout, ">> {index}: {option}")?;
+                            }
+                        }
                     }
                 }
             }
@@ -926,6 +937,18 @@ async fn message_event_handle(
                     MessageEvent::Typing => {
                         writeln!(stdout, ">>> {username} is typing",)?;
                     }
+                    MessageEvent::RecordingVoiceNote => {
+                        writeln!(stdout, ">>> {username} is recording a voice note",)?;
+                    }
+                    MessageEvent::Viewing => {
+                        writeln!(stdout, ">>> {username} is viewing",)?;
+                    }
+                    MessageEvent::ScreenSharing => {
+                        writeln!(stdout, ">>> {username} started screen sharing",)?;
+                    }
+                    MessageEvent::Custom { namespace, payload } => {
+                        writeln!(stdout, ">>> {username} sent event {namespace}: {payload}",)?;
+                    }
                 }
             }
         }
@@ -941,6 +964,18 @@ async fn message_event_handle(
                     MessageEvent::Typing => {
                         writeln!(stdout, ">>> {username} is no longer typing",)?;
                     }
+                    MessageEvent::RecordingVoiceNote => {
+                        writeln!(stdout, ">>> {username} is no longer recording a voice note",)?;
+                    }
+                    MessageEvent::Viewing => {
+                        writeln!(stdout, ">>> {username} is no longer viewing",)?;
+                    }
+                    MessageEvent::ScreenSharing => {
+                        writeln!(stdout, ">>> {username} stopped screen sharing",)?;
+                    }
+                    MessageEvent::Custom { namespace, .. } => {
+                        writeln!(stdout, ">>> {username} cancelled event {namespace}",)?;
+                    }
                 }
             }
         }
@@ -972,6 +1007,37 @@ async fn message_event_handle(
                 writeln!(stdout, ">>> {username} was removed from {conversation_id}")?;
             }
         }
+        MessageEventKind::RecipientRoleUpdated {
+            conversation_id,
+            recipient,
+            role,
+        } => {
+            if main_conversation_id == conversation_id {
+                let username = get_username(multipass, recipient.clone()).await;
+
+                writeln!(stdout, ">>> {username} is now {role} of {conversation_id}")?;
+            }
+        }
+        MessageEventKind::RecipientBanned {
+            conversation_id,
+            recipient,
+        } => {
+            if main_conversation_id == conversation_id {
+                let username = get_username(multipass, recipient.clone()).await;
+
+                writeln!(stdout, ">>> {username} was banned from {conversation_id}")?;
+            }
+        }
+        MessageEventKind::RecipientUnbanned {
+            conversation_id,
+            recipient,
+        } => {
+            if main_conversation_id == conversation_id {
+                let username = get_username(multipass, recipient.clone()).await;
+
+                writeln!(stdout, ">>> {username} was unbanned from {conversation_id}")?;
+            }
+        }
         MessageEventKind::ConversationSettingsUpdated {
             conversation_id,
             settings,
@@ -980,6 +1046,103 @@ async fn message_event_handle(
                 writeln!(stdout, ">>> Conversation settings updated: {settings}")?;
             }
         }
+        MessageEventKind::MessageRead {
+            conversation_id,
+            message_id,
+            did_key,
+        } => {
+            if main_conversation_id == conversation_id {
+                let username = get_username(multipass, did_key.clone()).await;
+                writeln!(stdout, "> {username} has read {message_id}")?;
+            }
+        }
+        MessageEventKind::ThreadReplyAdded {
+            conversation_id,
+            thread_id,
+            message_id,
+        } => {
+            if main_conversation_id == conversation_id {
+                writeln!(
+                    stdout,
+                    "> Message {message_id} was added to thread {thread_id}"
+                )?;
+            }
+        }
+        MessageEventKind::ThreadReplyRemoved {
+            conversation_id,
+            thread_id,
+            message_id,
+        } => {
+            if main_conversation_id == conversation_id {
+                writeln!(
+                    stdout,
+                    "> Message {message_id} was removed from thread {thread_id}"
+                )?;
+            }
+        }
+        MessageEventKind::PollVoted {
+            conversation_id,
+            message_id,
+            did_key,
+        } => {
+            if main_conversation_id == conversation_id {
+                let username = get_username(multipass, did_key.clone()).await;
+                if let Some(poll) = raygun
+                    .get_message(conversation_id, message_id)
+                    .await
+                    .ok()
+                    .and_then(|message| message.poll())
+                {
+                    writeln!(
+                        stdout,
+                        "> {username} voted on {message_id}. Tally: {:?}",
+                        poll.tally()
+                    )?;
+                }
+            }
+        }
+        MessageEventKind::PollClosed {
+            conversation_id,
+            message_id,
+        } => {
+            if main_conversation_id == conversation_id {
+                writeln!(stdout, "> Poll {message_id} has been closed")?;
+            }
+        }
+        MessageEventKind::MessageEmbedsUpdated {
+            conversation_id,
+            message_id,
+        } => {
+            if main_conversation_id == conversation_id {
+                writeln!(
+                    stdout,
+                    "> Previews of message {message_id} have been updated"
+                )?;
+            }
+        }
+        MessageEventKind::ScheduledMessageSent {
+            conversation_id,
+            scheduled_id,
+            message_id,
+        } => {
+            if main_conversation_id == conversation_id {
+                writeln!(
+                    stdout,
+                    "> Scheduled message {scheduled_id} was sent as {message_id}"
+                )?;
+            }
+        }
+        MessageEventKind::ScheduledMessageFailed {
+            conversation_id,
+            scheduled_id,
+        } => {
+            if main_conversation_id == conversation_id {
+                writeln!(
+                    stdout,
+                    "> Scheduled message {scheduled_id} could not be sent and will be retried"
+                )?;
+            }
+        }
     }
 
     Ok(())
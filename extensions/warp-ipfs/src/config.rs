@@ -1,10 +1,12 @@
-use std::{path::PathBuf, str::FromStr, time::Duration};
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 
 use ipfs::{Multiaddr, Protocol};
 use rust_ipfs as ipfs;
 
 use warp::{constellation::file::FileType, multipass::identity::Identity};
 
+use crate::embed::EmbedFetcher;
+
 #[derive(Default, Debug, Clone)]
 pub enum Bootstrap {
     Ipfs,
@@ -142,10 +144,20 @@ pub struct StoreSetting {
     pub friend_request_response_duration: Option<Duration>,
     /// Disable providing images for identities
     pub disable_images: bool,
+    /// Disables sending delivery and read receipts to other members of a conversation
+    pub disable_read_receipts: bool,
     /// Announce to mesh network
     pub announce_to_mesh: bool,
     /// Function to call to provide data for a default profile picture if one is not apart of the identity
     pub default_profile_picture: Option<DefaultPfpFn>,
+    /// Used to fetch the pages linked within sent messages to build their previews.
+    /// Note: Previews are disabled if `None`
+    pub embed_fetcher: Option<Arc<dyn EmbedFetcher>>,
+    /// Interval for syncing the root document with the other devices linked to the identity through shuttle
+    /// Note:
+    ///     - If `None`, this will be disabled
+    ///     - Only used with shuttle discovery
+    pub device_sync: Option<Duration>,
 }
 
 impl std::fmt::Debug for StoreSetting {
@@ -166,8 +178,11 @@ impl Default for StoreSetting {
             share_platform: false,
             friend_request_response_duration: None,
             disable_images: false,
+            disable_read_receipts: false,
             with_friends: false,
             default_profile_picture: None,
+            embed_fetcher: None,
+            device_sync: Some(Duration::from_secs(30)),
             announce_to_mesh: false,
         }
     }
@@ -187,6 +202,8 @@ pub struct Config {
     max_file_size: Option<usize>,
     thumbnail_size: (u32, u32),
     thumbnail_exact_format: bool,
+    /// Duration items are held within the trash before being purged. Items are held until the trash is emptied if `None`
+    trash_retention: Option<Duration>,
 }
 
 impl Config {
@@ -233,6 +250,10 @@ impl Config {
     pub fn thumbnail_exact_format(&self) -> bool {
         self.thumbnail_exact_format
     }
+
+    pub fn trash_retention(&self) -> Option<Duration> {
+        self.trash_retention
+    }
 }
 
 impl Config {
@@ -279,6 +300,10 @@ impl Config {
     pub fn thumbnail_exact_format_mut(&mut self) -> &mut bool {
         &mut self.thumbnail_exact_format
     }
+
+    pub fn trash_retention_mut(&mut self) -> &mut Option<Duration> {
+        &mut self.trash_retention
+    }
 }
 
 impl Default for Config {
@@ -307,6 +332,7 @@ impl Default for Config {
             max_file_size: Some(50 * 1024 * 1024),
             thumbnail_size: (128, 128),
             thumbnail_exact_format: true,
+            trash_retention: Some(Duration::from_secs(30 * 24 * 60 * 60)),
         }
     }
 }
@@ -0,0 +1,709 @@
+//! Previews of the links within messages, built from the OpenGraph or oEmbed metadata of the linked page.
+//!
+//! Requests are made through an [`EmbedFetcher`] supplied by the application, so the transport, proxies
+//! and any restriction on the hosts being contacted are left to it.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use futures_timeout::TimeoutExt;
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use warp::{error::Error, raygun::Embed};
+
+use crate::{store::document::image_dag::ImageDag, thumbnail::ThumbnailGenerator};
+
+/// Maximum number of links previewed within a message
+pub const MAX_EMBEDS: usize = 3;
+
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_DESCRIPTION_LENGTH: usize = 1024;
+const MAX_SITE_LENGTH: usize = 128;
+
+/// Largest page or image that is read to build a preview
+const MAX_BODY_SIZE: usize = 5 * 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const THUMBNAIL_SIZE: (u32, u32) = (256, 256);
+
+/// Response to a request made by an [`EmbedFetcher`]
+#[derive(Debug, Clone, Default)]
+pub struct EmbedResponse {
+    /// Value of the `Content-Type` header, if any
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[async_trait::async_trait]
+pub trait EmbedFetcher: Send + Sync {
+    /// Fetches the contents of the url with a GET request, following any redirect
+    async fn fetch(&self, url: &str) -> Result<EmbedResponse, Error>;
+}
+
+/// Preview of a link that is encrypted alongside the message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EmbedDocument {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    /// Cid of the [`ImageDag`] of the thumbnail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Cid>,
+}
+
+impl EmbedDocument {
+    pub async fn resolve(&self, ipfs: &Ipfs, local: bool) -> Embed {
+        let mut embed = Embed::new(&self.url);
+        embed.set_title(self.title.clone());
+        embed.set_description(self.description.clone());
+        embed.set_site(self.site.clone());
+
+        if let Some(cid) = self.thumbnail {
+            match ipfs
+                .get_dag(cid)
+                .timeout(Duration::from_secs(10))
+                .set_local(local)
+                .deserialized::<ImageDag>()
+                .await
+            {
+                Ok(image) => {
+                    let data = ipfs
+                        .unixfs()
+                        .cat(image.link)
+                        .set_local(local)
+                        .timeout(Duration::from_secs(10))
+                        .await
+                        .unwrap_or_default();
+
+                    if !data.is_empty() {
+                        embed.set_thumbnail(data.to_vec(), Some(image.mime));
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(url = %self.url, %cid, error = %e, "unable to obtain thumbnail of preview");
+                }
+            }
+        }
+
+        embed
+    }
+}
+
+/// Unpins the thumbnails of the previews, leaving those still in use by `keep`
+pub async fn remove_thumbnails(ipfs: &Ipfs, embeds: &[EmbedDocument], keep: &[EmbedDocument]) {
+    let thumbnails = embeds
+        .iter()
+        .filter_map(|embed| embed.thumbnail)
+        .filter(|cid| !keep.iter().any(|embed| embed.thumbnail == Some(*cid)));
+
+    for cid in thumbnails {
+        if !ipfs.is_pinned(&cid).await.unwrap_or_default() {
+            continue;
+        }
+
+        if let Err(e) = ipfs.remove_pin(&cid).recursive().await {
+            tracing::warn!(%cid, error = %e, "unable to unpin thumbnail of preview");
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EmbedBuilder {
+    ipfs: Ipfs,
+    fetcher: Arc<dyn EmbedFetcher>,
+    thumbnail: ThumbnailGenerator,
+}
+
+impl EmbedBuilder {
+    pub fn new(ipfs: &Ipfs, fetcher: Arc<dyn EmbedFetcher>) -> Self {
+        Self {
+            ipfs: ipfs.clone(),
+            fetcher,
+            thumbnail: ThumbnailGenerator::new(ipfs.clone()),
+        }
+    }
+
+    /// Builds the previews of the links within the lines. Links that cannot be previewed are skipped
+    pub async fn build(&self, lines: &[String]) -> Vec<EmbedDocument> {
+        let mut embeds = Vec::new();
+
+        for url in find_urls(lines).into_iter().take(MAX_EMBEDS) {
+            match self.preview(&url).await {
+                Ok(Some(embed)) => embeds.push(embed),
+                Ok(None) => tracing::debug!(%url, "link has nothing to preview"),
+                Err(e) => tracing::warn!(%url, error = %e, "unable to build preview of link"),
+            }
+        }
+
+        embeds
+    }
+
+    async fn fetch(&self, url: &str) -> Result<EmbedResponse, Error> {
+        let response = self
+            .fetcher
+            .fetch(url)
+            .timeout(FETCH_TIMEOUT)
+            .await
+            .map_err(|_| Error::OtherWithContext(format!("request to {url} timed out")))??;
+
+        if response.body.len() > MAX_BODY_SIZE {
+            return Err(Error::InvalidLength {
+                context: "body".into(),
+                current: response.body.len(),
+                minimum: None,
+                maximum: Some(MAX_BODY_SIZE),
+            });
+        }
+
+        Ok(response)
+    }
+
+    async fn preview(&self, url: &str) -> Result<Option<EmbedDocument>, Error> {
+        let response = self.fetch(url).await?;
+
+        let (mut metadata, image) = match ContentKind::from(&response) {
+            ContentKind::Html => (parse_html(url, &response.body), None),
+            ContentKind::Json => (parse_oembed(url, &response.body)?, None),
+            ContentKind::Image => (Metadata::default(), Some(response.body)),
+            ContentKind::Other => return Ok(None),
+        };
+
+        // oEmbed is only requested when the page itself does not describe its contents
+        if metadata.title.is_none() {
+            if let Some(oembed) = metadata.oembed.take() {
+                match self
+                    .fetch(&oembed)
+                    .await
+                    .and_then(|response| Ok(parse_oembed(&oembed, &response.body)?))
+                {
+                    Ok(data) => metadata.merge(data),
+                    Err(e) => tracing::debug!(%url, %oembed, error = %e, "unable to obtain oembed"),
+                }
+            }
+        }
+
+        let thumbnail = match (image, metadata.image.as_deref()) {
+            (Some(data), _) => self.thumbnail(url, data).await,
+            (None, Some(image)) => match self.fetch(image).await {
+                Ok(response) => self.thumbnail(image, response.body).await,
+                Err(e) => Err(e),
+            },
+            (None, None) => Ok(None),
+        }
+        .unwrap_or_else(|e| {
+            tracing::debug!(%url, error = %e, "unable to create thumbnail of preview");
+            None
+        });
+
+        let embed = EmbedDocument {
+            url: url.to_string(),
+            title: clean(metadata.title.as_deref(), MAX_TITLE_LENGTH),
+            description: clean(metadata.description.as_deref(), MAX_DESCRIPTION_LENGTH),
+            site: clean(metadata.site.as_deref(), MAX_SITE_LENGTH),
+            thumbnail,
+        };
+
+        if embed.title.is_none() && embed.description.is_none() && embed.thumbnail.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(embed))
+    }
+
+    async fn thumbnail(&self, url: &str, data: Vec<u8>) -> Result<Option<Cid>, Error> {
+        let name = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .unwrap_or_default();
+
+        let id = self
+            .thumbnail
+            .insert_buffer(name, &data, THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1, false)
+            .await;
+
+        let (_, path, _) = self.thumbnail.get(id).await?;
+
+        let cid = path.root().cid().copied().ok_or(Error::Other)?;
+
+        self.ipfs.insert_pin(&cid).recursive().await?;
+
+        Ok(Some(cid))
+    }
+}
+
+enum ContentKind {
+    Html,
+    Json,
+    Image,
+    Other,
+}
+
+impl From<&EmbedResponse> for ContentKind {
+    fn from(response: &EmbedResponse) -> Self {
+        if let Some(content_type) = response.content_type.as_deref() {
+            let content_type = content_type.to_ascii_lowercase();
+            let essence = content_type.split(';').next().unwrap_or_default().trim();
+            return match essence {
+                "text/html" | "application/xhtml+xml" => ContentKind::Html,
+                "application/json" | "application/json+oembed" | "text/json" => ContentKind::Json,
+                essence if essence.starts_with("image/") => ContentKind::Image,
+                _ => ContentKind::Other,
+            };
+        }
+
+        match String::from_utf8_lossy(&response.body[..response.body.len().min(512)])
+            .trim_start()
+            .chars()
+            .next()
+        {
+            Some('<') => ContentKind::Html,
+            Some('{') => ContentKind::Json,
+            _ => ContentKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Metadata {
+    title: Option<String>,
+    description: Option<String>,
+    site: Option<String>,
+    image: Option<String>,
+    /// Link to the oEmbed representation of the page
+    oembed: Option<String>,
+}
+
+impl Metadata {
+    fn merge(&mut self, other: Metadata) {
+        self.title = self.title.take().or(other.title);
+        self.description = self.description.take().or(other.description);
+        self.site = self.site.take().or(other.site);
+        self.image = self.image.take().or(other.image);
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OEmbed {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    author_name: Option<String>,
+    provider_name: Option<String>,
+    thumbnail_url: Option<String>,
+    url: Option<String>,
+}
+
+impl OEmbed {
+    fn resolve(self, base: &str) -> Metadata {
+        let image = match self.kind.as_deref() {
+            Some("photo") => self.url.or(self.thumbnail_url),
+            _ => self.thumbnail_url,
+        };
+
+        Metadata {
+            title: self.title,
+            description: self.description.or(self.author_name),
+            site: self.provider_name,
+            image: image.and_then(|image| resolve_url(base, &image)),
+            oembed: None,
+        }
+    }
+}
+
+fn parse_oembed(base: &str, body: &[u8]) -> Result<Metadata, serde_json::Error> {
+    serde_json::from_slice::<OEmbed>(body).map(|oembed| oembed.resolve(base))
+}
+
+/// Extracts the OpenGraph metadata of a page, falling back to the twitter card or the standard tags
+fn parse_html(base: &str, body: &[u8]) -> Metadata {
+    let html = String::from_utf8_lossy(body);
+
+    let mut properties: HashMap<String, String> = HashMap::new();
+    let mut document_title = None;
+    let mut oembed = None;
+
+    let mut rest = &html[..];
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        let name_length = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let name = rest[..name_length].to_ascii_lowercase();
+
+        if name == "body" {
+            break;
+        }
+
+        let Some(end) = tag_end(rest) else {
+            break;
+        };
+
+        let attributes = parse_attributes(&rest[name_length..end]);
+        rest = &rest[end + 1..];
+
+        match name.as_str() {
+            "meta" => {
+                let key = attributes
+                    .get("property")
+                    .or_else(|| attributes.get("name"))
+                    .map(|key| key.to_ascii_lowercase());
+
+                if let (Some(key), Some(content)) = (key, attributes.get("content")) {
+                    properties.entry(key).or_insert_with(|| content.clone());
+                }
+            }
+            "link" => {
+                let alternate = attributes
+                    .get("rel")
+                    .map(|rel| {
+                        rel.split_whitespace()
+                            .any(|rel| rel.eq_ignore_ascii_case("alternate"))
+                    })
+                    .unwrap_or_default();
+
+                let is_oembed = attributes
+                    .get("type")
+                    .map(|kind| kind.eq_ignore_ascii_case("application/json+oembed"))
+                    .unwrap_or_default();
+
+                if alternate && is_oembed && oembed.is_none() {
+                    oembed = attributes
+                        .get("href")
+                        .and_then(|href| resolve_url(base, &decode_entities(href)));
+                }
+            }
+            "title" if document_title.is_none() => {
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find("</title")
+                    .unwrap_or(rest.len());
+                document_title = Some(rest[..end].to_string());
+                rest = &rest[end..];
+            }
+            _ => {}
+        }
+    }
+
+    let mut property = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| properties.remove(*key))
+            .map(|value| decode_entities(&value))
+    };
+
+    let title = property(&["og:title", "twitter:title"])
+        .or(document_title.map(|title| decode_entities(&title)));
+    let description = property(&["og:description", "twitter:description", "description"]);
+    let site = property(&["og:site_name", "application-name"]);
+    let image = property(&[
+        "og:image:secure_url",
+        "og:image",
+        "og:image:url",
+        "twitter:image",
+        "twitter:image:src",
+    ])
+    .and_then(|image| resolve_url(base, &image));
+
+    Metadata {
+        title,
+        description,
+        site,
+        image,
+        oembed,
+    }
+}
+
+/// Position of the `>` closing the tag, skipping those within quoted values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attributes(mut input: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+
+    loop {
+        input = input.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if input.is_empty() {
+            break;
+        }
+
+        let name_length = input
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(input.len());
+        let name = input[..name_length].to_ascii_lowercase();
+        input = input[name_length..].trim_start();
+
+        let value = match input.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let value = &value[1..];
+                        let end = value.find(quote).unwrap_or(value.len());
+                        input = value.get(end + 1..).unwrap_or_default();
+                        value[..end].to_string()
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        input = &value[end..];
+                        value[..end].to_string()
+                    }
+                }
+            }
+            None => String::new(),
+        };
+
+        if !name.is_empty() {
+            attributes.entry(name).or_insert(value);
+        }
+    }
+
+    attributes
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| {
+                let name = &rest[1..end + 1];
+                let c = match name {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some(' '),
+                    _ => match name.strip_prefix('#') {
+                        Some(code) => match code.strip_prefix(['x', 'X']) {
+                            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                            None => code.parse().ok(),
+                        }
+                        .and_then(char::from_u32),
+                        None => None,
+                    },
+                };
+                c.map(|c| (c, end + 2))
+            });
+
+        match entity {
+            Some((c, length)) => {
+                decoded.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Collapses the whitespace of the value, limiting it to `max` characters
+fn clean(value: Option<&str>, max: usize) -> Option<String> {
+    let value = value?.split_whitespace().collect::<Vec<_>>().join(" ");
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.chars().take(max).collect())
+}
+
+/// Resolves a link found within a page into an absolute http(s) url
+fn resolve_url(base: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+
+    if is_http(href) {
+        return Some(href.to_string());
+    }
+
+    let (scheme, remaining) = base.split_once("://")?;
+
+    if let Some(href) = href.strip_prefix("//") {
+        return Some(format!("{scheme}://{href}"));
+    }
+
+    // other schemes, such as `data:` or `javascript:`, are never followed
+    if href.is_empty() || href.split(['/', '?', '#']).next()?.contains(':') {
+        return None;
+    }
+
+    let host_length = remaining.find(['/', '?', '#']).unwrap_or(remaining.len());
+    let origin = &base[..scheme.len() + 3 + host_length];
+
+    if href.starts_with('/') {
+        return Some(format!("{origin}{href}"));
+    }
+
+    let path = remaining[host_length..]
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    let directory = &path[..path.rfind('/').map(|index| index + 1).unwrap_or(0)];
+    let directory = if directory.is_empty() { "/" } else { directory };
+
+    Some(format!("{origin}{directory}{href}"))
+}
+
+fn is_http(url: &str) -> bool {
+    let lowercase = url.to_ascii_lowercase();
+    ["http://", "https://"].iter().any(|scheme| {
+        lowercase
+            .strip_prefix(scheme)
+            .map(|host| !host.is_empty())
+            .unwrap_or_default()
+    })
+}
+
+/// Finds the http(s) links within the lines of a message, in the order they appear
+pub fn find_urls(lines: &[String]) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+
+    for word in lines.iter().flat_map(|line| line.split_whitespace()) {
+        let word = word.trim_start_matches(['(', '<', '[', '"', '\'']);
+        let word = word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '>', ']', '"', '\'']);
+
+        if !is_http(word) {
+            continue;
+        }
+
+        if !urls.iter().any(|url| url == word) {
+            urls.push(word.to_string());
+        }
+    }
+
+    urls
+}
+
+#[cfg(test)]
+mod test {
+    use super::{clean, decode_entities, find_urls, parse_html, parse_oembed, resolve_url};
+
+    #[test]
+    fn urls_within_lines() {
+        let lines = vec![
+            "have a look at https://example.com/page?id=1.".to_string(),
+            "(http://example.org) and https://example.com/page?id=1".to_string(),
+            "not a link: ftp://example.com https:// example.com".to_string(),
+        ];
+
+        assert_eq!(
+            find_urls(&lines),
+            vec!["https://example.com/page?id=1", "http://example.org"]
+        );
+    }
+
+    #[test]
+    fn opengraph_metadata() {
+        let html = r#"<!doctype html><html><head>
+            <title>Fallback title</title>
+            <meta property="og:title" content="Tom &amp; Jerry">
+            <meta name="description" content='A "classic" cartoon'>
+            <meta property="og:site_name" content=Cartoons />
+            <meta property="og:image" content="/images/cover.png">
+            <link rel="alternate" type="application/json+oembed" href="https://example.com/oembed?url=a&amp;format=json">
+            </head><body><meta property="og:title" content="Ignored"></body></html>"#;
+
+        let metadata = parse_html("https://example.com/shows/tom", html.as_bytes());
+
+        assert_eq!(metadata.title.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("A \"classic\" cartoon")
+        );
+        assert_eq!(metadata.site.as_deref(), Some("Cartoons"));
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://example.com/images/cover.png")
+        );
+        assert_eq!(
+            metadata.oembed.as_deref(),
+            Some("https://example.com/oembed?url=a&format=json")
+        );
+    }
+
+    #[test]
+    fn document_title_fallback() {
+        let metadata = parse_html(
+            "http://example.com",
+            b"<html><head><title>My  page</title></head></html>",
+        );
+        assert_eq!(
+            clean(metadata.title.as_deref(), 256).as_deref(),
+            Some("My page")
+        );
+        assert!(metadata.image.is_none());
+    }
+
+    #[test]
+    fn oembed_metadata() -> anyhow::Result<()> {
+        let metadata = parse_oembed(
+            "https://example.com/oembed",
+            br#"{"type":"video","title":"A video","author_name":"Someone","provider_name":"Videos","thumbnail_url":"https://example.com/thumb.jpg"}"#,
+        )?;
+        assert_eq!(metadata.title.as_deref(), Some("A video"));
+        assert_eq!(metadata.description.as_deref(), Some("Someone"));
+        assert_eq!(metadata.site.as_deref(), Some("Videos"));
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://example.com/thumb.jpg")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn relative_urls() {
+        let base = "https://example.com/a/b?c=d";
+        assert_eq!(
+            resolve_url(base, "img.png").as_deref(),
+            Some("https://example.com/a/img.png")
+        );
+        assert_eq!(
+            resolve_url(base, "/img.png").as_deref(),
+            Some("https://example.com/img.png")
+        );
+        assert_eq!(
+            resolve_url(base, "//cdn.example.com/img.png").as_deref(),
+            Some("https://cdn.example.com/img.png")
+        );
+        assert_eq!(
+            resolve_url("https://example.com", "img.png").as_deref(),
+            Some("https://example.com/img.png")
+        );
+        assert_eq!(resolve_url(base, "javascript:alert(1)"), None);
+        assert_eq!(resolve_url(base, "data:image/png;base64,AAAA"), None);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &#39;c&#x27; &unknown; &"),
+            "a & b 'c' &unknown; &"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use std::any::Any;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -25,17 +25,18 @@ use tracing::{error, info, warn, Instrument, Span};
 use uuid::Uuid;
 
 use config::Config;
-use store::document::ResolvedRootDocument;
+use store::document::{device::DeviceLinkBundle, ResolvedRootDocument};
 use store::event_subscription::EventSubscription;
 use store::files::FileStore;
 use store::identity::{IdentityStore, LookupBy};
 use store::message::MessageStore;
 use utils::ExtensionType;
 use warp::constellation::directory::Directory;
-use warp::constellation::file::FileType;
+use warp::constellation::file::{FileType, FileVersion, PruneVersions};
 use warp::constellation::{
     Constellation, ConstellationEvent, ConstellationEventKind, ConstellationEventStream,
-    ConstellationProgressStream,
+    ConstellationProgressStream, ConstellationSearchStream, Share, SharePermission, Transfer,
+    TrashItem,
 };
 use warp::crypto::keypair::PhraseType;
 use warp::crypto::zeroize::Zeroizing;
@@ -43,17 +44,19 @@ use warp::crypto::{KeyMaterial, DID};
 use warp::error::Error;
 use warp::module::Module;
 use warp::multipass::identity::{
-    Identifier, Identity, IdentityImage, IdentityProfile, IdentityUpdate, Relationship,
+    Device, Identifier, Identity, IdentityImage, IdentityProfile, IdentityUpdate, Relationship,
 };
 use warp::multipass::{
-    identity, Friends, IdentityImportOption, IdentityInformation, ImportLocation, MultiPass,
-    MultiPassEvent, MultiPassEventKind, MultiPassEventStream, MultiPassImportExport,
+    identity, Devices, Friends, IdentityImportOption, IdentityInformation, ImportLocation,
+    KeyRotation, MultiPass, MultiPassEvent, MultiPassEventKind, MultiPassEventStream,
+    MultiPassImportExport,
 };
 use warp::raygun::{
-    AttachmentEventStream, Conversation, ConversationSettings, EmbedState, GroupSettings, Location,
-    Message, MessageEvent, MessageEventStream, MessageOptions, MessageReference, MessageStatus,
-    Messages, PinState, RayGun, RayGunAttachment, RayGunEventKind, RayGunEventStream, RayGunEvents,
-    RayGunGroupConversation, RayGunStream, ReactionState,
+    AttachmentEventStream, Conversation, ConversationSettings, DraftMessage, EmbedState, GroupRole,
+    GroupSettings, Location, Message, MessageEvent, MessageEventStream, MessageOptions,
+    MessageReference, MessageRevision, MessageStatus, Messages, PinState, Poll, RayGun,
+    RayGunAttachment, RayGunEventKind, RayGunEventStream, RayGunEvents, RayGunGroupConversation,
+    RayGunStream, ReactionState, ScheduledMessage,
 };
 use warp::tesseract::{Tesseract, TesseractEvent};
 use warp::{Extension, SingleHandle};
@@ -61,11 +64,12 @@ use warp::{Extension, SingleHandle};
 use crate::config::{Bootstrap, DiscoveryType};
 use crate::store::discovery::Discovery;
 use crate::store::phonebook::PhoneBook;
-use crate::store::{ecdh_decrypt, PeerIdExt};
+use crate::store::{device_keypair, ecdh_decrypt, PeerIdExt};
 use crate::store::{MAX_IMAGE_SIZE, MAX_USERNAME_LENGTH, MIN_USERNAME_LENGTH};
 
 mod behaviour;
 pub mod config;
+pub mod embed;
 pub(crate) mod rt;
 pub mod store;
 mod thumbnail;
@@ -621,6 +625,7 @@ impl WarpIpfs {
         let filestore = FileStore::new(
             ipfs.clone(),
             root.clone(),
+            identity_store.did_key(),
             &self.inner.config,
             self.constellation_tx.clone(),
             span.clone(),
@@ -634,6 +639,8 @@ impl WarpIpfs {
             self.raygun_tx.clone(),
             identity_store.clone(),
             msg_sh_tx,
+            self.inner.config.store_setting().disable_read_receipts,
+            self.inner.config.store_setting().embed_fetcher.clone(),
         )
         .await;
 
@@ -1126,6 +1133,32 @@ impl MultiPass for WarpIpfs {
 
         store.identity_update(identity).await
     }
+
+    async fn delete_identity(&mut self) -> Result<(), Error> {
+        let _g = self.inner.identity_guard.lock().await;
+        let store = self.identity_store(true).await?;
+
+        store.unregister().await?;
+
+        let ipfs = self.ipfs()?;
+        store::clear_ds(&ipfs).await?;
+
+        // drop the components so the stores of the deleted identity are no longer used
+        *self.inner.components.write() = None;
+        ipfs.exit_daemon().await;
+
+        // without the keypair the identity would be registered again the next time it is loaded.
+        // the device key is removed as well since it may have been derived from the identity
+        for key in ["keypair", "chain", "mnemonic", "device"] {
+            if self.tesseract.exist(key) {
+                self.tesseract.delete(key)?;
+            }
+        }
+
+        info!("identity deleted");
+
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -1233,6 +1266,34 @@ impl MultiPassImportExport for WarpIpfs {
 
                 return store.import_identity_remote_resolve().await;
             }
+            IdentityImportOption::Device { bundle } => {
+                if self.tesseract.exist("keypair") {
+                    return Err(Error::IdentityExist);
+                }
+
+                let device = device_keypair(&self.tesseract)?;
+                let bundle = DeviceLinkBundle::from_bytes(&bundle)?;
+                let (keypair, list) = bundle.open(&device)?;
+
+                info!(%device, "Linking device to identity");
+
+                let kp = Zeroizing::new(bs58::decode(keypair.as_str()).into_vec()?);
+                let id_kp = warp::crypto::ed25519_dalek::Keypair::from_bytes(&kp)?;
+
+                self.tesseract.set("keypair", &keypair)?;
+
+                self.init_ipfs(
+                    rust_ipfs::Keypair::ed25519_from_bytes(id_kp.secret.to_bytes())
+                        .map_err(|_| Error::PrivateKeyInvalid)?,
+                )
+                .await?;
+
+                let mut store = self.identity_store(false).await?;
+
+                let identity = store.import_identity_remote_resolve().await?;
+                store.set_linked_devices(list).await?;
+                return Ok(identity);
+            }
         }
     }
 
@@ -1257,6 +1318,94 @@ impl MultiPassImportExport for WarpIpfs {
     }
 }
 
+#[async_trait::async_trait]
+impl Devices for WarpIpfs {
+    async fn device_key(&self) -> Result<DID, Error> {
+        if !self.tesseract.is_unlock() {
+            return Err(Error::TesseractLocked);
+        }
+        device_keypair(&self.tesseract)
+    }
+
+    async fn link_device(&mut self, device: &DID, name: &str) -> Result<Vec<u8>, Error> {
+        let mut store = self.identity_store(true).await?;
+        store.link_device(device, name).await
+    }
+
+    async fn list_devices(&self) -> Result<Vec<Device>, Error> {
+        let store = self.identity_store(true).await?;
+        store.list_devices().await
+    }
+
+    async fn revoke_device(&mut self, device: &DID) -> Result<(), Error> {
+        let mut store = self.identity_store(true).await?;
+        store.revoke_device(device).await?;
+
+        // messages sent after the revocation should not be readable with keys the device may have kept
+        if let Ok(messaging) = self.messaging_store() {
+            if let Err(e) = messaging.rotate_keys().await {
+                warn!(error = %e, "Unable to rotate conversation keys after revoking device");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove_revoked_device(&mut self) -> Result<(), Error> {
+        let _g = self.inner.identity_guard.lock().await;
+        let store = self.identity_store(true).await?;
+
+        if !store.is_device_revoked().await? {
+            return Err(Error::DeviceNotRevoked);
+        }
+
+        let ipfs = self.ipfs()?;
+        store::clear_ds(&ipfs).await?;
+
+        *self.inner.components.write() = None;
+        ipfs.exit_daemon().await;
+
+        // the mnemonic phrase and chain code are left for the user to remove
+        for key in ["keypair", "device"] {
+            if self.tesseract.exist(key) {
+                self.tesseract.delete(key)?;
+            }
+        }
+
+        info!("revoked device removed");
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl KeyRotation for WarpIpfs {
+    async fn set_recovery_key(&mut self, recovery: &DID) -> Result<(), Error> {
+        let mut store = self.identity_store(true).await?;
+        store.set_recovery_key(recovery).await
+    }
+
+    async fn recovery_key(&self) -> Result<Option<DID>, Error> {
+        let store = self.identity_store(true).await?;
+        store.recovery_key().await
+    }
+
+    async fn issue_rotation(&mut self, new: &DID) -> Result<Vec<u8>, Error> {
+        let store = self.identity_store(true).await?;
+        store.issue_rotation(new).await
+    }
+
+    async fn publish_rotation(&mut self, statement: &[u8]) -> Result<(), Error> {
+        let store = self.identity_store(true).await?;
+        store.publish_rotation(statement).await
+    }
+
+    async fn recover_identity(&mut self, old: &DID, recovery: &DID) -> Result<(), Error> {
+        let store = self.identity_store(true).await?;
+        store.recover_identity(old, recovery).await
+    }
+}
+
 #[async_trait::async_trait]
 impl Friends for WarpIpfs {
     async fn send_request(&mut self, pubkey: &DID) -> Result<(), Error> {
@@ -1431,6 +1580,16 @@ impl RayGun for WarpIpfs {
             .await
     }
 
+    async fn message_revisions(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, Error> {
+        self.messaging_store()?
+            .message_revisions(conversation_id, message_id)
+            .await
+    }
+
     async fn get_message_references(
         &self,
         conversation_id: Uuid,
@@ -1451,6 +1610,26 @@ impl RayGun for WarpIpfs {
             .await
     }
 
+    async fn get_thread(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<Message>, Error> {
+        self.messaging_store()?
+            .get_thread(conversation_id, message_id)
+            .await
+    }
+
+    async fn search_messages(
+        &self,
+        conversation_id: Option<Uuid>,
+        query: &str,
+    ) -> Result<Vec<MessageReference>, Error> {
+        self.messaging_store()?
+            .search_messages(conversation_id, query)
+            .await
+    }
+
     async fn message_status(
         &self,
         conversation_id: Uuid,
@@ -1461,6 +1640,26 @@ impl RayGun for WarpIpfs {
             .await
     }
 
+    async fn mark_as_read(&mut self, conversation_id: Uuid, message_id: Uuid) -> Result<(), Error> {
+        self.messaging_store()?
+            .mark_as_read(conversation_id, message_id)
+            .await
+    }
+
+    async fn message_receipts(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<HashMap<DID, MessageStatus>, Error> {
+        self.messaging_store()?
+            .message_receipts(conversation_id, message_id)
+            .await
+    }
+
+    async fn unread_count(&self, conversation_id: Uuid) -> Result<usize, Error> {
+        self.messaging_store()?.unread_count(conversation_id).await
+    }
+
     async fn get_messages(
         &self,
         conversation_id: Uuid,
@@ -1471,12 +1670,108 @@ impl RayGun for WarpIpfs {
             .await
     }
 
+    async fn save_draft(
+        &mut self,
+        conversation_id: Uuid,
+        value: Vec<String>,
+    ) -> Result<Uuid, Error> {
+        self.messaging_store()?
+            .save_draft(conversation_id, value)
+            .await
+    }
+
+    async fn update_draft(
+        &mut self,
+        conversation_id: Uuid,
+        draft_id: Uuid,
+        value: Vec<String>,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .update_draft(conversation_id, draft_id, value)
+            .await
+    }
+
+    async fn list_drafts(&self, conversation_id: Uuid) -> Result<Vec<DraftMessage>, Error> {
+        self.messaging_store()?.list_drafts(conversation_id).await
+    }
+
+    async fn delete_draft(&mut self, conversation_id: Uuid, draft_id: Uuid) -> Result<(), Error> {
+        self.messaging_store()?
+            .delete_draft(conversation_id, draft_id)
+            .await
+    }
+
+    async fn schedule_message(
+        &mut self,
+        conversation_id: Uuid,
+        value: Vec<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<Uuid, Error> {
+        self.messaging_store()?
+            .schedule_message(conversation_id, value, send_at)
+            .await
+    }
+
+    async fn list_scheduled_messages(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<ScheduledMessage>, Error> {
+        self.messaging_store()?
+            .list_scheduled_messages(conversation_id)
+            .await
+    }
+
+    async fn update_scheduled_message(
+        &mut self,
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+        value: Vec<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .update_scheduled_message(conversation_id, scheduled_id, value, send_at)
+            .await
+    }
+
+    async fn cancel_scheduled_message(
+        &mut self,
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .cancel_scheduled_message(conversation_id, scheduled_id)
+            .await
+    }
+
     async fn send(&mut self, conversation_id: Uuid, value: Vec<String>) -> Result<Uuid, Error> {
         self.messaging_store()?
             .send_message(conversation_id, value)
             .await
     }
 
+    async fn send_poll(&mut self, conversation_id: Uuid, poll: Poll) -> Result<Uuid, Error> {
+        self.messaging_store()?
+            .send_poll(conversation_id, poll)
+            .await
+    }
+
+    async fn vote(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        choices: Vec<usize>,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .vote(conversation_id, message_id, choices)
+            .await
+    }
+
+    async fn close_poll(&mut self, conversation_id: Uuid, message_id: Uuid) -> Result<(), Error> {
+        self.messaging_store()?
+            .close_poll(conversation_id, message_id)
+            .await
+    }
+
     async fn edit(
         &mut self,
         conversation_id: Uuid,
@@ -1534,8 +1829,15 @@ impl RayGun for WarpIpfs {
             .await
     }
 
-    async fn embeds(&mut self, _: Uuid, _: Uuid, _: EmbedState) -> Result<(), Error> {
-        Err(Error::Unimplemented)
+    async fn embeds(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        state: EmbedState,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .embeds(conversation_id, message_id, state)
+            .await
     }
 
     async fn update_conversation_settings(
@@ -1547,6 +1849,27 @@ impl RayGun for WarpIpfs {
             .update_conversation_settings(conversation_id, settings)
             .await
     }
+
+    async fn export_conversation(
+        &self,
+        conversation_id: Uuid,
+        path: PathBuf,
+        passphrase: Option<&str>,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .export_conversation(conversation_id, path, passphrase)
+            .await
+    }
+
+    async fn import_conversation(
+        &mut self,
+        path: PathBuf,
+        passphrase: Option<&str>,
+    ) -> Result<Uuid, Error> {
+        self.messaging_store()?
+            .import_conversation(path, passphrase)
+            .await
+    }
 }
 
 #[async_trait::async_trait]
@@ -1614,6 +1937,57 @@ impl RayGunGroupConversation for WarpIpfs {
             .remove_recipient(conversation_id, did_key)
             .await
     }
+
+    async fn set_role(
+        &mut self,
+        conversation_id: Uuid,
+        did_key: &DID,
+        role: GroupRole,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .set_role(conversation_id, did_key, role)
+            .await
+    }
+
+    async fn transfer_ownership(
+        &mut self,
+        conversation_id: Uuid,
+        did_key: &DID,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .transfer_ownership(conversation_id, did_key)
+            .await
+    }
+
+    async fn ban(&mut self, conversation_id: Uuid, did_key: &DID) -> Result<(), Error> {
+        self.messaging_store()?.ban(conversation_id, did_key).await
+    }
+
+    async fn unban(&mut self, conversation_id: Uuid, did_key: &DID) -> Result<(), Error> {
+        self.messaging_store()?
+            .unban(conversation_id, did_key)
+            .await
+    }
+
+    async fn list_banned(&self, conversation_id: Uuid) -> Result<Vec<DID>, Error> {
+        self.messaging_store()?.list_banned(conversation_id).await
+    }
+
+    async fn add_restricted(&mut self, conversation_id: Uuid, did_key: &DID) -> Result<(), Error> {
+        self.messaging_store()?
+            .add_restricted(conversation_id, did_key)
+            .await
+    }
+
+    async fn remove_restricted(
+        &mut self,
+        conversation_id: Uuid,
+        did_key: &DID,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .remove_restricted(conversation_id, did_key)
+            .await
+    }
 }
 
 #[async_trait::async_trait]
@@ -1640,8 +2014,20 @@ impl RayGunEvents for WarpIpfs {
         conversation_id: Uuid,
         event: MessageEvent,
     ) -> Result<(), Error> {
+        let ttl = event.default_ttl();
         self.messaging_store()?
-            .send_event(conversation_id, event)
+            .send_event(conversation_id, event, ttl)
+            .await
+    }
+
+    async fn send_event_with_ttl(
+        &mut self,
+        conversation_id: Uuid,
+        event: MessageEvent,
+        ttl: Duration,
+    ) -> Result<(), Error> {
+        self.messaging_store()?
+            .send_event(conversation_id, event, ttl)
             .await
     }
 
@@ -1670,6 +2056,12 @@ impl Constellation for WarpIpfs {
             .unwrap_or_default()
     }
 
+    fn current_size(&self) -> usize {
+        self.file_store()
+            .map(|store| store.current_size())
+            .unwrap_or_default()
+    }
+
     fn max_size(&self) -> usize {
         self.file_store()
             .map(|store| store.max_size())
@@ -1721,6 +2113,10 @@ impl Constellation for WarpIpfs {
         self.file_store()?.rename(current, new).await
     }
 
+    async fn move_item(&mut self, from: &str, to: &str) -> Result<(), Error> {
+        self.file_store()?.move_item(from, to).await
+    }
+
     async fn create_directory(&mut self, name: &str, recursive: bool) -> Result<(), Error> {
         self.file_store()?.create_directory(name, recursive).await
     }
@@ -1729,6 +2125,91 @@ impl Constellation for WarpIpfs {
         self.file_store()?.sync_ref(path).await
     }
 
+    async fn share(
+        &mut self,
+        path: &str,
+        did: &DID,
+        permission: SharePermission,
+    ) -> Result<Uuid, Error> {
+        self.file_store()?.share(path, did, permission).await
+    }
+
+    async fn revoke_share(&mut self, path: &str, did: &DID) -> Result<(), Error> {
+        self.file_store()?.revoke_share(path, did).await
+    }
+
+    async fn list_shares(&self) -> Result<Vec<Share>, Error> {
+        self.file_store()?.list_shares().await
+    }
+
+    async fn shared_with_me(&self) -> Result<Vec<Share>, Error> {
+        self.file_store()?.shared_with_me().await
+    }
+
+    async fn shared_directory(&self) -> Result<Directory, Error> {
+        self.file_store()?.shared_directory().await
+    }
+
+    async fn get_shared_buffer(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.file_store()?.get_shared_buffer(path).await
+    }
+
+    async fn put_shared_buffer(&mut self, path: &str, buffer: &[u8]) -> Result<(), Error> {
+        self.file_store()?.put_shared_buffer(path, buffer).await
+    }
+
+    async fn list_trash(&self) -> Result<Vec<TrashItem>, Error> {
+        self.file_store()?.list_trash().await
+    }
+
+    async fn restore(&mut self, id: Uuid) -> Result<(), Error> {
+        self.file_store()?.restore(id).await
+    }
+
+    async fn empty_trash(&mut self) -> Result<(), Error> {
+        self.file_store()?.empty_trash().await
+    }
+
+    async fn list_versions(&self, path: &str) -> Result<Vec<FileVersion>, Error> {
+        self.file_store()?.list_versions(path).await
+    }
+
+    async fn get_version_buffer(&self, path: &str, id: Uuid) -> Result<Vec<u8>, Error> {
+        self.file_store()?.get_version_buffer(path, id).await
+    }
+
+    async fn restore_version(&mut self, path: &str, id: Uuid) -> Result<(), Error> {
+        self.file_store()?.restore_version(path, id).await
+    }
+
+    async fn prune_versions(&mut self, path: &str, prune: PruneVersions) -> Result<(), Error> {
+        self.file_store()?.prune_versions(path, prune).await
+    }
+
+    async fn list_transfers(&self) -> Result<Vec<Transfer>, Error> {
+        self.file_store()?.list_transfers().await
+    }
+
+    async fn cancel_transfer(&mut self, id: Uuid) -> Result<(), Error> {
+        self.file_store()?.cancel_transfer(id).await
+    }
+
+    async fn search(&self, query: &str) -> Result<ConstellationSearchStream, Error> {
+        self.file_store()?.search(query).await
+    }
+
+    async fn publish(&mut self, path: &str) -> Result<(), Error> {
+        let file = self.root_directory().get_item_by_path(path)?.get_file()?;
+        let store = self.identity_store(true).await?;
+        store.publish_file(&file).await
+    }
+
+    async fn unpublish(&mut self, path: &str) -> Result<(), Error> {
+        let file = self.root_directory().get_item_by_path(path)?.get_file()?;
+        let store = self.identity_store(true).await?;
+        store.unpublish_file(&file).await
+    }
+
     fn set_path(&mut self, path: PathBuf) {
         if let Ok(mut store) = self.file_store() {
             store.set_path(path)
@@ -1750,7 +2231,8 @@ impl ConstellationEvent for WarpIpfs {
     }
 }
 
-pub(crate) fn to_file_type(name: &str) -> FileType {
+/// Determines the type of the file from the start of its contents, falling back to the extension of its name
+pub(crate) fn to_file_type(name: &str, header: &[u8]) -> FileType {
     let name = PathBuf::from(name.trim());
     let extension = name
         .extension()
@@ -1758,7 +2240,7 @@ pub(crate) fn to_file_type(name: &str) -> FileType {
         .map(ExtensionType::from)
         .unwrap_or(ExtensionType::Other);
 
-    extension.into()
+    FileType::from_content(header, extension.into())
 }
 
 // pub mod ffi {
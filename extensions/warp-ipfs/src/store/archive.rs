@@ -0,0 +1,534 @@
+//! Portable archive of a conversation.
+//!
+//! An archive is a directory containing the following:
+//!
+//! - `manifest.json`: Information about the archive. This is never encrypted
+//! - `conversation.json`: The conversation document along with its keystore. The keystore is only
+//!   included when the archive is encrypted with a passphrase
+//! - `messages.jsonl`: One message per line, containing the decrypted message along with
+//!   the signed document and the raw objects it references so it can be restored as is
+//! - `blobs/<cid>`: Contents of attachments and thumbnails referenced by the messages
+//!
+//! When a passphrase is supplied, every file other than the manifest is encrypted with a key derived
+//! from the passphrase using Argon2id along with a random salt stored in the manifest, with each line
+//! of `messages.jsonl` being encrypted and encoded individually.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::{
+    crypto::{
+        cipher::Cipher,
+        rand::{self, RngCore},
+        zeroize::Zeroizing,
+        DID,
+    },
+    error::Error,
+    raygun::{Conversation, Message},
+};
+
+use super::{
+    conversation::{
+        ConversationDocument, MessageDocument, MessageRevisionDocument, PollVoteDocument,
+    },
+    document::{image_dag::ImageDag, FileAttachmentDocument},
+    keystore::Keystore,
+};
+
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CONVERSATION_FILE: &str = "conversation.json";
+const MESSAGES_FILE: &str = "messages.jsonl";
+const BLOBS_DIRECTORY: &str = "blobs";
+const SALT_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u8,
+    pub conversation_id: Uuid,
+    pub created: DateTime<Utc>,
+    pub messages: usize,
+    /// Conversation id encrypted with the passphrase, used to validate the passphrase
+    /// before reading the rest of the archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<String>,
+    /// Salt used to derive the key from the passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+}
+
+impl ArchiveManifest {
+    pub fn encrypted(&self) -> bool {
+        self.check.is_some()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArchivedConversation {
+    pub conversation: Conversation,
+    pub document: ConversationDocument,
+    /// Keystore of a group conversation. Only included in archives encrypted with a passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keystore: Option<Keystore>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArchivedMessage {
+    /// Decrypted message
+    pub message: Message,
+    /// Signed document of the message
+    pub document: MessageDocument,
+    /// Encrypted contents of the message referenced by the document
+    pub payload: Vec<u8>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub reactions: BTreeMap<String, Vec<DID>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<FileAttachmentDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thumbnails: Vec<ImageDag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub votes: Vec<PollVoteDocument>,
+    /// Previous revisions of the message along with their encrypted contents
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revisions: Vec<(MessageRevisionDocument, Vec<u8>)>,
+}
+
+impl ArchivedMessage {
+    pub async fn new(
+        ipfs: &Ipfs,
+        message: Message,
+        document: MessageDocument,
+    ) -> Result<Self, Error> {
+        let payload = document.raw_encrypted_message(ipfs).await?;
+
+        let reactions = match document.reactions {
+            Some(cid) => ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized()
+                .await
+                .unwrap_or_default(),
+            None => BTreeMap::new(),
+        };
+
+        let attachments = document.attachments(ipfs).await;
+
+        let mut thumbnails = vec![];
+
+        for cid in attachments
+            .iter()
+            .filter_map(|attachment| attachment.thumbnail)
+        {
+            match ipfs.get_dag(cid).local().deserialized::<ImageDag>().await {
+                Ok(image) => thumbnails.push(image),
+                Err(e) => tracing::warn!(%cid, "unable to obtain thumbnail: {e}"),
+            }
+        }
+
+        let poll = match document.poll {
+            Some(cid) => Some(ipfs.get_dag(cid).local().deserialized::<Vec<u8>>().await?),
+            None => None,
+        };
+
+        let votes = document.poll_votes(ipfs, true).await;
+
+        let mut revisions = vec![];
+
+        for revision in document.revisions(ipfs, true).await {
+            let payload = ipfs
+                .get_dag(revision.message)
+                .local()
+                .deserialized::<Vec<u8>>()
+                .await?;
+            revisions.push((revision, payload));
+        }
+
+        Ok(Self {
+            message,
+            document,
+            payload,
+            reactions,
+            attachments,
+            thumbnails,
+            poll,
+            votes,
+            revisions,
+        })
+    }
+
+    /// Cids of the attachments and thumbnails contents referenced by the message
+    pub fn blobs(&self) -> Vec<Cid> {
+        self.attachments
+            .iter()
+            .filter_map(|attachment| Cid::from_str(&attachment.data).ok())
+            .chain(self.thumbnails.iter().map(|image| image.link))
+            .collect()
+    }
+
+    /// Store the objects of the message and returns the document referencing them.
+    /// The objects covered by the signature of the document must match to be restored
+    pub async fn restore(&self, ipfs: &Ipfs) -> Result<MessageDocument, Error> {
+        let mut document = self.document;
+
+        if !document.verify() || document.id != self.message.id() {
+            return Err(Error::InvalidMessage);
+        }
+
+        let message = ipfs.dag().put().serialize(self.payload.clone()).await?;
+
+        if document.message != Some(message) {
+            return Err(Error::InvalidMessage);
+        }
+
+        if !self.attachments.is_empty() {
+            let cid = ipfs.dag().put().serialize(self.attachments.clone()).await?;
+
+            if document.attachments != Some(cid) {
+                return Err(Error::InvalidMessage);
+            }
+        }
+
+        for image in &self.thumbnails {
+            ipfs.dag().put().serialize(image.clone()).await?;
+        }
+
+        if let Some(poll) = self.poll.as_ref() {
+            let cid = ipfs.dag().put().serialize(poll.clone()).await?;
+
+            if document.poll != Some(cid) {
+                return Err(Error::InvalidMessage);
+            }
+        }
+
+        // Revisions are stored from the oldest so each one links to the previous
+        let mut previous = None;
+
+        for (revision, payload) in &self.revisions {
+            let message = ipfs.dag().put().serialize(payload.clone()).await?;
+
+            if revision.message != message || revision.previous != previous {
+                return Err(Error::InvalidMessage);
+            }
+
+            previous = Some(ipfs.dag().put().serialize(*revision).await?);
+        }
+
+        if previous.is_some() && document.history != previous {
+            return Err(Error::InvalidMessage);
+        }
+
+        // Reactions and votes are not covered by the signature of the document so they
+        // are stored again and referenced from the restored document
+        document.reactions = match self.reactions.is_empty() {
+            true => None,
+            false => Some(ipfs.dag().put().serialize(self.reactions.clone()).await?),
+        };
+
+        let votes = self
+            .votes
+            .iter()
+            .filter(|vote| vote.message_id == document.id && vote.verify())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        document.votes = match votes.is_empty() {
+            true => None,
+            false => Some(ipfs.dag().put().serialize(votes).await?),
+        };
+
+        Ok(document)
+    }
+}
+
+/// Derive the key used to encrypt the archive from the passphrase
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::Any(anyhow::anyhow!("{e}")))?;
+    Ok(key)
+}
+
+pub struct ArchiveWriter {
+    path: PathBuf,
+    key: Option<(Zeroizing<Vec<u8>>, Vec<u8>)>,
+    messages: Vec<u8>,
+    count: usize,
+}
+
+impl ArchiveWriter {
+    pub async fn create(path: impl AsRef<Path>, passphrase: Option<&str>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+
+        let key = match passphrase {
+            Some(passphrase) => {
+                let mut salt = vec![0u8; SALT_SIZE];
+                rand::thread_rng().fill_bytes(&mut salt);
+                Some((derive_key(passphrase, &salt)?, salt))
+            }
+            None => None,
+        };
+
+        fs::create_dir_all(path.join(BLOBS_DIRECTORY)).await?;
+
+        Ok(Self {
+            path,
+            key,
+            messages: Vec::new(),
+            count: 0,
+        })
+    }
+
+    pub fn encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    fn seal(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.key.as_ref() {
+            Some((key, _)) => Cipher::direct_encrypt(data, key),
+            None => Ok(data.to_vec()),
+        }
+    }
+
+    pub async fn write_conversation(
+        &self,
+        conversation: &ArchivedConversation,
+    ) -> Result<(), Error> {
+        // The keys of the conversation must never be written in plaintext
+        if conversation.keystore.is_some() && !self.encrypted() {
+            return Err(Error::OtherWithContext(
+                "keystore can only be archived with a passphrase".into(),
+            ));
+        }
+
+        let bytes = self.seal(&serde_json::to_vec(conversation)?)?;
+        fs::write(self.path.join(CONVERSATION_FILE), bytes).await?;
+        Ok(())
+    }
+
+    pub fn push_message(&mut self, message: &ArchivedMessage) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(message)?;
+        match self.encrypted() {
+            true => {
+                let line = bs58::encode(self.seal(&bytes)?).into_string();
+                self.messages.extend(line.into_bytes());
+            }
+            false => self.messages.extend(bytes),
+        }
+        self.messages.push(b'\n');
+        self.count += 1;
+        Ok(())
+    }
+
+    pub async fn write_blob(&self, cid: &Cid, data: &[u8]) -> Result<(), Error> {
+        let bytes = self.seal(data)?;
+        fs::write(self.path.join(BLOBS_DIRECTORY).join(cid.to_string()), bytes).await?;
+        Ok(())
+    }
+
+    pub async fn finish(self, conversation_id: Uuid) -> Result<(), Error> {
+        fs::write(self.path.join(MESSAGES_FILE), &self.messages).await?;
+
+        let (check, salt) = match self.key.as_ref() {
+            Some((_, salt)) => (
+                Some(bs58::encode(self.seal(conversation_id.as_bytes())?).into_string()),
+                Some(bs58::encode(salt).into_string()),
+            ),
+            None => (None, None),
+        };
+
+        let manifest = ArchiveManifest {
+            version: ARCHIVE_VERSION,
+            conversation_id,
+            created: Utc::now(),
+            messages: self.count,
+            check,
+            salt,
+        };
+
+        fs::write(
+            self.path.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&manifest)?,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+pub struct ArchiveReader {
+    path: PathBuf,
+    key: Option<Zeroizing<Vec<u8>>>,
+    manifest: ArchiveManifest,
+}
+
+impl ArchiveReader {
+    pub async fn open(path: impl AsRef<Path>, passphrase: Option<&str>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let bytes = fs::read(path.join(MANIFEST_FILE)).await?;
+        let manifest: ArchiveManifest =
+            serde_json::from_slice(&bytes).map_err(|_| Error::InvalidArchive)?;
+
+        if manifest.version != ARCHIVE_VERSION {
+            return Err(Error::InvalidArchive);
+        }
+
+        let key = match (manifest.check.as_ref(), passphrase) {
+            (Some(check), Some(passphrase)) => {
+                let salt = manifest.salt.as_ref().ok_or(Error::InvalidArchive)?;
+                let salt = bs58::decode(salt).into_vec()?;
+                let key = derive_key(passphrase, &salt)?;
+                let check = bs58::decode(check).into_vec()?;
+                let id =
+                    Cipher::direct_decrypt(&check, &key).map_err(|_| Error::InvalidPassphrase)?;
+                if id != manifest.conversation_id.as_bytes() {
+                    return Err(Error::InvalidPassphrase);
+                }
+                Some(key)
+            }
+            (Some(_), None) => return Err(Error::InvalidPassphrase),
+            (None, _) => None,
+        };
+
+        Ok(Self {
+            path,
+            key,
+            manifest,
+        })
+    }
+
+    pub fn manifest(&self) -> &ArchiveManifest {
+        &self.manifest
+    }
+
+    fn open_sealed(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.key.as_ref() {
+            Some(key) => Cipher::direct_decrypt(data, key),
+            None => Ok(data.to_vec()),
+        }
+    }
+
+    pub async fn conversation(&self) -> Result<ArchivedConversation, Error> {
+        let bytes = fs::read(self.path.join(CONVERSATION_FILE)).await?;
+        let conversation: ArchivedConversation =
+            serde_json::from_slice(&self.open_sealed(&bytes)?)?;
+
+        if conversation.document.id() != self.manifest.conversation_id {
+            return Err(Error::InvalidArchive);
+        }
+
+        Ok(conversation)
+    }
+
+    pub async fn messages(&self) -> Result<Vec<ArchivedMessage>, Error> {
+        let bytes = fs::read(self.path.join(MESSAGES_FILE)).await?;
+
+        let mut messages = Vec::with_capacity(self.manifest.messages);
+
+        for line in bytes.split(|byte| *byte == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+
+            let message: ArchivedMessage = match self.key.is_some() {
+                true => {
+                    let data = bs58::decode(line).into_vec()?;
+                    serde_json::from_slice(&self.open_sealed(&data)?)?
+                }
+                false => serde_json::from_slice(line)?,
+            };
+
+            if message.document.conversation_id != self.manifest.conversation_id {
+                return Err(Error::InvalidArchive);
+            }
+
+            messages.push(message);
+        }
+
+        Ok(messages)
+    }
+
+    pub async fn blob(&self, cid: &Cid) -> Result<Vec<u8>, Error> {
+        let bytes = fs::read(self.path.join(BLOBS_DIRECTORY).join(cid.to_string())).await?;
+        self.open_sealed(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use warp::{
+        crypto::DID,
+        raygun::{Conversation, DirectConversationSettings, GroupSettings},
+    };
+
+    use crate::store::{conversation::ConversationDocument, keystore::Keystore};
+
+    use super::{derive_key, ArchiveWriter, ArchivedConversation};
+
+    #[test]
+    fn key_derived_from_passphrase_and_salt() -> anyhow::Result<()> {
+        let key = derive_key("passphrase", b"0123456789abcdef")?;
+        assert_eq!(key.len(), 32);
+        assert_eq!(key, derive_key("passphrase", b"0123456789abcdef")?);
+        assert_ne!(key, derive_key("passphrase", b"fedcba9876543210")?);
+        assert_ne!(key, derive_key("Passphrase", b"0123456789abcdef")?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keystore_requires_passphrase() -> anyhow::Result<()> {
+        let did = DID::default();
+        let document = ConversationDocument::new_group(
+            &did,
+            None,
+            [did.clone(), DID::default()],
+            &[],
+            GroupSettings::default(),
+        )?;
+
+        let mut keystore = Keystore::new(document.id());
+        keystore.insert(&did, &did, warp::crypto::generate::<64>())?;
+
+        let archived = ArchivedConversation {
+            conversation: Conversation::from(&document),
+            document,
+            keystore: Some(keystore),
+        };
+
+        let dir = std::env::temp_dir().join(format!("archive-{}", archived.document.id()));
+
+        let writer = ArchiveWriter::create(dir.join("plain"), None).await?;
+        assert!(writer.write_conversation(&archived).await.is_err());
+
+        let writer = ArchiveWriter::create(dir.join("sealed"), Some("passphrase")).await?;
+        writer.write_conversation(&archived).await?;
+
+        let direct = ConversationDocument::new_direct(
+            &did,
+            [did.clone(), DID::default()],
+            DirectConversationSettings::default(),
+        )?;
+        let archived = ArchivedConversation {
+            conversation: Conversation::from(&direct),
+            document: direct,
+            keystore: None,
+        };
+
+        let writer = ArchiveWriter::create(dir.join("direct"), None).await?;
+        writer.write_conversation(&archived).await?;
+
+        _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
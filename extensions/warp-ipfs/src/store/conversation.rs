@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use core::hash::Hash;
 use either::Either;
 use futures::{
-    stream::{self, BoxStream, FuturesUnordered},
+    stream::{self, BoxStream, FuturesOrdered, FuturesUnordered},
     StreamExt, TryFutureExt,
 };
 use libipld::Cid;
@@ -21,17 +21,20 @@ use warp::{
     },
     error::Error,
     raygun::{
-        Conversation, ConversationSettings, ConversationType, DirectConversationSettings,
-        GroupSettings, Message, MessageOptions, MessagePage, MessageReference, MessageType,
-        Messages, MessagesType,
+        Conversation, ConversationSettings, ConversationType, DirectConversationSettings, Embed,
+        GroupRole, GroupSettings, Message, MessageOptions, MessagePage, MessageReference,
+        MessageRevision, MessageStatus, MessageType, Messages, MessagesType, Poll,
     },
 };
 
-use crate::store::{ecdh_encrypt, ecdh_encrypt_with_nonce};
+use crate::{
+    embed::{EmbedDocument, MAX_EMBEDS},
+    store::{ecdh_encrypt, ecdh_encrypt_with_nonce},
+};
 
 use super::{
     document::FileAttachmentDocument, ecdh_decrypt, keystore::Keystore, verify_serde_sig,
-    MAX_ATTACHMENT, MAX_MESSAGE_SIZE, MIN_MESSAGE_SIZE,
+    MAX_ATTACHMENT, MAX_MESSAGE_SIZE, MAX_VOTE_CLOCK_SKEW, MIN_MESSAGE_SIZE,
 };
 
 #[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -60,8 +63,19 @@ pub struct ConversationDocument {
     pub restrict: Vec<DID>,
     #[serde(default)]
     pub deleted: bool,
+    /// Signed assignments of roles to members of a group conversation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<RoleDocument>,
+    /// Signed transfers of ownership, starting from the creator of the group conversation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ownership: Vec<RoleDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned: Vec<DID>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub messages: Option<Cid>,
+    /// Member that signed the document, if it was not signed by the owner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<DID>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
 }
@@ -133,6 +147,88 @@ impl ConversationDocument {
             ConversationSettings::Group(_) => ConversationType::Group,
         }
     }
+
+    /// Current owner of a group conversation. Ownership starts with the creator and follows
+    /// each transfer that was signed by the owner at the time
+    pub fn owner(&self) -> Option<DID> {
+        self.owners().pop()
+    }
+
+    /// Current and previous owners of a group conversation, in the order ownership was transferred.
+    /// Transfers are only followed while each one is signed by the owner at the time
+    fn owners(&self) -> Vec<DID> {
+        let Some(creator) = self.creator.clone() else {
+            return vec![];
+        };
+
+        let mut owners = vec![creator];
+
+        for transfer in &self.ownership {
+            let owner = owners.last().expect("owner exist");
+            if transfer.conversation_id != self.id
+                || transfer.role != GroupRole::Owner
+                || transfer.assigner.to_did().ne(owner)
+                || !transfer.verify()
+            {
+                break;
+            }
+            owners.push(transfer.member.to_did());
+        }
+
+        owners
+    }
+
+    /// Members of a group conversation that hold a role other than [`GroupRole::Member`]
+    pub fn roles(&self) -> HashMap<DID, GroupRole> {
+        let mut roles = HashMap::new();
+
+        // Admins are assigned by the owner, or a previous owner, while moderators may also be assigned by admins
+        let owners = self.owners();
+
+        let Some(owner) = owners.last().cloned() else {
+            return roles;
+        };
+
+        roles.insert(owner, GroupRole::Owner);
+
+        for role in [GroupRole::Admin, GroupRole::Moderator] {
+            for assignment in self.roles.iter().filter(|assignment| {
+                assignment.role == role && assignment.conversation_id == self.id
+            }) {
+                let member = assignment.member.to_did();
+                let assigner = assignment.assigner.to_did();
+
+                if roles.contains_key(&member) || !self.recipients.contains(&member) {
+                    continue;
+                }
+
+                let authorized = match role {
+                    GroupRole::Admin => owners.contains(&assigner),
+                    _ => {
+                        matches!(roles.get(&assigner), Some(assigner_role) if *assigner_role > role)
+                    }
+                };
+
+                if authorized && assignment.verify() {
+                    roles.insert(member, role);
+                }
+            }
+        }
+
+        roles
+    }
+
+    /// Role of a member within the conversation
+    pub fn role(&self, did: &DID) -> GroupRole {
+        self.roles().get(did).copied().unwrap_or_default()
+    }
+
+    /// Date in which the message will be deleted, if messages are set to expire in the conversation
+    pub fn message_expire(&self, message: &MessageDocument) -> Option<DateTime<Utc>> {
+        let expiry = self.settings.message_expiry()?;
+        let expiry = chrono::Duration::from_std(Duration::from_secs(expiry)).ok()?;
+        message.date.checked_add_signed(expiry)
+    }
 }
 
 impl ConversationDocument {
@@ -179,6 +275,10 @@ impl ConversationDocument {
             signature,
             restrict,
             deleted: false,
+            roles: vec![],
+            ownership: vec![],
+            banned: vec![],
+            signer: None,
         };
 
         if document.signature.is_some() {
@@ -255,7 +355,10 @@ impl ConversationDocument {
                 return Err(Error::PublicKeyInvalid);
             };
 
-            if !settings.members_can_add_participants() && !creator.eq(did) {
+            let owner = self.owner().unwrap_or(creator);
+
+            // Only members with the ability to moderate the conversation are able to sign in place of the owner
+            if owner.ne(did) && self.role(did) < GroupRole::Moderator {
                 return Err(Error::PublicKeyInvalid);
             }
 
@@ -263,25 +366,9 @@ impl ConversationDocument {
                 self.version = ConversationVersion::V1;
             }
 
-            let construct = warp::crypto::hash::sha256_iter(
-                [
-                    Some(self.id().into_bytes().to_vec()),
-                    // self.name.as_deref().map(|s| s.as_bytes().to_vec()),
-                    Some(creator.to_string().as_bytes().to_vec()),
-                    Some(Vec::from_iter(
-                        self.restrict
-                            .iter()
-                            .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
-                    )),
-                    (!settings.members_can_add_participants()).then_some(Vec::from_iter(
-                        self.recipients
-                            .iter()
-                            .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
-                    )),
-                ]
-                .into_iter(),
-                None,
-            );
+            self.signer = owner.ne(did).then(|| did.clone());
+
+            let construct = self.construct(settings);
 
             let signature = did.sign(&construct);
             self.signature = Some(bs58::encode(signature).into_string());
@@ -302,46 +389,82 @@ impl ConversationDocument {
 
             let signature = bs58::decode(signature).into_vec()?;
 
-            let construct = match self.version {
-                ConversationVersion::V0 => [
-                    self.id().into_bytes().to_vec(),
-                    vec![0xdc, 0xfc],
-                    creator.to_string().as_bytes().to_vec(),
-                    Vec::from_iter(
-                        self.recipients
-                            .iter()
-                            .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
-                    ),
-                ]
-                .concat(),
-                ConversationVersion::V1 => warp::crypto::hash::sha256_iter(
+            let (signer, construct) = match self.version {
+                ConversationVersion::V0 => (
+                    creator.clone(),
                     [
-                        Some(self.id().into_bytes().to_vec()),
-                        // self.name.as_deref().map(|s| s.as_bytes().to_vec()),
-                        Some(creator.to_string().as_bytes().to_vec()),
-                        Some(Vec::from_iter(
-                            self.restrict
-                                .iter()
-                                .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
-                        )),
-                        (!settings.members_can_add_participants()).then_some(Vec::from_iter(
+                        self.id().into_bytes().to_vec(),
+                        vec![0xdc, 0xfc],
+                        creator.to_string().as_bytes().to_vec(),
+                        Vec::from_iter(
                             self.recipients
                                 .iter()
                                 .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
-                        )),
+                        ),
                     ]
-                    .into_iter(),
-                    None,
+                    .concat(),
                 ),
+                ConversationVersion::V1 => {
+                    let owner = self.owner().unwrap_or(creator.clone());
+                    let signer = match self.signer.clone() {
+                        Some(signer) if signer.ne(&owner) => {
+                            if self.role(&signer) < GroupRole::Moderator {
+                                return Err(Error::PublicKeyInvalid);
+                            }
+                            signer
+                        }
+                        _ => owner,
+                    };
+                    (signer, self.construct(settings))
+                }
             };
 
-            creator
+            signer
                 .verify(&construct, &signature)
                 .map_err(|e| anyhow::anyhow!("{:?}", e))?;
         }
         Ok(())
     }
 
+    fn construct(&self, settings: GroupSettings) -> Vec<u8> {
+        let creator = self.creator.as_ref();
+        warp::crypto::hash::sha256_iter(
+            [
+                Some(self.id().into_bytes().to_vec()),
+                // self.name.as_deref().map(|s| s.as_bytes().to_vec()),
+                creator.map(|creator| creator.to_string().as_bytes().to_vec()),
+                Some(Vec::from_iter(
+                    self.restrict
+                        .iter()
+                        .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
+                )),
+                (!settings.members_can_add_participants()).then_some(Vec::from_iter(
+                    self.recipients
+                        .iter()
+                        .flat_map(|rec| rec.to_string().as_bytes().to_vec()),
+                )),
+                (!self.ownership.is_empty()).then(|| {
+                    Vec::from_iter(self.ownership.iter().flat_map(|transfer| transfer.hash()))
+                }),
+                (!self.roles.is_empty()).then(|| {
+                    Vec::from_iter(self.roles.iter().flat_map(|assignment| assignment.hash()))
+                }),
+                (!self.banned.is_empty()).then(|| {
+                    Vec::from_iter(
+                        self.banned
+                            .iter()
+                            .flat_map(|did| did.to_string().as_bytes().to_vec()),
+                    )
+                }),
+                self.signer
+                    .as_ref()
+                    .map(|signer| signer.to_string().as_bytes().to_vec()),
+            ]
+            .into_iter(),
+            None,
+        )
+    }
+
     pub async fn message_reference_list(&self, ipfs: &Ipfs) -> Result<MessageReferenceList, Error> {
         let refs = match self.messages {
             Some(cid) => {
@@ -379,6 +502,10 @@ impl ConversationDocument {
     ) -> Result<Cid, Error> {
         let mut list = self.message_reference_list(ipfs).await?;
         let cid = list.insert(ipfs, message_document).await?;
+        if let Some(thread_id) = message_document.thread {
+            list.insert_thread_reply(ipfs, thread_id, &message_document)
+                .await?;
+        }
         self.set_message_reference_list(ipfs, list).await?;
         Ok(cid)
     }
@@ -431,20 +558,37 @@ impl ConversationDocument {
             return Ok(stream::empty().boxed());
         }
 
+        let threads = self.message_reference_list(ipfs).await?.threads(ipfs).await;
+
+        let into_reference = move |document: &MessageDocument| {
+            let mut reference = MessageReference::from(document);
+            if let Some(thread) = threads.get(&document.id.to_string()) {
+                reference.set_reply_count(thread.count());
+                reference.set_last_reply(thread.last_reply());
+            }
+            reference
+        };
+
         let mut messages = Vec::from_iter(message_list);
 
+        if option.exclude_threads() {
+            messages.retain(|document| document.thread.is_none());
+        }
+
         if option.reverse() {
             messages.reverse()
         }
 
         if option.first_message() && !messages.is_empty() {
-            let message = messages.first().copied().ok_or(Error::MessageNotFound)?;
-            return Ok(stream::once(async move { message.into() }).boxed());
+            let message = messages.first().ok_or(Error::MessageNotFound)?;
+            let reference = into_reference(message);
+            return Ok(stream::once(async move { reference }).boxed());
         }
 
         if option.last_message() && !messages.is_empty() {
-            let message = messages.last().copied().ok_or(Error::MessageNotFound)?;
-            return Ok(stream::once(async move { message.into() }).boxed());
+            let message = messages.last().ok_or(Error::MessageNotFound)?;
+            let reference = into_reference(message);
+            return Ok(stream::once(async move { reference }).boxed());
         }
 
         let stream = async_stream::stream! {
@@ -472,7 +616,7 @@ impl ConversationDocument {
                     *remaining = remaining.saturating_sub(1);
                 }
 
-                yield document.into()
+                yield into_reference(document)
             }
         };
 
@@ -494,6 +638,10 @@ impl ConversationDocument {
 
         let mut messages = Vec::from_iter(message_list);
 
+        if option.exclude_threads() {
+            messages.retain(|document| document.thread.is_none());
+        }
+
         if option.reverse() {
             messages.reverse()
         }
@@ -578,6 +726,10 @@ impl ConversationDocument {
 
         let mut messages = Vec::from_iter(message_list);
 
+        if option.exclude_threads() {
+            messages.retain(|document| document.thread.is_none());
+        }
+
         if option.reverse() {
             messages.reverse()
         }
@@ -656,11 +808,122 @@ impl ConversationDocument {
             .await
     }
 
-    pub async fn delete_message(&mut self, ipfs: &Ipfs, message_id: Uuid) -> Result<(), Error> {
+    /// Removes the message from the conversation, returning the id of the thread if the message was a reply within one
+    pub async fn delete_message(
+        &mut self,
+        ipfs: &Ipfs,
+        message_id: Uuid,
+    ) -> Result<Option<Uuid>, Error> {
         let mut list = self.message_reference_list(ipfs).await?;
+        let thread = list
+            .get(ipfs, message_id)
+            .await
+            .ok()
+            .and_then(|document| document.thread);
         list.remove(ipfs, message_id).await?;
+        if let Some(thread_id) = thread {
+            list.remove_thread_reply(ipfs, thread_id, message_id)
+                .await?;
+        }
+        list.insert_deleted(ipfs, message_id).await?;
         self.set_message_reference_list(ipfs, list).await?;
-        Ok(())
+        Ok(thread)
+    }
+
+    /// Merge the messages of the document from another device, returning true if the messages changed
+    pub async fn merge_messages(
+        &mut self,
+        ipfs: &Ipfs,
+        other: &ConversationDocument,
+    ) -> Result<bool, Error> {
+        if other.messages.is_none() || self.messages == other.messages {
+            return Ok(false);
+        }
+
+        let mut list = self.message_reference_list(ipfs).await?;
+        let other_list = other.message_reference_list(ipfs).await?;
+
+        if !list.merge(ipfs, &other_list).await? {
+            return Ok(false);
+        }
+
+        self.set_message_reference_list(ipfs, list).await?;
+        Ok(true)
+    }
+
+    pub async fn receipts(&self, ipfs: &Ipfs) -> Result<BTreeMap<String, MemberReceipts>, Error> {
+        let list = self.message_reference_list(ipfs).await?;
+        Ok(list.receipts(ipfs).await)
+    }
+
+    /// Store the receipt, returning true if it is newer than the current receipt of the member
+    pub async fn insert_receipt(
+        &mut self,
+        ipfs: &Ipfs,
+        receipt: ReceiptDocument,
+    ) -> Result<bool, Error> {
+        let mut list = self.message_reference_list(ipfs).await?;
+        if !list.insert_receipt(ipfs, receipt).await? {
+            return Ok(false);
+        }
+        self.set_message_reference_list(ipfs, list).await?;
+        Ok(true)
+    }
+
+    pub async fn get_message_reference(
+        &self,
+        ipfs: &Ipfs,
+        message_id: Uuid,
+    ) -> Result<MessageReference, Error> {
+        let document = self.get_message_document(ipfs, message_id).await?;
+        let threads = self.message_reference_list(ipfs).await?.threads(ipfs).await;
+        let mut reference = MessageReference::from(document);
+        if let Some(thread) = threads.get(&message_id.to_string()) {
+            reference.set_reply_count(thread.count());
+            reference.set_last_reply(thread.last_reply());
+        }
+        Ok(reference)
+    }
+
+    /// Resolve every message within the thread the message belongs to, starting with the root message
+    pub async fn get_thread(
+        &self,
+        ipfs: &Ipfs,
+        did: &DID,
+        message_id: Uuid,
+        keystore: Either<&DID, &Keystore>,
+    ) -> Result<Vec<Message>, Error> {
+        let message_list = self.get_message_list(ipfs).await?;
+
+        let document = message_list
+            .iter()
+            .find(|document| document.id == message_id)
+            .ok_or(Error::MessageNotFound)?;
+
+        let thread_id = document.thread.unwrap_or(document.id);
+
+        let mut messages = vec![];
+
+        // Note: The list is ordered by date so the replies will be in the order they were sent
+        for document in message_list
+            .iter()
+            .filter(|document| document.id == thread_id || document.thread == Some(thread_id))
+        {
+            if let Ok(message) = document.resolve(ipfs, did, true, keystore).await {
+                messages.push(message);
+            }
+        }
+
+        // Move the root message to the front in case a reply is dated before it
+        if let Some(position) = messages
+            .iter()
+            .position(|message| message.id() == thread_id)
+        {
+            let root = messages.remove(position);
+            messages.insert(0, root);
+        }
+
+        Ok(messages)
     }
 }
 
@@ -680,6 +943,10 @@ impl From<&ConversationDocument> for Conversation {
         conversation.set_created(document.created);
         conversation.set_settings(document.settings);
         conversation.set_modified(document.modified);
+        if let ConversationType::Group = document.conversation_type() {
+            conversation.set_roles(document.roles());
+            conversation.set_banned(document.banned.clone());
+        }
         conversation
     }
 }
@@ -699,9 +966,31 @@ pub struct MessageDocument {
     pub modified: Option<DateTime<Utc>>,
     #[serde(default)]
     pub pinned: bool,
+    /// Time the reactions were last changed on this device, used when merging with another device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reactions_modified: Option<DateTime<Utc>>,
+    /// Time the message was last pinned or unpinned on this device, used when merging with another device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_modified: Option<DateTime<Utc>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub replied: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<Uuid>,
     pub message: Option<Cid>,
+    /// Encrypted poll for messages of type [`MessageType::Poll`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<Cid>,
+    /// List of signed votes for the poll
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub votes: Option<Cid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_closed: Option<DateTime<Utc>>,
+    /// Encrypted previews of the links within the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embeds: Option<Cid>,
+    /// Latest previous revision of the message, if the history is kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<Cid>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<MessageSignature>,
 }
@@ -723,6 +1012,7 @@ impl From<&MessageDocument> for MessageReference {
         }
         reference.set_pinned(document.pinned);
         reference.set_replied(document.replied);
+        reference.set_thread(document.thread);
         reference.set_sender(document.sender.to_did());
         reference.set_delete(document.message.is_none());
         reference
@@ -756,7 +1046,9 @@ impl MessageDocument {
         let pinned = message.pinned();
         let modified = message.modified();
         let replied = message.replied();
+        let thread = message.thread();
         let lines = message.lines();
+        let message_poll = message.poll();
 
         let attachments = FuturesUnordered::from_iter(
             message
@@ -806,6 +1098,23 @@ impl MessageDocument {
 
         let message = Some(ipfs.dag().put().serialize(data).await?);
 
+        let poll = match message_poll {
+            Some(poll) => {
+                let bytes = serde_json::to_vec(&PollDefinition::from(&poll))?;
+
+                let data = match key {
+                    Either::Right(keystore) => {
+                        let key = keystore.get_latest(keypair, &sender)?;
+                        Cipher::direct_encrypt(&bytes, &key)?
+                    }
+                    Either::Left(key) => ecdh_encrypt(keypair, Some(key), &bytes)?,
+                };
+
+                Some(ipfs.dag().put().serialize(data).await?)
+            }
+            None => None,
+        };
+
         let sender = DIDEd25519Reference::from_did(&sender);
 
         let document = MessageDocument {
@@ -818,8 +1127,16 @@ impl MessageDocument {
             attachments,
             message,
             pinned,
+            reactions_modified: None,
+            pinned_modified: None,
             modified,
             replied,
+            thread,
+            poll,
+            votes: None,
+            poll_closed: None,
+            embeds: None,
+            history: None,
             signature: None,
         };
 
@@ -832,22 +1149,7 @@ impl MessageDocument {
         };
 
         let sender = self.sender.to_did();
-        let hash = sha256_iter(
-            [
-                Some(self.conversation_id.as_bytes().to_vec()),
-                Some(self.id.as_bytes().to_vec()),
-                Some(sender.public_key_bytes()),
-                Some(self.date.to_string().into_bytes()),
-                self.modified.map(|time| time.to_string().into_bytes()),
-                self.replied.map(|id| id.as_bytes().to_vec()),
-                self.attachments.map(|cid| cid.to_bytes()),
-                self.message.map(|cid| cid.to_bytes()),
-            ]
-            .into_iter(),
-            None,
-        );
-
-        sender.verify(&hash, signature.as_ref()).is_ok()
+        sender.verify(&self.hash(), signature.as_ref()).is_ok()
     }
 
     pub async fn raw_encrypted_message(&self, ipfs: &Ipfs) -> Result<Vec<u8>, Error> {
@@ -878,111 +1180,536 @@ impl MessageDocument {
             .unwrap_or_default()
     }
 
-    pub async fn update(
-        &mut self,
-        ipfs: &Ipfs,
-        did: &DID,
-        message: Message,
-        signature: Option<Vec<u8>>,
-        key: Either<&DID, &Keystore>,
-        nonce: Option<&[u8]>,
-    ) -> Result<(), Error> {
-        tracing::info!(id = %self.conversation_id, message_id = %self.id, "Updating message");
-        let old_message = self.resolve(ipfs, did, true, key).await?;
+    pub async fn poll_votes(&self, ipfs: &Ipfs, local: bool) -> Vec<PollVoteDocument> {
+        let cid = match self.votes {
+            Some(cid) => cid,
+            None => return vec![],
+        };
 
-        let sender = self.sender.to_did();
+        ipfs.get_dag(cid)
+            .timeout(Duration::from_secs(10))
+            .set_local(local)
+            .deserialized::<Vec<PollVoteDocument>>()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|vote| vote.conversation_id == self.conversation_id)
+            .filter(|vote| vote.message_id == self.id)
+            .filter(PollVoteDocument::verify)
+            .collect()
+    }
 
-        if message.id() != self.id
-            || message.conversation_id() != self.conversation_id
-            || message.sender() != sender
-        {
-            tracing::error!(id = %self.conversation_id, message_id = %self.id, "Message does not exist, is invalid or has invalid sender");
-            //TODO: Maybe remove message from this point?
+    /// Stores the vote, replacing any previous vote from the same member.
+    /// Returns false if the vote is older than the one currently stored.
+    ///
+    /// Whether the poll is closed is checked against the time the vote was received rather than the date
+    /// of the vote, since the date is chosen by the voter
+    pub async fn insert_poll_vote(
+        &mut self,
+        ipfs: &Ipfs,
+        vote: PollVoteDocument,
+        received: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        if self.poll.is_none() {
             return Err(Error::InvalidMessage);
         }
 
-        self.pinned = message.pinned();
-        self.modified = message.modified();
-
-        let reactions = message.reactions();
+        if vote.conversation_id != self.conversation_id
+            || vote.message_id != self.id
+            || !vote.verify()
+        {
+            return Err(Error::InvalidSignature);
+        }
 
-        self.reactions =
-            (!reactions.is_empty()).then_some(ipfs.dag().put().serialize(reactions).await?);
+        let skew = chrono::Duration::from_std(MAX_VOTE_CLOCK_SKEW).expect("valid duration");
 
-        if message.lines() != old_message.lines() {
-            let lines = message.lines();
-            if !lines.is_empty() {
-                let lines_value_length: usize = lines
-                    .iter()
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.trim())
-                    .map(|s| s.chars().count())
-                    .sum();
+        if vote.date > received + skew {
+            return Err(Error::InvalidMessage);
+        }
 
-                if lines_value_length > MAX_MESSAGE_SIZE {
-                    return Err(Error::InvalidLength {
-                        context: "message".into(),
-                        current: lines_value_length,
-                        minimum: None,
-                        maximum: Some(MAX_MESSAGE_SIZE),
-                    });
-                }
-            }
+        if matches!(self.poll_closed, Some(date) if received > date) {
+            return Err(Error::PollClosed);
+        }
 
-            let current_nonce = self.nonce_from_message(ipfs).await?;
+        let mut votes = self.poll_votes(ipfs, true).await;
 
-            if matches!(nonce, Some(nonce) if nonce.eq(&current_nonce)) {
-                // Since the nonce from the current message matches the new one sent,
-                // we would consider this as an invalid message as a nonce should
-                // NOT be reused
-                // TODO: Maybe track previous nonces?
-                return Err(Error::InvalidMessage);
+        if let Some(index) = votes.iter().position(|item| item.voter == vote.voter) {
+            if votes[index].date >= vote.date {
+                return Ok(false);
             }
+            votes.remove(index);
+        }
 
-            let bytes = serde_json::to_vec(&lines)?;
+        // Retracted votes are kept so merging with another device does not restore the previous vote
+        votes.push(vote);
 
-            let data = match (key, nonce) {
-                (Either::Right(keystore), Some(nonce)) => {
-                    let key = keystore.get_latest(did, &sender)?;
-                    Cipher::direct_encrypt_with_nonce(&bytes, &key, nonce)?
-                }
-                (Either::Left(key), Some(nonce)) => {
-                    ecdh_encrypt_with_nonce(did, Some(key), &bytes, nonce)?
-                }
-                (Either::Right(keystore), None) => {
-                    let key = keystore.get_latest(did, &sender)?;
-                    Cipher::direct_encrypt(&bytes, &key)?
-                }
-                (Either::Left(key), None) => ecdh_encrypt(did, Some(key), &bytes)?,
-            };
+        self.votes = Some(ipfs.dag().put().serialize(votes).await?);
 
-            let message = ipfs.dag().put().serialize(data).await?;
+        Ok(true)
+    }
 
-            self.message.replace(message);
+    /// Marks the poll as closed and signs the document. Only the sender is able to close the poll
+    pub fn close_poll(&mut self, did: &DID) -> Result<(), Error> {
+        if self.poll.is_none() {
+            return Err(Error::InvalidMessage);
+        }
 
-            match (sender.eq(did), signature) {
-                (true, None) => {
-                    *self = self.sign(did)?;
-                }
-                (false, None) | (true, Some(_)) => return Err(Error::InvalidMessage),
-                (false, Some(sig)) => {
-                    let new_signature = MessageSignature::try_from(sig)?;
-                    self.signature.replace(new_signature);
-                    if !self.verify() {
-                        return Err(Error::InvalidSignature);
-                    }
-                }
-            };
+        if self.poll_closed.is_some() {
+            return Err(Error::PollClosed);
         }
 
-        tracing::info!(id = %self.conversation_id, message_id = %self.id, "Message is updated");
+        self.poll_closed = Some(Utc::now());
+        *self = self.sign(did)?;
         Ok(())
     }
 
-    pub async fn resolve(
-        &self,
-        ipfs: &Ipfs,
-        did: &DID,
+    /// Marks the poll as closed using the signature provided by the sender
+    pub fn set_poll_closed(
+        &mut self,
+        date: DateTime<Utc>,
+        signature: Vec<u8>,
+    ) -> Result<(), Error> {
+        if self.poll.is_none() {
+            return Err(Error::InvalidMessage);
+        }
+
+        let mut document = *self;
+        document.poll_closed = Some(date);
+        document.signature = Some(MessageSignature::try_from(signature)?);
+
+        if !document.verify() {
+            return Err(Error::InvalidSignature);
+        }
+
+        *self = document;
+        Ok(())
+    }
+
+    /// Replaces the previews of the links within the message and signs the document.
+    /// Only the sender is able to set the previews
+    pub async fn set_embeds(
+        &mut self,
+        ipfs: &Ipfs,
+        did: &DID,
+        embeds: &[EmbedDocument],
+        key: Either<&DID, &Keystore>,
+    ) -> Result<(), Error> {
+        let sender = self.sender.to_did();
+
+        if !sender.eq(did) {
+            return Err(Error::InvalidMessage);
+        }
+
+        self.embeds = match embeds.is_empty() {
+            true => None,
+            false => {
+                let bytes = serde_json::to_vec(embeds)?;
+
+                let data = match key {
+                    Either::Right(keystore) => {
+                        let key = keystore.get_latest(did, &sender)?;
+                        Cipher::direct_encrypt(&bytes, &key)?
+                    }
+                    Either::Left(key) => ecdh_encrypt(did, Some(key), &bytes)?,
+                };
+
+                Some(ipfs.dag().put().serialize(data).await?)
+            }
+        };
+
+        *self = self.sign(did)?;
+        Ok(())
+    }
+
+    /// Replaces the previews of the links within the message using the signature provided by the sender
+    pub fn set_embeds_signed(
+        &mut self,
+        embeds: Option<Cid>,
+        signature: Vec<u8>,
+    ) -> Result<(), Error> {
+        let mut document = *self;
+        document.embeds = embeds;
+        document.signature = Some(MessageSignature::try_from(signature)?);
+
+        if !document.verify() {
+            return Err(Error::InvalidSignature);
+        }
+
+        *self = document;
+        Ok(())
+    }
+
+    /// Decrypts the previews of the links within the message
+    pub async fn embed_documents(
+        &self,
+        ipfs: &Ipfs,
+        did: &DID,
+        local: bool,
+        key: Either<&DID, &Keystore>,
+    ) -> Result<Vec<EmbedDocument>, Error> {
+        let Some(cid) = self.embeds else {
+            return Ok(vec![]);
+        };
+
+        let bytes: Vec<u8> = ipfs
+            .get_dag(cid)
+            .timeout(Duration::from_secs(10))
+            .set_local(local)
+            .deserialized()
+            .await?;
+
+        let sender = self.sender.to_did();
+
+        let data = match key {
+            Either::Left(exchange) => ecdh_decrypt(did, Some(exchange), &bytes)?,
+            Either::Right(keystore) => keystore.try_decrypt(did, &sender, &bytes)?,
+        };
+
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    async fn resolve_embeds(
+        &self,
+        ipfs: &Ipfs,
+        did: &DID,
+        local: bool,
+        key: Either<&DID, &Keystore>,
+    ) -> Result<Vec<Embed>, Error> {
+        let embeds = self.embed_documents(ipfs, did, local, key).await?;
+
+        Ok(FuturesOrdered::from_iter(
+            embeds
+                .iter()
+                .take(MAX_EMBEDS)
+                .map(|embed| embed.resolve(ipfs, local)),
+        )
+        .collect::<Vec<_>>()
+        .await)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update(
+        &mut self,
+        ipfs: &Ipfs,
+        did: &DID,
+        message: Message,
+        signature: Option<Vec<u8>>,
+        key: Either<&DID, &Keystore>,
+        nonce: Option<&[u8]>,
+        keep_history: bool,
+    ) -> Result<(), Error> {
+        tracing::info!(id = %self.conversation_id, message_id = %self.id, "Updating message");
+        let old_message = self.resolve(ipfs, did, true, key).await?;
+        let previous = *self;
+
+        let sender = self.sender.to_did();
+
+        if message.id() != self.id
+            || message.conversation_id() != self.conversation_id
+            || message.sender() != sender
+        {
+            tracing::error!(id = %self.conversation_id, message_id = %self.id, "Message does not exist, is invalid or has invalid sender");
+            //TODO: Maybe remove message from this point?
+            return Err(Error::InvalidMessage);
+        }
+
+        self.pinned = message.pinned();
+        self.modified = message.modified();
+
+        let reactions = message.reactions();
+
+        self.reactions =
+            (!reactions.is_empty()).then_some(ipfs.dag().put().serialize(reactions).await?);
+
+        let now = Utc::now();
+
+        if self.pinned != previous.pinned {
+            self.pinned_modified = Some(now);
+        }
+
+        if self.reactions != previous.reactions {
+            self.reactions_modified = Some(now);
+        }
+
+        if message.lines() != old_message.lines() {
+            let lines = message.lines();
+            if !lines.is_empty() {
+                let lines_value_length: usize = lines
+                    .iter()
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.trim())
+                    .map(|s| s.chars().count())
+                    .sum();
+
+                if lines_value_length > MAX_MESSAGE_SIZE {
+                    return Err(Error::InvalidLength {
+                        context: "message".into(),
+                        current: lines_value_length,
+                        minimum: None,
+                        maximum: Some(MAX_MESSAGE_SIZE),
+                    });
+                }
+            }
+
+            let current_nonce = self.nonce_from_message(ipfs).await?;
+
+            if matches!(nonce, Some(nonce) if nonce.eq(&current_nonce)) {
+                // Since the nonce from the current message matches the new one sent,
+                // we would consider this as an invalid message as a nonce should
+                // NOT be reused
+                // TODO: Maybe track previous nonces?
+                return Err(Error::InvalidMessage);
+            }
+
+            let bytes = serde_json::to_vec(&lines)?;
+
+            let data = match (key, nonce) {
+                (Either::Right(keystore), Some(nonce)) => {
+                    let key = keystore.get_latest(did, &sender)?;
+                    Cipher::direct_encrypt_with_nonce(&bytes, &key, nonce)?
+                }
+                (Either::Left(key), Some(nonce)) => {
+                    ecdh_encrypt_with_nonce(did, Some(key), &bytes, nonce)?
+                }
+                (Either::Right(keystore), None) => {
+                    let key = keystore.get_latest(did, &sender)?;
+                    Cipher::direct_encrypt(&bytes, &key)?
+                }
+                (Either::Left(key), None) => ecdh_encrypt(did, Some(key), &bytes)?,
+            };
+
+            let message = ipfs.dag().put().serialize(data).await?;
+
+            if keep_history {
+                self.push_revision(ipfs, &previous).await?;
+            }
+
+            self.message.replace(message);
+
+            match (sender.eq(did), signature) {
+                (true, None) => {
+                    *self = self.sign(did)?;
+                }
+                (false, None) | (true, Some(_)) => return Err(Error::InvalidMessage),
+                (false, Some(sig)) => {
+                    let new_signature = MessageSignature::try_from(sig)?;
+                    self.signature.replace(new_signature);
+                    if !self.verify() {
+                        return Err(Error::InvalidSignature);
+                    }
+                }
+            };
+        }
+
+        tracing::info!(id = %self.conversation_id, message_id = %self.id, "Message is updated");
+        Ok(())
+    }
+
+    /// Merge the document of the same message from another device, returning true if the document changed.
+    ///
+    /// The signed contents are taken from the latest revision of the two, while the reactions and the pin
+    /// are each taken from the device that changed them last and the votes are merged per voter
+    pub async fn merge(&mut self, ipfs: &Ipfs, other: &MessageDocument) -> Result<bool, Error> {
+        if self.id != other.id
+            || self.conversation_id != other.conversation_id
+            || self.sender != other.sender
+            || !other.verify()
+        {
+            return Ok(false);
+        }
+
+        let revision = |document: &MessageDocument| {
+            (
+                document.modified,
+                document.poll_closed,
+                document.embeds.is_some(),
+            )
+        };
+
+        let mut document = *self;
+
+        if revision(other) > revision(self) {
+            document = MessageDocument {
+                reactions: self.reactions,
+                reactions_modified: self.reactions_modified,
+                pinned: self.pinned,
+                pinned_modified: self.pinned_modified,
+                votes: self.votes,
+                ..*other
+            };
+        }
+
+        if other.reactions_modified > document.reactions_modified {
+            document.reactions = other.reactions;
+            document.reactions_modified = other.reactions_modified;
+        }
+
+        if other.pinned_modified > document.pinned_modified {
+            document.pinned = other.pinned;
+            document.pinned_modified = other.pinned_modified;
+        }
+
+        if other.votes.is_some() && other.votes != document.votes {
+            let mut votes = document.poll_votes(ipfs, true).await;
+            let mut votes_changed = false;
+
+            for vote in other.poll_votes(ipfs, false).await {
+                match votes.iter().position(|item| item.voter == vote.voter) {
+                    Some(index) if votes[index].date >= vote.date => continue,
+                    Some(index) => votes[index] = vote,
+                    None => votes.push(vote),
+                }
+                votes_changed = true;
+            }
+
+            if votes_changed {
+                document.votes = Some(ipfs.dag().put().serialize(votes).await?);
+            }
+        }
+
+        if document == *self {
+            return Ok(false);
+        }
+
+        *self = document;
+        Ok(true)
+    }
+
+    /// Stores the signed contents of the document being replaced as a revision, linking it to the previous one
+    async fn push_revision(
+        &mut self,
+        ipfs: &Ipfs,
+        previous: &MessageDocument,
+    ) -> Result<(), Error> {
+        let (Some(message), Some(signature)) = (previous.message, previous.signature) else {
+            return Err(Error::InvalidMessage);
+        };
+
+        if !previous.verify() {
+            return Err(Error::InvalidSignature);
+        }
+
+        let revision = MessageRevisionDocument {
+            message,
+            modified: previous.modified,
+            embeds: previous.embeds,
+            previous: previous.history,
+            signature,
+        };
+
+        self.history = Some(ipfs.dag().put().serialize(revision).await?);
+        Ok(())
+    }
+
+    /// Checks that the latest revision of the message holds the contents of the previous document
+    pub async fn links_to(&self, ipfs: &Ipfs, previous: &MessageDocument) -> bool {
+        let Some(cid) = self.history else {
+            return false;
+        };
+
+        if previous.history == Some(cid) {
+            return false;
+        }
+
+        let Ok(revision) = ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized::<MessageRevisionDocument>()
+            .await
+        else {
+            return false;
+        };
+
+        previous.message == Some(revision.message)
+            && previous.signature == Some(revision.signature)
+            && previous.history == revision.previous
+            && self.verify_revision(&revision)
+    }
+
+    /// Previous revisions of the message, ordered from the oldest to the newest.
+    /// The chain stops at the first revision that cannot be found or is not signed by the sender
+    pub async fn revisions(&self, ipfs: &Ipfs, local: bool) -> Vec<MessageRevisionDocument> {
+        let mut revisions = vec![];
+        let mut next = self.history;
+
+        while let Some(cid) = next {
+            let revision = match ipfs
+                .get_dag(cid)
+                .timeout(Duration::from_secs(10))
+                .set_local(local)
+                .deserialized::<MessageRevisionDocument>()
+                .await
+            {
+                Ok(revision) => revision,
+                Err(e) => {
+                    tracing::warn!(id = %self.conversation_id, message_id = %self.id, %cid, error = %e, "unable to obtain revision");
+                    break;
+                }
+            };
+
+            if !self.verify_revision(&revision) {
+                tracing::warn!(id = %self.conversation_id, message_id = %self.id, %cid, "revision has an invalid signature");
+                break;
+            }
+
+            next = revision.previous;
+            revisions.push(revision);
+        }
+
+        revisions.reverse();
+        revisions
+    }
+
+    /// Verify that the revision was signed by the sender as part of this message
+    fn verify_revision(&self, revision: &MessageRevisionDocument) -> bool {
+        let mut document = *self;
+        document.message = Some(revision.message);
+        document.modified = revision.modified;
+        document.embeds = revision.embeds;
+        document.history = revision.previous;
+        document.signature = Some(revision.signature);
+        document.verify()
+    }
+
+    /// Decrypts the contents of the previous revisions of the message
+    pub async fn resolve_revisions(
+        &self,
+        ipfs: &Ipfs,
+        did: &DID,
+        local: bool,
+        key: Either<&DID, &Keystore>,
+    ) -> Result<Vec<MessageRevision>, Error> {
+        let sender = self.sender.to_did();
+        let mut revisions = vec![];
+
+        for revision in self.revisions(ipfs, local).await {
+            let bytes: Vec<u8> = ipfs
+                .get_dag(revision.message)
+                .timeout(Duration::from_secs(10))
+                .set_local(local)
+                .deserialized()
+                .await?;
+
+            let data = match key {
+                Either::Left(exchange) => ecdh_decrypt(did, Some(exchange), &bytes)?,
+                Either::Right(keystore) => keystore.try_decrypt(did, &sender, &bytes)?,
+            };
+
+            let lines: Vec<String> = serde_json::from_slice(&data)?;
+
+            revisions.push(MessageRevision::new(
+                lines,
+                revision.modified.unwrap_or(self.date),
+            ));
+        }
+
+        Ok(revisions)
+    }
+
+    pub async fn resolve(
+        &self,
+        ipfs: &Ipfs,
+        did: &DID,
         local: bool,
         key: Either<&DID, &Keystore>,
     ) -> Result<Message, Error> {
@@ -1001,6 +1728,7 @@ impl MessageDocument {
         }
         message.set_pinned(self.pinned);
         message.set_replied(self.replied);
+        message.set_thread(self.thread);
 
         if let Some(cid) = self.attachments {
             let attachments: Vec<FileAttachmentDocument> = ipfs
@@ -1053,6 +1781,43 @@ impl MessageDocument {
 
         let sender = self.sender.to_did();
 
+        if let Some(cid) = self.poll {
+            let bytes: Vec<u8> = ipfs
+                .get_dag(cid)
+                .timeout(Duration::from_secs(10))
+                .set_local(local)
+                .deserialized()
+                .await?;
+
+            let data = match key {
+                Either::Left(exchange) => ecdh_decrypt(did, Some(exchange), &bytes)?,
+                Either::Right(keystore) => keystore.try_decrypt(did, &sender, &bytes)?,
+            };
+
+            let definition: PollDefinition = serde_json::from_slice(&data)?;
+
+            let mut poll = Poll::from(definition);
+            poll.set_closed(self.poll_closed);
+            poll.set_votes(
+                self.poll_votes(ipfs, local)
+                    .await
+                    .into_iter()
+                    .filter(|vote| !vote.choices.is_empty())
+                    .map(|vote| (vote.voter.to_did(), vote.choices))
+                    .collect(),
+            );
+
+            message.set_poll(Some(poll));
+        }
+
+        // previews are not part of the contents of the message so they are skipped if they cannot be resolved
+        match self.resolve_embeds(ipfs, did, local, key).await {
+            Ok(embeds) => message.set_embeds(embeds),
+            Err(e) => {
+                tracing::warn!(id = %self.conversation_id, message_id = %self.id, error = %e, "unable to resolve previews of message")
+            }
+        }
+
         let data = match key {
             Either::Left(exchange) => ecdh_decrypt(did, Some(exchange), &bytes)?,
             Either::Right(keystore) => keystore.try_decrypt(did, &sender, &bytes)?,
@@ -1067,164 +1832,702 @@ impl MessageDocument {
             .map(|s| s.chars().count())
             .sum();
 
-        if lines_value_length == 0 && lines_value_length > MAX_MESSAGE_SIZE {
-            return Err(Error::InvalidLength {
-                context: "message".into(),
-                current: lines_value_length,
-                minimum: Some(MIN_MESSAGE_SIZE),
-                maximum: Some(MAX_MESSAGE_SIZE),
-            });
-        }
+        if lines_value_length == 0 && lines_value_length > MAX_MESSAGE_SIZE {
+            return Err(Error::InvalidLength {
+                context: "message".into(),
+                current: lines_value_length,
+                minimum: Some(MIN_MESSAGE_SIZE),
+                maximum: Some(MAX_MESSAGE_SIZE),
+            });
+        }
+
+        message.set_lines(lines);
+
+        Ok(message)
+    }
+
+    /// Hash of the signed fields of the document. Every field is prefixed with its tag, whether it is set and
+    /// its length, so the value of a field cannot be moved into another field without invalidating the signature
+    fn hash(&self) -> Vec<u8> {
+        let sender = self.sender.to_did();
+        let fields = [
+            Some(self.conversation_id.as_bytes().to_vec()),
+            Some(self.id.as_bytes().to_vec()),
+            Some(sender.public_key_bytes()),
+            Some(self.date.to_string().into_bytes()),
+            self.modified.map(|time| time.to_string().into_bytes()),
+            self.replied.map(|id| id.as_bytes().to_vec()),
+            self.thread.map(|id| id.as_bytes().to_vec()),
+            self.attachments.map(|cid| cid.to_bytes()),
+            self.message.map(|cid| cid.to_bytes()),
+            self.poll.map(|cid| cid.to_bytes()),
+            self.poll_closed.map(|time| time.to_string().into_bytes()),
+            self.history.map(|cid| cid.to_bytes()),
+            self.embeds.map(|cid| cid.to_bytes()),
+            serde_json::to_vec(&self.message_type).ok(),
+        ];
+
+        sha256_iter(
+            fields.into_iter().enumerate().map(|(tag, field)| {
+                let mut bytes = vec![tag as u8, field.is_some() as u8];
+                if let Some(field) = field {
+                    bytes.extend((field.len() as u32).to_be_bytes());
+                    bytes.extend(field);
+                }
+                Some(bytes)
+            }),
+            None,
+        )
+    }
+
+    fn sign(mut self, keypair: &DID) -> Result<MessageDocument, Error> {
+        let sender = self.sender.to_did();
+        if !sender.eq(keypair) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let signature = keypair.sign(&self.hash());
+
+        self.signature = Some(MessageSignature::try_from(signature)?);
+        Ok(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DIDEd25519Reference([u8; 32]);
+
+impl From<DID> for DIDEd25519Reference {
+    fn from(value: DID) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl From<&DID> for DIDEd25519Reference {
+    fn from(value: &DID) -> Self {
+        Self::from_did(value)
+    }
+}
+
+impl From<DIDEd25519Reference> for DID {
+    fn from(value: DIDEd25519Reference) -> Self {
+        value.to_did()
+    }
+}
+
+impl DIDEd25519Reference {
+    pub fn from_did(did: &DID) -> Self {
+        let mut pubkey_bytes: [u8; 32] = [0u8; 32];
+        pubkey_bytes.copy_from_slice(&did.public_key_bytes());
+        Self(pubkey_bytes)
+    }
+
+    pub fn to_did(self) -> DID {
+        DIDKey::Ed25519(Ed25519KeyPair::from_public_key(&self.0)).into()
+    }
+}
+
+impl Serialize for DIDEd25519Reference {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let did = self.to_did();
+        serializer.serialize_str(&did.to_string())
+    }
+}
+
+impl<'d> Deserialize<'d> for DIDEd25519Reference {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'d>,
+    {
+        let did_str = <String>::deserialize(deserializer)?;
+        let did = DID::try_from(did_str).map_err(serde::de::Error::custom)?;
+        Ok(did.into())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MessageSignature([u8; 64]);
+
+impl TryFrom<Vec<u8>> for MessageSignature {
+    type Error = anyhow::Error;
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        let signature = Self(bytes[..].try_into()?);
+        Ok(signature)
+    }
+}
+
+impl From<[u8; 64]> for MessageSignature {
+    fn from(signature: [u8; 64]) -> Self {
+        MessageSignature(signature)
+    }
+}
+
+impl AsRef<[u8]> for MessageSignature {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl From<MessageSignature> for Vec<u8> {
+    fn from(sig: MessageSignature) -> Self {
+        sig.0.to_vec()
+    }
+}
+
+impl Serialize for MessageSignature {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let signature = bs58::encode(self).into_string();
+        serializer.serialize_str(&signature)
+    }
+}
+
+impl<'d> Deserialize<'d> for MessageSignature {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'d>,
+    {
+        let sig = <String>::deserialize(deserializer)?;
+        let bytes = bs58::decode(sig)
+            .into_vec()
+            .map_err(serde::de::Error::custom)?;
+
+        Self::try_from(bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+const REFERENCE_LENGTH: usize = 500;
+
+#[derive(Default, Debug, Serialize, Deserialize, Copy, Clone)]
+pub struct MessageReferenceList {
+    pub messages: Option<Cid>,
+    pub next: Option<Cid>,
+    /// Map of the root message of a thread to its replies. Only used by the first list in the chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<Cid>,
+    /// Map of the members to their latest receipts. Only used by the first list in the chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipts: Option<Cid>,
+    /// Map of the deleted messages to the date they were deleted, so a deleted message is not restored when
+    /// merging the list of another device. Only used by the first list in the chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<Cid>,
+}
+
+/// Content of a poll that is encrypted alongside the message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct PollDefinition {
+    question: String,
+    options: Vec<String>,
+    multiple_choice: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    close_at: Option<DateTime<Utc>>,
+}
+
+impl From<&Poll> for PollDefinition {
+    fn from(poll: &Poll) -> Self {
+        PollDefinition {
+            question: poll.question(),
+            options: poll.options(),
+            multiple_choice: poll.multiple_choice(),
+            close_at: poll.close_at(),
+        }
+    }
+}
+
+impl From<PollDefinition> for Poll {
+    fn from(definition: PollDefinition) -> Self {
+        let mut poll = Poll::new(definition.question, definition.options);
+        poll.set_multiple_choice(definition.multiple_choice);
+        poll.set_close_at(definition.close_at);
+        poll
+    }
+}
+
+/// Previous contents of a message, along with the signature of the message document
+/// at the time so the revision can be verified against the sender
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MessageRevisionDocument {
+    /// Encrypted contents of the revision
+    pub message: Cid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    /// Previews of the message at the time of the revision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embeds: Option<Cid>,
+    /// Revision prior to this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Cid>,
+    pub signature: MessageSignature,
+}
+
+/// Signed vote from a member on a poll. An empty list of choices represents a retracted vote
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PollVoteDocument {
+    pub conversation_id: Uuid,
+    pub message_id: Uuid,
+    pub voter: DIDEd25519Reference,
+    pub choices: Vec<usize>,
+    pub date: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<MessageSignature>,
+}
+
+impl PollVoteDocument {
+    pub fn new(
+        keypair: &DID,
+        message: &MessageDocument,
+        choices: Vec<usize>,
+    ) -> Result<Self, Error> {
+        let mut document = PollVoteDocument {
+            conversation_id: message.conversation_id,
+            message_id: message.id,
+            voter: DIDEd25519Reference::from_did(keypair),
+            choices,
+            date: Utc::now(),
+            signature: None,
+        };
+
+        let signature = keypair.sign(&document.hash());
+        document.signature = Some(MessageSignature::try_from(signature)?);
+        Ok(document)
+    }
+
+    pub fn verify(&self) -> bool {
+        let Some(signature) = self.signature else {
+            return false;
+        };
+
+        self.voter
+            .to_did()
+            .verify(&self.hash(), signature.as_ref())
+            .is_ok()
+    }
+
+    fn hash(&self) -> Vec<u8> {
+        sha256_iter(
+            [
+                Some(self.conversation_id.as_bytes().to_vec()),
+                Some(self.message_id.as_bytes().to_vec()),
+                Some(self.voter.to_did().public_key_bytes()),
+                Some(
+                    self.choices
+                        .iter()
+                        .flat_map(|choice| (*choice as u64).to_le_bytes())
+                        .collect(),
+                ),
+                Some(self.date.to_string().into_bytes()),
+            ]
+            .into_iter(),
+            None,
+        )
+    }
+}
+
+/// Signed assignment of a role to a member of a group conversation by another member
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoleDocument {
+    pub conversation_id: Uuid,
+    pub member: DIDEd25519Reference,
+    pub role: GroupRole,
+    pub assigner: DIDEd25519Reference,
+    pub date: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<MessageSignature>,
+}
+
+impl RoleDocument {
+    pub fn new(
+        keypair: &DID,
+        conversation_id: Uuid,
+        member: &DID,
+        role: GroupRole,
+    ) -> Result<Self, Error> {
+        let mut document = RoleDocument {
+            conversation_id,
+            member: DIDEd25519Reference::from_did(member),
+            role,
+            assigner: DIDEd25519Reference::from_did(keypair),
+            date: Utc::now(),
+            signature: None,
+        };
+
+        let signature = keypair.sign(&document.hash());
+        document.signature = Some(MessageSignature::try_from(signature)?);
+        Ok(document)
+    }
+
+    pub fn verify(&self) -> bool {
+        let Some(signature) = self.signature else {
+            return false;
+        };
+
+        self.assigner
+            .to_did()
+            .verify(&self.hash(), signature.as_ref())
+            .is_ok()
+    }
+
+    fn hash(&self) -> Vec<u8> {
+        sha256_iter(
+            [
+                Some(self.conversation_id.as_bytes().to_vec()),
+                Some(self.member.to_did().public_key_bytes()),
+                Some(self.role.to_string().into_bytes()),
+                Some(self.assigner.to_did().public_key_bytes()),
+                Some(self.date.to_string().into_bytes()),
+            ]
+            .into_iter(),
+            None,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptKind {
+    Delivered,
+    Read,
+}
+
+/// Signed marker stating that a member has received or read every message up to, and including, `message_id`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReceiptDocument {
+    pub conversation_id: Uuid,
+    pub member: DIDEd25519Reference,
+    pub kind: ReceiptKind,
+    pub message_id: Uuid,
+    pub date: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<MessageSignature>,
+}
+
+impl ReceiptDocument {
+    pub fn new(keypair: &DID, kind: ReceiptKind, message: &MessageDocument) -> Result<Self, Error> {
+        let mut document = ReceiptDocument {
+            conversation_id: message.conversation_id,
+            member: DIDEd25519Reference::from_did(keypair),
+            kind,
+            message_id: message.id,
+            date: message.date,
+            signature: None,
+        };
 
-        message.set_lines(lines);
+        let signature = keypair.sign(&document.hash());
+        document.signature = Some(MessageSignature::try_from(signature)?);
+        Ok(document)
+    }
 
-        Ok(message)
+    pub fn verify(&self) -> bool {
+        let Some(signature) = self.signature else {
+            return false;
+        };
+
+        self.member
+            .to_did()
+            .verify(&self.hash(), signature.as_ref())
+            .is_ok()
     }
 
-    fn sign(mut self, keypair: &DID) -> Result<MessageDocument, Error> {
-        let sender = self.sender.to_did();
-        if !sender.eq(keypair) {
-            return Err(Error::PublicKeyInvalid);
-        }
+    fn hash(&self) -> Vec<u8> {
+        let kind: &[u8] = match self.kind {
+            ReceiptKind::Delivered => b"delivered",
+            ReceiptKind::Read => b"read",
+        };
 
-        let hash = sha256_iter(
+        sha256_iter(
             [
                 Some(self.conversation_id.as_bytes().to_vec()),
-                Some(self.id.as_bytes().to_vec()),
-                Some(sender.public_key_bytes()),
+                Some(self.member.to_did().public_key_bytes()),
+                Some(kind.to_vec()),
+                Some(self.message_id.as_bytes().to_vec()),
                 Some(self.date.to_string().into_bytes()),
-                self.modified.map(|time| time.to_string().into_bytes()),
-                self.replied.map(|id| id.as_bytes().to_vec()),
-                self.attachments.map(|cid| cid.to_bytes()),
-                self.message.map(|cid| cid.to_bytes()),
             ]
             .into_iter(),
             None,
-        );
-
-        let signature = keypair.sign(&hash);
-
-        self.signature = Some(MessageSignature::try_from(signature)?);
-        Ok(self)
+        )
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct DIDEd25519Reference([u8; 32]);
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MemberReceipts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivered: Option<ReceiptDocument>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<ReceiptDocument>,
+}
 
-impl From<DID> for DIDEd25519Reference {
-    fn from(value: DID) -> Self {
-        Self::from(&value)
+impl MemberReceipts {
+    /// Store the receipt if it is newer than the current receipt of the same kind
+    pub fn insert(&mut self, receipt: ReceiptDocument) -> bool {
+        let current = match receipt.kind {
+            ReceiptKind::Delivered => &mut self.delivered,
+            ReceiptKind::Read => &mut self.read,
+        };
+
+        if matches!(current, Some(current) if current.date >= receipt.date) {
+            return false;
+        }
+
+        current.replace(receipt);
+        true
     }
-}
 
-impl From<&DID> for DIDEd25519Reference {
-    fn from(value: &DID) -> Self {
-        Self::from_did(value)
+    /// State of a message sent at the given date. A message that was read is also considered delivered
+    pub fn status(&self, date: DateTime<Utc>) -> Option<MessageStatus> {
+        if matches!(self.read, Some(receipt) if receipt.date >= date) {
+            return Some(MessageStatus::Read);
+        }
+
+        if matches!(self.delivered, Some(receipt) if receipt.date >= date) {
+            return Some(MessageStatus::Delivered);
+        }
+
+        None
     }
 }
 
-impl From<DIDEd25519Reference> for DID {
-    fn from(value: DIDEd25519Reference) -> Self {
-        value.to_did()
-    }
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThreadReference {
+    pub replies: BTreeMap<String, DateTime<Utc>>,
 }
 
-impl DIDEd25519Reference {
-    pub fn from_did(did: &DID) -> Self {
-        let mut pubkey_bytes: [u8; 32] = [0u8; 32];
-        pubkey_bytes.copy_from_slice(&did.public_key_bytes());
-        Self(pubkey_bytes)
+impl ThreadReference {
+    pub fn count(&self) -> usize {
+        self.replies.len()
     }
 
-    pub fn to_did(self) -> DID {
-        DIDKey::Ed25519(Ed25519KeyPair::from_public_key(&self.0)).into()
+    pub fn last_reply(&self) -> Option<DateTime<Utc>> {
+        self.replies.values().max().copied()
     }
 }
 
-impl Serialize for DIDEd25519Reference {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        let did = self.to_did();
-        serializer.serialize_str(&did.to_string())
+impl MessageReferenceList {
+    pub async fn receipts(&self, ipfs: &Ipfs) -> BTreeMap<String, MemberReceipts> {
+        let Some(cid) = self.receipts else {
+            return BTreeMap::new();
+        };
+
+        ipfs.get_dag(cid)
+            .timeout(Duration::from_secs(10))
+            .deserialized()
+            .await
+            .unwrap_or_default()
     }
-}
 
-impl<'d> Deserialize<'d> for DIDEd25519Reference {
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
-    where
-        D: Deserializer<'d>,
-    {
-        let did_str = <String>::deserialize(deserializer)?;
-        let did = DID::try_from(did_str).map_err(serde::de::Error::custom)?;
-        Ok(did.into())
+    /// Store the receipt, returning true if it is newer than the current receipt of the member
+    pub async fn insert_receipt(
+        &mut self,
+        ipfs: &Ipfs,
+        receipt: ReceiptDocument,
+    ) -> Result<bool, Error> {
+        let mut receipts = self.receipts(ipfs).await;
+
+        if !receipts
+            .entry(receipt.member.to_did().to_string())
+            .or_default()
+            .insert(receipt)
+        {
+            return Ok(false);
+        }
+
+        let cid = ipfs.dag().put().serialize(receipts).await?;
+        self.receipts.replace(cid);
+        Ok(true)
     }
-}
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct MessageSignature([u8; 64]);
+    pub async fn deleted(&self, ipfs: &Ipfs) -> BTreeMap<String, DateTime<Utc>> {
+        let Some(cid) = self.deleted else {
+            return BTreeMap::new();
+        };
 
-impl TryFrom<Vec<u8>> for MessageSignature {
-    type Error = anyhow::Error;
-    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
-        let signature = Self(bytes[..].try_into()?);
-        Ok(signature)
+        ipfs.get_dag(cid)
+            .timeout(Duration::from_secs(10))
+            .deserialized()
+            .await
+            .unwrap_or_default()
     }
-}
 
-impl From<[u8; 64]> for MessageSignature {
-    fn from(signature: [u8; 64]) -> Self {
-        MessageSignature(signature)
+    async fn set_deleted(
+        &mut self,
+        ipfs: &Ipfs,
+        deleted: BTreeMap<String, DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        let cid = ipfs.dag().put().serialize(deleted).await?;
+        self.deleted.replace(cid);
+        Ok(())
     }
-}
 
-impl AsRef<[u8]> for MessageSignature {
-    fn as_ref(&self) -> &[u8] {
-        &self.0[..]
+    pub async fn insert_deleted(&mut self, ipfs: &Ipfs, message_id: Uuid) -> Result<(), Error> {
+        let mut deleted = self.deleted(ipfs).await;
+        deleted.insert(message_id.to_string(), Utc::now());
+        self.set_deleted(ipfs, deleted).await
     }
-}
 
-impl From<MessageSignature> for Vec<u8> {
-    fn from(sig: MessageSignature) -> Self {
-        sig.0.to_vec()
+    /// Merge the list from another device, returning true if the list changed.
+    ///
+    /// Messages missing from this list are inserted and messages modified more recently on the other device
+    /// replace the current document, while messages deleted on either device are removed. Receipts are merged
+    /// so the latest receipt of each member is kept
+    pub async fn merge(
+        &mut self,
+        ipfs: &Ipfs,
+        other: &MessageReferenceList,
+    ) -> Result<bool, Error> {
+        let mut changed = false;
+
+        let mut deleted = self.deleted(ipfs).await;
+        let mut newly_deleted = vec![];
+
+        for (id, date) in other.deleted(ipfs).await {
+            if deleted.contains_key(&id) {
+                continue;
+            }
+            deleted.insert(id.clone(), date);
+            newly_deleted.push(id);
+        }
+
+        if !newly_deleted.is_empty() {
+            self.set_deleted(ipfs, deleted.clone()).await?;
+            changed = true;
+        }
+
+        let messages = other.list(ipfs).await.collect::<Vec<_>>().await;
+
+        for message in messages {
+            if deleted.contains_key(&message.id.to_string()) || !message.verify() {
+                continue;
+            }
+
+            match self.get(ipfs, message.id).await {
+                Ok(mut current) => {
+                    if !current.merge(ipfs, &message).await? {
+                        continue;
+                    }
+                    self.update(ipfs, current).await?;
+                }
+                Err(_) => {
+                    self.insert(ipfs, message).await?;
+                    if let Some(thread_id) = message.thread {
+                        self.insert_thread_reply(ipfs, thread_id, &message).await?;
+                    }
+                }
+            }
+
+            changed = true;
+        }
+
+        for id in newly_deleted {
+            let Ok(message_id) = id.parse::<Uuid>() else {
+                continue;
+            };
+
+            let Ok(message) = self.get(ipfs, message_id).await else {
+                continue;
+            };
+
+            self.remove(ipfs, message_id).await?;
+
+            if let Some(thread_id) = message.thread {
+                self.remove_thread_reply(ipfs, thread_id, message_id)
+                    .await?;
+            }
+
+            changed = true;
+        }
+
+        let mut receipts = self.receipts(ipfs).await;
+        let mut receipts_changed = false;
+
+        for (member, other_receipts) in other.receipts(ipfs).await {
+            let entry = receipts.entry(member).or_default();
+            for receipt in [other_receipts.delivered, other_receipts.read]
+                .into_iter()
+                .flatten()
+            {
+                receipts_changed |= entry.insert(receipt);
+            }
+        }
+
+        if receipts_changed {
+            let cid = ipfs.dag().put().serialize(receipts).await?;
+            self.receipts.replace(cid);
+            changed = true;
+        }
+
+        Ok(changed)
     }
-}
 
-impl Serialize for MessageSignature {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        let signature = bs58::encode(self).into_string();
-        serializer.serialize_str(&signature)
+    pub async fn threads(&self, ipfs: &Ipfs) -> BTreeMap<String, ThreadReference> {
+        let Some(cid) = self.threads else {
+            return BTreeMap::new();
+        };
+
+        ipfs.get_dag(cid)
+            .timeout(Duration::from_secs(10))
+            .deserialized()
+            .await
+            .unwrap_or_default()
     }
-}
 
-impl<'d> Deserialize<'d> for MessageSignature {
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
-    where
-        D: Deserializer<'d>,
-    {
-        let sig = <String>::deserialize(deserializer)?;
-        let bytes = bs58::decode(sig)
-            .into_vec()
-            .map_err(serde::de::Error::custom)?;
+    async fn set_threads(
+        &mut self,
+        ipfs: &Ipfs,
+        threads: BTreeMap<String, ThreadReference>,
+    ) -> Result<(), Error> {
+        match threads.is_empty() {
+            true => {
+                self.threads.take();
+            }
+            false => {
+                let cid = ipfs.dag().put().serialize(threads).await?;
+                self.threads.replace(cid);
+            }
+        }
+        Ok(())
+    }
 
-        Self::try_from(bytes).map_err(serde::de::Error::custom)
+    pub async fn insert_thread_reply(
+        &mut self,
+        ipfs: &Ipfs,
+        thread_id: Uuid,
+        message: &MessageDocument,
+    ) -> Result<(), Error> {
+        let mut threads = self.threads(ipfs).await;
+        threads
+            .entry(thread_id.to_string())
+            .or_default()
+            .replies
+            .insert(message.id.to_string(), message.date);
+        self.set_threads(ipfs, threads).await
     }
-}
 
-const REFERENCE_LENGTH: usize = 500;
+    pub async fn remove_thread_reply(
+        &mut self,
+        ipfs: &Ipfs,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), Error> {
+        let mut threads = self.threads(ipfs).await;
+        let id = thread_id.to_string();
 
-#[derive(Default, Debug, Serialize, Deserialize, Copy, Clone)]
-pub struct MessageReferenceList {
-    pub messages: Option<Cid>,
-    pub next: Option<Cid>,
-}
+        let Some(thread) = threads.get_mut(&id) else {
+            return Ok(());
+        };
+
+        thread.replies.remove(&message_id.to_string());
+
+        if thread.replies.is_empty() {
+            threads.remove(&id);
+        }
+
+        self.set_threads(ipfs, threads).await
+    }
 
-impl MessageReferenceList {
     #[async_recursion::async_recursion]
     pub async fn insert(&mut self, ipfs: &Ipfs, message: MessageDocument) -> Result<Cid, Error> {
         let mut list_refs = match self.messages {
@@ -1494,3 +2797,160 @@ impl MessageReferenceList {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ConversationDocument, MessageDocument, RoleDocument};
+    use either::Either;
+    use rust_ipfs::UninitializedIpfsNoop;
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+    use warp::{
+        crypto::DID,
+        raygun::{GroupRole, GroupSettings, Message},
+    };
+
+    #[test]
+    fn self_signed_ownership_is_ignored() -> anyhow::Result<()> {
+        let owner = DID::default();
+        let member = DID::default();
+
+        let mut document = ConversationDocument::new_group(
+            &owner,
+            None,
+            [member.clone()],
+            &[],
+            GroupSettings::default(),
+        )?;
+
+        let id = document.id();
+
+        // A member is unable to claim ownership or assign themselves a role
+        document
+            .ownership
+            .push(RoleDocument::new(&member, id, &member, GroupRole::Owner)?);
+        document
+            .roles
+            .push(RoleDocument::new(&member, id, &member, GroupRole::Admin)?);
+
+        assert_eq!(document.owner(), Some(owner.clone()));
+        assert_eq!(document.role(&member), GroupRole::Member);
+        assert!(document.sign(&member).is_err());
+
+        document.sign(&owner)?;
+        document.verify()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn ownership_follows_signed_transfers() -> anyhow::Result<()> {
+        let owner = DID::default();
+        let member = DID::default();
+        let other = DID::default();
+
+        let mut document = ConversationDocument::new_group(
+            &owner,
+            None,
+            [member.clone(), other.clone()],
+            &[],
+            GroupSettings::default(),
+        )?;
+
+        let id = document.id();
+
+        document
+            .ownership
+            .push(RoleDocument::new(&owner, id, &member, GroupRole::Owner)?);
+        document
+            .roles
+            .push(RoleDocument::new(&owner, id, &owner, GroupRole::Admin)?);
+
+        assert_eq!(document.owner(), Some(member.clone()));
+        assert_eq!(document.role(&owner), GroupRole::Admin);
+
+        // Transfers that are not signed by the owner at the time end the chain
+        document
+            .ownership
+            .push(RoleDocument::new(&other, id, &other, GroupRole::Owner)?);
+
+        assert_eq!(document.owner(), Some(member.clone()));
+        assert_eq!(document.role(&other), GroupRole::Member);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn merge_keeps_reactions_and_pins_of_both_devices() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfsNoop::new().start().await?;
+        let sender = DID::default();
+        let recipient = DID::default();
+        let key = Either::Left(&recipient);
+
+        let mut message = Message::default();
+        message.set_conversation_id(Uuid::new_v4());
+        message.set_sender(sender.clone());
+        message.set_lines(vec!["Hello, World".into()]);
+
+        let document = MessageDocument::new(&ipfs, &sender, message, key).await?;
+
+        // the message is pinned on one device while a reaction is added on the other
+        let mut device_a = document;
+        let mut message = device_a.resolve(&ipfs, &sender, true, key).await?;
+        message.set_pinned(true);
+        device_a
+            .update(&ipfs, &sender, message, None, key, None, false)
+            .await?;
+
+        let mut device_b = document;
+        let mut message = device_b.resolve(&ipfs, &sender, true, key).await?;
+        message.set_reactions(BTreeMap::from([("👍".into(), vec![recipient.clone()])]));
+        device_b
+            .update(&ipfs, &sender, message, None, key, None, false)
+            .await?;
+
+        let other = device_a;
+        assert!(device_a.merge(&ipfs, &device_b).await?);
+        assert!(device_b.merge(&ipfs, &other).await?);
+        assert_eq!(device_a, device_b);
+
+        let message = device_a.resolve(&ipfs, &sender, true, key).await?;
+        assert!(message.pinned());
+        assert_eq!(
+            message.reactions().get("👍"),
+            Some(&vec![recipient.clone()])
+        );
+
+        // merging again does not change anything
+        assert!(!device_a.merge(&ipfs, &device_b).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn signed_fields_cannot_be_swapped() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfsNoop::new().start().await?;
+        let sender = DID::default();
+        let recipient = DID::default();
+        let key = Either::Left(&recipient);
+
+        let mut message = Message::default();
+        message.set_conversation_id(Uuid::new_v4());
+        message.set_sender(sender.clone());
+        message.set_lines(vec!["Hello, World".into()]);
+        message.set_replied(Some(Uuid::new_v4()));
+
+        let document = MessageDocument::new(&ipfs, &sender, message, key).await?;
+        assert!(document.verify());
+
+        // a reply cannot be moved into a thread
+        let mut moved = document;
+        moved.thread = moved.replied.take();
+        assert!(!moved.verify());
+
+        // nor can the contents be moved into another slot
+        let mut moved = document;
+        moved.attachments = moved.message.take();
+        assert!(!moved.verify());
+        Ok(())
+    }
+}
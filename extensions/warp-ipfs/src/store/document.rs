@@ -1,8 +1,12 @@
 pub mod cache;
+pub mod device;
 pub mod files;
 pub mod identity;
 pub mod image_dag;
 pub mod root;
+pub mod rotation;
+pub mod share;
+pub mod transfer;
 
 use std::{collections::BTreeMap, path::Path, str::FromStr, time::Duration};
 
@@ -50,6 +54,10 @@ pub struct ResolvedRootDocument {
     pub file_index: Option<Directory>,
     pub request: Vec<u8>,
     pub conversation_keystore: BTreeMap<Uuid, Keystore>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drafts: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<u8>,
     pub signature: Option<Vec<u8>>,
 }
 
@@ -97,6 +105,21 @@ pub struct RootDocument {
     /// index to constellation
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file_index: Option<Cid>,
+    /// map of drafts for each conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drafts: Option<Cid>,
+    /// items shared with, or by, other identities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shares: Option<Cid>,
+    /// items removed from constellation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash: Option<Cid>,
+    /// uploads and downloads that have not completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfers: Option<Cid>,
+    /// array of devices linked to the identity (DeviceDocument)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub devices: Option<Cid>,
     /// Online/Away/Busy/Offline status
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<IdentityStatus>,
@@ -200,6 +223,28 @@ impl RootDocument {
             .await
             .unwrap_or_default();
 
+        let drafts = futures::future::ready(self.drafts.ok_or(Error::Other))
+            .and_then(|document| async move {
+                ipfs.get_dag(document)
+                    .local()
+                    .deserialized()
+                    .await
+                    .map_err(Error::from)
+            })
+            .await
+            .unwrap_or_default();
+
+        let devices = futures::future::ready(self.devices.ok_or(Error::Other))
+            .and_then(|document| async move {
+                ipfs.get_dag(document)
+                    .local()
+                    .deserialized()
+                    .await
+                    .map_err(Error::from)
+            })
+            .await
+            .unwrap_or_default();
+
         let conversation_keystore =
             futures::future::ready(self.conversations_keystore.ok_or(Error::Other))
                 .and_then(|document| async move {
@@ -244,6 +289,8 @@ impl RootDocument {
             request,
             file_index,
             conversation_keystore,
+            drafts,
+            devices,
             signature: None,
         };
 
@@ -301,6 +348,24 @@ impl RootDocument {
             })
             .await;
 
+        _ = futures::future::ready(self.drafts.ok_or(Error::Other))
+            .and_then(|document| async move {
+                ipfs.get_dag(document)
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .map_err(Error::from)
+            })
+            .await;
+
+        _ = futures::future::ready(self.devices.ok_or(Error::Other))
+            .and_then(|document| async move {
+                ipfs.get_dag(document)
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .map_err(Error::from)
+            })
+            .await;
+
         _ = futures::future::ready(self.conversations_keystore.ok_or(Error::Other))
             .and_then(|document| async move {
                 let map: BTreeMap<String, Cid> = ipfs.get_dag(document).deserialized().await?;
@@ -343,6 +408,11 @@ impl RootDocument {
             conversations: None,
             conversations_keystore: None,
             file_index: None,
+            drafts: None,
+            shares: None,
+            trash: None,
+            transfers: None,
+            devices: None,
             status: None,
             signature: None,
         };
@@ -352,6 +422,8 @@ impl RootDocument {
         let has_block_by_list = !data.block_by_list.is_empty();
         let has_requests = !data.request.is_empty();
         let has_keystore = !data.conversation_keystore.is_empty();
+        let has_drafts = !data.drafts.is_empty();
+        let has_devices = !data.devices.is_empty();
 
         if has_friends {
             root_document.friends = ipfs.dag().put().serialize(data.friends).await.ok();
@@ -369,6 +441,14 @@ impl RootDocument {
             root_document.request = ipfs.dag().put().serialize(data.request).await.ok();
         }
 
+        if has_drafts {
+            root_document.drafts = ipfs.dag().put().serialize(data.drafts).await.ok();
+        }
+
+        if has_devices {
+            root_document.devices = ipfs.dag().put().serialize(data.devices).await.ok();
+        }
+
         if has_keystore {
             let mut pointer_map: BTreeMap<String, Cid> = BTreeMap::new();
             for (k, v) in data.conversation_keystore {
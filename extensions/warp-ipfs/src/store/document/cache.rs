@@ -278,6 +278,7 @@ mod test {
             status_message: None,
             metadata: Default::default(),
             version: Default::default(),
+            recovery: None,
             signature: None,
         };
 
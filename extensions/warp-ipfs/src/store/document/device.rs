@@ -0,0 +1,574 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use warp::{
+    crypto::{did_key::CoreSign, hash::sha256_iter, zeroize::Zeroizing, DID},
+    error::Error,
+    multipass::identity::Device,
+};
+
+use crate::store::{ecdh_decrypt, ecdh_encrypt};
+
+/// Certificate authorizing a device to use and sync the identity.
+///
+/// The certificate is signed by the identity and by the device that issued it. The primary device issues its
+/// own certificate and is the only device able to certify or revoke other devices
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceDocument {
+    pub device: DID,
+    pub identity: DID,
+    pub issuer: DID,
+    pub name: String,
+    pub created: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer_signature: Option<String>,
+}
+
+impl DeviceDocument {
+    /// Certificate of the primary device, issued by the device itself
+    pub fn primary(keypair: &DID, device: &DID, name: &str) -> Result<Self, Error> {
+        Self::new(keypair, device, device, name)
+    }
+
+    /// Certificate of a device, issued by the primary device
+    pub fn new(keypair: &DID, issuer: &DID, device: &DID, name: &str) -> Result<Self, Error> {
+        let document = DeviceDocument {
+            device: device.clone(),
+            identity: keypair.clone(),
+            issuer: issuer.clone(),
+            name: name.to_string(),
+            created: Utc::now(),
+            signature: None,
+            issuer_signature: None,
+        };
+
+        document.sign(keypair, issuer)
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.issuer.eq(&self.device)
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        let hash = self.hash();
+
+        let signature = self.signature.as_ref().ok_or(Error::InvalidSignature)?;
+        let signature = bs58::decode(signature).into_vec()?;
+
+        self.identity
+            .verify(&hash, &signature)
+            .map_err(|_| Error::InvalidSignature)?;
+
+        let signature = self
+            .issuer_signature
+            .as_ref()
+            .ok_or(Error::InvalidSignature)?;
+        let signature = bs58::decode(signature).into_vec()?;
+
+        self.issuer
+            .verify(&hash, &signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    fn sign(mut self, keypair: &DID, issuer: &DID) -> Result<Self, Error> {
+        if self.identity.ne(keypair) || self.issuer.ne(issuer) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let hash = self.hash();
+        self.signature = Some(bs58::encode(keypair.sign(&hash)).into_string());
+        self.issuer_signature = Some(bs58::encode(issuer.sign(&hash)).into_string());
+        Ok(self)
+    }
+
+    fn hash(&self) -> Vec<u8> {
+        sha256_iter(
+            [
+                Some(self.device.public_key_bytes()),
+                Some(self.identity.public_key_bytes()),
+                Some(self.issuer.public_key_bytes()),
+                Some(self.name.as_bytes().to_vec()),
+                Some(self.created.to_string().into_bytes()),
+            ]
+            .into_iter(),
+            None,
+        )
+    }
+}
+
+impl From<&DeviceDocument> for Device {
+    fn from(document: &DeviceDocument) -> Self {
+        Device::new(
+            document.device.clone(),
+            document.name.clone(),
+            document.created,
+        )
+    }
+}
+
+/// Revocation of a device, issued by the primary device of the identity
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceRevocation {
+    pub device: DID,
+    pub identity: DID,
+    pub issuer: DID,
+    pub created: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl DeviceRevocation {
+    pub fn new(identity: &DID, issuer: &DID, device: &DID) -> Result<Self, Error> {
+        let revocation = DeviceRevocation {
+            device: device.clone(),
+            identity: identity.clone(),
+            issuer: issuer.clone(),
+            created: Utc::now(),
+            signature: None,
+        };
+
+        revocation.sign(issuer)
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        let signature = self.signature.as_ref().ok_or(Error::InvalidSignature)?;
+        let signature = bs58::decode(signature).into_vec()?;
+
+        self.issuer
+            .verify(&self.hash(), &signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    fn sign(mut self, issuer: &DID) -> Result<Self, Error> {
+        if self.issuer.ne(issuer) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let signature = issuer.sign(&self.hash());
+        self.signature = Some(bs58::encode(signature).into_string());
+        Ok(self)
+    }
+
+    fn hash(&self) -> Vec<u8> {
+        sha256_iter(
+            [
+                Some(b"revoke".to_vec()),
+                Some(self.device.public_key_bytes()),
+                Some(self.identity.public_key_bytes()),
+                Some(self.issuer.public_key_bytes()),
+                Some(self.created.to_string().into_bytes()),
+            ]
+            .into_iter(),
+            None,
+        )
+    }
+}
+
+/// Devices linked to an identity, along with the devices that were revoked.
+///
+/// Revoked devices are kept so merging the list from another device cannot link them again. Certificates and
+/// revocations are only accepted when issued by the primary device, whose key is never handed to linked devices,
+/// so a linked device is unable to link, revoke or take the place of another device.
+///
+/// Note: Linked devices hold the keypair of the identity, which is needed to decrypt and sign as the identity.
+/// Revoking a device stops it from syncing and rotates the keys of group conversations, but a device that kept
+/// a copy of the keypair can only be cut off entirely by rotating the identity to a new key
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceList {
+    #[serde(default)]
+    pub devices: Vec<DeviceDocument>,
+    #[serde(default)]
+    pub revoked: Vec<DeviceRevocation>,
+}
+
+impl DeviceList {
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty() && self.revoked.is_empty()
+    }
+
+    pub fn contains(&self, device: &DID) -> bool {
+        self.devices
+            .iter()
+            .any(|document| document.device.eq(device))
+    }
+
+    pub fn is_revoked(&self, device: &DID) -> bool {
+        self.revoked
+            .iter()
+            .any(|revocation| revocation.device.eq(device))
+    }
+
+    /// Certificate of the primary device
+    pub fn primary(&self) -> Option<&DeviceDocument> {
+        self.devices.iter().find(|document| document.is_primary())
+    }
+
+    pub fn is_primary(&self, device: &DID) -> bool {
+        self.primary()
+            .map(|document| document.device.eq(device))
+            .unwrap_or_default()
+    }
+
+    /// Returns false if the device is already linked, was revoked or was not issued by the primary device.
+    /// The certificate of the primary device is only accepted while the list does not have one
+    pub fn insert(&mut self, document: DeviceDocument) -> bool {
+        if self.contains(&document.device) || self.is_revoked(&document.device) {
+            return false;
+        }
+
+        let issued = match self.primary() {
+            Some(primary) => !document.is_primary() && document.issuer.eq(&primary.device),
+            None => document.is_primary(),
+        };
+
+        if !issued {
+            return false;
+        }
+
+        self.devices.push(document);
+        true
+    }
+
+    /// Returns false if the device is not linked, is the primary device or the revocation was not
+    /// issued by the primary device
+    pub fn revoke(&mut self, revocation: DeviceRevocation) -> bool {
+        if !self.contains(&revocation.device)
+            || self.is_primary(&revocation.device)
+            || !self.is_primary(&revocation.issuer)
+        {
+            return false;
+        }
+        self.devices
+            .retain(|document| document.device.ne(&revocation.device));
+        self.revoked.push(revocation);
+        true
+    }
+
+    /// Merge the list from another device, returning true if the list changed.
+    /// Certificates and revocations that were not signed by the identity and the primary device are ignored,
+    /// as is a list with a different primary device
+    pub fn merge(&mut self, identity: &DID, other: DeviceList) -> bool {
+        let mut changed = false;
+
+        let primary = other.primary().cloned();
+        match (self.primary(), primary) {
+            (Some(current), Some(primary)) if current.ne(&primary) => return false,
+            (None, Some(primary)) => {
+                if primary.identity.ne(identity) || primary.verify().is_err() {
+                    return false;
+                }
+                changed |= self.insert(primary);
+            }
+            _ => {}
+        }
+
+        for revocation in other.revoked {
+            if revocation.identity.ne(identity)
+                || !self.is_primary(&revocation.issuer)
+                || self.is_primary(&revocation.device)
+                || self.is_revoked(&revocation.device)
+                || revocation.verify().is_err()
+            {
+                continue;
+            }
+            self.devices
+                .retain(|document| document.device.ne(&revocation.device));
+            self.revoked.push(revocation);
+            changed = true;
+        }
+
+        for document in other.devices {
+            if document.identity.ne(identity) || document.verify().is_err() {
+                continue;
+            }
+            changed |= self.insert(document);
+        }
+
+        changed
+    }
+}
+
+/// Bundle handed to a device that is being linked.
+///
+/// The keypair of the identity, the certificate of the device and the certificate of the primary device that
+/// issued it are encrypted using the shared secret between the identity and the device, so only the device
+/// the bundle was made for can open it. The keypair of the primary device is not part of the bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceLinkBundle {
+    pub identity: DID,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeviceLinkPayload {
+    keypair: String,
+    certificate: DeviceDocument,
+    primary: DeviceDocument,
+}
+
+impl DeviceLinkBundle {
+    /// Creates the bundle from the identity keypair and the base58 encoded bytes of the keypair
+    pub fn new(
+        keypair: &DID,
+        encoded_keypair: &str,
+        certificate: DeviceDocument,
+        primary: DeviceDocument,
+    ) -> Result<Self, Error> {
+        if certificate.identity.ne(keypair)
+            || primary.identity.ne(keypair)
+            || !primary.is_primary()
+            || certificate.issuer.ne(&primary.device)
+        {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let payload = DeviceLinkPayload {
+            keypair: encoded_keypair.to_string(),
+            certificate,
+            primary,
+        };
+
+        let bytes = Zeroizing::new(serde_json::to_vec(&payload)?);
+        let data = ecdh_encrypt(keypair, Some(&payload.certificate.device), &*bytes)?;
+
+        Ok(DeviceLinkBundle {
+            identity: keypair.clone(),
+            data,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self).map_err(Error::from)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        serde_json::from_slice(bytes).map_err(Error::from)
+    }
+
+    /// Decrypts the bundle using the device keypair, returning the base58 encoded identity keypair
+    /// and the list of devices made of the verified certificates of the primary device and the device
+    pub fn open(&self, device: &DID) -> Result<(Zeroizing<String>, DeviceList), Error> {
+        let bytes = Zeroizing::new(ecdh_decrypt(device, Some(&self.identity), &self.data)?);
+        let payload: DeviceLinkPayload = serde_json::from_slice(&bytes)?;
+
+        let DeviceLinkPayload {
+            keypair,
+            certificate,
+            primary,
+        } = payload;
+
+        let keypair = Zeroizing::new(keypair);
+
+        if certificate.identity.ne(&self.identity)
+            || primary.identity.ne(&self.identity)
+            || certificate.device.ne(device)
+        {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        certificate.verify()?;
+        primary.verify()?;
+
+        let mut list = DeviceList::default();
+        if !list.insert(primary) || !list.insert(certificate) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        Ok((keypair, list))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use warp::{crypto::DID, error::Error};
+
+    use super::{DeviceDocument, DeviceLinkBundle, DeviceList, DeviceRevocation};
+
+    #[test]
+    fn sign_and_verify_certificate() -> Result<(), Error> {
+        let identity = DID::default();
+        let primary = DID::default();
+        let device = DID::default();
+
+        let mut certificate = DeviceDocument::new(&identity, &primary, &device, "desktop")?;
+        certificate.verify()?;
+        assert!(!certificate.is_primary());
+
+        certificate.name = "mobile".into();
+        assert!(certificate.verify().is_err());
+
+        assert!(
+            DeviceDocument::new(&identity, &primary, &device, "desktop")?
+                .sign(&device, &primary)
+                .is_err()
+        );
+
+        let certificate = DeviceDocument::primary(&identity, &primary, "desktop")?;
+        certificate.verify()?;
+        assert!(certificate.is_primary());
+        Ok(())
+    }
+
+    #[test]
+    fn merge_device_list() -> Result<(), Error> {
+        let identity = DID::default();
+        let primary_key = DID::default();
+        let primary = DeviceDocument::primary(&identity, &primary_key, "primary")?;
+        let desktop = DeviceDocument::new(&identity, &primary_key, &DID::default(), "desktop")?;
+        let mobile = DeviceDocument::new(&identity, &primary_key, &DID::default(), "mobile")?;
+
+        let mut list = DeviceList::default();
+        assert!(!list.insert(desktop.clone()));
+        assert!(list.insert(primary.clone()));
+        assert!(list.insert(desktop.clone()));
+        assert!(!list.insert(desktop.clone()));
+
+        let mut other = list.clone();
+        assert!(other.insert(mobile.clone()));
+        assert!(other.revoke(DeviceRevocation::new(
+            &identity,
+            &primary_key,
+            &desktop.device
+        )?));
+
+        assert!(list.merge(&identity, other.clone()));
+        assert!(!list.merge(&identity, other));
+        assert!(list.contains(&mobile.device));
+        assert!(list.is_revoked(&desktop.device));
+
+        // a revoked device cannot be linked again through a stale list
+        let mut stale = DeviceList::default();
+        stale.insert(primary.clone());
+        stale.insert(desktop.clone());
+        assert!(!list.merge(&identity, stale));
+        assert!(!list.contains(&desktop.device));
+        Ok(())
+    }
+
+    #[test]
+    fn merge_ignores_other_identities() -> Result<(), Error> {
+        let identity = DID::default();
+        let other_identity = DID::default();
+        let primary_key = DID::default();
+
+        let primary = DeviceDocument::primary(&identity, &primary_key, "primary")?;
+        let desktop = DeviceDocument::new(&identity, &primary_key, &DID::default(), "desktop")?;
+
+        let mut list = DeviceList::default();
+        assert!(list.insert(primary.clone()));
+        assert!(list.insert(desktop.clone()));
+
+        // certificates and revocations signed by another identity are not accepted
+        let mut other = list.clone();
+        other.devices.push(DeviceDocument::new(
+            &other_identity,
+            &primary_key,
+            &DID::default(),
+            "mobile",
+        )?);
+        other.revoked.push(DeviceRevocation::new(
+            &other_identity,
+            &primary_key,
+            &desktop.device,
+        )?);
+
+        assert!(!list.merge(&identity, other));
+        assert_eq!(list.devices, vec![primary.clone(), desktop.clone()]);
+
+        // nor are revocations that were altered after being signed
+        let mut revocation = DeviceRevocation::new(&identity, &primary_key, &DID::default())?;
+        revocation.device = desktop.device.clone();
+
+        let other = DeviceList {
+            devices: vec![],
+            revoked: vec![revocation],
+        };
+
+        assert!(!list.merge(&identity, other));
+        assert!(!list.is_revoked(&desktop.device));
+        Ok(())
+    }
+
+    #[test]
+    fn linked_device_cannot_revoke() -> Result<(), Error> {
+        let identity = DID::default();
+        let primary_key = DID::default();
+        let desktop_key = DID::default();
+
+        let primary = DeviceDocument::primary(&identity, &primary_key, "primary")?;
+        let desktop = DeviceDocument::new(&identity, &primary_key, &desktop_key, "desktop")?;
+        let mobile = DeviceDocument::new(&identity, &primary_key, &DID::default(), "mobile")?;
+
+        let mut list = DeviceList::default();
+        assert!(list.insert(primary.clone()));
+        assert!(list.insert(desktop.clone()));
+        assert!(list.insert(mobile.clone()));
+
+        // a linked device holding the identity keypair cannot revoke other devices
+        let mut forged = list.clone();
+        forged.revoked.push(DeviceRevocation::new(
+            &identity,
+            &desktop_key,
+            &primary.device,
+        )?);
+        forged.revoked.push(DeviceRevocation::new(
+            &identity,
+            &desktop_key,
+            &mobile.device,
+        )?);
+
+        assert!(!list.merge(&identity, forged));
+        assert!(list.contains(&primary.device) && list.contains(&mobile.device));
+
+        // nor can it replace the primary device with itself
+        let mut replaced = DeviceList::default();
+        replaced.insert(DeviceDocument::primary(&identity, &desktop_key, "primary")?);
+        replaced.revoked.push(DeviceRevocation::new(
+            &identity,
+            &desktop_key,
+            &mobile.device,
+        )?);
+
+        assert!(!list.merge(&identity, replaced));
+        assert!(list.is_primary(&primary.device));
+        assert!(!list.is_revoked(&mobile.device));
+
+        // the primary device cannot be revoked
+        assert!(!list.revoke(DeviceRevocation::new(
+            &identity,
+            &primary_key,
+            &primary.device
+        )?));
+        Ok(())
+    }
+
+    #[test]
+    fn open_link_bundle() -> Result<(), Error> {
+        let identity = DID::default();
+        let primary_key = DID::default();
+        let device = DID::default();
+
+        let primary = DeviceDocument::primary(&identity, &primary_key, "primary")?;
+        let certificate = DeviceDocument::new(&identity, &primary_key, &device, "mobile")?;
+        let bundle = DeviceLinkBundle::new(
+            &identity,
+            "encoded-keypair",
+            certificate.clone(),
+            primary.clone(),
+        )?;
+        let bundle = DeviceLinkBundle::from_bytes(&bundle.to_bytes()?)?;
+
+        let (keypair, list) = bundle.open(&device)?;
+        assert_eq!(keypair.as_str(), "encoded-keypair");
+        assert_eq!(list.devices, vec![primary.clone(), certificate.clone()]);
+        assert!(list.is_primary(&primary.device));
+
+        assert!(bundle.open(&DID::default()).is_err());
+
+        // the certificate has to be issued by the primary device in the bundle
+        let certificate = DeviceDocument::new(&identity, &DID::default(), &device, "mobile")?;
+        assert!(DeviceLinkBundle::new(&identity, "encoded-keypair", certificate, primary).is_err());
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use futures::stream::FuturesUnordered;
+use futures::stream::{FuturesOrdered, FuturesUnordered};
 use futures::{StreamExt, TryFutureExt};
 use libipld::Cid;
 use rust_ipfs::{Ipfs, IpfsPath};
@@ -13,7 +13,8 @@ use warp::error::Error;
 
 use warp::constellation::{
     directory::Directory,
-    file::{File, FileType, Hash},
+    file::{File, FileType, FileVersion, Hash},
+    TrashItem,
 };
 
 use crate::store::document::image_dag::ImageDag;
@@ -45,7 +46,7 @@ impl DirectoryDocument {
             items: None,
         };
 
-        let items = FuturesUnordered::from_iter(
+        let items = FuturesOrdered::from_iter(
             root.get_items()
                 .iter()
                 .map(|item| ItemDocument::new(ipfs, item).into_future()),
@@ -130,7 +131,7 @@ impl DirectoryDocument {
     }
 }
 
-#[derive(Clone, Debug, Copy, Deserialize, Serialize)]
+#[derive(Clone, Debug, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ItemDocument {
     Directory(Cid),
@@ -183,6 +184,30 @@ impl ItemDocument {
     }
 }
 
+/// Item held within the trash along with the path of the directory it was removed from
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TrashDocument {
+    pub id: Uuid,
+    pub path: String,
+    pub deleted: DateTime<Utc>,
+    pub item: ItemDocument,
+    /// Size of the contents of the item when it was moved into the trash
+    #[serde(default)]
+    pub size: usize,
+}
+
+impl TrashDocument {
+    pub async fn resolve(&self, ipfs: &Ipfs) -> Result<TrashItem, Error> {
+        let item = self.item.resolve(ipfs, true).await?;
+        Ok(TrashItem::new(
+            self.id,
+            item,
+            self.path.clone(),
+            self.deleted,
+        ))
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct FileDocument {
     pub name: String,
@@ -195,6 +220,8 @@ pub struct FileDocument {
     pub file_type: FileType,
     pub reference: Option<String>,
     pub hash: Hash,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<FileVersion>,
 }
 
 impl FileDocument {
@@ -210,6 +237,7 @@ impl FileDocument {
             hash: file.hash(),
             reference: None,
             thumbnail: None,
+            versions: file.versions(),
         };
 
         if let Some(cid) = file
@@ -263,6 +291,7 @@ impl FileDocument {
         file.set_modified(Some(self.modified));
         file.set_hash(self.hash.clone());
         file.set_file_type(self.file_type.clone());
+        file.set_versions(self.versions.clone());
 
         if let Some(cid) = self.thumbnail {
             file.set_thumbnail_reference(&IpfsPath::from(cid).to_string());
@@ -322,10 +351,12 @@ mod test {
     ) -> Result<FileStore, Error> {
         let key = get_keypair_did(ipfs.keypair())?;
 
-        let root_document = RootDocumentMap::new(ipfs, Arc::new(key)).await;
+        let key = Arc::new(key);
+        let root_document = RootDocumentMap::new(ipfs, key.clone()).await;
         let store = FileStore::new(
             ipfs.clone(),
             root_document,
+            key,
             &Config::development(),
             event.clone(),
             Span::current(),
@@ -37,6 +37,10 @@ pub struct IdentityDocument {
     #[serde(default)]
     pub version: IdentityDocumentVersion,
 
+    /// Recovery key able to rotate the identity to a new key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<DID>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
 }
@@ -112,6 +116,7 @@ impl From<Identity> for IdentityDocument {
             modified,
             metadata: Default::default(),
             version: IdentityDocumentVersion::V0,
+            recovery: None,
             signature: None,
         }
     }
@@ -128,6 +133,7 @@ impl From<shuttle::identity::document::IdentityDocument> for IdentityDocument {
             status_message: document.status_message,
             metadata: document.metadata.into(),
             version: document.version.into(),
+            recovery: document.recovery,
             signature: document.signature,
         }
     }
@@ -144,6 +150,7 @@ impl From<IdentityDocument> for shuttle::identity::document::IdentityDocument {
             status_message: document.status_message,
             metadata: document.metadata.into(),
             version: document.version.into(),
+            recovery: document.recovery,
             signature: document.signature,
         }
     }
@@ -199,6 +206,7 @@ impl IdentityDocument {
         self.username != other.username
             || self.status_message != other.status_message
             || self.metadata != other.metadata
+            || self.recovery != other.recovery
     }
 }
 
@@ -1,17 +1,18 @@
 use std::{collections::BTreeMap, future::IntoFuture, sync::Arc};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use futures::{
     stream::{BoxStream, FuturesUnordered},
     StreamExt,
 };
 use libipld::Cid;
 use rust_ipfs::{Ipfs, IpfsPath};
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use warp::{
     constellation::directory::Directory, crypto::DID, error::Error,
-    multipass::identity::IdentityStatus,
+    multipass::identity::IdentityStatus, raygun::DraftMessage,
 };
 
 use crate::store::{
@@ -20,9 +21,69 @@ use crate::store::{
 };
 
 use super::{
-    files::DirectoryDocument, identity::IdentityDocument, ResolvedRootDocument, RootDocument,
+    device::DeviceList,
+    files::{DirectoryDocument, TrashDocument},
+    identity::IdentityDocument,
+    share::ShareList,
+    transfer::TransferDocument,
+    ResolvedRootDocument, RootDocument,
 };
 
+/// Drafts of each conversation, along with the drafts that were deleted so they are not restored when merging
+/// the drafts of another device
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct DraftList {
+    #[serde(default)]
+    drafts: BTreeMap<Uuid, Vec<DraftMessage>>,
+    #[serde(default)]
+    deleted: BTreeMap<Uuid, DateTime<Utc>>,
+}
+
+impl DraftList {
+    fn is_empty(&self) -> bool {
+        self.drafts.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Merge the list from another device, returning true if the list changed
+    fn merge(&mut self, other: DraftList) -> bool {
+        let mut changed = false;
+
+        for (id, date) in other.deleted {
+            if self.deleted.contains_key(&id) {
+                continue;
+            }
+            self.deleted.insert(id, date);
+            changed = true;
+        }
+
+        for draft in other.drafts.into_values().flatten() {
+            if self.deleted.contains_key(&draft.id()) {
+                continue;
+            }
+
+            let list = self.drafts.entry(draft.conversation_id()).or_default();
+
+            match list.iter_mut().find(|item| item.id() == draft.id()) {
+                Some(current) if current.modified() >= draft.modified() => continue,
+                Some(current) => *current = draft,
+                None => list.push(draft),
+            }
+
+            changed = true;
+        }
+
+        for list in self.drafts.values_mut() {
+            let len = list.len();
+            list.retain(|draft| !self.deleted.contains_key(&draft.id()));
+            changed |= list.len() != len;
+        }
+
+        self.drafts.retain(|_, list| !list.is_empty());
+
+        changed
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RootDocumentMap {
     inner: Arc<RwLock<RootDocumentInner>>,
@@ -154,6 +215,12 @@ impl RootDocumentMap {
         inner.set_root_cid(cid).await
     }
 
+    /// Merge the root document of another device, returning true if the local root document changed
+    pub async fn sync_root_cid(&self, cid: Cid) -> Result<bool, Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.sync_root_cid(cid).await
+    }
+
     pub async fn export(&self) -> Result<ResolvedRootDocument, Error> {
         let inner = &*self.inner.read().await;
         inner.export().await
@@ -209,6 +276,56 @@ impl RootDocumentMap {
         let inner = &mut *self.inner.write().await;
         inner.set_root_index(root).await
     }
+
+    pub async fn get_drafts(&self) -> Result<BTreeMap<Uuid, Vec<DraftMessage>>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get_drafts().await
+    }
+
+    pub async fn set_drafts(&self, drafts: BTreeMap<Uuid, Vec<DraftMessage>>) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.set_drafts(drafts).await
+    }
+
+    pub async fn get_shares(&self) -> Result<ShareList, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get_shares().await
+    }
+
+    pub async fn set_shares(&self, shares: ShareList) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.set_shares(shares).await
+    }
+
+    pub async fn get_trash(&self) -> Result<Vec<TrashDocument>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get_trash().await
+    }
+
+    pub async fn set_trash(&self, trash: Vec<TrashDocument>) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.set_trash(trash).await
+    }
+
+    pub async fn get_transfers(&self) -> Result<Vec<TransferDocument>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get_transfers().await
+    }
+
+    pub async fn set_transfers(&self, transfers: Vec<TransferDocument>) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.set_transfers(transfers).await
+    }
+
+    pub async fn get_devices(&self) -> Result<DeviceList, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get_devices().await
+    }
+
+    pub async fn set_devices(&self, devices: DeviceList) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.set_devices(devices).await
+    }
 }
 
 #[derive(Debug)]
@@ -311,6 +428,10 @@ impl RootDocumentInner {
 
         let root_cid = self.ipfs.dag().put().serialize(document).await?;
 
+        self.replace_root_cid(root_cid, local).await
+    }
+
+    async fn replace_root_cid(&mut self, root_cid: Cid, local: bool) -> Result<(), Error> {
         self.ipfs
             .insert_pin(&root_cid)
             .set_local(local)
@@ -753,6 +874,197 @@ impl RootDocumentInner {
         Ok(())
     }
 
+    async fn draft_list(&self, cid: Option<Cid>, local: bool) -> Result<DraftList, Error> {
+        let cid = match cid {
+            Some(cid) => cid,
+            None => return Ok(DraftList::default()),
+        };
+
+        let drafts = self
+            .ipfs
+            .get_dag(cid)
+            .set_local(local)
+            .deserialized::<Vec<u8>>()
+            .await?;
+
+        let bytes = ecdh_decrypt(&self.keypair, None, drafts)?;
+        let list = serde_json::from_slice(&bytes)?;
+
+        Ok(list)
+    }
+
+    async fn get_drafts(&self) -> Result<BTreeMap<Uuid, Vec<DraftMessage>>, Error> {
+        let document = self.get_root_document().await?;
+        let list = self.draft_list(document.drafts, true).await?;
+        Ok(list.drafts)
+    }
+
+    async fn set_drafts(&mut self, drafts: BTreeMap<Uuid, Vec<DraftMessage>>) -> Result<(), Error> {
+        let mut document = self.get_root_document().await?;
+
+        let mut list = self.draft_list(document.drafts, true).await?;
+
+        // drafts that are no longer present were deleted
+        let date = Utc::now();
+        for draft in list.drafts.values().flatten() {
+            let exist = drafts
+                .get(&draft.conversation_id())
+                .map(|current| current.iter().any(|item| item.id() == draft.id()))
+                .unwrap_or_default();
+
+            if !exist {
+                list.deleted.insert(draft.id(), date);
+            }
+        }
+
+        list.drafts = drafts
+            .into_iter()
+            .filter(|(_, list)| !list.is_empty())
+            .collect::<BTreeMap<_, _>>();
+
+        document.drafts = match !list.is_empty() {
+            true => {
+                let bytes = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(&list)?)?;
+                Some(self.ipfs.dag().put().serialize(bytes).await?)
+            }
+            false => None,
+        };
+
+        self.set_root_document(document).await
+    }
+
+    async fn get_shares(&self) -> Result<ShareList, Error> {
+        let document = self.get_root_document().await?;
+
+        let cid = match document.shares {
+            Some(cid) => cid,
+            None => return Ok(ShareList::default()),
+        };
+
+        let shares = self
+            .ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized::<Vec<u8>>()
+            .await
+            .and_then(|bytes| {
+                let bytes = ecdh_decrypt(&self.keypair, None, bytes)?;
+                serde_json::from_slice(&bytes).map_err(anyhow::Error::from)
+            })
+            .unwrap_or_default();
+
+        Ok(shares)
+    }
+
+    async fn set_shares(&mut self, shares: ShareList) -> Result<(), Error> {
+        let mut document = self.get_root_document().await?;
+
+        document.shares = match !shares.outgoing.is_empty() || !shares.incoming.is_empty() {
+            true => {
+                let bytes = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(&shares)?)?;
+                Some(self.ipfs.dag().put().serialize(bytes).await?)
+            }
+            false => None,
+        };
+
+        self.set_root_document(document).await
+    }
+
+    async fn get_trash(&self) -> Result<Vec<TrashDocument>, Error> {
+        let document = self.get_root_document().await?;
+
+        let cid = match document.trash {
+            Some(cid) => cid,
+            None => return Ok(vec![]),
+        };
+
+        let trash = self
+            .ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized::<Vec<TrashDocument>>()
+            .await?;
+
+        Ok(trash)
+    }
+
+    async fn set_trash(&mut self, trash: Vec<TrashDocument>) -> Result<(), Error> {
+        let mut document = self.get_root_document().await?;
+
+        document.trash = match trash.is_empty() {
+            true => None,
+            false => Some(self.ipfs.dag().put().serialize(trash).await?),
+        };
+
+        self.set_root_document(document).await
+    }
+
+    async fn get_transfers(&self) -> Result<Vec<TransferDocument>, Error> {
+        let document = self.get_root_document().await?;
+
+        let cid = match document.transfers {
+            Some(cid) => cid,
+            None => return Ok(vec![]),
+        };
+
+        let transfers = self
+            .ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized::<Vec<TransferDocument>>()
+            .await?;
+
+        Ok(transfers)
+    }
+
+    async fn set_transfers(&mut self, transfers: Vec<TransferDocument>) -> Result<(), Error> {
+        let mut document = self.get_root_document().await?;
+
+        document.transfers = match transfers.is_empty() {
+            true => None,
+            false => Some(self.ipfs.dag().put().serialize(transfers).await?),
+        };
+
+        self.set_root_document(document).await
+    }
+
+    async fn get_devices(&self) -> Result<DeviceList, Error> {
+        let document = self.get_root_document().await?;
+
+        let cid = match document.devices {
+            Some(cid) => cid,
+            None => return Ok(DeviceList::default()),
+        };
+
+        let devices = self
+            .ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized::<Vec<u8>>()
+            .await
+            .and_then(|bytes| {
+                let bytes = ecdh_decrypt(&self.keypair, None, bytes)?;
+                serde_json::from_slice(&bytes).map_err(anyhow::Error::from)
+            })
+            .unwrap_or_default();
+
+        Ok(devices)
+    }
+
+    async fn set_devices(&mut self, devices: DeviceList) -> Result<(), Error> {
+        let mut document = self.get_root_document().await?;
+
+        document.devices = match !devices.is_empty() {
+            true => {
+                let bytes = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(&devices)?)?;
+                Some(self.ipfs.dag().put().serialize(bytes).await?)
+            }
+            false => None,
+        };
+
+        self.set_root_document(document).await
+    }
+
     async fn set_conversation_keystore(&mut self, map: BTreeMap<String, Cid>) -> Result<(), Error> {
         let mut document = self.get_root_document().await?;
         document.conversations_keystore = Some(self.ipfs.dag().put().serialize(map).await?);
@@ -918,4 +1230,325 @@ impl RootDocumentInner {
         self._set_root_document(root_document, false).await?;
         Ok(())
     }
+
+    async fn sync_root_cid(&mut self, cid: Cid) -> Result<bool, Error> {
+        if self.cid == Some(cid) {
+            return Ok(false);
+        }
+
+        let remote = self
+            .ipfs
+            .get_dag(cid)
+            .deserialized::<RootDocument>()
+            .await?;
+
+        remote.resolve2(&self.ipfs).await?;
+
+        let local = match self.get_root_document().await {
+            Ok(document) => document,
+            Err(_) => {
+                self.replace_root_cid(cid, false).await?;
+                return Ok(true);
+            }
+        };
+
+        // The newest document is taken as a whole, while conversations, keystores, drafts and devices are merged
+        // since they are updated independently on each device. Transfers refer to files on the device itself,
+        // so the local transfers are always kept
+        let remote_is_newer = remote.modified > local.modified;
+
+        let transfers = local.transfers;
+
+        let (mut document, other) = match remote_is_newer {
+            true => (remote, local),
+            false => (local, remote),
+        };
+
+        let conversations = self
+            .merge_conversations(document.conversations, other.conversations)
+            .await?;
+        let conversations_keystore = self
+            .merge_keystores(
+                conversations,
+                document.conversations_keystore,
+                other.conversations_keystore,
+            )
+            .await?;
+        let drafts = self.merge_drafts(document.drafts, other.drafts).await?;
+        let devices = self.merge_devices(document.devices, other.devices).await?;
+
+        let merged = conversations != document.conversations
+            || conversations_keystore != document.conversations_keystore
+            || drafts != document.drafts
+            || devices != document.devices
+            || transfers != document.transfers;
+
+        match (remote_is_newer, merged) {
+            (true, false) => self.replace_root_cid(cid, false).await?,
+            (_, true) => {
+                document.conversations = conversations;
+                document.conversations_keystore = conversations_keystore;
+                document.drafts = drafts;
+                document.devices = devices;
+                document.transfers = transfers;
+                self._set_root_document(document, false).await?;
+            }
+            (false, false) => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Merge two conversation maps, keeping the most recently modified document of each conversation along with
+    /// the messages of both documents. A conversation deleted on one device stays deleted unless the document
+    /// on the other device was modified afterward
+    async fn merge_conversations(
+        &self,
+        current: Option<Cid>,
+        other: Option<Cid>,
+    ) -> Result<Option<Cid>, Error> {
+        let (current_cid, other) = match (current, other) {
+            (Some(current), Some(other)) if current != other => (current, other),
+            (None, Some(other)) => return Ok(Some(other)),
+            _ => return Ok(current),
+        };
+
+        let mut current: BTreeMap<String, Cid> =
+            self.ipfs.get_dag(current_cid).deserialized().await?;
+        let other: BTreeMap<String, Cid> = self.ipfs.get_dag(other).deserialized().await?;
+
+        let mut changed = false;
+
+        for (id, other_cid) in other {
+            let current_cid = match current.get(&id) {
+                Some(cid) if *cid == other_cid => continue,
+                Some(cid) => *cid,
+                None => {
+                    current.insert(id, other_cid);
+                    changed = true;
+                    continue;
+                }
+            };
+
+            let current_document = self
+                .ipfs
+                .get_dag(current_cid)
+                .deserialized::<ConversationDocument>()
+                .await?;
+
+            let Ok(other_document) = self
+                .ipfs
+                .get_dag(other_cid)
+                .deserialized::<ConversationDocument>()
+                .await
+            else {
+                continue;
+            };
+
+            if other_document.verify().is_err() {
+                continue;
+            }
+
+            let (mut document, oldest) = match other_document.modified > current_document.modified {
+                true => (other_document, current_document),
+                false => (current_document, other_document),
+            };
+
+            if !document.deleted && !oldest.deleted {
+                document.merge_messages(&self.ipfs, &oldest).await?;
+            }
+
+            let cid = self.ipfs.dag().put().serialize(document).await?;
+
+            if cid != current_cid {
+                current.insert(id, cid);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return Ok(Some(current_cid));
+        }
+
+        let cid = self.ipfs.dag().put().serialize(current).await?;
+        Ok(Some(cid))
+    }
+
+    /// Checks if the conversation within the map has been deleted
+    async fn is_deleted_conversation(&self, conversations: Option<Cid>, id: &str) -> bool {
+        let Some(cid) = conversations else {
+            return false;
+        };
+
+        let Ok(path) = IpfsPath::from(cid).sub_path(id) else {
+            return false;
+        };
+
+        self.ipfs
+            .get_dag(path)
+            .deserialized::<ConversationDocument>()
+            .await
+            .map(|document| document.deleted)
+            .unwrap_or_default()
+    }
+
+    /// Merge two draft lists, keeping the most recently modified version of each draft while drafts deleted
+    /// on either device are removed
+    async fn merge_drafts(
+        &self,
+        current: Option<Cid>,
+        other: Option<Cid>,
+    ) -> Result<Option<Cid>, Error> {
+        let (current_cid, other) = match (current, other) {
+            (Some(current), Some(other)) if current != other => (current, other),
+            (None, Some(other)) => return Ok(Some(other)),
+            _ => return Ok(current),
+        };
+
+        let mut current = self.draft_list(Some(current_cid), false).await?;
+        let other = self.draft_list(Some(other), false).await?;
+
+        if !current.merge(other) {
+            return Ok(Some(current_cid));
+        }
+
+        let bytes = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(&current)?)?;
+        let cid = self.ipfs.dag().put().serialize(bytes).await?;
+        Ok(Some(cid))
+    }
+
+    /// Merge two device lists so devices linked or revoked on either device are kept
+    async fn merge_devices(
+        &self,
+        current: Option<Cid>,
+        other: Option<Cid>,
+    ) -> Result<Option<Cid>, Error> {
+        let (current_cid, other) = match (current, other) {
+            (Some(current), Some(other)) if current != other => (current, other),
+            (None, Some(other)) => return Ok(Some(other)),
+            _ => return Ok(current),
+        };
+
+        let mut current: DeviceList = self.decrypt_devices(current_cid).await?;
+        let other: DeviceList = self.decrypt_devices(other).await?;
+
+        if !current.merge(&self.keypair, other) {
+            return Ok(Some(current_cid));
+        }
+
+        let bytes = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(&current)?)?;
+        let cid = self.ipfs.dag().put().serialize(bytes).await?;
+        Ok(Some(cid))
+    }
+
+    async fn decrypt_devices(&self, cid: Cid) -> Result<DeviceList, Error> {
+        let bytes = self.ipfs.get_dag(cid).deserialized::<Vec<u8>>().await?;
+        let bytes = ecdh_decrypt(&self.keypair, None, bytes)?;
+        serde_json::from_slice(&bytes).map_err(Error::from)
+    }
+
+    /// Merge two keystore maps, adding the keys that are missing from the keystore of each conversation.
+    /// Keystores of conversations that were deleted are not restored
+    async fn merge_keystores(
+        &self,
+        conversations: Option<Cid>,
+        current: Option<Cid>,
+        other: Option<Cid>,
+    ) -> Result<Option<Cid>, Error> {
+        let other = match (current, other) {
+            (Some(current), Some(other)) if current == other => return Ok(Some(current)),
+            (_, Some(other)) => other,
+            (_, None) => return Ok(current),
+        };
+
+        let mut map: BTreeMap<String, Cid> = match current {
+            Some(cid) => self.ipfs.get_dag(cid).deserialized().await?,
+            None => BTreeMap::new(),
+        };
+        let other: BTreeMap<String, Cid> = self.ipfs.get_dag(other).deserialized().await?;
+
+        let mut changed = false;
+
+        for (id, other_cid) in other {
+            let current_cid = match map.get(&id) {
+                Some(cid) if *cid == other_cid => continue,
+                Some(cid) => *cid,
+                None => {
+                    if self.is_deleted_conversation(conversations, &id).await {
+                        continue;
+                    }
+                    map.insert(id, other_cid);
+                    changed = true;
+                    continue;
+                }
+            };
+
+            let mut keystore = self
+                .ipfs
+                .get_dag(current_cid)
+                .deserialized::<Keystore>()
+                .await?;
+
+            let Ok(other_keystore) = self
+                .ipfs
+                .get_dag(other_cid)
+                .deserialized::<Keystore>()
+                .await
+            else {
+                continue;
+            };
+
+            if !keystore.merge(&self.keypair, &other_keystore)? {
+                continue;
+            }
+
+            let cid = self.ipfs.dag().put().serialize(keystore).await?;
+            map.insert(id, cid);
+            changed = true;
+        }
+
+        if !changed {
+            return Ok(current);
+        }
+
+        let cid = self.ipfs.dag().put().serialize(map).await?;
+        Ok(Some(cid))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+    use warp::raygun::DraftMessage;
+
+    use super::DraftList;
+
+    #[test]
+    fn merge_drafts_keeps_deletions() {
+        let conversation_id = Uuid::new_v4();
+        let kept = DraftMessage::new(conversation_id, vec!["kept".into()]);
+        let removed = DraftMessage::new(conversation_id, vec!["removed".into()]);
+
+        let mut local = DraftList::default();
+        local
+            .drafts
+            .insert(conversation_id, vec![kept.clone(), removed.clone()]);
+
+        // the other device removed a draft and added a new one
+        let added = DraftMessage::new(conversation_id, vec!["added".into()]);
+        let mut other = DraftList::default();
+        other.drafts.insert(conversation_id, vec![added.clone()]);
+        other.deleted.insert(removed.id(), chrono::Utc::now());
+
+        assert!(local.merge(other.clone()));
+
+        let ids = local.drafts[&conversation_id]
+            .iter()
+            .map(DraftMessage::id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![kept.id(), added.id()]);
+
+        // merging the same list again does not change anything
+        assert!(!local.merge(other));
+    }
 }
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use shuttle::identity::document::RotationDocument;
+use warp::{crypto::DID, error::Error};
+
+use crate::store::{ds_key::DataStoreKey, ecdh_decrypt, ecdh_encrypt};
+
+/// Statement handed to the new identity, issued by the previous identity.
+///
+/// The contacts of the previous identity are encrypted using the shared secret between the previous
+/// and the new key, so only the new identity is able to carry them over when publishing the statement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotationBundle {
+    pub document: RotationDocument,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data: Vec<u8>,
+}
+
+/// Contacts of the previous identity
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RotationContacts {
+    #[serde(default)]
+    pub friends: Vec<DID>,
+    #[serde(default)]
+    pub blocks: Vec<DID>,
+}
+
+impl RotationBundle {
+    /// Creates the bundle from the statement issued by the previous identity
+    pub fn new(
+        keypair: &DID,
+        document: RotationDocument,
+        contacts: &RotationContacts,
+    ) -> Result<Self, Error> {
+        if document.old.ne(keypair) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let bytes = serde_json::to_vec(contacts)?;
+        let data = ecdh_encrypt(keypair, Some(&document.new), bytes)?;
+
+        Ok(RotationBundle { document, data })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self).map_err(Error::from)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        serde_json::from_slice(bytes).map_err(Error::from)
+    }
+
+    /// Decrypts the contacts of the previous identity using the new keypair
+    pub fn contacts(&self, keypair: &DID) -> Result<RotationContacts, Error> {
+        if self.document.new.ne(keypair) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        if self.data.is_empty() {
+            return Ok(RotationContacts::default());
+        }
+
+        let bytes = ecdh_decrypt(keypair, Some(&self.document.old), &self.data)?;
+        serde_json::from_slice(&bytes).map_err(Error::from)
+    }
+}
+
+/// Statement processed for a contact, along with the recovery key the previous identity committed to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedRotation {
+    pub document: RotationDocument,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<DID>,
+}
+
+/// Statements processed for contacts that rotated their identity, keyed by the previous identity.
+///
+/// Kept so a statement issued by the recovery key can still supersede a statement issued by the
+/// previous key after the contacts have been migrated to the key of the first statement
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RotationLog {
+    #[serde(default)]
+    list: BTreeMap<String, ProcessedRotation>,
+}
+
+impl RotationLog {
+    pub async fn load(ipfs: &Ipfs) -> Self {
+        let Some(cid) = Self::current_cid(ipfs).await else {
+            return RotationLog::default();
+        };
+
+        ipfs.get_dag(cid)
+            .local()
+            .deserialized()
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn save(&self, ipfs: &Ipfs) -> Result<(), Error> {
+        let key = ipfs.rotations();
+        let current_cid = Self::current_cid(ipfs).await;
+
+        let cid = ipfs.dag().put().serialize(self).pin(true).await?;
+
+        let cid_str = cid.to_string();
+
+        ipfs.repo()
+            .data_store()
+            .put(key.as_bytes(), cid_str.as_bytes())
+            .await?;
+
+        if let Some(old_cid) = current_cid {
+            if old_cid != cid && ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                _ = ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn current_cid(ipfs: &Ipfs) -> Option<Cid> {
+        ipfs.repo()
+            .data_store()
+            .get(ipfs.rotations().as_bytes())
+            .await
+            .unwrap_or_default()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+            .and_then(|cid_str| cid_str.parse::<Cid>().ok())
+    }
+
+    pub fn get(&self, old: &DID) -> Option<&ProcessedRotation> {
+        self.list.get(&old.to_string())
+    }
+
+    pub fn insert(&mut self, document: RotationDocument, recovery: Option<DID>) {
+        self.list.insert(
+            document.old.to_string(),
+            ProcessedRotation { document, recovery },
+        );
+    }
+
+    /// Previous identities whose statement was issued by the previous key and could still be
+    /// superseded by a statement issued by the recovery key
+    pub fn supersedable(&self) -> Vec<DID> {
+        self.list
+            .values()
+            .filter(|processed| processed.document.recovery.is_none())
+            .map(|processed| processed.document.old.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_ipfs::UninitializedIpfsNoop;
+    use shuttle::identity::document::RotationDocument;
+    use warp::{crypto::DID, error::Error};
+
+    use super::{RotationBundle, RotationContacts, RotationLog};
+
+    #[test]
+    fn open_rotation_bundle() -> Result<(), Error> {
+        let old = DID::default();
+        let new = DID::default();
+
+        let contacts = RotationContacts {
+            friends: vec![DID::default(), DID::default()],
+            blocks: vec![DID::default()],
+        };
+
+        let document = RotationDocument::new(&old, &new)?;
+        let bundle = RotationBundle::new(&old, document, &contacts)?;
+        let bundle = RotationBundle::from_bytes(&bundle.to_bytes()?)?;
+
+        let opened = bundle.contacts(&new)?;
+        assert_eq!(opened.friends, contacts.friends);
+        assert_eq!(opened.blocks, contacts.blocks);
+
+        // only the new identity is able to open the contacts
+        assert!(bundle.contacts(&DID::default()).is_err());
+        assert!(bundle.contacts(&old).is_err());
+
+        // the statement can only be bundled by the identity that issued it
+        let document = RotationDocument::new(&old, &new)?;
+        assert!(RotationBundle::new(&DID::default(), document, &contacts).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_rotation_log() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfsNoop::new().start().await?;
+
+        let old = DID::default();
+        let new = DID::default();
+        let recovery = DID::default();
+
+        let mut log = RotationLog::load(&ipfs).await;
+        assert!(log.get(&old).is_none());
+
+        log.insert(
+            RotationDocument::new(&old, &new)?.accept(&new)?,
+            Some(recovery.clone()),
+        );
+        log.save(&ipfs).await?;
+
+        let mut log = RotationLog::load(&ipfs).await;
+        let processed = log.get(&old).expect("statement processed");
+        assert_eq!(processed.document.new, new);
+        assert_eq!(processed.recovery.as_ref(), Some(&recovery));
+        assert_eq!(log.supersedable(), vec![old.clone()]);
+
+        // once superseded by the recovery key, the statement is final
+        let taken = DID::default();
+        log.insert(
+            RotationDocument::new_with_recovery(&old, &recovery, &taken)?.accept(&taken)?,
+            Some(recovery),
+        );
+        log.save(&ipfs).await?;
+
+        let log = RotationLog::load(&ipfs).await;
+        assert_eq!(log.get(&old).map(|p| &p.document.new), Some(&taken));
+        assert!(log.supersedable().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::{
+    constellation::{item::Item, Share, SharePermission},
+    crypto::{cipher::Cipher, did_key::CoreSign, hash::sha256_iter, zeroize::Zeroizing, DID},
+    error::Error,
+};
+
+use crate::store::{ecdh_decrypt, ecdh_encrypt};
+
+use super::files::ItemDocument;
+
+/// Item shared by its owner with a set of recipients.
+///
+/// The item is encrypted with a key unique to each revision of the share, which is then
+/// wrapped for every recipient using the shared secret between the owner and the recipient.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ShareDocument {
+    pub id: Uuid,
+    pub owner: DID,
+    pub name: String,
+    /// Encrypted [`ItemDocument`] of the shared item
+    pub item: Vec<u8>,
+    /// Key of the share wrapped for each recipient
+    pub keys: HashMap<DID, Vec<u8>>,
+    pub permissions: HashMap<DID, SharePermission>,
+    pub created: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl ShareDocument {
+    pub fn new(
+        keypair: &DID,
+        id: Uuid,
+        name: &str,
+        item: ItemDocument,
+        permissions: HashMap<DID, SharePermission>,
+        created: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let key = Zeroizing::new(warp::crypto::generate::<32>());
+
+        let item = Cipher::direct_encrypt(&serde_json::to_vec(&item)?, &key[..])?;
+
+        let keys = permissions
+            .keys()
+            .map(|did| ecdh_encrypt(keypair, Some(did), &*key).map(|key| (did.clone(), key)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        let document = ShareDocument {
+            id,
+            owner: keypair.clone(),
+            name: name.to_string(),
+            item,
+            keys,
+            permissions,
+            created,
+            modified: Utc::now(),
+            signature: None,
+        };
+
+        document.sign(keypair)
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        let signature = self.signature.as_ref().ok_or(Error::InvalidSignature)?;
+        let signature = bs58::decode(signature).into_vec()?;
+
+        if self.keys.len() != self.permissions.len()
+            || self
+                .keys
+                .keys()
+                .any(|did| !self.permissions.contains_key(did))
+        {
+            return Err(Error::InvalidSignature);
+        }
+
+        self.owner
+            .verify(&self.hash(), &signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    pub fn permission(&self, did: &DID) -> Option<SharePermission> {
+        self.permissions.get(did).copied()
+    }
+
+    /// Decrypts the shared item using the key wrapped for the identity
+    pub fn item(&self, keypair: &DID) -> Result<ItemDocument, Error> {
+        let wrapped = self.keys.get(keypair).ok_or(Error::PublicKeyDoesntExist)?;
+        let key = Zeroizing::new(ecdh_decrypt(keypair, Some(&self.owner), wrapped)?);
+        let bytes = Cipher::direct_decrypt(&self.item, &key)?;
+        let item = serde_json::from_slice(&bytes)?;
+        Ok(item)
+    }
+
+    pub async fn resolve(&self, ipfs: &Ipfs, keypair: &DID) -> Result<Item, Error> {
+        self.item(keypair)?.resolve(ipfs, false).await
+    }
+
+    fn sign(mut self, keypair: &DID) -> Result<Self, Error> {
+        if self.owner.ne(keypair) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let signature = keypair.sign(&self.hash());
+        self.signature = Some(bs58::encode(signature).into_string());
+        Ok(self)
+    }
+
+    fn hash(&self) -> Vec<u8> {
+        // Maps are sorted so the hash does not depend on their order
+        let keys = self
+            .keys
+            .iter()
+            .map(|(did, key)| (did.to_string(), key))
+            .collect::<BTreeMap<_, _>>();
+
+        let permissions = self
+            .permissions
+            .iter()
+            .map(|(did, permission)| (did.to_string(), permission.to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        sha256_iter(
+            [
+                Some(self.id.as_bytes().to_vec()),
+                Some(self.owner.public_key_bytes()),
+                Some(self.name.as_bytes().to_vec()),
+                Some(self.item.clone()),
+                Some(self.created.to_string().into_bytes()),
+                Some(self.modified.to_string().into_bytes()),
+            ]
+            .into_iter()
+            .chain(
+                keys.into_iter()
+                    .flat_map(|(did, key)| [Some(did.into_bytes()), Some(key.clone())]),
+            )
+            .chain(permissions.into_iter().flat_map(|(did, permission)| {
+                [Some(did.into_bytes()), Some(permission.into_bytes())]
+            })),
+            None,
+        )
+    }
+}
+
+impl From<&ShareDocument> for Share {
+    fn from(document: &ShareDocument) -> Self {
+        Share::new(
+            document.id,
+            document.owner.clone(),
+            document.name.clone(),
+            document.permissions.clone(),
+            document.created,
+            document.modified,
+        )
+    }
+}
+
+/// Share created by the own identity along with the location of the item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingShare {
+    pub path: String,
+    pub item: ItemDocument,
+    pub document: ShareDocument,
+}
+
+impl From<&OutgoingShare> for Share {
+    fn from(share: &OutgoingShare) -> Self {
+        let mut item = Share::from(&share.document);
+        item.set_path(Some(share.path.clone()));
+        item
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShareList {
+    #[serde(default)]
+    pub outgoing: BTreeMap<Uuid, OutgoingShare>,
+    #[serde(default)]
+    pub incoming: BTreeMap<Uuid, ShareDocument>,
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::{
+    constellation::{Transfer, TransferKind},
+    crypto::hash::sha256_hash,
+    error::Error,
+};
+
+/// Size of the chunks a transfer is split into. Each chunk is verified against its hash
+/// before a transfer resumes past it
+pub const TRANSFER_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Chunk that was transferred. Chunks are stored as a list linked to the chunk before them,
+/// so the list grows without storing the chunks that came before it again
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ChunkDocument {
+    /// Sha256 hash of the chunk
+    pub hash: Vec<u8>,
+    pub size: usize,
+    /// Leaf block holding the contents of the chunk while uploading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<Cid>,
+    /// Chunk transferred prior to this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Cid>,
+}
+
+impl ChunkDocument {
+    pub fn new(data: &[u8], link: Option<Cid>) -> Self {
+        Self {
+            hash: sha256_hash(data, None),
+            size: data.len(),
+            link,
+            previous: None,
+        }
+    }
+
+    pub fn verify(&self, data: &[u8]) -> bool {
+        self.size == data.len() && self.hash == sha256_hash(data, None)
+    }
+}
+
+/// Upload or download that has not completed along with the chunks that were transferred
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransferDocument {
+    pub id: Uuid,
+    pub kind: TransferKind,
+    /// Path of the file within the filesystem
+    pub name: String,
+    /// Local path of the file, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Root of the file being downloaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// Latest chunk that was transferred
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<Cid>,
+    /// Amount of bytes within the chunks that were transferred
+    #[serde(default)]
+    pub written: usize,
+    pub started: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+}
+
+impl TransferDocument {
+    pub fn new(
+        kind: TransferKind,
+        name: &str,
+        location: Option<String>,
+        reference: Option<String>,
+        total: Option<usize>,
+    ) -> Self {
+        let started = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            name: name.to_string(),
+            location,
+            reference,
+            total,
+            head: None,
+            written: 0,
+            started,
+            modified: started,
+        }
+    }
+
+    /// Chunks that were transferred, in order, along with the block each of them is stored in
+    pub async fn chunks(&self, ipfs: &Ipfs) -> Result<Vec<(Cid, ChunkDocument)>, Error> {
+        let mut chunks = vec![];
+        let mut next = self.head;
+
+        while let Some(cid) = next {
+            let chunk: ChunkDocument = ipfs.get_dag(cid).local().deserialized().await?;
+            next = chunk.previous;
+            chunks.push((cid, chunk));
+        }
+
+        chunks.reverse();
+        Ok(chunks)
+    }
+
+    /// Stores the chunk after the latest chunk of the transfer
+    pub async fn push(
+        &mut self,
+        ipfs: &Ipfs,
+        mut chunk: ChunkDocument,
+    ) -> Result<(Cid, ChunkDocument), Error> {
+        chunk.previous = self.head;
+        let cid = ipfs.dag().put().serialize(chunk.clone()).await?;
+        self.head = Some(cid);
+        self.written += chunk.size;
+        self.modified = Utc::now();
+        Ok((cid, chunk))
+    }
+
+    /// Discards the chunks from `index` onward, returning the ones removed
+    pub fn truncate(
+        &mut self,
+        chunks: &mut Vec<(Cid, ChunkDocument)>,
+        index: usize,
+    ) -> Vec<(Cid, ChunkDocument)> {
+        if index >= chunks.len() {
+            return vec![];
+        }
+
+        let removed = chunks.split_off(index);
+        self.head = chunks.last().map(|(cid, _)| *cid);
+        self.written = chunks.iter().map(|(_, chunk)| chunk.size).sum();
+        self.modified = Utc::now();
+        removed
+    }
+}
+
+impl From<&TransferDocument> for Transfer {
+    fn from(document: &TransferDocument) -> Self {
+        Transfer::new(
+            document.id,
+            document.kind,
+            document.name.clone(),
+            document.location.clone(),
+            document.written,
+            document.total,
+            document.started,
+            document.modified,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libipld::{
+        multihash::{Code, MultihashDigest},
+        Cid,
+    };
+    use warp::constellation::TransferKind;
+
+    use super::{ChunkDocument, TransferDocument};
+
+    fn chunk(data: &[u8]) -> (Cid, ChunkDocument) {
+        let cid = Cid::new_v1(0x55, Code::Sha2_256.digest(data));
+        (cid, ChunkDocument::new(data, Some(cid)))
+    }
+
+    #[test]
+    fn verify_and_truncate_chunks() {
+        let mut document =
+            TransferDocument::new(TransferKind::Upload, "data.bin", None, None, None);
+
+        let mut chunks = vec![chunk(&[1; 8]), chunk(&[2; 8]), chunk(&[3; 4])];
+
+        assert!(chunks[1].1.verify(&[2; 8]));
+        assert!(!chunks[1].1.verify(&[1; 8]));
+        assert!(!chunks[1].1.verify(&[2; 4]));
+
+        let removed = document.truncate(&mut chunks, 1);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(document.written, 8);
+        assert_eq!(document.head, Some(chunks[0].0));
+        assert!(document.truncate(&mut chunks, 4).is_empty());
+
+        document.truncate(&mut chunks, 0);
+        assert_eq!(document.written, 0);
+        assert_eq!(document.head, None);
+    }
+}
@@ -1,5 +1,16 @@
-use std::{collections::VecDeque, ffi::OsStr, path::PathBuf, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    ffi::OsStr,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 
 use futures::{
@@ -9,18 +20,32 @@ use futures::{
     FutureExt, SinkExt, StreamExt,
 };
 
-use rust_ipfs::{unixfs::UnixfsStatus, Ipfs, IpfsPath};
+use futures_timeout::TimeoutExt;
+use futures_timer::Delay;
+use libipld::{
+    multihash::{Code, MultihashDigest},
+    Cid, IpldCodec,
+};
+use rust_ipfs::{libp2p::gossipsub::Message, unixfs::UnixfsStatus, Block, Ipfs, IpfsPath};
 
 use tokio_util::sync::{CancellationToken, DropGuard};
 use tracing::{Instrument, Span};
+use uuid::Uuid;
 use warp::{
     constellation::{
-        directory::Directory, ConstellationEventKind, ConstellationProgressStream, Progression,
+        directory::Directory,
+        file::{File, FileVersion, PruneVersions},
+        item::Item,
+        mime::HEADER_LENGTH,
+        search::SearchQuery,
+        ConstellationEventKind, ConstellationProgressStream, ConstellationSearchStream,
+        Progression, Share, SharePermission, Transfer, TransferKind, TrashItem, SHARED_DIRECTORY,
     },
+    crypto::DID,
     error::Error,
 };
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use crate::{
     config::{self, Config},
@@ -28,12 +53,27 @@ use crate::{
     to_file_type,
 };
 
-use super::{document::root::RootDocumentMap, event_subscription::EventSubscription};
+use super::{
+    document::{
+        files::{FileDocument, ItemDocument, TrashDocument},
+        root::RootDocumentMap,
+        share::{OutgoingShare, ShareDocument, ShareList},
+        transfer::{ChunkDocument, TransferDocument, TRANSFER_CHUNK_SIZE},
+    },
+    ecdh_decrypt, ecdh_encrypt,
+    event_subscription::EventSubscription,
+    payload::Payload,
+    search::ItemIndex,
+    sign_serde,
+    topics::PeerTopic,
+    DidExt, ShareEvents,
+};
 
 #[derive(Clone)]
 pub struct FileStore {
     index: Directory,
     path: Arc<RwLock<PathBuf>>,
+    trash_size: Arc<AtomicUsize>,
     config: config::Config,
     command_sender: mpsc::Sender<FileTaskCommand>,
     _guard: Arc<DropGuard>,
@@ -43,12 +83,15 @@ impl FileStore {
     pub async fn new(
         ipfs: Ipfs,
         root: RootDocumentMap,
+        keypair: Arc<DID>,
         config: &Config,
         constellation_tx: EventSubscription<ConstellationEventKind>,
         span: Span,
     ) -> Result<Self, Error> {
         let config = config.clone();
 
+        let share_stream = ipfs.pubsub_subscribe(keypair.shares()).await?.boxed();
+
         let index = Directory::new("root");
 
         let thumbnail_store = ThumbnailGenerator::new(ipfs.clone());
@@ -56,11 +99,27 @@ impl FileStore {
         let (command_sender, command_receiver) = futures::channel::mpsc::channel(1);
         let (export_tx, export_rx) = futures::channel::mpsc::channel(0);
         let (signal_tx, signal_rx) = futures::channel::mpsc::unbounded();
+        let (share_event_tx, share_event_rx) = futures::channel::mpsc::unbounded();
+        let (transfer_tx, transfer_rx) = futures::channel::mpsc::unbounded();
 
         let mut task = FileTask {
             index,
             path: Arc::default(),
             root,
+            keypair,
+            shares: ShareList::default(),
+            share_stream,
+            share_queue: vec![],
+            share_refresh: false,
+            share_event_tx,
+            share_event_rx,
+            trash: vec![],
+            trash_size: Arc::default(),
+            item_index: ItemIndex::default(),
+            transfers: BTreeMap::new(),
+            transfer_tokens: HashMap::new(),
+            transfer_tx,
+            transfer_rx,
             thumbnail_store,
             ipfs,
             constellation_tx,
@@ -76,8 +135,43 @@ impl FileStore {
             tracing::warn!("Unable to import index: {e}");
         }
 
+        match task.root.get_shares().await {
+            Ok(shares) => task.shares = shares,
+            Err(e) => tracing::warn!("Unable to import shares: {e}"),
+        }
+
+        match task.root.get_trash().await {
+            Ok(trash) => task.trash = trash,
+            Err(e) => tracing::warn!("Unable to import trash: {e}"),
+        }
+
+        // Items moved into the trash prior to their size being recorded
+        for document in task.trash.iter_mut().filter(|document| document.size == 0) {
+            match document.item.resolve(&task.ipfs, true).await {
+                Ok(item) => document.size = stored_size(&item),
+                Err(e) => {
+                    tracing::warn!(id = %document.id, error = %e, "Unable to resolve item in trash")
+                }
+            }
+        }
+
+        task.update_trash_size();
+
+        match task.root.get_transfers().await {
+            Ok(transfers) => {
+                task.transfers = transfers
+                    .into_iter()
+                    .map(|transfer| (transfer.id, transfer))
+                    .collect()
+            }
+            Err(e) => tracing::warn!("Unable to import transfers: {e}"),
+        }
+
+        task.item_index = ItemIndex::new(&task.index);
+
         let mut index = task.index.clone();
         let path = task.path.clone();
+        let trash_size = task.trash_size.clone();
         let config = task.config.clone();
 
         let signal = Some(task.signal_tx.clone());
@@ -97,6 +191,7 @@ impl FileStore {
             index,
             config,
             path,
+            trash_size,
             command_sender,
             _guard,
         })
@@ -128,9 +223,9 @@ impl FileStore {
         }
     }
 
-    /// Current size of the file system
+    /// Current size of the file system, including previous versions of files and items in the trash
     pub fn current_size(&self) -> usize {
-        self.root_directory().size()
+        stored_size(&self.root_directory().into()) + self.trash_size.load(Ordering::SeqCst)
     }
 
     pub fn max_size(&self) -> usize {
@@ -283,6 +378,24 @@ impl FileStore {
         rx.await.map_err(anyhow::Error::from)?
     }
 
+    pub async fn move_item(
+        &mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::Move {
+                from: from.into(),
+                to: to.into(),
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
     pub async fn create_directory(
         &mut self,
         name: impl Into<String>,
@@ -313,6 +426,234 @@ impl FileStore {
             .await;
         rx.await.map_err(anyhow::Error::from)??.await
     }
+
+    pub async fn share(
+        &mut self,
+        path: impl Into<String>,
+        did: &DID,
+        permission: SharePermission,
+    ) -> Result<Uuid, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::Share {
+                path: path.into(),
+                did: did.clone(),
+                permission,
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn revoke_share(&mut self, path: impl Into<String>, did: &DID) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::RevokeShare {
+                path: path.into(),
+                did: did.clone(),
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn list_shares(&self) -> Result<Vec<Share>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::ListShares { response: tx })
+            .await;
+        Ok(rx.await.map_err(anyhow::Error::from)?)
+    }
+
+    pub async fn shared_with_me(&self) -> Result<Vec<Share>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::SharedWithMe { response: tx })
+            .await;
+        Ok(rx.await.map_err(anyhow::Error::from)?)
+    }
+
+    pub async fn shared_directory(&self) -> Result<Directory, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::SharedDirectory { response: tx })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?.await
+    }
+
+    pub async fn get_shared_buffer(&self, path: impl Into<String>) -> Result<Vec<u8>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::GetSharedBuffer {
+                path: path.into(),
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)??.await
+    }
+
+    pub async fn put_shared_buffer(
+        &mut self,
+        path: impl Into<String>,
+        buffer: impl Into<Vec<u8>>,
+    ) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::PutSharedBuffer {
+                path: path.into(),
+                buffer: buffer.into(),
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)??.await
+    }
+
+    pub async fn list_trash(&self) -> Result<Vec<TrashItem>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::ListTrash { response: tx })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?.await
+    }
+
+    pub async fn restore(&mut self, id: Uuid) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::Restore { id, response: tx })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn empty_trash(&mut self) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::EmptyTrash { response: tx })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn list_versions(&self, path: impl Into<String>) -> Result<Vec<FileVersion>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::ListVersions {
+                path: path.into(),
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn get_version_buffer(
+        &self,
+        path: impl Into<String>,
+        id: Uuid,
+    ) -> Result<Vec<u8>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::GetVersionBuffer {
+                path: path.into(),
+                id,
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)??.await
+    }
+
+    pub async fn restore_version(
+        &mut self,
+        path: impl Into<String>,
+        id: Uuid,
+    ) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::RestoreVersion {
+                path: path.into(),
+                id,
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn prune_versions(
+        &mut self,
+        path: impl Into<String>,
+        prune: PruneVersions,
+    ) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::PruneVersions {
+                path: path.into(),
+                prune,
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn list_transfers(&self) -> Result<Vec<Transfer>, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::ListTransfers { response: tx })
+            .await;
+        Ok(rx.await.map_err(anyhow::Error::from)?)
+    }
+
+    pub async fn cancel_transfer(&mut self, id: Uuid) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::CancelTransfer { id, response: tx })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
+
+    pub async fn search(
+        &self,
+        query: impl Into<String>,
+    ) -> Result<ConstellationSearchStream, Error> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .command_sender
+            .clone()
+            .send(FileTaskCommand::Search {
+                query: query.into(),
+                response: tx,
+            })
+            .await;
+        rx.await.map_err(anyhow::Error::from)?
+    }
 }
 
 type GetStream = BoxStream<'static, Result<Vec<u8>, Error>>;
@@ -359,6 +700,11 @@ enum FileTaskCommand {
         new: String,
         response: oneshot::Sender<Result<(), Error>>,
     },
+    Move {
+        from: String,
+        to: String,
+        response: oneshot::Sender<Result<(), Error>>,
+    },
     CreateDirectory {
         name: String,
         recursive: bool,
@@ -368,12 +714,102 @@ enum FileTaskCommand {
         path: String,
         response: oneshot::Sender<Result<BoxFuture<'static, Result<(), Error>>, Error>>,
     },
+
+    Share {
+        path: String,
+        did: DID,
+        permission: SharePermission,
+        response: oneshot::Sender<Result<Uuid, Error>>,
+    },
+    RevokeShare {
+        path: String,
+        did: DID,
+        response: oneshot::Sender<Result<(), Error>>,
+    },
+    ListShares {
+        response: oneshot::Sender<Vec<Share>>,
+    },
+    SharedWithMe {
+        response: oneshot::Sender<Vec<Share>>,
+    },
+    SharedDirectory {
+        response: oneshot::Sender<BoxFuture<'static, Result<Directory, Error>>>,
+    },
+    GetSharedBuffer {
+        path: String,
+        response: oneshot::Sender<Result<GetBufferFutResult, Error>>,
+    },
+    PutSharedBuffer {
+        path: String,
+        buffer: Vec<u8>,
+        response: oneshot::Sender<Result<BoxFuture<'static, Result<(), Error>>, Error>>,
+    },
+
+    ListTrash {
+        response: oneshot::Sender<BoxFuture<'static, Result<Vec<TrashItem>, Error>>>,
+    },
+    Restore {
+        id: Uuid,
+        response: oneshot::Sender<Result<(), Error>>,
+    },
+    EmptyTrash {
+        response: oneshot::Sender<Result<(), Error>>,
+    },
+
+    ListVersions {
+        path: String,
+        response: oneshot::Sender<Result<Vec<FileVersion>, Error>>,
+    },
+    GetVersionBuffer {
+        path: String,
+        id: Uuid,
+        response: oneshot::Sender<Result<GetBufferFutResult, Error>>,
+    },
+    RestoreVersion {
+        path: String,
+        id: Uuid,
+        response: oneshot::Sender<Result<(), Error>>,
+    },
+    PruneVersions {
+        path: String,
+        prune: PruneVersions,
+        response: oneshot::Sender<Result<(), Error>>,
+    },
+
+    ListTransfers {
+        response: oneshot::Sender<Vec<Transfer>>,
+    },
+    CancelTransfer {
+        id: Uuid,
+        response: oneshot::Sender<Result<(), Error>>,
+    },
+
+    Search {
+        query: String,
+        response: oneshot::Sender<Result<ConstellationSearchStream, Error>>,
+    },
 }
 
 struct FileTask {
     index: Directory,
     path: Arc<RwLock<PathBuf>>,
     root: RootDocumentMap,
+    keypair: Arc<DID>,
+    shares: ShareList,
+    share_stream: BoxStream<'static, Message>,
+    share_queue: Vec<(DID, Bytes)>,
+    /// Set when the tree changed, so the outgoing shares are refreshed once the changes settle
+    share_refresh: bool,
+    share_event_tx: futures::channel::mpsc::UnboundedSender<(DID, ShareEvents)>,
+    share_event_rx: futures::channel::mpsc::UnboundedReceiver<(DID, ShareEvents)>,
+    trash: Vec<TrashDocument>,
+    /// Combined size of the items in the trash, shared with the store
+    trash_size: Arc<AtomicUsize>,
+    item_index: ItemIndex,
+    transfers: BTreeMap<Uuid, TransferDocument>,
+    transfer_tokens: HashMap<Uuid, CancellationToken>,
+    transfer_tx: futures::channel::mpsc::UnboundedSender<TransferUpdate>,
+    transfer_rx: futures::channel::mpsc::UnboundedReceiver<TransferUpdate>,
     config: config::Config,
     ipfs: Ipfs,
     export_tx: futures::channel::mpsc::Sender<()>,
@@ -387,6 +823,11 @@ struct FileTask {
 
 impl FileTask {
     async fn run(&mut self) {
+        let mut share_queue_timer = Delay::new(Duration::from_secs(1));
+
+        // Checked shortly after starting so items that expired while offline are purged
+        let mut trash_timer = Delay::new(Duration::from_secs(1));
+
         loop {
             tokio::select! {
                 biased;
@@ -441,6 +882,13 @@ impl FileTask {
                         } => {
                             _ = response.send(self.rename(&current, &new).await);
                         },
+                        FileTaskCommand::Move {
+                            from,
+                            to,
+                            response,
+                        } => {
+                            _ = response.send(self.move_item(&from, &to).await);
+                        },
                         FileTaskCommand::CreateDirectory {
                             name,
                             recursive,
@@ -451,9 +899,92 @@ impl FileTask {
                         FileTaskCommand::SyncRef { path, response } => {
                             _ = response.send(self.sync_ref(&path));
                         },
-                    }
-                },
-                Some(_) = self.export_rx.next() => {
+                        FileTaskCommand::Share {
+                            path,
+                            did,
+                            permission,
+                            response,
+                        } => {
+                            _ = response.send(self.share(&path, &did, permission).await);
+                        },
+                        FileTaskCommand::RevokeShare {
+                            path,
+                            did,
+                            response,
+                        } => {
+                            _ = response.send(self.revoke_share(&path, &did).await);
+                        },
+                        FileTaskCommand::ListShares { response } => {
+                            _ = response.send(self.list_shares());
+                        },
+                        FileTaskCommand::SharedWithMe { response } => {
+                            _ = response.send(self.shared_with_me());
+                        },
+                        FileTaskCommand::SharedDirectory { response } => {
+                            _ = response.send(self.shared_directory());
+                        },
+                        FileTaskCommand::GetSharedBuffer { path, response } => {
+                            _ = response.send(self.get_shared_buffer(&path));
+                        },
+                        FileTaskCommand::PutSharedBuffer {
+                            path,
+                            buffer,
+                            response,
+                        } => {
+                            _ = response.send(self.put_shared_buffer(&path, buffer));
+                        },
+                        FileTaskCommand::ListTrash { response } => {
+                            _ = response.send(self.list_trash());
+                        },
+                        FileTaskCommand::Restore { id, response } => {
+                            _ = response.send(self.restore(id).await);
+                        },
+                        FileTaskCommand::EmptyTrash { response } => {
+                            _ = response.send(self.empty_trash().await);
+                        },
+                        FileTaskCommand::ListVersions { path, response } => {
+                            _ = response.send(self.list_versions(&path));
+                        },
+                        FileTaskCommand::GetVersionBuffer { path, id, response } => {
+                            _ = response.send(self.get_version_buffer(&path, id));
+                        },
+                        FileTaskCommand::RestoreVersion { path, id, response } => {
+                            _ = response.send(self.restore_version(&path, id).await);
+                        },
+                        FileTaskCommand::PruneVersions {
+                            path,
+                            prune,
+                            response,
+                        } => {
+                            _ = response.send(self.prune_versions(&path, prune).await);
+                        },
+                        FileTaskCommand::ListTransfers { response } => {
+                            _ = response.send(self.list_transfers());
+                        },
+                        FileTaskCommand::CancelTransfer { id, response } => {
+                            _ = response.send(self.cancel_transfer(id).await);
+                        },
+                        FileTaskCommand::Search { query, response } => {
+                            _ = response.send(self.search(&query).await);
+                        },
+                    }
+                },
+                Some((did, event)) = self.share_event_rx.next() => {
+                    if let Err(e) = self.send_share_event(&did, &event).await {
+                        tracing::warn!(%did, error = %e, "Unable to send share event");
+                    }
+                },
+                Some(update) = self.transfer_rx.next() => {
+                    if let Err(e) = self.process_transfer_update(update).await {
+                        tracing::warn!(error = %e, "Unable to update transfers");
+                    }
+                },
+                Some(message) = self.share_stream.next() => {
+                    if let Err(e) = self.process_share_event(message).await {
+                        tracing::warn!(error = %e, "Unable to process share event");
+                    }
+                },
+                Some(_) = self.export_rx.next() => {
                     _ = self.export().await;
                 }
                 Some(_) = self.signal_rx.next() => {
@@ -461,6 +992,22 @@ impl FileTask {
                         tracing::error!("Error exporting index: {_e}");
                     }
                 },
+                _ = &mut share_queue_timer => {
+                    // Changes made since the last tick are sent in a single revision of each affected share
+                    if std::mem::take(&mut self.share_refresh) {
+                        if let Err(e) = self.refresh_shares().await {
+                            tracing::warn!(error = %e, "Unable to refresh shares");
+                        }
+                    }
+                    self.process_share_queue().await;
+                    share_queue_timer.reset(Duration::from_secs(1));
+                }
+                _ = &mut trash_timer => {
+                    if let Err(e) = self.purge_expired_trash().await {
+                        tracing::warn!(error = %e, "Unable to purge expired items from trash");
+                    }
+                    trash_timer.reset(Duration::from_secs(60));
+                }
             }
         }
     }
@@ -472,7 +1019,7 @@ impl FileTask {
     }
 
     #[tracing::instrument(skip(self))]
-    pub async fn export(&self) -> Result<(), Error> {
+    pub async fn export(&mut self) -> Result<(), Error> {
         tracing::trace!("Exporting index");
 
         let mut index = self.index.clone();
@@ -480,9 +1027,16 @@ impl FileTask {
 
         index.rebuild_paths(&signal);
 
+        self.item_index = ItemIndex::new(&index);
+
         self.root.set_directory_index(index).await?;
 
         tracing::trace!("Index exported");
+
+        if !self.shares.outgoing.is_empty() {
+            self.share_refresh = true;
+        }
+
         Ok(())
     }
 
@@ -508,7 +1062,7 @@ impl FileTask {
 
     /// Current size of the file system
     fn current_size(&self) -> usize {
-        self.root_directory().size()
+        stored_size(&self.root_directory().into()) + self.trash_size.load(Ordering::SeqCst)
     }
 
     fn max_size(&self) -> usize {
@@ -520,16 +1074,19 @@ impl FileTask {
     }
 
     async fn put(&mut self, name: &str, path: &str) -> Result<ConstellationProgressStream, Error> {
+        let transfer_name = name.to_string();
         let (name, dest_path) = split_file_from_path(name)?;
 
         let ipfs = self.ipfs.clone();
 
+        let location = path.to_string();
         let path = PathBuf::from(path);
         if !path.is_file() {
             return Err(Error::FileNotFound);
         }
 
         let file_size = fs::file_size(&path).await?;
+        let header = fs::read_header(&path, HEADER_LENGTH).await?;
 
         if self.current_size() + (file_size as usize) >= self.max_size() {
             return Err(Error::InvalidLength {
@@ -549,9 +1106,8 @@ impl FileTask {
             None => self.current_directory()?,
         };
 
-        if current_directory.get_item(&name).is_ok() {
-            return Err(Error::FileExist);
-        }
+        // Putting a file with the same name replaces its contents, keeping the prior contents as a version
+        let existing = existing_file(&current_directory, &name)?;
 
         let ((width, height), exact) = (
             self.config.thumbnail_size(),
@@ -562,35 +1118,43 @@ impl FileTask {
 
         let ticket = thumbnail_store.insert(&path, width, height, exact).await?;
 
+        let (transfer, token) = self.begin_transfer(
+            TransferKind::Upload,
+            &transfer_name,
+            Some(location),
+            None,
+            Some(file_size),
+        );
+
         let constellation_tx = self.constellation_tx.clone();
         let mut export_tx = self.export_tx.clone();
+        let transfer_tx = self.transfer_tx.clone();
 
         let progress_stream = async_stream::stream! {
             let mut last_written = 0;
 
-            let mut total_written = 0;
-            let mut returned_path = None;
+            let mut chunks = upload_chunks(
+                ipfs.clone(),
+                transfer,
+                read_chunks(path),
+                token,
+                transfer_tx.clone(),
+            );
 
-            let mut stream = ipfs.unixfs().add(path);
+            let mut transfer = None;
 
-            while let Some(status) = stream.next().await {
-                let name = name.clone();
+            while let Some(status) = chunks.next().await {
                 match status {
-                    UnixfsStatus::CompletedStatus { path, written, total_size } => {
-                        returned_path = Some(path);
-                        total_written = written;
+                    Ok(ChunkStatus::Progress(written)) => {
                         last_written = written;
                         yield Progression::CurrentProgress {
-                            name,
+                            name: name.clone(),
                             current: written,
-                            total: total_size,
+                            total: Some(file_size),
                         };
                     }
-                    UnixfsStatus::FailedStatus {
-                        written, error, ..
-                    } => {
-                        last_written = written;
-                        let error = error.map(Error::Any).unwrap_or(Error::Other);
+                    Ok(ChunkStatus::Completed(document)) => transfer = Some(document),
+                    Err(error) => {
                         yield Progression::ProgressFailed {
                             name,
                             last_size: Some(last_written),
@@ -598,33 +1162,34 @@ impl FileTask {
                         };
                         return;
                     }
-                    UnixfsStatus::ProgressStatus { written, total_size } => {
-                        last_written = written;
-                        yield Progression::CurrentProgress {
-                            name,
-                            current: written,
-                            total: total_size,
-                        };
-                    }
                 }
             }
-            let ipfs_path = match
-                returned_path {
-                    Some(path) => path,
-                    None => {
-                        yield Progression::ProgressFailed {
-                            name,
-                            last_size: Some(last_written),
-                            error: Error::Other,
-                        };
-                        return;
-                    }
+
+            let Some(transfer) = transfer else {
+                yield Progression::ProgressFailed {
+                    name,
+                    last_size: Some(last_written),
+                    error: Error::Other,
                 };
+                return;
+            };
+
+            let (ipfs_path, total_written) = match add_chunks(&ipfs, &transfer).await {
+                Ok(added) => added,
+                Err(error) => {
+                    yield Progression::ProgressFailed {
+                        name,
+                        last_size: Some(last_written),
+                        error,
+                    };
+                    return;
+                }
+            };
 
             let file = warp::constellation::file::File::new(&name);
             file.set_size(total_written);
             file.set_reference(&format!("{ipfs_path}"));
-            file.set_file_type(to_file_type(&name));
+            file.set_file_type(to_file_type(&name, &header));
 
             match thumbnail_store.get(ticket).await {
                 Ok((extension_type, path, thumbnail)) => {
@@ -637,7 +1202,7 @@ impl FileTask {
                 }
             }
 
-            if let Err(e) = current_directory.add_item(file) {
+            if let Err(e) = add_or_replace(&ipfs, &current_directory, existing, file).await {
                 yield Progression::ProgressFailed {
                     name,
                     last_size: Some(last_written),
@@ -646,6 +1211,7 @@ impl FileTask {
                 return;
             }
 
+            _ = transfer_tx.unbounded_send(TransferUpdate::Completed(transfer.id));
             _ = export_tx.try_send(());
 
             yield Progression::ProgressComplete {
@@ -662,47 +1228,160 @@ impl FileTask {
         Ok(progress_stream.boxed())
     }
 
-    fn get(&self, name: &str, path: &str) -> Result<ConstellationProgressStream, Error> {
+    fn get(&mut self, name: &str, path: &str) -> Result<ConstellationProgressStream, Error> {
         let ipfs = self.ipfs.clone();
 
+        let location = path.to_string();
         let path = PathBuf::from(path);
         let item = self.current_directory()?.get_item_by_path(name)?;
         let file = item.get_file()?;
-        let reference = file.reference().ok_or(Error::Other)?.parse::<IpfsPath>()?; //Reference not found
+        let reference = file.reference().ok_or(Error::Other)?; //Reference not found
+        let ipfs_path = reference.parse::<IpfsPath>()?;
+        let size = file.size();
         let fs_tx = self.constellation_tx.clone();
+        let transfer_tx = self.transfer_tx.clone();
         let name = name.to_string();
 
+        // Downloading the same file to the same location again resumes from what was written
+        let (mut transfer, token) = self.begin_transfer(
+            TransferKind::Download,
+            &name,
+            Some(location),
+            Some(reference),
+            Some(size),
+        );
+
         let stream = async_stream::stream! {
-            let mut stream = ipfs.get_unixfs(reference, &path);
-            while let Some(status) = stream.next().await {
-                match status {
-                    UnixfsStatus::CompletedStatus { total_size, .. } => {
-                        yield Progression::ProgressComplete {
-                            name: name.to_string(),
-                            total: total_size,
+            // Only the chunks that are still intact within the local file are kept
+            let mut chunks = match transfer.chunks(&ipfs).await {
+                Ok(chunks) => chunks,
+                Err(e) => {
+                    tracing::warn!(id = %transfer.id, error = %e, "Unable to load chunks of transfer");
+                    transfer.head = None;
+                    transfer.written = 0;
+                    vec![]
+                }
+            };
+
+            let mut offset = 0;
+            let mut verified = 0;
+            for (_, chunk) in &chunks {
+                match fs::read_range(&path, offset as u64, chunk.size).await {
+                    Ok(data) if chunk.verify(&data) => {
+                        offset += chunk.size;
+                        verified += 1;
+                    }
+                    _ => break,
+                }
+            }
+
+            let removed = transfer.truncate(&mut chunks, verified);
+            remove_chunks(&ipfs, &removed, false).await;
+
+            let prepared = match offset {
+                0 => fs::write(&path, b"").await,
+                _ => fs::truncate(&path, offset as u64).await,
+            };
+
+            if let Err(e) = prepared {
+                yield Progression::ProgressFailed {
+                    name,
+                    last_size: Some(offset),
+                    error: e.into(),
+                };
+                return;
+            }
+
+            let mut written = offset;
+            let mut buffer = Vec::with_capacity(TRANSFER_CHUNK_SIZE);
+
+            // Contents before the offset were written and verified by a prior attempt
+            if offset > 0 {
+                yield Progression::CurrentProgress {
+                    name: name.to_string(),
+                    current: written,
+                    total: Some(size),
+                };
+            }
+
+            let cat_stream = match offset < size {
+                true => ipfs
+                    .cat_unixfs(ipfs_path)
+                    .range(offset as u64..size as u64)
+                    .boxed(),
+                false => futures::stream::empty().boxed(),
+            };
+
+            // An empty item marks the end of the contents so the remainder is written
+            let cat_stream = cat_stream
+                .map(Some)
+                .chain(futures::stream::once(async { None }));
+
+            for await data in cat_stream {
+                if token.is_cancelled() {
+                    yield Progression::ProgressFailed {
+                        name,
+                        last_size: Some(written),
+                        error: Error::TransferCancelled,
+                    };
+                    return;
+                }
+
+                let finished = data.is_none();
+
+                match data {
+                    Some(Ok(data)) => buffer.extend_from_slice(&data),
+                    Some(Err(e)) => {
+                        yield Progression::ProgressFailed {
+                            name,
+                            last_size: Some(written),
+                            error: Error::from(anyhow::anyhow!("{e}")),
                         };
+                        return;
                     }
-                    UnixfsStatus::FailedStatus {
-                        written, error, ..
-                    } => {
-                        let error = error.map(Error::Any).unwrap_or(Error::Other);
+                    None => {}
+                }
+
+                while buffer.len() >= TRANSFER_CHUNK_SIZE || (finished && !buffer.is_empty()) {
+                    let len = buffer.len().min(TRANSFER_CHUNK_SIZE);
+                    let chunk = buffer.drain(..len).collect::<Vec<_>>();
+
+                    if let Err(e) = fs::append(&path, &chunk).await {
                         yield Progression::ProgressFailed {
-                            name: name.to_string(),
+                            name,
                             last_size: Some(written),
-                            error,
+                            error: e.into(),
                         };
                         return;
                     }
-                    UnixfsStatus::ProgressStatus { written, total_size } => {
-                        yield Progression::CurrentProgress {
-                            name: name.to_string(),
-                            current: written,
-                            total: total_size,
+
+                    if let Err(e) = transfer.push(&ipfs, ChunkDocument::new(&chunk, None)).await {
+                        yield Progression::ProgressFailed {
+                            name,
+                            last_size: Some(written),
+                            error: e,
                         };
+                        return;
                     }
+
+                    _ = transfer_tx.unbounded_send(TransferUpdate::Checkpoint(transfer.clone()));
+
+                    written += len;
+                    yield Progression::CurrentProgress {
+                        name: name.to_string(),
+                        current: written,
+                        total: Some(size),
+                    };
                 }
             }
 
+            _ = transfer_tx.unbounded_send(TransferUpdate::Completed(transfer.id));
+
+            yield Progression::ProgressComplete {
+                name: name.to_string(),
+                total: Some(written),
+            };
+
             fs_tx
                 .emit(ConstellationEventKind::Downloaded {
                     filename: file.name(),
@@ -745,9 +1424,7 @@ impl FileTask {
         };
 
         Ok(async move {
-            if current_directory.get_item_by_path(&name).is_ok() {
-                return Err(Error::FileExist);
-            }
+            let existing = existing_file(&current_directory, &name)?;
 
             let ((width, height), exact) = (thumbnail_size, thumbnail_format);
 
@@ -758,6 +1435,8 @@ impl FileTask {
             let mut total_written = 0;
             let mut returned_path = None;
 
+            let file_type = to_file_type(&name, &buffer);
+
             let mut stream = ipfs.unixfs().add(buffer);
 
             while let Some(status) = stream.next().await {
@@ -778,7 +1457,7 @@ impl FileTask {
             let file = warp::constellation::file::File::new(&name);
             file.set_size(total_written);
             file.set_reference(&format!("{ipfs_path}"));
-            file.set_file_type(to_file_type(&name));
+            file.set_file_type(file_type);
 
             match thumbnail_store.get(ticket).await {
                 Ok((extension_type, path, thumbnail)) => {
@@ -791,7 +1470,7 @@ impl FileTask {
                 }
             }
 
-            current_directory.add_item(file)?;
+            add_or_replace(&ipfs, &current_directory, existing, file).await?;
 
             _ = export_tx.try_send(());
 
@@ -843,6 +1522,7 @@ impl FileTask {
         total_size: Option<usize>,
         stream: BoxStream<'static, Vec<u8>>,
     ) -> Result<ConstellationProgressStream, Error> {
+        let transfer_name = name.to_string();
         let (name, dest_path) = split_file_from_path(name)?;
 
         let ipfs = self.ipfs.clone();
@@ -852,94 +1532,108 @@ impl FileTask {
             None => self.current_directory()?,
         };
 
-        if current_directory.get_item_by_path(&name).is_ok() {
-            return Err(Error::FileExist);
-        }
+        let existing = existing_file(&current_directory, &name)?;
+
+        // Start of the contents, kept as the stream is consumed to detect the type of the file
+        let header = Arc::new(Mutex::new(Vec::with_capacity(HEADER_LENGTH)));
+
+        let stream = stream
+            .inspect({
+                let header = header.clone();
+                move |chunk| {
+                    let mut header = header.lock();
+                    let remaining = HEADER_LENGTH.saturating_sub(header.len());
+                    header.extend_from_slice(&chunk[..remaining.min(chunk.len())]);
+                }
+            })
+            .map(Ok::<_, std::io::Error>)
+            .boxed();
+        // Putting the same contents under the same name again resumes from what was stored
+        let (transfer, token) =
+            self.begin_transfer(TransferKind::Upload, &transfer_name, None, None, total_size);
 
-        let stream = stream.map(Ok::<_, std::io::Error>).boxed();
         let constellation_tx = self.constellation_tx.clone();
         let mut export_tx = self.export_tx.clone();
+        let transfer_tx = self.transfer_tx.clone();
         let max_size = self.max_size();
-        let root = self.root_directory();
+        let current_size = self.current_size();
 
         let progress_stream = async_stream::stream! {
-
             let mut last_written = 0;
 
-            let mut total_written = 0;
-            let mut returned_path = None;
+            let mut chunks = upload_chunks(
+                ipfs.clone(),
+                transfer,
+                stream,
+                token,
+                transfer_tx.clone(),
+            );
 
-            let mut stream = ipfs.unixfs().add(stream);
+            let mut transfer = None;
 
-            while let Some(status) = stream.next().await {
-                let n = name.clone();
+            while let Some(status) = chunks.next().await {
                 match status {
-                    UnixfsStatus::CompletedStatus { path, written, .. } => {
-                        returned_path = Some(path);
-                        total_written = written;
+                    Ok(ChunkStatus::Progress(written)) => {
                         last_written = written;
                         yield Progression::CurrentProgress {
-                            name: n,
+                            name: name.clone(),
                             current: written,
                             total: total_size,
                         };
                     }
-                    UnixfsStatus::FailedStatus {
-                        written, error, ..
-                    } => {
-                        last_written = written;
-                        let error = error.map(Error::Any).unwrap_or(Error::Other);
+                    Ok(ChunkStatus::Completed(document)) => transfer = Some(document),
+                    Err(error) => {
                         yield Progression::ProgressFailed {
-                            name: n,
+                            name,
                             last_size: Some(last_written),
                             error,
                         };
                         return;
                     }
-                    UnixfsStatus::ProgressStatus { written, .. } => {
-                        last_written = written;
-                        yield Progression::CurrentProgress {
-                            name: n,
-                            current: written,
-                            total: total_size,
-                        };
-                    }
                 }
 
-                if root.size() + last_written >= max_size {
+                if current_size + last_written >= max_size {
                     yield Progression::ProgressFailed {
                         name,
                         last_size: Some(last_written),
                         error: Error::InvalidLength {
                             context: "buffer".into(),
-                            current: root.size() + last_written,
+                            current: current_size + last_written,
                             minimum: None,
                             maximum: Some(max_size),
                         }
                     };
                     return;
                 }
-
             }
-            let ipfs_path = match
-                returned_path {
-                    Some(path) => path,
-                    None => {
-                        yield Progression::ProgressFailed {
-                            name,
-                            last_size: Some(last_written),
-                            error: Error::Other,
-                        };
-                        return;
-                    }
+
+            let Some(transfer) = transfer else {
+                yield Progression::ProgressFailed {
+                    name,
+                    last_size: Some(last_written),
+                    error: Error::Other,
                 };
+                return;
+            };
+
+            let (ipfs_path, total_written) = match add_chunks(&ipfs, &transfer).await {
+                Ok(added) => added,
+                Err(error) => {
+                    yield Progression::ProgressFailed {
+                        name,
+                        last_size: Some(last_written),
+                        error,
+                    };
+                    return;
+                }
+            };
 
             let file = warp::constellation::file::File::new(&name);
             file.set_size(total_written);
             file.set_reference(&format!("{ipfs_path}"));
-            file.set_file_type(to_file_type(&name));
+            file.set_file_type(to_file_type(&name, &header.lock()));
 
-            if let Err(e) = current_directory.add_item(file) {
+            if let Err(e) = add_or_replace(&ipfs, &current_directory, existing, file).await {
                 yield Progression::ProgressFailed {
                     name,
                     last_size: Some(last_written),
@@ -948,6 +1642,7 @@ impl FileTask {
                 return;
             }
 
+            _ = transfer_tx.unbounded_send(TransferUpdate::Completed(transfer.id));
             _ = export_tx.try_send(());
 
             yield Progression::ProgressComplete {
@@ -993,31 +1688,43 @@ impl FileTask {
         Ok(stream.boxed())
     }
 
-    /// Used to remove data from the filesystem
-    async fn remove(&mut self, name: &str, _: bool) -> Result<(), Error> {
-        let ipfs = self.ipfs.clone();
-        //TODO: Recursively delete directory but for now only support deleting a file
+    /// Used to move an item from the filesystem into the trash
+    async fn remove(&mut self, name: &str, recursive: bool) -> Result<(), Error> {
         let directory = self.current_directory()?;
 
         let item = directory.get_item_by_path(name)?;
 
-        let file = item.get_file()?;
-        let reference = file
-            .reference()
-            .ok_or(Error::ObjectNotFound)?
-            .parse::<IpfsPath>()?; //Reference not found
-
-        let cid = reference
-            .root()
-            .cid()
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Invalid path root"))?;
-
-        if ipfs.is_pinned(&cid).await? {
-            ipfs.remove_pin(&cid).recursive().await?;
+        match &item {
+            Item::File(file) if file.reference().is_none() => {
+                return Err(Error::ObjectNotFound); //Reference not found
+            }
+            Item::Directory(directory) if !recursive && !directory.get_items().is_empty() => {
+                return Err(Error::DirectoryNotEmpty);
+            }
+            _ => {}
         }
 
-        directory.remove_item(&item.name())?;
+        // the item may be nested within a directory relative to the current directory
+        let (parent, path) = match name.trim_end_matches('/').rsplit_once('/') {
+            Some((parent, _)) if !parent.trim_matches('/').is_empty() => (
+                directory.get_last_directory_from_path(parent)?,
+                self.absolute_path(parent),
+            ),
+            _ => (directory, self.absolute_path("")),
+        };
+
+        let document = TrashDocument {
+            id: Uuid::new_v4(),
+            path,
+            deleted: Utc::now(),
+            item: ItemDocument::new(&self.ipfs, &item).await?,
+            size: stored_size(&item),
+        };
+
+        parent.remove_item(&item.name())?;
+
+        self.trash.push(document);
+        self.save_trash().await?;
 
         _ = self.export().await;
 
@@ -1030,55 +1737,541 @@ impl FileTask {
         Ok(())
     }
 
-    async fn rename(&mut self, current: &str, new: &str) -> Result<(), Error> {
-        let (current, dest_path) = split_file_from_path(current)?;
+    fn list_trash(&self) -> BoxFuture<'static, Result<Vec<TrashItem>, Error>> {
+        let ipfs = self.ipfs.clone();
+        let trash = self.trash.clone();
 
-        let current_directory = match dest_path {
-            Some(dest) => self.root_directory().get_last_directory_from_path(&dest)?,
-            None => self.current_directory()?,
+        async move {
+            let mut items = Vec::with_capacity(trash.len());
+
+            for document in trash {
+                match document.resolve(&ipfs).await {
+                    Ok(item) => items.push(item),
+                    Err(e) => {
+                        tracing::warn!(id = %document.id, error = %e, "Unable to resolve item in trash")
+                    }
+                }
+            }
+
+            Ok(items)
+        }
+        .boxed()
+    }
+
+    async fn restore(&mut self, id: Uuid) -> Result<(), Error> {
+        let index = self
+            .trash
+            .iter()
+            .position(|document| document.id == id)
+            .ok_or(Error::TrashItemNotFound)?;
+
+        let item = self.trash[index].item.resolve(&self.ipfs, true).await?;
+
+        // The item is restored into the root directory if the directory it was removed from no longer exist
+        let root = self.root_directory();
+        let directory = match self.trash[index].path.is_empty() {
+            true => root,
+            false => root
+                .get_item_by_path(&self.trash[index].path)
+                .and_then(|item| item.get_directory())
+                .unwrap_or(root),
         };
 
-        if current_directory.has_item(new) {
+        let item_name = item.name();
+
+        if directory.has_item(&item_name) {
             return Err(Error::DuplicateName);
         }
 
-        current_directory.rename_item(&current, new)?;
+        directory.add_item(item)?;
 
-        self.export().await?;
+        self.trash.remove(index);
+        self.save_trash().await?;
+
+        _ = self.export().await;
 
         self.constellation_tx
-            .emit(ConstellationEventKind::Renamed {
-                old_item_name: current.to_string(),
-                new_item_name: new.to_string(),
-            })
+            .emit(ConstellationEventKind::Restored { item_name })
             .await;
+
         Ok(())
     }
 
-    async fn create_directory(&mut self, name: &str, recursive: bool) -> Result<(), Error> {
-        let directory = self.current_directory()?;
+    async fn empty_trash(&mut self) -> Result<(), Error> {
+        let trash = std::mem::take(&mut self.trash);
 
-        //Prevent creating recursive/nested directorieis if `recursive` isnt true
-        if name.contains('/') && !recursive {
-            return Err(Error::InvalidDirectory);
-        }
+        self.save_trash().await?;
 
-        if directory.has_item(name) || directory.get_item_by_path(name).is_ok() {
-            return Err(Error::DirectoryExist);
+        for document in trash {
+            self.purge(document).await;
         }
 
-        directory.add_directory(Directory::new(name))?;
-
-        _ = self.export().await;
-
         Ok(())
     }
 
-    fn sync_ref(&mut self, path: &str) -> Result<BoxFuture<'static, Result<(), Error>>, Error> {
-        let ipfs = self.ipfs.clone();
-        let thumbnail_store = self.thumbnail_store.clone();
-        let thumbnail_size = self.config.thumbnail_size();
-        let thumbnail_format = self.config.thumbnail_exact_format();
+    async fn purge_expired_trash(&mut self) -> Result<(), Error> {
+        let Some(retention) = self
+            .config
+            .trash_retention()
+            .and_then(|retention| chrono::Duration::from_std(retention).ok())
+        else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+
+        let (expired, trash): (Vec<_>, Vec<_>) = std::mem::take(&mut self.trash)
+            .into_iter()
+            .partition(|document| {
+                document
+                    .deleted
+                    .checked_add_signed(retention)
+                    .map(|expire| expire <= now)
+                    .unwrap_or_default()
+            });
+
+        self.trash = trash;
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        self.save_trash().await?;
+
+        for document in expired {
+            self.purge(document).await;
+        }
+
+        Ok(())
+    }
+
+    async fn save_trash(&self) -> Result<(), Error> {
+        self.update_trash_size();
+        self.root.set_trash(self.trash.clone()).await
+    }
+
+    fn update_trash_size(&self) {
+        let size = self.trash.iter().map(|document| document.size).sum();
+        self.trash_size.store(size, Ordering::SeqCst);
+    }
+
+    /// Unpins the content of an item that was removed from the trash
+    async fn purge(&self, document: TrashDocument) {
+        let item = match document.item.resolve(&self.ipfs, false).await {
+            Ok(item) => item,
+            Err(e) => {
+                tracing::warn!(id = %document.id, error = %e, "Unable to resolve item in trash");
+                return;
+            }
+        };
+
+        let references = item_files(&item)
+            .iter()
+            .flat_map(file_references)
+            .collect::<Vec<_>>();
+
+        self.unpin_unreachable(references).await;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::Purged {
+                item_name: item.name(),
+            })
+            .await;
+    }
+
+    /// Contents referenced by the files and their versions in the file system and in the trash
+    async fn reachable_references(&self) -> Result<HashSet<Cid>, Error> {
+        let mut files = item_files(&self.root_directory().into());
+
+        for document in &self.trash {
+            let item = document.item.resolve(&self.ipfs, false).await?;
+            files.extend(item_files(&item));
+        }
+
+        Ok(files
+            .iter()
+            .flat_map(file_references)
+            .filter_map(|reference| reference_cid(&reference))
+            .collect())
+    }
+
+    /// Unpins the contents of the references that are no longer reachable from the file system or the trash.
+    /// Nothing is unpinned if the reachable contents cannot be determined
+    async fn unpin_unreachable(&self, references: Vec<String>) {
+        let reachable = match self.reachable_references().await {
+            Ok(reachable) => reachable,
+            Err(e) => {
+                tracing::warn!(error = %e, "Unable to determine the contents in use");
+                return;
+            }
+        };
+
+        for reference in references {
+            if reference_cid(&reference).map_or(false, |cid| !reachable.contains(&cid)) {
+                unpin_reference(&self.ipfs, &reference).await;
+            }
+        }
+    }
+
+    fn list_versions(&self, path: &str) -> Result<Vec<FileVersion>, Error> {
+        let file = self
+            .current_directory()?
+            .get_item_by_path(path)?
+            .get_file()?;
+        Ok(file.versions())
+    }
+
+    fn get_version_buffer(&self, path: &str, id: Uuid) -> Result<GetBufferFutResult, Error> {
+        let ipfs = self.ipfs.clone();
+        let tx = self.constellation_tx.clone();
+
+        let file = self
+            .current_directory()?
+            .get_item_by_path(path)?
+            .get_file()?;
+
+        let version = file
+            .versions()
+            .into_iter()
+            .find(|version| version.id() == id)
+            .ok_or(Error::FileVersionNotFound)?;
+
+        Ok(async move {
+            let buffer = ipfs
+                .cat_unixfs(version.reference().parse::<IpfsPath>()?)
+                .await
+                .map_err(anyhow::Error::new)?;
+
+            tx.emit(ConstellationEventKind::Downloaded {
+                filename: file.name(),
+                size: Some(version.size()),
+                location: None,
+            })
+            .await;
+
+            Ok(buffer.into())
+        }
+        .boxed())
+    }
+
+    async fn restore_version(&mut self, path: &str, id: Uuid) -> Result<(), Error> {
+        let file = self
+            .current_directory()?
+            .get_item_by_path(path)?
+            .get_file()?;
+
+        let mut versions = file.versions();
+
+        let index = versions
+            .iter()
+            .position(|version| version.id() == id)
+            .ok_or(Error::FileVersionNotFound)?;
+
+        let version = versions.remove(index);
+
+        if let Some(reference) = file.reference() {
+            versions.push(FileVersion::new(
+                &reference,
+                file.hash(),
+                file.size(),
+                file.modified(),
+            ));
+        }
+
+        file.set_versions(versions);
+        file.set_size(version.size());
+        file.set_hash(version.hash());
+        file.set_reference(&version.reference());
+        file.set_modified(None);
+
+        self.export().await?;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::VersionRestored {
+                item_name: file.name(),
+                version: id,
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn prune_versions(&mut self, path: &str, prune: PruneVersions) -> Result<(), Error> {
+        let file = self
+            .current_directory()?
+            .get_item_by_path(path)?
+            .get_file()?;
+
+        let (kept, pruned) = prune.split(file.versions());
+
+        if pruned.is_empty() {
+            return Ok(());
+        }
+
+        file.set_versions(kept);
+
+        self.export().await?;
+
+        // Contents identical to another file, a remaining version or an item in the trash remain pinned
+        let references = pruned.iter().map(FileVersion::reference).collect();
+        self.unpin_unreachable(references).await;
+
+        Ok(())
+    }
+
+    /// Starts a transfer, or resumes the pending transfer of the same file between the same locations
+    fn begin_transfer(
+        &mut self,
+        kind: TransferKind,
+        name: &str,
+        location: Option<String>,
+        reference: Option<String>,
+        total: Option<usize>,
+    ) -> (TransferDocument, CancellationToken) {
+        let pending = self
+            .transfers
+            .values()
+            .find(|transfer| {
+                transfer.kind == kind
+                    && transfer.name == name
+                    && transfer.location == location
+                    && transfer.reference == reference
+            })
+            .cloned();
+
+        let transfer = match pending {
+            Some(mut transfer) => {
+                // Only a single attempt of a transfer is able to make progress
+                if let Some(token) = self.transfer_tokens.remove(&transfer.id) {
+                    token.cancel();
+                }
+                transfer.total = total;
+                transfer
+            }
+            None => TransferDocument::new(kind, name, location, reference, total),
+        };
+
+        let token = CancellationToken::new();
+        self.transfer_tokens.insert(transfer.id, token.clone());
+        self.transfers.insert(transfer.id, transfer.clone());
+
+        _ = self
+            .transfer_tx
+            .unbounded_send(TransferUpdate::Checkpoint(transfer.clone()));
+
+        (transfer, token)
+    }
+
+    async fn process_transfer_update(&mut self, update: TransferUpdate) -> Result<(), Error> {
+        match update {
+            TransferUpdate::Checkpoint(document) => {
+                // Transfers that were cancelled are not tracked anymore
+                let Some(transfer) = self.transfers.get_mut(&document.id) else {
+                    return Ok(());
+                };
+                *transfer = document;
+            }
+            TransferUpdate::Completed(id) => {
+                self.transfer_tokens.remove(&id);
+                let Some(transfer) = self.transfers.remove(&id) else {
+                    return Ok(());
+                };
+                // The leaves of an upload are now part of the file
+                if let Ok(chunks) = transfer.chunks(&self.ipfs).await {
+                    remove_chunks(&self.ipfs, &chunks, false).await;
+                }
+            }
+        }
+
+        self.root
+            .set_transfers(self.transfers.values().cloned().collect())
+            .await
+    }
+
+    fn list_transfers(&self) -> Vec<Transfer> {
+        self.transfers.values().map(Transfer::from).collect()
+    }
+
+    async fn cancel_transfer(&mut self, id: Uuid) -> Result<(), Error> {
+        let transfer = self.transfers.remove(&id).ok_or(Error::TransferNotFound)?;
+
+        if let Some(token) = self.transfer_tokens.remove(&id) {
+            token.cancel();
+        }
+
+        if let Ok(chunks) = transfer.chunks(&self.ipfs).await {
+            remove_chunks(&self.ipfs, &chunks, true).await;
+        }
+
+        // The partially written file is incomplete without the rest of the transfer
+        if transfer.kind == TransferKind::Download {
+            if let Some(location) = &transfer.location {
+                if let Err(e) = fs::remove_file(location).await {
+                    tracing::warn!(%location, error = %e, "Unable to remove partial download");
+                }
+            }
+        }
+
+        self.root
+            .set_transfers(self.transfers.values().cloned().collect())
+            .await
+    }
+
+    /// Consumes the notifications of changes to the tree, returning true if there were any
+    fn has_pending_changes(&mut self) -> bool {
+        let mut pending = false;
+        while let Ok(Some(_)) = self.signal_rx.try_next() {
+            pending = true;
+        }
+        while let Ok(Some(_)) = self.export_rx.try_next() {
+            pending = true;
+        }
+        pending
+    }
+
+    async fn search(&mut self, query: &str) -> Result<ConstellationSearchStream, Error> {
+        let query = SearchQuery::parse(query)?;
+
+        // Changes that were not exported yet are applied first so the results reflect them
+        if self.has_pending_changes() {
+            if let Err(e) = self.export().await {
+                // The changes are still pending, so the export is attempted again
+                _ = self.signal_tx.unbounded_send(());
+                return Err(e);
+            }
+        }
+
+        let results = self.item_index.search(&query);
+        Ok(futures::stream::iter(results).boxed())
+    }
+
+    async fn rename(&mut self, current: &str, new: &str) -> Result<(), Error> {
+        let (current, dest_path) = split_file_from_path(current)?;
+
+        let current_directory = match dest_path.as_deref() {
+            Some(dest) => self.root_directory().get_last_directory_from_path(dest)?,
+            None => self.current_directory()?,
+        };
+
+        if current_directory.has_item(new) {
+            return Err(Error::DuplicateName);
+        }
+
+        current_directory.rename_item(&current, new)?;
+
+        let parent = match dest_path.as_deref() {
+            Some(dest) => normalize_path(dest),
+            None => self.absolute_path(""),
+        };
+
+        if self.update_share_paths(
+            &normalize_path(&format!("{parent}/{current}")),
+            &normalize_path(&format!("{parent}/{new}")),
+        ) {
+            self.root.set_shares(self.shares.clone()).await?;
+        }
+
+        self.export().await?;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::Renamed {
+                old_item_name: current.to_string(),
+                new_item_name: new.to_string(),
+            })
+            .await;
+        Ok(())
+    }
+
+    async fn move_item(&mut self, from: &str, to: &str) -> Result<(), Error> {
+        let source = self.absolute_path(from);
+        let destination = self.absolute_path(to);
+
+        // the root directory cannot be moved
+        if source.is_empty() {
+            return Err(Error::InvalidPath);
+        }
+
+        let (parent, name) = source.rsplit_once('/').unwrap_or(("", source.as_str()));
+
+        if destination == source || destination.starts_with(&format!("{source}/")) {
+            return Err(Error::DirParadox);
+        }
+
+        let source_directory = self.open_directory(parent)?;
+        let destination_directory = self.open_directory(&destination)?;
+
+        if parent == destination {
+            return Ok(());
+        }
+
+        if destination_directory.has_item(name) {
+            return Err(Error::DuplicateName);
+        }
+
+        let item = source_directory.remove_item(name)?;
+
+        // place the item back if it could not be added so the move either completes or has no effect
+        if let Err(e) = destination_directory.add_item(item.clone()) {
+            source_directory.add_item(item)?;
+            return Err(e);
+        }
+
+        if self.update_share_paths(&source, &normalize_path(&format!("{destination}/{name}"))) {
+            self.root.set_shares(self.shares.clone()).await?;
+        }
+
+        // paths of the items are rebuilt when exporting the index
+        self.export().await?;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::Moved {
+                item_name: name.to_string(),
+                from: parent.to_string(),
+                to: destination,
+            })
+            .await;
+        Ok(())
+    }
+
+    async fn create_directory(&mut self, name: &str, recursive: bool) -> Result<(), Error> {
+        let directory = self.current_directory()?;
+
+        //Prevent creating recursive/nested directorieis if `recursive` isnt true
+        if name.contains('/') && !recursive {
+            return Err(Error::InvalidDirectory);
+        }
+
+        if directory.has_item(name) || directory.get_item_by_path(name).is_ok() {
+            return Err(Error::DirectoryExist);
+        }
+
+        // directories that already exist along the path are reused so only the missing ones are created
+        let mut parent = directory;
+        let components = name
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        for (index, component) in components.iter().enumerate() {
+            match parent.get_item(component) {
+                Ok(Item::Directory(directory)) => parent = directory,
+                Ok(Item::File(_)) => return Err(Error::ItemNotDirectory),
+                Err(_) => {
+                    parent.add_directory(Directory::new(&components[index..].join("/")))?;
+                    break;
+                }
+            }
+        }
+
+        _ = self.export().await;
+
+        Ok(())
+    }
+
+    fn sync_ref(&mut self, path: &str) -> Result<BoxFuture<'static, Result<(), Error>>, Error> {
+        let ipfs = self.ipfs.clone();
+        let thumbnail_store = self.thumbnail_store.clone();
+        let thumbnail_size = self.config.thumbnail_size();
+        let thumbnail_format = self.config.thumbnail_exact_format();
 
         let directory = self.current_directory()?;
         let file = directory
@@ -1114,6 +2307,1053 @@ impl FileTask {
         }
         .boxed())
     }
+
+    /// Path of an item relative to the root directory
+    fn absolute_path(&self, path: &str) -> String {
+        normalize_path(&format!("{}/{path}", self.get_path().to_string_lossy()))
+    }
+
+    /// Updates the path of the outgoing shares after an item was renamed or moved, returning true if any were changed
+    fn update_share_paths(&mut self, old: &str, new: &str) -> bool {
+        let prefix = format!("{old}/");
+        let mut changed = false;
+        for share in self.shares.outgoing.values_mut() {
+            if share.path == old {
+                share.path = new.to_string();
+            } else if let Some(rest) = share.path.strip_prefix(&prefix) {
+                share.path = format!("{new}/{rest}");
+            } else {
+                continue;
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    async fn share(
+        &mut self,
+        path: &str,
+        did: &DID,
+        permission: SharePermission,
+    ) -> Result<Uuid, Error> {
+        if (*self.keypair).eq(did) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        if self.root.is_blocked(did).await? {
+            return Err(Error::PublicKeyIsBlocked);
+        }
+
+        let path = self.absolute_path(path);
+        let item = self.root_directory().get_item_by_path(&path)?;
+        let document = ItemDocument::new(&self.ipfs, &item).await?;
+
+        let (id, created, mut permissions) = match self
+            .shares
+            .outgoing
+            .values()
+            .find(|share| share.path == path)
+        {
+            Some(share) => (
+                share.document.id,
+                share.document.created,
+                share.document.permissions.clone(),
+            ),
+            None => (Uuid::new_v4(), Utc::now(), HashMap::new()),
+        };
+
+        permissions.insert(did.clone(), permission);
+
+        let share = OutgoingShare {
+            path,
+            item: document,
+            document: ShareDocument::new(
+                &self.keypair,
+                id,
+                &item.name(),
+                document,
+                permissions,
+                created,
+            )?,
+        };
+
+        self.set_outgoing_share(share).await?;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::Shared {
+                share_id: id,
+                item_name: item.name(),
+                recipient: did.clone(),
+                permission,
+            })
+            .await;
+
+        Ok(id)
+    }
+
+    async fn revoke_share(&mut self, path: &str, did: &DID) -> Result<(), Error> {
+        let path = self.absolute_path(path);
+
+        let mut share = self
+            .shares
+            .outgoing
+            .values()
+            .find(|share| share.path == path && share.document.permissions.contains_key(did))
+            .cloned()
+            .ok_or(Error::ShareNotFound)?;
+
+        let share_id = share.document.id;
+        let item_name = share.document.name.clone();
+
+        let mut permissions = share.document.permissions.clone();
+        permissions.remove(did);
+
+        match permissions.is_empty() {
+            true => {
+                self.shares.outgoing.remove(&share_id);
+                self.root.set_shares(self.shares.clone()).await?;
+            }
+            false => {
+                // A new key is generated so the identity is unable to read future revisions of the share
+                share.document = ShareDocument::new(
+                    &self.keypair,
+                    share_id,
+                    &item_name,
+                    share.item,
+                    permissions,
+                    share.document.created,
+                )?;
+                self.set_outgoing_share(share).await?;
+            }
+        }
+
+        self.send_share_event(did, &ShareEvents::Revoke { share_id })
+            .await?;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::ShareRevoked {
+                share_id,
+                item_name,
+                recipient: did.clone(),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    fn list_shares(&self) -> Vec<Share> {
+        self.shares.outgoing.values().map(Share::from).collect()
+    }
+
+    fn shared_with_me(&self) -> Vec<Share> {
+        self.shares.incoming.values().map(Share::from).collect()
+    }
+
+    fn shared_directory(&self) -> BoxFuture<'static, Result<Directory, Error>> {
+        let ipfs = self.ipfs.clone();
+        let keypair = self.keypair.clone();
+        let shares = self.shares.incoming.values().cloned().collect::<Vec<_>>();
+
+        async move {
+            let mut directory = Directory::new(SHARED_DIRECTORY);
+
+            for share in shares {
+                let item = match share.resolve(&ipfs, &keypair).await {
+                    Ok(item) => item,
+                    Err(e) => {
+                        tracing::warn!(share_id = %share.id, error = %e, "Unable to resolve shared item");
+                        continue;
+                    }
+                };
+
+                // Items are placed under the id of their share, as different shares may have the same name
+                let container = Directory::new(&share.id.to_string());
+
+                if let Err(e) = container
+                    .add_item(item)
+                    .and_then(|_| directory.add_item(container))
+                {
+                    tracing::warn!(share_id = %share.id, error = %e, "Unable to add shared item");
+                }
+            }
+
+            directory.rebuild_paths(&None);
+
+            Ok(directory)
+        }
+        .boxed()
+    }
+
+    /// Resolves a path within the shared directory, made of the id of the share followed by the name of
+    /// the shared item, into the share and the path relative to the shared item
+    fn locate_share(&self, path: &str) -> Result<(ShareDocument, Option<String>), Error> {
+        let path = normalize_path(path);
+
+        let (id, path) = path.split_once('/').ok_or(Error::InvalidPath)?;
+        let id = Uuid::from_str(id).map_err(|_| Error::InvalidPath)?;
+
+        let share = self
+            .shares
+            .incoming
+            .get(&id)
+            .cloned()
+            .ok_or(Error::ShareNotFound)?;
+
+        let (name, relative) = match path.split_once('/') {
+            Some((name, relative)) => (name, Some(relative.to_string())),
+            None => (path, None),
+        };
+
+        if name != share.name {
+            return Err(Error::InvalidPath);
+        }
+
+        Ok((share, relative))
+    }
+
+    fn get_shared_buffer(&self, path: &str) -> Result<GetBufferFutResult, Error> {
+        let (share, relative) = self.locate_share(path)?;
+
+        let ipfs = self.ipfs.clone();
+        let keypair = self.keypair.clone();
+        let tx = self.constellation_tx.clone();
+
+        Ok(async move {
+            let item = share.resolve(&ipfs, &keypair).await?;
+
+            let file = match relative {
+                Some(relative) => item
+                    .get_directory()?
+                    .get_item_by_path(&relative)?
+                    .get_file()?,
+                None => item.get_file()?,
+            };
+
+            let reference = file.reference().ok_or(Error::FileNotFound)?;
+
+            let buffer = ipfs
+                .cat_unixfs(reference.parse::<IpfsPath>()?)
+                .await
+                .map_err(anyhow::Error::new)?;
+
+            tx.emit(ConstellationEventKind::Downloaded {
+                filename: file.name(),
+                size: Some(file.size()),
+                location: None,
+            })
+            .await;
+
+            Ok(buffer.into())
+        }
+        .boxed())
+    }
+
+    fn put_shared_buffer(
+        &self,
+        path: &str,
+        buffer: Vec<u8>,
+    ) -> Result<BoxFuture<'static, Result<(), Error>>, Error> {
+        let (name, dest_path) = split_file_from_path(path)?;
+        let (share, relative) = self.locate_share(&dest_path.ok_or(Error::InvalidPath)?)?;
+        let relative = relative.unwrap_or_default();
+
+        if share.permission(&self.keypair) != Some(SharePermission::ReadWrite) {
+            return Err(Error::SharePermissionDenied);
+        }
+
+        let ipfs = self.ipfs.clone();
+        let keypair = self.keypair.clone();
+        let tx = self.constellation_tx.clone();
+        let share_event_tx = self.share_event_tx.clone();
+
+        Ok(async move {
+            let directory = share.resolve(&ipfs, &keypair).await?.get_directory()?;
+
+            let directory = match relative.is_empty() {
+                true => directory,
+                false => directory.get_item_by_path(&relative)?.get_directory()?,
+            };
+
+            if directory.has_item(&name) {
+                return Err(Error::FileExist);
+            }
+
+            let mut total_written = 0;
+            let mut returned_path = None;
+
+            let file_type = to_file_type(&name, &buffer);
+
+            let mut stream = ipfs.unixfs().add(buffer);
+
+            while let Some(status) = stream.next().await {
+                match status {
+                    UnixfsStatus::CompletedStatus { path, written, .. } => {
+                        returned_path = Some(path);
+                        total_written = written;
+                    }
+                    UnixfsStatus::FailedStatus { error, .. } => {
+                        return Err(error.map(Error::Any).unwrap_or(Error::Other))
+                    }
+                    _ => {}
+                }
+            }
+
+            let ipfs_path = returned_path.ok_or_else(|| anyhow::anyhow!("Cid was never set"))?;
+
+            let file = warp::constellation::file::File::new(&name);
+            file.set_size(total_written);
+            file.set_reference(&format!("{ipfs_path}"));
+            file.set_file_type(file_type);
+
+            let document = FileDocument::new(&ipfs, &file).await?;
+
+            share_event_tx
+                .unbounded_send((
+                    share.owner.clone(),
+                    ShareEvents::Put {
+                        share_id: share.id,
+                        path: relative,
+                        document,
+                    },
+                ))
+                .map_err(|_| Error::SenderChannelUnavailable)?;
+
+            tx.emit(ConstellationEventKind::Uploaded {
+                filename: name.to_string(),
+                size: Some(total_written),
+            })
+            .await;
+
+            Ok(())
+        }
+        .boxed())
+    }
+
+    /// Stores the share and sends its latest revision to each of its recipients
+    async fn set_outgoing_share(&mut self, share: OutgoingShare) -> Result<(), Error> {
+        let event = ShareEvents::Update {
+            document: share.document.clone(),
+        };
+
+        let recipients = share
+            .document
+            .permissions
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.shares.outgoing.insert(share.document.id, share);
+        self.root.set_shares(self.shares.clone()).await?;
+
+        for did in recipients {
+            if let Err(e) = self.send_share_event(&did, &event).await {
+                tracing::warn!(%did, error = %e, "Unable to send share");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends a new revision of the outgoing shares that had their item changed, and revokes
+    /// those that no longer point to an item within the filesystem
+    async fn refresh_shares(&mut self) -> Result<(), Error> {
+        let root = self.root_directory();
+
+        let mut updated = vec![];
+        let mut removed = vec![];
+
+        for (id, share) in &self.shares.outgoing {
+            let item = match root.get_item_by_path(&share.path) {
+                Ok(item) => item,
+                Err(_) => {
+                    removed.push(*id);
+                    continue;
+                }
+            };
+
+            let document = ItemDocument::new(&self.ipfs, &item).await?;
+
+            if document == share.item && item.name() == share.document.name {
+                continue;
+            }
+
+            updated.push(OutgoingShare {
+                path: share.path.clone(),
+                item: document,
+                document: ShareDocument::new(
+                    &self.keypair,
+                    *id,
+                    &item.name(),
+                    document,
+                    share.document.permissions.clone(),
+                    share.document.created,
+                )?,
+            });
+        }
+
+        for share in updated {
+            self.set_outgoing_share(share).await?;
+        }
+
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        let removed = removed
+            .iter()
+            .filter_map(|id| self.shares.outgoing.remove(id))
+            .collect::<Vec<_>>();
+
+        self.root.set_shares(self.shares.clone()).await?;
+
+        for share in removed {
+            let share_id = share.document.id;
+            for did in share.document.permissions.keys() {
+                if let Err(e) = self
+                    .send_share_event(did, &ShareEvents::Revoke { share_id })
+                    .await
+                {
+                    tracing::warn!(%did, error = %e, "Unable to revoke share");
+                }
+
+                self.constellation_tx
+                    .emit(ConstellationEventKind::ShareRevoked {
+                        share_id,
+                        item_name: share.document.name.clone(),
+                        recipient: did.clone(),
+                    })
+                    .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_share_event(&mut self, did: &DID, event: &ShareEvents) -> Result<(), Error> {
+        let bytes = ecdh_encrypt(&self.keypair, Some(did), serde_json::to_vec(event)?)?;
+        let signature = sign_serde(&self.keypair, &bytes)?;
+
+        let payload = Payload::new(&self.keypair, &bytes, &signature).to_bytes()?;
+
+        self.share_queue.push((did.clone(), payload));
+        self.process_share_queue().await;
+        Ok(())
+    }
+
+    /// Publishes the queued share events to the recipients that are online, retaining the remaining events
+    async fn process_share_queue(&mut self) {
+        if self.share_queue.is_empty() {
+            return;
+        }
+
+        for (did, payload) in std::mem::take(&mut self.share_queue) {
+            let Ok(peer_id) = did.to_peer_id() else {
+                continue;
+            };
+
+            let peers = self
+                .ipfs
+                .pubsub_peers(Some(did.shares()))
+                .await
+                .unwrap_or_default();
+
+            if !peers.contains(&peer_id)
+                || self
+                    .ipfs
+                    .pubsub_publish(did.shares(), payload.clone())
+                    .await
+                    .is_err()
+            {
+                self.share_queue.push((did, payload));
+            }
+        }
+    }
+
+    async fn process_share_event(&mut self, message: Message) -> Result<(), Error> {
+        let payload = Payload::from_bytes(&message.data)?;
+
+        let sender = payload.sender();
+
+        if self.root.is_blocked(&sender).await.unwrap_or_default() {
+            return Ok(());
+        }
+
+        let data = ecdh_decrypt(&self.keypair, Some(&sender), payload.data())?;
+
+        match serde_json::from_slice::<ShareEvents>(&data)? {
+            ShareEvents::Update { document } => self.receive_share(sender, document).await,
+            ShareEvents::Revoke { share_id } => self.remove_incoming_share(sender, share_id).await,
+            ShareEvents::Put {
+                share_id,
+                path,
+                document,
+            } => self.put_from_share(sender, share_id, &path, document),
+        }
+    }
+
+    async fn receive_share(&mut self, sender: DID, document: ShareDocument) -> Result<(), Error> {
+        if document.owner.ne(&sender) {
+            return Err(Error::SenderMismatch);
+        }
+
+        document.verify()?;
+
+        // A revision without the own identity means that access was revoked
+        if document.permission(&self.keypair).is_none() {
+            return self.remove_incoming_share(sender, document.id).await;
+        }
+
+        document.item(&self.keypair)?;
+
+        let exist = match self.shares.incoming.get(&document.id) {
+            Some(current) if current.owner.ne(&sender) => return Err(Error::SenderMismatch),
+            Some(current) if current.modified >= document.modified => return Ok(()),
+            Some(_) => true,
+            None => false,
+        };
+
+        let share_id = document.id;
+        let item_name = document.name.clone();
+
+        self.shares.incoming.insert(share_id, document);
+        self.root.set_shares(self.shares.clone()).await?;
+
+        let event = match exist {
+            true => ConstellationEventKind::ShareUpdated {
+                share_id,
+                owner: sender,
+                item_name,
+            },
+            false => ConstellationEventKind::ShareReceived {
+                share_id,
+                owner: sender,
+                item_name,
+            },
+        };
+
+        self.constellation_tx.emit(event).await;
+
+        Ok(())
+    }
+
+    async fn remove_incoming_share(&mut self, sender: DID, share_id: Uuid) -> Result<(), Error> {
+        match self.shares.incoming.get(&share_id) {
+            Some(document) if document.owner.eq(&sender) => {}
+            Some(_) => return Err(Error::SenderMismatch),
+            None => return Ok(()),
+        }
+
+        let document = self
+            .shares
+            .incoming
+            .remove(&share_id)
+            .ok_or(Error::ShareNotFound)?;
+
+        self.root.set_shares(self.shares.clone()).await?;
+
+        self.constellation_tx
+            .emit(ConstellationEventKind::ShareRemoved {
+                share_id,
+                owner: sender,
+                item_name: document.name,
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// Adds a file uploaded by a recipient into the shared directory
+    fn put_from_share(
+        &self,
+        sender: DID,
+        share_id: Uuid,
+        path: &str,
+        document: FileDocument,
+    ) -> Result<(), Error> {
+        let share = self
+            .shares
+            .outgoing
+            .get(&share_id)
+            .ok_or(Error::ShareNotFound)?;
+
+        if share.document.permission(&sender) != Some(SharePermission::ReadWrite) {
+            return Err(Error::SharePermissionDenied);
+        }
+
+        let (name, _) = split_file_from_path(document.name.as_str())?;
+        if name != document.name {
+            return Err(Error::InvalidPath);
+        }
+
+        let directory = self
+            .root_directory()
+            .get_item_by_path(&normalize_path(&format!("{}/{path}", share.path)))?
+            .get_directory()?;
+
+        if directory.has_item(&name) {
+            return Err(Error::FileExist);
+        }
+
+        if self.current_size() + document.size >= self.max_size() {
+            return Err(Error::InvalidLength {
+                context: name,
+                current: self.current_size() + document.size,
+                minimum: None,
+                maximum: Some(self.max_size()),
+            });
+        }
+
+        let cid = document
+            .reference
+            .as_deref()
+            .and_then(|reference| Cid::from_str(reference).ok())
+            .ok_or(Error::FileNotFound)?;
+
+        let ipfs = self.ipfs.clone();
+        let tx = self.constellation_tx.clone();
+        let mut export_tx = self.export_tx.clone();
+        let available = self.max_size().saturating_sub(self.current_size());
+
+        crate::rt::spawn(async move {
+            let result = async {
+                // The size in the document is provided by the sender, so the contents are fetched,
+                // up to the available space, to confirm it before they are pinned
+                let size = unixfs_size(&ipfs, cid, available)
+                    .timeout(SHARE_FETCH_TIMEOUT)
+                    .await
+                    .map_err(|_| {
+                        Error::from(anyhow::anyhow!("timed out fetching shared file"))
+                    })??;
+
+                if size != document.size {
+                    return Err(Error::InvalidLength {
+                        context: name.clone(),
+                        current: size,
+                        minimum: Some(document.size),
+                        maximum: Some(document.size),
+                    });
+                }
+
+                ipfs.insert_pin(&cid)
+                    .recursive()
+                    .await
+                    .map_err(anyhow::Error::from)?;
+                let file = document.resolve(&ipfs, true).await?;
+                let size = file.size();
+                directory.add_item(file)?;
+                Ok::<_, Error>(size)
+            };
+
+            match result.await {
+                Ok(size) => {
+                    _ = export_tx.try_send(());
+                    tx.emit(ConstellationEventKind::Uploaded {
+                        filename: name,
+                        size: Some(size),
+                    })
+                    .await;
+                }
+                Err(e) => {
+                    tracing::warn!(%sender, %share_id, error = %e, "Unable to add file uploaded to share");
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Duration allowed to fetch the contents of a file uploaded to a shared directory by a recipient
+const SHARE_FETCH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Size of the unixfs file, reading no more than `max_size` bytes of its contents
+async fn unixfs_size(ipfs: &Ipfs, cid: Cid, max_size: usize) -> Result<usize, Error> {
+    let mut stream = ipfs.cat_unixfs(cid).max_length(max_size);
+
+    let mut size = 0;
+    while let Some(data) = stream.next().await {
+        let data = data.map_err(|e| anyhow::anyhow!("{e}"))?;
+        size += data.len();
+    }
+
+    Ok(size)
+}
+
+/// File with the name within the directory, which would have its contents replaced by a new file with the same name
+fn existing_file(directory: &Directory, name: &str) -> Result<Option<File>, Error> {
+    match directory.get_item(name) {
+        Ok(Item::File(file)) => Ok(Some(file)),
+        Ok(Item::Directory(_)) => Err(Error::FileExist),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Adds the file to the directory, or replaces the contents of the existing file while keeping its prior contents as a version
+async fn add_or_replace(
+    ipfs: &Ipfs,
+    directory: &Directory,
+    existing: Option<File>,
+    file: File,
+) -> Result<(), Error> {
+    let Some(existing) = existing else {
+        return directory.add_item(file);
+    };
+
+    let mut versions = existing.versions();
+    if let Some(reference) = existing.reference() {
+        versions.push(FileVersion::new(
+            &reference,
+            existing.hash(),
+            existing.size(),
+            existing.modified(),
+        ));
+    }
+
+    existing.set_versions(versions);
+    existing.set_size(file.size());
+    existing.set_hash(file.hash());
+    existing.set_file_type(file.file_type());
+    existing.set_thumbnail(&file.thumbnail());
+    existing.set_thumbnail_format(file.thumbnail_format());
+    if let Some(reference) = file.thumbnail_reference() {
+        // The thumbnail is only kept for the current contents
+        if let Some(previous) = existing
+            .thumbnail_reference()
+            .filter(|previous| previous.ne(&reference))
+        {
+            unpin_reference(ipfs, &previous).await;
+        }
+        existing.set_thumbnail_reference(&reference);
+    }
+    if let Some(reference) = file.reference() {
+        existing.set_reference(&reference);
+    }
+    existing.set_modified(None);
+    Ok(())
+}
+
+fn reference_cid(reference: &str) -> Option<Cid> {
+    reference
+        .parse::<IpfsPath>()
+        .ok()
+        .and_then(|path| path.root().cid().copied())
+}
+
+async fn unpin_reference(ipfs: &Ipfs, reference: &str) {
+    let Some(cid) = reference_cid(reference) else {
+        return;
+    };
+
+    match ipfs.is_pinned(&cid).await {
+        Ok(true) => {
+            if let Err(e) = ipfs.remove_pin(&cid).recursive().await {
+                tracing::warn!(%cid, error = %e, "Unable to unpin file");
+            }
+        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!(%cid, error = %e, "Unable to check pin of file"),
+    }
+}
+
+/// Update sent from a transfer in progress to the task
+enum TransferUpdate {
+    /// Latest state of the transfer, persisted after each chunk so the transfer is able to resume
+    Checkpoint(TransferDocument),
+    Completed(Uuid),
+}
+
+enum ChunkStatus {
+    Progress(usize),
+    Completed(TransferDocument),
+}
+
+/// Reads the file at path in chunks
+fn read_chunks(path: PathBuf) -> BoxStream<'static, std::io::Result<Vec<u8>>> {
+    futures::stream::try_unfold(0u64, move |offset| {
+        let path = path.clone();
+        async move {
+            let data = fs::read_range(&path, offset, TRANSFER_CHUNK_SIZE).await?;
+            if data.is_empty() {
+                return Ok(None);
+            }
+            let next = offset + data.len() as u64;
+            Ok(Some((data, next)))
+        }
+    })
+    .boxed()
+}
+
+/// Stores the contents of the stream as leaves of a file, recording each chunk within the transfer.
+///
+/// Chunks stored by a prior attempt of the transfer are verified against the contents and reused,
+/// with the transfer continuing from the first chunk that no longer matches
+fn upload_chunks(
+    ipfs: Ipfs,
+    mut transfer: TransferDocument,
+    mut stream: BoxStream<'static, std::io::Result<Vec<u8>>>,
+    token: CancellationToken,
+    transfer_tx: mpsc::UnboundedSender<TransferUpdate>,
+) -> BoxStream<'static, Result<ChunkStatus, Error>> {
+    let stream = async_stream::stream! {
+        let mut chunks = match transfer.chunks(&ipfs).await {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                tracing::warn!(id = %transfer.id, error = %e, "Unable to load chunks of transfer");
+                transfer.head = None;
+                transfer.written = 0;
+                vec![]
+            }
+        };
+
+        let mut buffer = Vec::with_capacity(TRANSFER_CHUNK_SIZE);
+        let mut index = 0;
+        let mut written = 0;
+        let mut reported = 0;
+        let mut finished = false;
+
+        while !finished {
+            match stream.next().await {
+                Some(Ok(data)) => buffer.extend_from_slice(&data),
+                Some(Err(e)) => {
+                    yield Err(Error::from(e));
+                    return;
+                }
+                None => finished = true,
+            }
+
+            while buffer.len() >= TRANSFER_CHUNK_SIZE || (finished && !buffer.is_empty()) {
+                if token.is_cancelled() {
+                    yield Err(Error::TransferCancelled);
+                    return;
+                }
+
+                let len = buffer.len().min(TRANSFER_CHUNK_SIZE);
+                let chunk = buffer.drain(..len).collect::<Vec<_>>();
+
+                let stored = matches!(chunks.get(index), Some((_, document)) if document.verify(&chunk));
+
+                index += 1;
+                written += len;
+
+                if stored {
+                    continue;
+                }
+
+                // Chunks that were reused are reported at once
+                if written - len > reported {
+                    reported = written - len;
+                    yield Ok(ChunkStatus::Progress(reported));
+                }
+
+                remove_chunks(&ipfs, &transfer.truncate(&mut chunks, index - 1), true).await;
+
+                let result = async {
+                    let link = put_leaf(&ipfs, &chunk).await?;
+                    transfer.push(&ipfs, ChunkDocument::new(&chunk, Some(link))).await
+                };
+
+                match result.await {
+                    Ok(stored) => chunks.push(stored),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+
+                _ = transfer_tx.unbounded_send(TransferUpdate::Checkpoint(transfer.clone()));
+
+                reported = written;
+                yield Ok(ChunkStatus::Progress(written));
+            }
+        }
+
+        if written > reported {
+            yield Ok(ChunkStatus::Progress(written));
+        }
+
+        // Contents may be shorter than what was stored by a prior attempt
+        remove_chunks(&ipfs, &transfer.truncate(&mut chunks, index), true).await;
+        _ = transfer_tx.unbounded_send(TransferUpdate::Checkpoint(transfer.clone()));
+
+        yield Ok(ChunkStatus::Completed(transfer));
+    };
+
+    stream.boxed()
+}
+
+/// Maximum amount of links within a node of a file, matching the layout used when adding files
+const MAX_FILE_LINKS: usize = 174;
+
+/// Stores the chunk as a raw leaf of the file being uploaded
+async fn put_leaf(ipfs: &Ipfs, data: &[u8]) -> Result<Cid, Error> {
+    let cid = Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(data));
+    let block = Block::new(cid, data.to_vec()).map_err(anyhow::Error::from)?;
+    ipfs.put_block(block).await?;
+    Ok(cid)
+}
+
+/// Builds the file from the leaves stored for the chunks of the transfer, returning its path and size
+async fn add_chunks(ipfs: &Ipfs, transfer: &TransferDocument) -> Result<(IpfsPath, usize), Error> {
+    let chunks = transfer.chunks(ipfs).await?;
+
+    // Root of each part of the file, along with the size of its contents and of its blocks
+    let mut nodes = chunks
+        .iter()
+        .map(|(_, chunk)| {
+            let link = chunk.link.ok_or(Error::ObjectNotFound)?;
+            Ok((link, chunk.size as u64, chunk.size as u64))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let total = chunks.iter().map(|(_, chunk)| chunk.size).sum();
+
+    loop {
+        let mut parents = vec![];
+
+        for links in nodes.chunks(MAX_FILE_LINKS) {
+            parents.push(put_file_node(ipfs, links).await?);
+        }
+
+        if nodes.is_empty() {
+            parents.push(put_file_node(ipfs, &[]).await?);
+        }
+
+        nodes = parents;
+
+        if nodes.len() == 1 {
+            break;
+        }
+    }
+
+    let (root, _, _) = nodes[0];
+
+    ipfs.insert_pin(&root)
+        .recursive()
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    Ok((IpfsPath::from(root), total))
+}
+
+/// Stores a unixfs file node linking to the parts of the file, returning the node in the same form as the links
+async fn put_file_node(ipfs: &Ipfs, links: &[(Cid, u64, u64)]) -> Result<(Cid, u64, u64), Error> {
+    let filesize = links.iter().map(|(_, size, _)| size).sum::<u64>();
+
+    // unixfs `Data` of a file, which lists the size of the contents beneath each link
+    let mut data = vec![];
+    encode_key(&mut data, 1, 0);
+    encode_varint(&mut data, 2);
+    encode_key(&mut data, 3, 0);
+    encode_varint(&mut data, filesize);
+    for (_, size, _) in links {
+        encode_key(&mut data, 4, 0);
+        encode_varint(&mut data, *size);
+    }
+
+    // dag-pb `PBNode`, where the links are encoded before the data
+    let mut node = vec![];
+    for (cid, _, tsize) in links {
+        let mut link = vec![];
+        encode_bytes(&mut link, 1, &cid.to_bytes());
+        encode_bytes(&mut link, 2, &[]);
+        encode_key(&mut link, 3, 0);
+        encode_varint(&mut link, *tsize);
+        encode_bytes(&mut node, 2, &link);
+    }
+    encode_bytes(&mut node, 1, &data);
+
+    let tsize = node.len() as u64 + links.iter().map(|(_, _, tsize)| tsize).sum::<u64>();
+
+    let cid = Cid::new_v1(IpldCodec::DagPb.into(), Code::Sha2_256.digest(&node));
+    let block = Block::new(cid, node).map_err(anyhow::Error::from)?;
+    ipfs.put_block(block).await?;
+
+    Ok((cid, filesize, tsize))
+}
+
+fn encode_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn encode_key(buffer: &mut Vec<u8>, field: u64, wire_type: u64) {
+    encode_varint(buffer, field << 3 | wire_type);
+}
+
+fn encode_bytes(buffer: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    encode_key(buffer, field, 2);
+    encode_varint(buffer, bytes.len() as u64);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Removes the stored list of chunks, along with the leaves of the chunks if they did not become part of a file
+async fn remove_chunks(ipfs: &Ipfs, chunks: &[(Cid, ChunkDocument)], leaves: bool) {
+    for (cid, chunk) in chunks {
+        if let Err(e) = ipfs.remove_block(*cid, false).await {
+            tracing::debug!(%cid, error = %e, "Unable to remove chunk");
+        }
+
+        let Some(link) = chunk.link.filter(|_| leaves) else {
+            continue;
+        };
+
+        // Identical contents may be part of a file that was stored
+        if ipfs.is_pinned(&link).await.unwrap_or(true) {
+            continue;
+        }
+
+        if let Err(e) = ipfs.remove_block(link, false).await {
+            tracing::debug!(cid = %link, error = %e, "Unable to remove leaf of chunk");
+        }
+    }
+}
+
+/// Size of the contents stored for the item, including previous versions of its files.
+/// Contents shared between versions of a file are only counted once
+fn stored_size(item: &Item) -> usize {
+    item_files(item)
+        .iter()
+        .map(|file| {
+            let current = (file.reference().unwrap_or_default(), file.size());
+            let versions = file
+                .versions()
+                .into_iter()
+                .map(|version| (version.reference(), version.size()));
+
+            std::iter::once(current)
+                .chain(versions)
+                .collect::<HashMap<_, _>>()
+                .into_values()
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Files contained within an item, including those nested within directories
+/// References of the contents of the file along with its previous versions
+fn file_references(file: &File) -> Vec<String> {
+    file.reference()
+        .into_iter()
+        .chain(file.versions().iter().map(FileVersion::reference))
+        .collect()
+}
+
+fn item_files(item: &Item) -> Vec<File> {
+    match item {
+        Item::File(file) => vec![file.clone()],
+        Item::Directory(directory) => directory.get_items().iter().flat_map(item_files).collect(),
+    }
+}
+
+/// Removes empty and leading or trailing separators from a path
+fn normalize_path(path: &str) -> String {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
 fn split_file_from_path(name: impl Into<String>) -> Result<(String, Option<String>), Error> {
@@ -13,7 +13,7 @@ use futures::{
 };
 use futures_timeout::TimeoutExt;
 use futures_timer::Delay;
-use ipfs::{p2p::MultiaddrExt, Ipfs, Keypair};
+use ipfs::{p2p::MultiaddrExt, Ipfs, IpfsPath, Keypair, PeerId};
 use libipld::Cid;
 use rust_ipfs as ipfs;
 use serde::{Deserialize, Serialize};
@@ -21,11 +21,14 @@ use tokio::sync::RwLock;
 use tracing::{error, warn, Span};
 use web_time::Instant;
 
-use shuttle::identity::{RequestEvent, RequestPayload};
+use shuttle::{
+    gateway::PublishedDocument,
+    identity::{document::RotationDocument, RequestEvent, RequestPayload},
+};
 use warp::{
-    constellation::file::FileType,
+    constellation::file::{File, FileType},
     crypto::{did_key::CoreSign, zeroize::Zeroizing},
-    multipass::identity::{IdentityImage, Platform},
+    multipass::identity::{Device, IdentityImage, Platform},
 };
 use warp::{
     crypto::{did_key::Generate, DIDKey, Ed25519KeyPair, Fingerprint, DID},
@@ -43,10 +46,15 @@ use crate::{
 };
 
 use super::{
-    connected_to_peer, did_keypair,
+    connected_to_peer, device_keypair, did_keypair,
     document::{
-        cache::IdentityCache, identity::IdentityDocument, image_dag::get_image,
-        root::RootDocumentMap, ResolvedRootDocument, RootDocument,
+        cache::IdentityCache,
+        device::{DeviceDocument, DeviceLinkBundle, DeviceList, DeviceRevocation},
+        identity::IdentityDocument,
+        image_dag::get_image,
+        root::RootDocumentMap,
+        rotation::{RotationBundle, RotationContacts, RotationLog},
+        ResolvedRootDocument, RootDocument,
     },
     ecdh_decrypt, ecdh_encrypt,
     event_subscription::EventSubscription,
@@ -409,6 +417,9 @@ impl IdentityStore {
                             if let Err(e) = store.fetch_mailbox().await {
                                 tracing::warn!(error = %e, "Unable to fetch or process mailbox");
                             }
+                            if let Err(e) = store.fetch_rotations().await {
+                                tracing::warn!(error = %e, "Unable to fetch or process rotations");
+                            }
                         }
                         false => {
                             if let Err(e) = store.register().await {
@@ -420,6 +431,39 @@ impl IdentityStore {
             }
         });
 
+        if let (Some(interval), DiscoveryConfig::Shuttle { .. }) = (
+            store.config.store_setting().device_sync,
+            store.discovery.discovery_config(),
+        ) {
+            crate::rt::spawn({
+                let mut store = store.clone();
+                async move {
+                    loop {
+                        Delay::new(interval).await;
+
+                        // Only identities with linked devices have anything to sync
+                        if store
+                            .root_document
+                            .get_devices()
+                            .await
+                            .map(|list| list.is_empty())
+                            .unwrap_or(true)
+                        {
+                            continue;
+                        }
+
+                        match store.sync_devices().await {
+                            Ok(true) => break,
+                            Ok(false) => {}
+                            Err(e) => {
+                                tracing::warn!(error = %e, "Unable to sync with linked devices")
+                            }
+                        }
+                    }
+                }
+            });
+        }
+
         store.discovery.start().await?;
 
         let mut discovery_rx = store.discovery.events();
@@ -496,6 +540,14 @@ impl IdentityStore {
                             let payload: PayloadRequest<IdentityDocument> = match serde_json::from_slice(&message.data) {
                                 Ok(p) => p,
                                 Err(e) => {
+                                    // Rotation statements are announced over the same topic
+                                    if let Ok(payload) = serde_json::from_slice::<PayloadRequest<RotationDocument>>(&message.data) {
+                                        let document = payload.message().clone();
+                                        if let Err(e) = store.process_rotation(document).await {
+                                            error!(from = ?message.source, "Failed to process rotation: {e}");
+                                        }
+                                        continue;
+                                    }
                                     tracing::error!(from = ?message.source, "Unable to decode payload: {e}");
                                     continue;
                                 }
@@ -1580,6 +1632,7 @@ impl IdentityStore {
             status_message: None,
             metadata: Default::default(),
             version: Default::default(),
+            recovery: None,
             signature: None,
         };
 
@@ -1775,6 +1828,631 @@ impl IdentityStore {
         Ok(())
     }
 
+    /// Public key of the current device
+    pub fn device_key(&self) -> Result<DID, Error> {
+        device_keypair(&self.tesseract)
+    }
+
+    /// Authorize a device, returning the bundle to be imported on the device.
+    /// Only the primary device, which is the first device linking another device, is able to link devices
+    pub async fn link_device(&mut self, device: &DID, name: &str) -> Result<Vec<u8>, Error> {
+        let own_device = self.device_key()?;
+
+        if device.eq(&own_device) || device.eq(&*self.did_key) {
+            return Err(Error::DeviceExist);
+        }
+
+        let mut list = self.root_document.get_devices().await?;
+
+        if list.contains(device) {
+            return Err(Error::DeviceExist);
+        }
+
+        // The device linking the first device certifies itself as the primary device
+        if list.primary().is_none() {
+            list.insert(DeviceDocument::primary(
+                &self.did_key,
+                &own_device,
+                "primary",
+            )?);
+        }
+
+        let primary = match list.primary() {
+            Some(primary) if primary.device.eq(&own_device) => primary.clone(),
+            _ => return Err(Error::DeviceNotPrimary),
+        };
+
+        let certificate = DeviceDocument::new(&self.did_key, &own_device, device, name)?;
+
+        if !list.insert(certificate.clone()) {
+            // the device was revoked and should generate a new key before being linked again
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        self.root_document.set_devices(list).await?;
+
+        if let Err(e) = self.export_root_document().await {
+            tracing::warn!(did = %self.did_key, error = %e, "Unable to export root document after linking device");
+        }
+
+        let keypair =
+            Zeroizing::new(bs58::encode(self.get_raw_keypair()?.to_bytes()).into_string());
+
+        let bundle = DeviceLinkBundle::new(&self.did_key, &keypair, certificate, primary)?;
+
+        self.emit_event(MultiPassEventKind::DeviceLinked {
+            did: device.clone(),
+        })
+        .await;
+
+        bundle.to_bytes()
+    }
+
+    pub async fn list_devices(&self) -> Result<Vec<Device>, Error> {
+        let own_device = self.device_key()?;
+        let list = self.root_document.get_devices().await?;
+
+        Ok(list
+            .devices
+            .iter()
+            .map(|document| {
+                let mut device = Device::from(document);
+                device.set_current(document.device.eq(&own_device));
+                device
+            })
+            .collect())
+    }
+
+    /// Revoke a device using the key of the primary device
+    pub async fn revoke_device(&mut self, device: &DID) -> Result<(), Error> {
+        let own_device = self.device_key()?;
+
+        if device.eq(&own_device) {
+            return Err(Error::CannotRevokeOwnDevice);
+        }
+
+        let mut list = self.root_document.get_devices().await?;
+
+        if !list.is_primary(&own_device) {
+            return Err(Error::DeviceNotPrimary);
+        }
+
+        if !list.revoke(DeviceRevocation::new(&self.did_key, &own_device, device)?) {
+            return Err(Error::DeviceDoesntExist);
+        }
+
+        self.root_document.set_devices(list).await?;
+
+        if let Err(e) = self.export_root_document().await {
+            tracing::warn!(did = %self.did_key, error = %e, "Unable to export root document after revoking device");
+        }
+
+        self.emit_event(MultiPassEventKind::DeviceRevoked {
+            did: device.clone(),
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Set the list of devices from the bundle the device was linked with, merging the list resolved for the
+    /// identity so the primary device in the bundle is kept even if the resolved list names another one
+    pub async fn set_linked_devices(&self, mut list: DeviceList) -> Result<(), Error> {
+        let current = self.root_document.get_devices().await?;
+        list.merge(&self.did_key, current);
+        self.root_document.set_devices(list).await
+    }
+
+    /// Returns true if the current device has been revoked by the primary device
+    pub async fn is_device_revoked(&self) -> Result<bool, Error> {
+        let own_device = self.device_key()?;
+        let list = self.root_document.get_devices().await?;
+        Ok(list.is_revoked(&own_device))
+    }
+
+    /// Merge the root document stored on shuttle with the local root document, pushing the result back.
+    /// Returns true if the current device has been revoked by the primary device, in which case syncing
+    /// stops. Nothing is removed from the device until the revocation is confirmed by the user.
+    pub async fn sync_devices(&mut self) -> Result<bool, Error> {
+        let remote_cid = self.import_identity_remote().await?;
+
+        let changed = self.root_document.sync_root_cid(remote_cid).await?;
+
+        let own_device = self.device_key()?;
+
+        if self.is_device_revoked().await? {
+            tracing::warn!(device = %own_device, "Device has been revoked");
+            self.emit_event(MultiPassEventKind::DeviceRevoked { did: own_device })
+                .await;
+            return Ok(true);
+        }
+
+        if self.root_document.export_root_cid().await? != remote_cid {
+            self.export_root_document().await?;
+        }
+
+        if changed {
+            // friends added on another device are tracked by the phonebook as well
+            for friend in self.friends_list().await.unwrap_or_default() {
+                _ = self.phonebook.add_friend(&friend).await;
+            }
+
+            self.emit_event(MultiPassEventKind::IdentitySynced {
+                did: (*self.did_key).clone(),
+            })
+            .await;
+        }
+
+        Ok(false)
+    }
+
+    /// Removes the identity from every shuttle node, along with the mailboxes and content stored for it
+    pub async fn unregister(&self) -> Result<(), Error> {
+        let mut result = Ok(());
+        if let DiscoveryConfig::Shuttle { addresses } = self.discovery.discovery_config() {
+            for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                let (tx, rx) = futures::channel::oneshot::channel();
+                let _ = self
+                    .identity_command
+                    .clone()
+                    .send(shuttle::identity::client::IdentityCommand::Unregister {
+                        peer_id,
+                        response: tx,
+                    })
+                    .await;
+
+                match rx.timeout(SHUTTLE_TIMEOUT).await {
+                    Ok(Ok(Ok(_))) | Ok(Ok(Err(Error::IdentityDoesntExist))) => {}
+                    Ok(Ok(Err(e))) => {
+                        tracing::error!("Error unregistering identity from {peer_id}: {e}");
+                        result = Err(e);
+                    }
+                    Ok(Err(Canceled)) => {
+                        tracing::error!("Channel been unexpectedly closed for {peer_id}");
+                        result = Err(Error::OtherWithContext(format!(
+                            "unable to unregister from {peer_id}"
+                        )));
+                    }
+                    Err(_) => {
+                        tracing::error!("Request timeout for {peer_id}");
+                        result = Err(Error::OtherWithContext(format!(
+                            "unable to unregister from {peer_id}"
+                        )));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Publishes the file to every shuttle node, which serve its contents through their gateway
+    pub async fn publish_file(&self, file: &File) -> Result<(), Error> {
+        let cid = file
+            .reference()
+            .ok_or(Error::FileNotFound)?
+            .parse::<IpfsPath>()?
+            .root()
+            .cid()
+            .copied()
+            .ok_or(Error::FileNotFound)?;
+
+        let document = PublishedDocument::new(
+            cid,
+            (*self.did_key).clone(),
+            &file.name(),
+            file.size() as _,
+            file.file_type(),
+        );
+
+        self.publish_command(|peer_id, response| {
+            shuttle::identity::client::IdentityCommand::Publish {
+                peer_id,
+                document: document.clone(),
+                response,
+            }
+        })
+        .await
+    }
+
+    /// Removes the file published through [`IdentityStore::publish_file`] from every shuttle node
+    pub async fn unpublish_file(&self, file: &File) -> Result<(), Error> {
+        let cid = file
+            .reference()
+            .ok_or(Error::FileNotFound)?
+            .parse::<IpfsPath>()?
+            .root()
+            .cid()
+            .copied()
+            .ok_or(Error::FileNotFound)?;
+
+        self.publish_command(|peer_id, response| {
+            shuttle::identity::client::IdentityCommand::Unpublish {
+                peer_id,
+                cid,
+                response,
+            }
+        })
+        .await
+    }
+
+    async fn publish_command<F>(&self, command: F) -> Result<(), Error>
+    where
+        F: Fn(
+            PeerId,
+            oneshot::Sender<Result<(), Error>>,
+        ) -> shuttle::identity::client::IdentityCommand,
+    {
+        let DiscoveryConfig::Shuttle { addresses } = self.discovery.discovery_config() else {
+            return Err(Error::OtherWithContext(
+                "Publishing requires a shuttle node".into(),
+            ));
+        };
+
+        let mut result = Ok(());
+        for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+            let (tx, rx) = futures::channel::oneshot::channel();
+            let _ = self
+                .identity_command
+                .clone()
+                .send(command(peer_id, tx))
+                .await;
+
+            match rx.timeout(SHUTTLE_TIMEOUT).await {
+                Ok(Ok(Ok(_))) => {}
+                Ok(Ok(Err(e))) => {
+                    tracing::error!("Error publishing to {peer_id}: {e}");
+                    result = Err(e);
+                }
+                Ok(Err(Canceled)) => {
+                    tracing::error!("Channel been unexpectedly closed for {peer_id}");
+                    result = Err(Error::OtherWithContext(format!(
+                        "unable to publish to {peer_id}"
+                    )));
+                }
+                Err(_) => {
+                    tracing::error!("Request timeout for {peer_id}");
+                    result = Err(Error::OtherWithContext(format!(
+                        "unable to publish to {peer_id}"
+                    )));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Commit to a recovery key, which is able to rotate the identity to a new key
+    pub async fn set_recovery_key(&mut self, recovery: &DID) -> Result<(), Error> {
+        if recovery.eq(&*self.did_key) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let mut identity = self.own_identity_document().await?;
+        identity.recovery = Some(recovery.clone());
+        self.identity_update(identity).await
+    }
+
+    pub async fn recovery_key(&self) -> Result<Option<DID>, Error> {
+        self.own_identity_document()
+            .await
+            .map(|identity| identity.recovery)
+    }
+
+    /// Issue a statement rotating the identity to a new key, to be published by the new identity.
+    /// The friends and blocked identities are included so the new identity can carry them over
+    pub async fn issue_rotation(&self, new: &DID) -> Result<Vec<u8>, Error> {
+        let document = RotationDocument::new(&self.did_key, new)?;
+        let contacts = RotationContacts {
+            friends: self.friends_list().await?,
+            blocks: self.block_list().await?,
+        };
+        RotationBundle::new(&self.did_key, document, &contacts)?.to_bytes()
+    }
+
+    /// Accept and publish a statement rotating a previous identity to the current identity
+    pub async fn publish_rotation(&self, statement: &[u8]) -> Result<(), Error> {
+        let bundle = RotationBundle::from_bytes(statement)?;
+        let contacts = bundle.contacts(&self.did_key)?;
+        let document = bundle.document.accept(&self.did_key)?;
+        document.verify(None)?;
+
+        self.import_contacts(&document.old, contacts).await?;
+        self.announce_rotation(document).await
+    }
+
+    /// Carry over the contacts of the previous identity, so friends that migrate the friendship to
+    /// the current identity are friends on both sides
+    async fn import_contacts(&self, old: &DID, contacts: RotationContacts) -> Result<(), Error> {
+        let own_did = &*self.did_key;
+
+        for did in contacts.blocks.iter().filter(|did| did.ne(&own_did)) {
+            if !self.is_blocked(did).await? {
+                self.root_document.add_block(did).await?;
+            }
+        }
+
+        for did in contacts
+            .friends
+            .iter()
+            .filter(|did| did.ne(&own_did) && did.ne(&old))
+        {
+            if self.is_friend(did).await? || self.is_blocked(did).await? {
+                continue;
+            }
+
+            self.root_document.add_friend(did).await?;
+
+            if let Err(e) = self.phonebook.add_friend(did).await {
+                error!("Error adding {did} to phonebook: {e}");
+            }
+        }
+
+        _ = self.export_root_document().await;
+
+        Ok(())
+    }
+
+    /// Rotate a previous identity to the current identity using the recovery key committed by the previous identity
+    pub async fn recover_identity(&self, old: &DID, recovery: &DID) -> Result<(), Error> {
+        let document = RotationDocument::new_with_recovery(old, recovery, &self.did_key)?
+            .accept(&self.did_key)?;
+        self.announce_rotation(document).await
+    }
+
+    async fn announce_rotation(&self, document: RotationDocument) -> Result<(), Error> {
+        let recovery = self.committed_recovery_key(&document.old).await;
+        document.verify(recovery.as_ref())?;
+
+        let payload = PayloadRequest::new(self.ipfs.keypair(), None, document.clone())?;
+        let bytes = serde_json::to_vec(&payload)?;
+        _ = self.ipfs.pubsub_publish(IDENTITY_ANNOUNCEMENT, bytes).await;
+
+        let mut result = Ok(());
+        if let DiscoveryConfig::Shuttle { addresses } = self.discovery.discovery_config() {
+            for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                let (tx, rx) = futures::channel::oneshot::channel();
+                let _ = self
+                    .identity_command
+                    .clone()
+                    .send(
+                        shuttle::identity::client::IdentityCommand::PublishRotation {
+                            peer_id,
+                            document: document.clone(),
+                            response: tx,
+                        },
+                    )
+                    .await;
+
+                match rx.timeout(SHUTTLE_TIMEOUT).await {
+                    Ok(Ok(Ok(_))) => {}
+                    Ok(Ok(Err(e))) => {
+                        tracing::error!("Error registering rotation with {peer_id}: {e}");
+                        result = Err(e);
+                    }
+                    Ok(Err(Canceled)) => {
+                        tracing::error!("Channel been unexpectedly closed for {peer_id}");
+                        result = Err(Error::OtherWithContext(format!(
+                            "unable to register rotation with {peer_id}"
+                        )));
+                    }
+                    Err(_) => {
+                        tracing::error!("Request timeout for {peer_id}");
+                        result = Err(Error::OtherWithContext(format!(
+                            "unable to register rotation with {peer_id}"
+                        )));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Recovery key an identity has committed to in its document, if the document is known
+    async fn committed_recovery_key(&self, did: &DID) -> Option<DID> {
+        if self.identity_cache.get(did).await.is_err() {
+            _ = self.lookup(LookupBy::DidKey(did.clone())).await;
+        }
+
+        self.identity_cache
+            .get(did)
+            .await
+            .ok()
+            .and_then(|document| document.recovery)
+    }
+
+    /// Migrate the friend list, block lists and requests of a contact that rotated to a new key
+    async fn process_rotation(&mut self, document: RotationDocument) -> Result<(), Error> {
+        let own_did = &*self.did_key;
+
+        if document.old.eq(own_did) || document.new.eq(own_did) {
+            return Ok(());
+        }
+
+        let mut log = RotationLog::load(&self.ipfs).await;
+
+        // A statement already processed is only replaced by a statement issued by the recovery key,
+        // in which case the contacts migrated to the key of the previous statement are migrated again
+        let (old, recovery, superseded) = match log.get(&document.old) {
+            Some(processed) => {
+                if !document.supersedes(&processed.document) {
+                    return Ok(());
+                }
+
+                let recovery = match processed.recovery.clone() {
+                    Some(recovery) => Some(recovery),
+                    None => self.committed_recovery_key(&document.old).await,
+                };
+
+                (processed.document.new.clone(), recovery, true)
+            }
+            None => {
+                // Only statements of contacts or identities already resolved, such as conversation members,
+                // are processed so an announced statement cannot trigger a lookup of an arbitrary identity
+                if !self.is_known(&document.old).await? {
+                    return Ok(());
+                }
+
+                let recovery = self.committed_recovery_key(&document.old).await;
+                (document.old.clone(), recovery, false)
+            }
+        };
+
+        document.verify(recovery.as_ref())?;
+
+        if old.eq(own_did) {
+            return Ok(());
+        }
+
+        let new = document.new.clone();
+
+        let mut migrated = superseded || self.identity_cache.get(&old).await.is_ok();
+
+        if self.is_friend(&old).await? {
+            self.root_document.remove_friend(&old).await?;
+
+            if let Err(e) = self.phonebook.remove_friend(&old).await {
+                error!("Error removing {old} from phonebook: {e}");
+            }
+
+            if !self.is_friend(&new).await? && !self.is_blocked(&new).await? {
+                self.root_document.add_friend(&new).await?;
+
+                if let Err(e) = self.phonebook.add_friend(&new).await {
+                    error!("Error adding {new} to phonebook: {e}");
+                }
+            }
+
+            migrated = true;
+        }
+
+        if self.is_blocked(&old).await? {
+            self.root_document.remove_block(&old).await?;
+
+            if !self.is_blocked(&new).await? {
+                self.root_document.add_block(&new).await?;
+            }
+
+            migrated = true;
+        }
+
+        if self.is_blocked_by(&old).await? {
+            self.root_document.remove_block_by(&old).await?;
+
+            if !self.is_blocked_by(&new).await? {
+                self.root_document.add_block_by(&new).await?;
+            }
+
+            migrated = true;
+        }
+
+        // Requests were signed with the previous key and would have to be sent again by the new identity
+        let list = self.list_all_raw_request().await?;
+        for req in list.iter().filter(|req| req.did().eq(&old)) {
+            self.root_document.remove_request(req).await?;
+            migrated = true;
+        }
+
+        if !migrated {
+            return Ok(());
+        }
+
+        log.insert(document, recovery);
+        if let Err(e) = log.save(&self.ipfs).await {
+            error!("Error saving rotation of {old}: {e}");
+        }
+
+        tracing::info!(%old, %new, "Identity has been rotated");
+
+        self.queue.remove(&old).await;
+        _ = self.identity_cache.remove(&old).await;
+        _ = self.export_root_document().await;
+
+        if !self.discovery.contains(&new).await {
+            let _ = self.discovery.insert(&new).await;
+        }
+
+        self.emit_event(MultiPassEventKind::IdentityRotated { old, new })
+            .await;
+
+        Ok(())
+    }
+
+    /// Whether the identity is a contact, has a pending request or has been resolved previously
+    async fn is_known(&self, did: &DID) -> Result<bool, Error> {
+        if self.identity_cache.get(did).await.is_ok()
+            || self.is_friend(did).await?
+            || self.is_blocked(did).await?
+            || self.is_blocked_by(did).await?
+        {
+            return Ok(true);
+        }
+
+        let list = self.list_all_raw_request().await?;
+        Ok(list.iter().any(|req| req.did().eq(did)))
+    }
+
+    /// Fetch the statements of contacts that rotated their identity while we were offline
+    async fn fetch_rotations(&mut self) -> Result<(), Error> {
+        let mut dids = self.friends_list().await?;
+        dids.extend(self.block_list().await?);
+        dids.extend(self.block_by_list().await?);
+        dids.extend(
+            self.list_all_raw_request()
+                .await?
+                .iter()
+                .map(|req| req.did().clone()),
+        );
+        // Statements issued by the previous key may since have been superseded by the recovery key
+        dids.extend(RotationLog::load(&self.ipfs).await.supersedable());
+
+        if dids.is_empty() {
+            return Ok(());
+        }
+
+        if let DiscoveryConfig::Shuttle { addresses } = self.discovery.discovery_config() {
+            for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                let (tx, rx) = futures::channel::oneshot::channel();
+                let _ = self
+                    .identity_command
+                    .clone()
+                    .send(shuttle::identity::client::IdentityCommand::FetchRotations {
+                        peer_id,
+                        dids: dids.clone(),
+                        response: tx,
+                    })
+                    .await;
+
+                match rx.timeout(SHUTTLE_TIMEOUT).await {
+                    Ok(Ok(Ok(list))) => {
+                        for document in list {
+                            let old = document.old.clone();
+                            if let Err(e) = self.process_rotation(document).await {
+                                tracing::warn!(%old, error = %e, "Unable to process rotation");
+                            }
+                        }
+                        break;
+                    }
+                    Ok(Ok(Err(e))) => {
+                        tracing::error!("Error fetching rotations from {peer_id}: {e}");
+                        break;
+                    }
+                    Ok(Err(Canceled)) => {
+                        tracing::error!("Channel been unexpectedly closed for {peer_id}");
+                        continue;
+                    }
+                    Err(_) => {
+                        tracing::error!("Request timeout for {peer_id}");
+                        continue;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn fetch_mailbox(&mut self) -> Result<(), Error> {
         if let DiscoveryConfig::Shuttle { addresses } = self.discovery.discovery_config() {
             for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
@@ -88,6 +88,42 @@ impl Keystore {
             .map(|list| list.len())
             .ok_or(Error::PublicKeyDoesntExist)
     }
+
+    /// Insert the keys from another keystore of the conversation that are missing from this keystore,
+    /// returning true if any key was inserted
+    pub fn merge(&mut self, did: &DID, other: &Keystore) -> Result<bool, Error> {
+        let mut merged = false;
+
+        for recipient in other.recipient_key.keys() {
+            let current = self.get_all(did, recipient).unwrap_or_default();
+            for key in other.get_all(did, recipient)? {
+                if current.contains(&key) {
+                    continue;
+                }
+                self.insert(did, recipient, key)?;
+                merged = true;
+            }
+        }
+
+        Ok(merged)
+    }
+
+    /// Make the keys of a recipient that rotated their identity available under the new key, until the new
+    /// identity exchanges its own key. The keys remain under the previous key so earlier messages can still
+    /// be decrypted. Returns true if any key was migrated
+    pub fn migrate(&mut self, did: &DID, old: &DID, new: &DID) -> Result<bool, Error> {
+        if self.exist(new) {
+            return Ok(false);
+        }
+
+        let keys = self.get_all(did, old).unwrap_or_default();
+
+        for key in &keys {
+            self.insert(did, new, key)?;
+        }
+
+        Ok(!keys.is_empty())
+    }
 }
 
 #[allow(dead_code)]
@@ -204,6 +240,55 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn keystore_migrate() -> anyhow::Result<()> {
+        let mut keystore = Keystore::default();
+
+        let keypair = DID::default();
+        let old = DID::default();
+        let new = DID::default();
+
+        let key = generate::<32>();
+        keystore.insert(&keypair, &old, key)?;
+
+        assert!(keystore.migrate(&keypair, &old, &new)?);
+        assert_eq!(keystore.get_latest(&keypair, &new)?, key);
+        assert_eq!(keystore.get_latest(&keypair, &old)?, key);
+
+        // keys exchanged by the new identity are not replaced
+        let new_key = generate::<32>();
+        let mut keystore = Keystore::default();
+        keystore.insert(&keypair, &old, key)?;
+        keystore.insert(&keypair, &new, new_key)?;
+        assert!(!keystore.migrate(&keypair, &old, &new)?);
+        assert_eq!(keystore.get_latest(&keypair, &new)?, new_key);
+        Ok(())
+    }
+
+    #[test]
+    fn keystore_merge() -> anyhow::Result<()> {
+        let keypair = DID::default();
+        let recipient = DID::default();
+
+        let key_1 = generate::<32>();
+        let key_2 = generate::<32>();
+
+        let mut keystore = Keystore::default();
+        keystore.insert(&keypair, &recipient, key_1)?;
+
+        let mut other = Keystore::default();
+        other.insert(&keypair, &recipient, key_1)?;
+        other.insert(&keypair, &recipient, key_2)?;
+
+        assert!(keystore.merge(&keypair, &other)?);
+        assert_eq!(keystore.count(&recipient)?, 2);
+        assert_eq!(keystore.get_latest(&keypair, &recipient)?, key_2);
+
+        assert!(!keystore.merge(&keypair, &other)?);
+
+        Ok(())
+    }
+
     #[test]
     fn keystore_try_decrypt() -> anyhow::Result<()> {
         let mut rng = rand::thread_rng();
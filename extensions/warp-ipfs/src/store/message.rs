@@ -1,4 +1,4 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use either::Either;
 use futures_timeout::TimeoutExt;
 use futures_timer::Delay;
@@ -7,7 +7,8 @@ use tracing::info;
 
 use std::{
     collections::{
-        btree_map::Entry as BTreeEntry, hash_map::Entry as HashEntry, BTreeMap, HashMap, HashSet,
+        btree_map::Entry as BTreeEntry, hash_map::Entry as HashEntry, BTreeMap, BTreeSet, HashMap,
+        HashSet,
     },
     ffi::OsStr,
     path::{Path, PathBuf},
@@ -25,7 +26,7 @@ use futures::{
     FutureExt, SinkExt, Stream, StreamExt, TryFutureExt,
 };
 use libipld::Cid;
-use rust_ipfs::{libp2p::gossipsub::Message, p2p::MultiaddrExt, Ipfs, PeerId};
+use rust_ipfs::{libp2p::gossipsub::Message, p2p::MultiaddrExt, Ipfs, IpfsPath, PeerId};
 
 use serde::{Deserialize, Serialize};
 use tokio::select;
@@ -40,16 +41,21 @@ use warp::{
     multipass::MultiPassEventKind,
     raygun::{
         AttachmentEventStream, AttachmentKind, Conversation, ConversationSettings,
-        ConversationType, DirectConversationSettings, GroupSettings, Location, MessageEvent,
-        MessageEventKind, MessageOptions, MessageReference, MessageStatus, MessageType, Messages,
-        MessagesType, PinState, RayGunEventKind, ReactionState,
+        ConversationType, DirectConversationSettings, DraftMessage, EmbedState, GroupRole,
+        GroupSettings, Location, MessageEvent, MessageEventKind, MessageOptions, MessageReference,
+        MessageRevision, MessageStatus, MessageType, Messages, MessagesType, PinState, Poll,
+        RayGunEventKind, ReactionState, ScheduledMessage,
     },
 };
 
 use crate::{
     config,
+    embed::{remove_thumbnails, EmbedBuilder, EmbedDocument, EmbedFetcher},
     store::{
-        conversation::{ConversationDocument, MessageDocument},
+        conversation::{
+            ConversationDocument, MessageDocument, PollVoteDocument, ReceiptDocument, ReceiptKind,
+            RoleDocument,
+        },
         discovery::Discovery,
         ecdh_decrypt, ecdh_encrypt, ecdh_shared_key,
         event_subscription::EventSubscription,
@@ -67,11 +73,19 @@ use crate::{
 };
 
 use super::{
-    document::root::RootDocumentMap, ds_key::DataStoreKey, MAX_MESSAGE_SIZE, SHUTTLE_TIMEOUT,
+    archive::{ArchiveReader, ArchiveWriter, ArchivedConversation, ArchivedMessage},
+    document::root::RootDocumentMap,
+    ds_key::DataStoreKey,
+    search::{MessageSearchIndex, SearchQuery},
+    MAX_ACTIVE_EVENTS, MAX_EVENT_NAMESPACE_LENGTH, MAX_EVENT_PAYLOAD_SIZE, MAX_EVENT_TTL,
+    MAX_MESSAGE_SIZE, MAX_POLL_OPTIONS, MIN_POLL_OPTIONS, SHUTTLE_TIMEOUT,
 };
 
 const CHAT_DIRECTORY: &str = "chat_media";
 
+/// Delay before a scheduled message that failed to send is attempted again
+const SCHEDULED_RETRY_DELAY: Duration = Duration::from_secs(30);
+
 pub type DownloadStream = BoxStream<'static, Result<Vec<u8>, Error>>;
 
 enum MessagingCommand {
@@ -94,6 +108,8 @@ impl MessageStore {
         event: EventSubscription<RayGunEventKind>,
         identity: IdentityStore,
         message_command: mpsc::Sender<shuttle::message::client::MessageCommand>,
+        disable_read_receipts: bool,
+        embed_fetcher: Option<Arc<dyn EmbedFetcher>>,
     ) -> Self {
         info!("Initializing MessageStore");
 
@@ -124,6 +140,13 @@ impl MessageStore {
             pending_key_exchange: Default::default(),
             message_command,
             queue: Default::default(),
+            search_index: MessageSearchIndex::new(ipfs, keypair.clone()).await,
+            active_events: Vec::new(),
+            expiring: HashMap::new(),
+            scheduled: Vec::new(),
+            scheduled_retry: HashMap::new(),
+            disable_read_receipts,
+            embeds: embed_fetcher.map(|fetcher| EmbedBuilder::new(ipfs, fetcher)),
         };
 
         if let Err(e) = inner.migrate().await {
@@ -257,6 +280,15 @@ impl MessageStore {
         inner.get_message(conversation_id, message_id).await
     }
 
+    pub async fn message_revisions(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.message_revisions(conversation_id, message_id).await
+    }
+
     pub async fn get_messages(
         &self,
         conversation_id: Uuid,
@@ -291,6 +323,24 @@ impl MessageStore {
         inner.get_message_references(conversation_id, opt).await
     }
 
+    pub async fn get_thread(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<warp::raygun::Message>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get_thread(conversation_id, message_id).await
+    }
+
+    pub async fn search_messages(
+        &self,
+        conversation_id: Option<Uuid>,
+        query: &str,
+    ) -> Result<Vec<MessageReference>, Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.search_messages(conversation_id, query).await
+    }
+
     pub async fn update_conversation_name(
         &self,
         conversation_id: Uuid,
@@ -311,6 +361,27 @@ impl MessageStore {
             .await
     }
 
+    pub async fn export_conversation(
+        &self,
+        conversation_id: Uuid,
+        path: PathBuf,
+        passphrase: Option<&str>,
+    ) -> Result<(), Error> {
+        let inner = &*self.inner.read().await;
+        inner
+            .export_conversation(conversation_id, path, passphrase)
+            .await
+    }
+
+    pub async fn import_conversation(
+        &self,
+        path: PathBuf,
+        passphrase: Option<&str>,
+    ) -> Result<Uuid, Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.import_conversation(path, passphrase).await
+    }
+
     pub async fn delete_conversation(&self, conversation_id: Uuid) -> Result<(), Error> {
         let inner = &mut *self.inner.write().await;
         inner.delete_conversation(conversation_id, true).await
@@ -326,6 +397,46 @@ impl MessageStore {
         inner.remove_recipient(conversation_id, did, true).await
     }
 
+    pub async fn set_role(
+        &self,
+        conversation_id: Uuid,
+        did: &DID,
+        role: GroupRole,
+    ) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.set_role(conversation_id, did, role).await
+    }
+
+    pub async fn transfer_ownership(&self, conversation_id: Uuid, did: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.transfer_ownership(conversation_id, did).await
+    }
+
+    pub async fn ban(&self, conversation_id: Uuid, did: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.ban(conversation_id, did).await
+    }
+
+    pub async fn unban(&self, conversation_id: Uuid, did: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.unban(conversation_id, did).await
+    }
+
+    pub async fn list_banned(&self, conversation_id: Uuid) -> Result<Vec<DID>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.list_banned(conversation_id).await
+    }
+
+    pub async fn add_restricted(&self, conversation_id: Uuid, did: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.add_restricted(conversation_id, did).await
+    }
+
+    pub async fn remove_restricted(&self, conversation_id: Uuid, did: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.remove_restricted(conversation_id, did).await
+    }
+
     pub async fn message_status(
         &self,
         conversation_id: Uuid,
@@ -335,13 +446,133 @@ impl MessageStore {
         inner.message_status(conversation_id, message_id).await
     }
 
+    pub async fn mark_as_read(&self, conversation_id: Uuid, message_id: Uuid) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.mark_as_read(conversation_id, message_id).await
+    }
+
+    pub async fn message_receipts(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<HashMap<DID, MessageStatus>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.message_receipts(conversation_id, message_id).await
+    }
+
+    pub async fn unread_count(&self, conversation_id: Uuid) -> Result<usize, Error> {
+        let inner = &*self.inner.read().await;
+        inner.unread_count(conversation_id).await
+    }
+
+    pub async fn save_draft(
+        &self,
+        conversation_id: Uuid,
+        lines: Vec<String>,
+    ) -> Result<Uuid, Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.save_draft(conversation_id, lines).await
+    }
+
+    pub async fn update_draft(
+        &self,
+        conversation_id: Uuid,
+        draft_id: Uuid,
+        lines: Vec<String>,
+    ) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.update_draft(conversation_id, draft_id, lines).await
+    }
+
+    pub async fn list_drafts(&self, conversation_id: Uuid) -> Result<Vec<DraftMessage>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.list_drafts(conversation_id).await
+    }
+
+    pub async fn delete_draft(&self, conversation_id: Uuid, draft_id: Uuid) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.delete_draft(conversation_id, draft_id).await
+    }
+
+    pub async fn schedule_message(
+        &self,
+        conversation_id: Uuid,
+        lines: Vec<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<Uuid, Error> {
+        let inner = &mut *self.inner.write().await;
+        inner
+            .schedule_message(conversation_id, lines, send_at)
+            .await
+    }
+
+    pub async fn list_scheduled_messages(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<ScheduledMessage>, Error> {
+        let inner = &*self.inner.read().await;
+        inner.list_scheduled_messages(conversation_id).await
+    }
+
+    pub async fn update_scheduled_message(
+        &self,
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+        lines: Vec<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner
+            .update_scheduled_message(conversation_id, scheduled_id, lines, send_at)
+            .await
+    }
+
+    pub async fn cancel_scheduled_message(
+        &self,
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+    ) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner
+            .cancel_scheduled_message(conversation_id, scheduled_id)
+            .await
+    }
+
     pub async fn send_message(
         &self,
         conversation_id: Uuid,
         lines: Vec<String>,
     ) -> Result<Uuid, Error> {
+        // previews are built prior to acquiring the lock as fetching the pages may take some time
+        let embeds = self.build_embeds(&lines).await;
+        let inner = &mut *self.inner.write().await;
+        let result = inner
+            .send_message(conversation_id, lines, embeds.clone())
+            .await;
+        if result.is_err() {
+            remove_thumbnails(&inner.ipfs, &embeds, &[]).await;
+        }
+        result
+    }
+
+    pub async fn send_poll(&self, conversation_id: Uuid, poll: Poll) -> Result<Uuid, Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.send_poll(conversation_id, poll).await
+    }
+
+    pub async fn vote(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        choices: Vec<usize>,
+    ) -> Result<(), Error> {
         let inner = &mut *self.inner.write().await;
-        inner.send_message(conversation_id, lines).await
+        inner.vote(conversation_id, message_id, choices).await
+    }
+
+    pub async fn close_poll(&self, conversation_id: Uuid, message_id: Uuid) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.close_poll(conversation_id, message_id).await
     }
 
     pub async fn edit_message(
@@ -350,8 +581,25 @@ impl MessageStore {
         message_id: Uuid,
         lines: Vec<String>,
     ) -> Result<(), Error> {
+        // previews are rebuilt prior to acquiring the lock since the links within the message may have changed
+        let embeds = match self.inner.read().await.embeds.clone() {
+            Some(builder) => Some(builder.build(&lines).await),
+            None => None,
+        };
+
         let inner = &mut *self.inner.write().await;
-        inner.edit_message(conversation_id, message_id, lines).await
+
+        if let Err(e) = inner.edit_message(conversation_id, message_id, lines).await {
+            if let Some(embeds) = embeds {
+                remove_thumbnails(&inner.ipfs, &embeds, &[]).await;
+            }
+            return Err(e);
+        }
+
+        match embeds {
+            Some(embeds) => inner.set_embeds(conversation_id, message_id, embeds).await,
+            None => Ok(()),
+        }
     }
 
     pub async fn reply(
@@ -360,10 +608,57 @@ impl MessageStore {
         message_id: Uuid,
         lines: Vec<String>,
     ) -> Result<Uuid, Error> {
+        let embeds = self.build_embeds(&lines).await;
         let inner = &mut *self.inner.write().await;
-        inner
-            .reply_message(conversation_id, message_id, lines)
-            .await
+        let result = inner
+            .reply_message(conversation_id, message_id, lines, embeds.clone())
+            .await;
+        if result.is_err() {
+            remove_thumbnails(&inner.ipfs, &embeds, &[]).await;
+        }
+        result
+    }
+
+    pub async fn embeds(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        state: EmbedState,
+    ) -> Result<(), Error> {
+        let embeds = match state {
+            EmbedState::Enabled => {
+                let (builder, lines) = {
+                    let inner = &*self.inner.read().await;
+                    let builder = inner.embeds.clone().ok_or(Error::Unimplemented)?;
+                    let message = inner.get_message(conversation_id, message_id).await?;
+                    (builder, message.lines())
+                };
+                builder.build(&lines).await
+            }
+            EmbedState::Disable => vec![],
+        };
+
+        let inner = &mut *self.inner.write().await;
+        let result = inner
+            .set_embeds(conversation_id, message_id, embeds.clone())
+            .await;
+        if result.is_err() {
+            remove_thumbnails(&inner.ipfs, &embeds, &[]).await;
+        }
+        result
+    }
+
+    async fn build_embeds(&self, lines: &[String]) -> Vec<EmbedDocument> {
+        let builder = self.inner.read().await.embeds.clone();
+        match builder {
+            Some(builder) => builder.build(lines).await,
+            None => vec![],
+        }
+    }
+
+    pub async fn rotate_keys(&self) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.rotate_keys().await
     }
 
     pub async fn delete_message(
@@ -441,9 +736,10 @@ impl MessageStore {
         &self,
         conversation_id: Uuid,
         event: MessageEvent,
+        ttl: Duration,
     ) -> Result<(), Error> {
         let inner = &mut *self.inner.write().await;
-        inner.send_event(conversation_id, event).await
+        inner.send_event(conversation_id, event, ttl).await
     }
 
     pub async fn cancel_event(
@@ -491,6 +787,14 @@ impl ConversationTask {
 
         let mut check_mailbox = Delay::new(Duration::from_secs(5));
 
+        let mut event_expiry_timer = Delay::new(Duration::from_secs(1));
+
+        let mut message_expiry_timer = Delay::new(Duration::from_secs(5));
+
+        let mut scheduled_timer = Delay::new(Duration::from_secs(1));
+
+        let mut search_index_timer = Delay::new(Duration::from_secs(5));
+
         loop {
             tokio::select! {
                 biased;
@@ -604,6 +908,31 @@ impl ConversationTask {
                     _ = inner.load_from_mailbox().await;
                     check_mailbox.reset(Duration::from_secs(60));
                 }
+                _ = &mut event_expiry_timer => {
+                    let inner = &mut *self.inner.write().await;
+                    if !inner.active_events.is_empty() {
+                        process_expired_events(inner).await;
+                    }
+                    event_expiry_timer.reset(Duration::from_secs(1));
+                }
+                _ = &mut message_expiry_timer => {
+                    let inner = &mut *self.inner.write().await;
+                    if !inner.expiring.is_empty() {
+                        process_expired_messages(inner).await;
+                    }
+                    message_expiry_timer.reset(Duration::from_secs(5));
+                }
+                _ = &mut scheduled_timer => {
+                    process_scheduled_messages(&self.inner).await;
+                    scheduled_timer.reset(Duration::from_secs(1));
+                }
+                _ = &mut search_index_timer => {
+                    let inner = &mut *self.inner.write().await;
+                    if let Err(e) = inner.search_index.flush().await {
+                        tracing::warn!(error = %e, "unable to store search index");
+                    }
+                    search_index_timer.reset(Duration::from_secs(5));
+                }
             }
         }
     }
@@ -628,6 +957,15 @@ struct ConversationInner {
     message_command: mpsc::Sender<shuttle::message::client::MessageCommand>,
     // Note: Temporary
     queue: HashMap<DID, Vec<Queue>>,
+    search_index: MessageSearchIndex,
+    active_events: Vec<ActiveEvent>,
+    /// Messages that are set to expire within each conversation, ordered by the date they expire
+    expiring: HashMap<Uuid, BTreeSet<(DateTime<Utc>, Uuid)>>,
+    scheduled: Vec<ScheduledMessage>,
+    /// Time in which scheduled messages that failed to send will be retried
+    scheduled_retry: HashMap<Uuid, Instant>,
+    disable_read_receipts: bool,
+    embeds: Option<EmbedBuilder>,
 }
 
 impl ConversationInner {
@@ -643,6 +981,10 @@ impl ConversationInner {
             if let Err(e) = self.create_conversation_task(id).await {
                 tracing::error!(id = %id, error = %e, "Failed to load conversation");
             }
+
+            if conversation.settings.message_expiry().is_some() {
+                self.refresh_expiry(&conversation).await;
+            }
         }
 
         let ipfs = &self.ipfs;
@@ -675,6 +1017,39 @@ impl ConversationInner {
         {
             self.queue = data;
         }
+
+        let key = ipfs.scheduled_messages();
+        let keypair = &self.keypair;
+
+        if let Ok(data) = futures::future::ready(
+            ipfs.repo()
+                .data_store()
+                .get(key.as_bytes())
+                .await
+                .unwrap_or_default()
+                .ok_or(Error::Other),
+        )
+        .and_then(|bytes| async move {
+            let cid_str = String::from_utf8_lossy(&bytes).to_string();
+
+            let cid = cid_str.parse::<Cid>().map_err(anyhow::Error::from)?;
+
+            Ok(cid)
+        })
+        .and_then(|cid| async move {
+            let bytes = ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized::<Vec<u8>>()
+                .await
+                .map_err(anyhow::Error::from)?;
+            let bytes = ecdh_decrypt(keypair, None, bytes)?;
+            serde_json::from_slice::<Vec<ScheduledMessage>>(&bytes).map_err(Error::from)
+        })
+        .await
+        {
+            self.scheduled = data;
+        }
     }
 
     async fn load_from_mailbox(&mut self) -> Result<(), Error> {
@@ -795,11 +1170,21 @@ impl ConversationInner {
         messages.sort_by(|a, b| b.cmp(a));
 
         let mut events = vec![];
+        let mut indexed = vec![];
+        let mut latest_received: Option<MessageDocument> = None;
+
+        let now = Utc::now();
 
         for message in messages {
             if !message.verify() {
                 continue;
             }
+
+            // messages that expired while we were offline are skipped
+            if matches!(conversation.message_expire(&message), Some(expire) if expire <= now) {
+                continue;
+            }
+
             let message_id = message.id;
             match conversation
                 .contains(&self.ipfs, message_id)
@@ -815,6 +1200,8 @@ impl ConversationInner {
                         .update_message_document(&self.ipfs, message)
                         .await?;
 
+                    indexed.push(message);
+
                     let is_edited = matches!((message.modified, current_message.modified), (Some(modified), Some(current_modified)) if modified > current_modified )
                         | matches!(
                             (message.modified, current_message.modified),
@@ -836,6 +1223,26 @@ impl ConversationInner {
                         .insert_message_document(&self.ipfs, message)
                         .await?;
 
+                    self.queue_expiry(&conversation, &message);
+
+                    indexed.push(message);
+
+                    if message.sender.to_did().ne(&*self.keypair)
+                        && latest_received
+                            .map(|latest| message.date > latest.date)
+                            .unwrap_or(true)
+                    {
+                        latest_received = Some(message);
+                    }
+
+                    if let Some(thread_id) = message.thread {
+                        events.push(MessageEventKind::ThreadReplyAdded {
+                            conversation_id,
+                            thread_id,
+                            message_id,
+                        });
+                    }
+
                     events.push(MessageEventKind::MessageReceived {
                         conversation_id,
                         message_id,
@@ -846,10 +1253,23 @@ impl ConversationInner {
 
         self.set_document(conversation).await?;
 
+        for message in indexed {
+            self.index_message_document(conversation_id, &message).await;
+        }
+
         while let Some(event) = events.pop() {
             _ = tx.send(event);
         }
 
+        if let Some(message) = latest_received {
+            if let Err(e) = self
+                .send_receipt(conversation_id, ReceiptKind::Delivered, &message)
+                .await
+            {
+                tracing::warn!(%conversation_id, error = %e, "unable to send delivery receipt");
+            }
+        }
+
         Ok(())
     }
 
@@ -1113,6 +1533,7 @@ impl ConversationInner {
 
         conversation.messages.take();
         conversation.deleted = true;
+        conversation.modified = Utc::now();
 
         self.set_document(conversation.clone()).await?;
 
@@ -1147,8 +1568,8 @@ impl ConversationInner {
     }
 
     pub async fn set_document(&mut self, mut document: ConversationDocument) -> Result<(), Error> {
-        if let Some(creator) = document.creator.as_ref() {
-            if creator.eq(&self.keypair)
+        if let Some(owner) = document.owner() {
+            if owner.eq(&self.keypair)
                 && matches!(document.conversation_type(), ConversationType::Group)
             {
                 document.sign(&self.keypair)?;
@@ -1230,16 +1651,202 @@ impl ConversationInner {
         }
     }
 
-    async fn process_msg_event(&mut self, id: Uuid, msg: Message) -> Result<(), Error> {
-        let data = Payload::from_bytes(&msg.data)?;
+    async fn save_scheduled(&self) {
+        let key = self.ipfs.scheduled_messages();
+        let current_cid = self
+            .ipfs
+            .repo()
+            .data_store()
+            .get(key.as_bytes())
+            .await
+            .unwrap_or_default()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+            .and_then(|cid_str| cid_str.parse::<Cid>().ok());
 
-        let own_did = &*self.keypair;
+        let bytes = match serde_json::to_vec(&self.scheduled)
+            .map_err(Error::from)
+            .and_then(|bytes| ecdh_encrypt(&self.keypair, None, bytes))
+        {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!(error = %e, "unable to save scheduled messages");
+                return;
+            }
+        };
 
-        let conversation = self.get(id).await?;
+        let cid = match self.ipfs.dag().put().serialize(bytes).pin(true).await {
+            Ok(cid) => cid,
+            Err(e) => {
+                tracing::error!(error = %e, "unable to save scheduled messages");
+                return;
+            }
+        };
 
-        let bytes = match conversation.conversation_type() {
-            ConversationType::Direct => {
-                let list = conversation.recipients();
+        let cid_str = cid.to_string();
+
+        if let Err(e) = self
+            .ipfs
+            .repo()
+            .data_store()
+            .put(key.as_bytes(), cid_str.as_bytes())
+            .await
+        {
+            tracing::error!(error = %e, "unable to save scheduled messages");
+            return;
+        }
+
+        if let Some(old_cid) = current_cid {
+            if old_cid != cid && self.ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                _ = self.ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+    }
+
+    async fn index_message(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        date: DateTime<Utc>,
+        lines: &[String],
+    ) {
+        // Only update conversations that have been indexed already. Any others will be indexed on first search
+        if !self.search_index.exist(conversation_id) {
+            return;
+        }
+
+        if let Err(e) = self
+            .search_index
+            .insert(conversation_id, message_id, date, lines)
+            .await
+        {
+            tracing::warn!(%conversation_id, %message_id, error = %e, "unable to index message");
+        }
+    }
+
+    async fn index_message_document(&mut self, conversation_id: Uuid, document: &MessageDocument) {
+        if !self.search_index.exist(conversation_id) {
+            return;
+        }
+
+        let keystore = match pubkey_or_keystore(&*self, conversation_id, &self.keypair).await {
+            Ok(keystore) => keystore,
+            Err(e) => {
+                tracing::warn!(%conversation_id, error = %e, "unable to index message");
+                return;
+            }
+        };
+
+        let message = match document
+            .resolve(&self.ipfs, &self.keypair, true, keystore.as_ref())
+            .await
+        {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!(%conversation_id, message_id = %document.id, error = %e, "unable to index message");
+                return;
+            }
+        };
+
+        self.index_message(
+            conversation_id,
+            document.id,
+            document.date,
+            &message.lines(),
+        )
+        .await;
+    }
+
+    async fn unindex_message(&mut self, conversation_id: Uuid, message_id: Uuid) {
+        if let Err(e) = self.search_index.remove(conversation_id, message_id).await {
+            tracing::warn!(%conversation_id, %message_id, error = %e, "unable to remove message from index");
+        }
+    }
+
+    /// Build the search index for the conversation from the messages stored locally
+    async fn build_search_index(&mut self, conversation_id: Uuid) -> Result<(), Error> {
+        let conversation = self.get(conversation_id).await?;
+        let keystore = pubkey_or_keystore(&*self, conversation_id, &self.keypair).await?;
+
+        let mut messages = vec![];
+
+        for document in conversation.get_message_list(&self.ipfs).await? {
+            match document
+                .resolve(&self.ipfs, &self.keypair, true, keystore.as_ref())
+                .await
+            {
+                Ok(message) => messages.push((document.id, document.date, message)),
+                Err(e) => {
+                    tracing::warn!(%conversation_id, message_id = %document.id, error = %e, "unable to index message");
+                }
+            }
+        }
+
+        let index = self.search_index.get(conversation_id).await?;
+
+        for (message_id, date, message) in messages {
+            index.insert(message_id, date, &message.lines());
+        }
+
+        self.search_index.save(conversation_id).await
+    }
+
+    pub async fn search_messages(
+        &mut self,
+        conversation_id: Option<Uuid>,
+        query: &str,
+    ) -> Result<Vec<MessageReference>, Error> {
+        let query = SearchQuery::parse(query);
+
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let conversations = match conversation_id {
+            Some(id) => vec![self.get(id).await?.id()],
+            None => self
+                .list()
+                .await
+                .iter()
+                .map(|document| document.id())
+                .collect(),
+        };
+
+        for id in &conversations {
+            if !self.search_index.exist(*id) {
+                self.build_search_index(*id).await?;
+            }
+        }
+
+        let results = self.search_index.search(&conversations, &query).await?;
+
+        let mut references = Vec::with_capacity(results.len());
+
+        for (conversation_id, message_id) in results {
+            match self
+                .get_message_reference(conversation_id, message_id)
+                .await
+            {
+                Ok(reference) => references.push(reference),
+                Err(_) => {
+                    // Message no longer exist so we can remove it from the index
+                    self.unindex_message(conversation_id, message_id).await;
+                }
+            }
+        }
+
+        Ok(references)
+    }
+
+    async fn process_msg_event(&mut self, id: Uuid, msg: Message) -> Result<(), Error> {
+        let data = Payload::from_bytes(&msg.data)?;
+
+        let own_did = &*self.keypair;
+
+        let conversation = self.get(id).await?;
+
+        let bytes = match conversation.conversation_type() {
+            ConversationType::Direct => {
+                let list = conversation.recipients();
 
                 let recipients = list
                     .iter()
@@ -1295,7 +1902,7 @@ impl ConversationInner {
             e
         })?;
 
-        message_event(self, id, event).await?;
+        message_event(self, id, &data.sender(), event).await?;
 
         Ok(())
     }
@@ -1351,6 +1958,112 @@ impl ConversationInner {
         Ok(())
     }
 
+    /// Sends our key of the group conversation to the member
+    async fn send_key(
+        &mut self,
+        conversation_id: Uuid,
+        did: &DID,
+        raw_key: &[u8],
+    ) -> Result<(), Error> {
+        let conversation = self.get(conversation_id).await?;
+
+        let own_did = &self.keypair;
+
+        let key = ecdh_encrypt(own_did, Some(did), raw_key)?;
+
+        let response = ConversationRequestResponse::Response {
+            conversation_id,
+            kind: ConversationResponseKind::Key { key },
+        };
+
+        let topic = conversation.reqres_topic(did);
+
+        let bytes = ecdh_encrypt(own_did, Some(did), serde_json::to_vec(&response)?)?;
+        let signature = sign_serde(own_did, &bytes)?;
+
+        let payload = Payload::new(own_did, &bytes, &signature);
+
+        let peers = self.ipfs.pubsub_peers(Some(topic.clone())).await?;
+
+        let peer_id = did.to_peer_id()?;
+
+        let bytes = payload.to_bytes()?;
+
+        tracing::trace!(%conversation_id, "Payload size: {} bytes", bytes.len());
+
+        tracing::info!(%conversation_id, "Sending key to {did}");
+
+        if !peers.contains(&peer_id)
+            || (peers.contains(&peer_id)
+                && self
+                    .ipfs
+                    .pubsub_publish(topic.clone(), bytes)
+                    .await
+                    .is_err())
+        {
+            warn!(%conversation_id, "Unable to publish to topic. Queuing event");
+            self.queue_event(
+                did.clone(),
+                Queue::direct(
+                    conversation_id,
+                    None,
+                    peer_id,
+                    topic.clone(),
+                    payload.data().into(),
+                ),
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces our key of every group conversation and sends the new key to the members,
+    /// so messages sent afterward cannot be decrypted using the previous key
+    pub async fn rotate_keys(&mut self) -> Result<(), Error> {
+        let conversations = self
+            .list_stream()
+            .await
+            .filter(|conversation| {
+                futures::future::ready(matches!(
+                    conversation.conversation_type(),
+                    ConversationType::Group
+                ))
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        for conversation in conversations {
+            let conversation_id = conversation.id();
+
+            let mut keystore = match self.get_keystore(conversation_id).await {
+                Ok(keystore) => keystore,
+                Err(e) => {
+                    warn!(%conversation_id, error = %e, "Unable to obtain keystore");
+                    continue;
+                }
+            };
+
+            let key = generate::<64>();
+            keystore.insert(&self.keypair, &self.keypair, key)?;
+            self.set_keystore(conversation_id, keystore).await?;
+
+            let recipients = conversation
+                .recipients()
+                .into_iter()
+                .filter(|did| did.ne(&*self.keypair))
+                .collect::<Vec<_>>();
+
+            for recipient in recipients {
+                if let Err(e) = self.send_key(conversation_id, &recipient, &key).await {
+                    warn!(%conversation_id, %recipient, error = %e, "Unable to send key");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn messages_count(&self, conversation_id: Uuid) -> Result<usize, Error> {
         self.get(conversation_id)
             .await?
@@ -1372,6 +2085,24 @@ impl ConversationInner {
             .await
     }
 
+    async fn message_revisions(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, Error> {
+        let conversation = self.get(conversation_id).await?;
+
+        let keystore = pubkey_or_keystore(self, conversation_id, &self.keypair).await?;
+
+        let message_document = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
+
+        message_document
+            .resolve_revisions(&self.ipfs, &self.keypair, false, keystore.as_ref())
+            .await
+    }
+
     async fn get_message_reference(
         &self,
         conversation_id: Uuid,
@@ -1379,9 +2110,22 @@ impl ConversationInner {
     ) -> Result<MessageReference, Error> {
         let conversation = self.get(conversation_id).await?;
         conversation
-            .get_message_document(&self.ipfs, message_id)
+            .get_message_reference(&self.ipfs, message_id)
+            .await
+    }
+
+    async fn get_thread(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<warp::raygun::Message>, Error> {
+        let conversation = self.get(conversation_id).await?;
+
+        let keystore = pubkey_or_keystore(self, conversation_id, &self.keypair).await?;
+
+        conversation
+            .get_thread(&self.ipfs, &self.keypair, message_id, keystore.as_ref())
             .await
-            .map(|document| document.into())
     }
 
     pub async fn get_message_references<'a>(
@@ -1431,95 +2175,424 @@ impl ConversationInner {
         }
     }
 
-    //TODO: Send a request to recipient(s) of the chat to ack if message been delivered if message is marked "sent" unless we receive an event acknowledging the message itself
-    //Note:
-    //  - For group chat, this can be ignored unless we decide to have a full acknowledgement from all recipients in which case, we can mark it as "sent"
-    //    until all confirm to have received the message
-    //  - If member sends an event stating that they do not have the message to grab the message from the store
-    //    and send it them, with a map marking the attempt(s)
+    /// Status of a message is based on the recipient that is the furthest behind.
     pub async fn message_status(
         &self,
         conversation_id: Uuid,
         message_id: Uuid,
     ) -> Result<MessageStatus, Error> {
+        let receipts = self.message_receipts(conversation_id, message_id).await?;
+
+        let status = receipts
+            .into_values()
+            .min_by_key(|status| match status {
+                MessageStatus::NotSent => 0,
+                MessageStatus::Sent => 1,
+                MessageStatus::Delivered => 2,
+                MessageStatus::Read => 3,
+            })
+            .unwrap_or(MessageStatus::Sent);
+
+        Ok(status)
+    }
+
+    pub async fn message_receipts(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<HashMap<DID, MessageStatus>, Error> {
         let conversation = self.get(conversation_id).await?;
 
-        if matches!(conversation.conversation_type(), ConversationType::Group) {
-            //TODO: Handle message status for group
-            return Err(Error::Unimplemented);
-        }
+        let message = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
 
-        let messages = conversation.get_message_list(&self.ipfs).await?;
+        let sender = message.sender.to_did();
 
-        if !messages.iter().any(|document| document.id == message_id) {
-            return Err(Error::MessageNotFound);
-        }
+        let receipts = conversation.receipts(&self.ipfs).await?;
 
-        let list = conversation
+        let statuses = conversation
             .recipients()
+            .into_iter()
+            .filter(|did| (*self.keypair).ne(did) && sender.ne(did))
+            .map(|did| {
+                let status = receipts
+                    .get(&did.to_string())
+                    .and_then(|receipts| receipts.status(message.date))
+                    .unwrap_or_else(|| match self.is_queued(conversation_id, message_id, &did) {
+                        true => MessageStatus::NotSent,
+                        //Not a guarantee that it been sent but for now since the message exist locally and not marked in queue, we will assume it have been sent
+                        false => MessageStatus::Sent,
+                    });
+                (did, status)
+            })
+            .collect();
+
+        Ok(statuses)
+    }
+
+    fn is_queued(&self, conversation_id: Uuid, message_id: Uuid, did: &DID) -> bool {
+        self.queue.get(did).map_or(false, |list| {
+            list.iter()
+                .any(|Queue { id, m_id, .. }| *id == conversation_id && *m_id == Some(message_id))
+        })
+    }
+
+    pub async fn unread_count(&self, conversation_id: Uuid) -> Result<usize, Error> {
+        let conversation = self.get(conversation_id).await?;
+
+        let read_marker = conversation
+            .receipts(&self.ipfs)
+            .await?
+            .remove(&self.keypair.to_string())
+            .and_then(|receipts| receipts.read)
+            .map(|receipt| receipt.date);
+
+        let count = conversation
+            .get_message_list(&self.ipfs)
+            .await?
             .iter()
-            .filter(|did| (*self.keypair).ne(did))
-            .cloned()
-            .collect::<Vec<_>>();
+            .filter(|document| document.sender.to_did().ne(&*self.keypair))
+            .filter(|document| read_marker.map_or(true, |date| document.date > date))
+            .count();
 
-        for peer in list {
-            if let Some(list) = self.queue.get(&peer) {
-                for item in list {
-                    let Queue { id, m_id, .. } = item;
-                    if conversation.id() == *id {
-                        if let Some(m_id) = m_id {
-                            if message_id == *m_id {
-                                return Ok(MessageStatus::NotSent);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        Ok(count)
+    }
 
-        //Not a guarantee that it been sent but for now since the message exist locally and not marked in queue, we will assume it have been sent
-        Ok(MessageStatus::Sent)
+    pub async fn mark_as_read(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), Error> {
+        let conversation = self.get(conversation_id).await?;
+
+        let message = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
+
+        self.send_receipt(conversation_id, ReceiptKind::Read, &message)
+            .await
     }
 
-    pub async fn send_message(
+    /// Stores our own receipt for the message and, unless disabled, sends it to the other members of the conversation
+    async fn send_receipt(
         &mut self,
         conversation_id: Uuid,
-        messages: Vec<String>,
-    ) -> Result<Uuid, Error> {
+        kind: ReceiptKind,
+        message: &MessageDocument,
+    ) -> Result<(), Error> {
         let mut conversation = self.get(conversation_id).await?;
-        let tx = self.subscribe(conversation_id).await?;
 
-        if messages.is_empty() {
-            return Err(Error::EmptyMessage);
+        let receipt = ReceiptDocument::new(&self.keypair, kind, message)?;
+
+        if !conversation.insert_receipt(&self.ipfs, receipt).await? {
+            return Ok(());
         }
 
-        let lines_value_length: usize = messages
-            .iter()
-            .filter(|s| !s.is_empty())
-            .map(|s| s.trim())
-            .map(|s| s.chars().count())
-            .sum();
+        self.set_document(conversation).await?;
 
-        if lines_value_length == 0 || lines_value_length > MAX_MESSAGE_SIZE {
-            tracing::error!(
-                current_size = lines_value_length,
-                max = MAX_MESSAGE_SIZE,
-                "length of message is invalid"
-            );
+        if self.disable_read_receipts {
+            return Ok(());
+        }
+
+        let event = MessagingEvents::Receipt { receipt };
+
+        // Delivery receipts are only useful while the peer is online, so we do not queue them
+        self.publish(
+            conversation_id,
+            None,
+            event,
+            matches!(kind, ReceiptKind::Read),
+        )
+        .await
+    }
+
+    pub async fn save_draft(
+        &mut self,
+        conversation_id: Uuid,
+        lines: Vec<String>,
+    ) -> Result<Uuid, Error> {
+        // Ensure the conversation exist
+        self.get(conversation_id).await?;
+
+        let draft = DraftMessage::new(conversation_id, lines);
+        let id = draft.id();
+
+        let mut drafts = self.root.get_drafts().await?;
+        drafts.entry(conversation_id).or_default().push(draft);
+
+        self.set_drafts(drafts).await?;
+        Ok(id)
+    }
+
+    pub async fn update_draft(
+        &mut self,
+        conversation_id: Uuid,
+        draft_id: Uuid,
+        lines: Vec<String>,
+    ) -> Result<(), Error> {
+        let mut drafts = self.root.get_drafts().await?;
+
+        let draft = drafts
+            .get_mut(&conversation_id)
+            .and_then(|list| list.iter_mut().find(|draft| draft.id() == draft_id))
+            .ok_or(Error::DraftNotFound)?;
+
+        draft.set_lines(lines);
+
+        self.set_drafts(drafts).await
+    }
+
+    pub async fn list_drafts(&self, conversation_id: Uuid) -> Result<Vec<DraftMessage>, Error> {
+        self.get(conversation_id).await?;
+
+        let mut list = self
+            .root
+            .get_drafts()
+            .await?
+            .remove(&conversation_id)
+            .unwrap_or_default();
+
+        list.sort_by_key(|draft| draft.created());
+
+        Ok(list)
+    }
+
+    pub async fn delete_draft(
+        &mut self,
+        conversation_id: Uuid,
+        draft_id: Uuid,
+    ) -> Result<(), Error> {
+        let mut drafts = self.root.get_drafts().await?;
+
+        let list = drafts
+            .get_mut(&conversation_id)
+            .ok_or(Error::DraftNotFound)?;
+
+        let index = list
+            .iter()
+            .position(|draft| draft.id() == draft_id)
+            .ok_or(Error::DraftNotFound)?;
+
+        list.remove(index);
+
+        self.set_drafts(drafts).await
+    }
+
+    async fn set_drafts(&mut self, drafts: BTreeMap<Uuid, Vec<DraftMessage>>) -> Result<(), Error> {
+        self.root.set_drafts(drafts).await?;
+        self.identity.export_root_document().await
+    }
+
+    pub async fn schedule_message(
+        &mut self,
+        conversation_id: Uuid,
+        lines: Vec<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<Uuid, Error> {
+        self.get(conversation_id).await?;
+
+        validate_message_lines(&lines)?;
+
+        if send_at <= Utc::now() {
+            return Err(Error::InvalidScheduledDate);
+        }
+
+        let scheduled = ScheduledMessage::new(conversation_id, lines, send_at);
+        let id = scheduled.id();
+
+        self.scheduled.push(scheduled);
+        self.save_scheduled().await;
+
+        Ok(id)
+    }
+
+    pub async fn list_scheduled_messages(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<ScheduledMessage>, Error> {
+        self.get(conversation_id).await?;
+
+        let mut list = self
+            .scheduled
+            .iter()
+            .filter(|scheduled| scheduled.conversation_id() == conversation_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        list.sort_by_key(|scheduled| scheduled.send_at());
+
+        Ok(list)
+    }
+
+    pub async fn update_scheduled_message(
+        &mut self,
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+        lines: Vec<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        validate_message_lines(&lines)?;
+
+        if send_at <= Utc::now() {
+            return Err(Error::InvalidScheduledDate);
+        }
+
+        let scheduled = self
+            .scheduled
+            .iter_mut()
+            .find(|scheduled| {
+                scheduled.conversation_id() == conversation_id && scheduled.id() == scheduled_id
+            })
+            .ok_or(Error::ScheduledMessageNotFound)?;
+
+        scheduled.set_lines(lines);
+        scheduled.set_send_at(send_at);
+
+        self.save_scheduled().await;
+        Ok(())
+    }
+
+    pub async fn cancel_scheduled_message(
+        &mut self,
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+    ) -> Result<(), Error> {
+        let index = self
+            .scheduled
+            .iter()
+            .position(|scheduled| {
+                scheduled.conversation_id() == conversation_id && scheduled.id() == scheduled_id
+            })
+            .ok_or(Error::ScheduledMessageNotFound)?;
+
+        self.scheduled.remove(index);
+
+        self.save_scheduled().await;
+        Ok(())
+    }
+
+    pub async fn send_message(
+        &mut self,
+        conversation_id: Uuid,
+        messages: Vec<String>,
+        embeds: Vec<EmbedDocument>,
+    ) -> Result<Uuid, Error> {
+        let mut conversation = self.get(conversation_id).await?;
+        let tx = self.subscribe(conversation_id).await?;
+
+        validate_message_lines(&messages)?;
+
+        let own_did = &*self.keypair;
+
+        let mut message = warp::raygun::Message::default();
+        message.set_conversation_id(conversation.id());
+        message.set_sender(own_did.clone());
+        message.set_lines(messages.clone());
+
+        let message_id = message.id();
+        let keystore = pubkey_or_keystore(self, conversation.id(), &self.keypair).await?;
+
+        let mut message =
+            MessageDocument::new(&self.ipfs, &self.keypair, message, keystore.as_ref()).await?;
+
+        if !embeds.is_empty() {
+            message
+                .set_embeds(&self.ipfs, &self.keypair, &embeds, keystore.as_ref())
+                .await?;
+        }
+
+        let message_cid = conversation
+            .insert_message_document(&self.ipfs, message)
+            .await?;
+
+        self.queue_expiry(&conversation, &message);
+
+        let recipients = conversation.recipients();
+
+        self.set_document(conversation).await?;
+
+        self.index_message(conversation_id, message_id, message.date, &messages)
+            .await;
+
+        let event = MessageEventKind::MessageSent {
+            conversation_id,
+            message_id,
+        };
+
+        if let Err(e) = tx.send(event) {
+            error!(%conversation_id, error = %e, "Error broadcasting event");
+        }
+
+        let event = MessagingEvents::New { message };
+
+        if !recipients.is_empty() {
+            if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
+                for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                    let _ = self
+                        .message_command
+                        .clone()
+                        .send(shuttle::message::client::MessageCommand::InsertMessage {
+                            peer_id,
+                            conversation_id,
+                            recipients: recipients.clone(),
+                            message_id: message.id,
+                            message_cid,
+                            expire,
+                        })
+                        .await;
+                }
+            }
+        }
+
+        self.publish(conversation_id, Some(message_id), event, true)
+            .await
+            .map(|_| message_id)
+    }
+
+    pub async fn send_poll(&mut self, conversation_id: Uuid, poll: Poll) -> Result<Uuid, Error> {
+        let mut conversation = self.get(conversation_id).await?;
+        let tx = self.subscribe(conversation_id).await?;
+
+        let question = vec![poll.question()];
+
+        validate_message_lines(&question)?;
+
+        let options = poll.options();
+
+        if options.len() < MIN_POLL_OPTIONS || options.len() > MAX_POLL_OPTIONS {
             return Err(Error::InvalidLength {
-                context: "message".into(),
-                current: lines_value_length,
-                minimum: Some(MIN_MESSAGE_SIZE),
-                maximum: Some(MAX_MESSAGE_SIZE),
+                context: "options".into(),
+                current: options.len(),
+                minimum: Some(MIN_POLL_OPTIONS),
+                maximum: Some(MAX_POLL_OPTIONS),
             });
         }
 
+        validate_message_lines(&options)?;
+
+        if options.iter().any(|option| option.trim().is_empty()) {
+            return Err(Error::EmptyMessage);
+        }
+
+        if matches!(poll.close_at(), Some(date) if date <= Utc::now()) {
+            return Err(Error::PollClosed);
+        }
+
+        let mut poll = poll;
+        poll.set_closed(None);
+        poll.votes_mut().clear();
+
         let own_did = &*self.keypair;
 
         let mut message = warp::raygun::Message::default();
+        message.set_message_type(MessageType::Poll);
         message.set_conversation_id(conversation.id());
         message.set_sender(own_did.clone());
-        message.set_lines(messages.clone());
+        // The question is used as the content so it can be displayed by clients unaware of polls
+        message.set_lines(question.clone());
+        message.set_poll(Some(poll));
 
         let message_id = message.id();
         let keystore = pubkey_or_keystore(self, conversation.id(), &self.keypair).await?;
@@ -1531,10 +2604,15 @@ impl ConversationInner {
             .insert_message_document(&self.ipfs, message)
             .await?;
 
+        self.queue_expiry(&conversation, &message);
+
         let recipients = conversation.recipients();
 
         self.set_document(conversation).await?;
 
+        self.index_message(conversation_id, message_id, message.date, &question)
+            .await;
+
         let event = MessageEventKind::MessageSent {
             conversation_id,
             message_id,
@@ -1548,6 +2626,7 @@ impl ConversationInner {
 
         if !recipients.is_empty() {
             if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
                 for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                     let _ = self
                         .message_command
@@ -1558,6 +2637,7 @@ impl ConversationInner {
                             recipients: recipients.clone(),
                             message_id: message.id,
                             message_cid,
+                            expire,
                         })
                         .await;
                 }
@@ -1569,6 +2649,223 @@ impl ConversationInner {
             .map(|_| message_id)
     }
 
+    pub async fn vote(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        choices: Vec<usize>,
+    ) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+        let tx = self.subscribe(conversation_id).await?;
+
+        let keystore = pubkey_or_keystore(&*self, conversation.id(), &self.keypair).await?;
+
+        let mut message_document = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
+
+        let message = message_document
+            .resolve(&self.ipfs, &self.keypair, true, keystore.as_ref())
+            .await?;
+
+        let poll = message.poll().ok_or(Error::InvalidMessage)?;
+
+        if poll.is_closed() {
+            return Err(Error::PollClosed);
+        }
+
+        validate_poll_choices(&poll, &choices)?;
+
+        let vote = PollVoteDocument::new(&self.keypair, &message_document, choices)?;
+
+        if !message_document
+            .insert_poll_vote(&self.ipfs, vote.clone(), Utc::now())
+            .await?
+        {
+            return Ok(());
+        }
+
+        let message_cid = conversation
+            .update_message_document(&self.ipfs, message_document)
+            .await?;
+
+        let recipients = conversation.recipients();
+
+        self.set_document(conversation).await?;
+
+        _ = tx.send(MessageEventKind::PollVoted {
+            conversation_id,
+            message_id,
+            did_key: (*self.keypair).clone(),
+        });
+
+        let event = MessagingEvents::PollVote { vote };
+
+        if !recipients.is_empty() {
+            if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
+                for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                    let _ = self
+                        .message_command
+                        .clone()
+                        .send(shuttle::message::client::MessageCommand::InsertMessage {
+                            peer_id,
+                            conversation_id,
+                            recipients: recipients.clone(),
+                            message_id,
+                            message_cid,
+                            expire,
+                        })
+                        .await;
+                }
+            }
+        }
+
+        self.publish(conversation_id, None, event, true).await
+    }
+
+    pub async fn close_poll(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+        let tx = self.subscribe(conversation_id).await?;
+
+        let mut message_document = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
+
+        if message_document.sender.to_did().ne(&*self.keypair) {
+            return Err(Error::InvalidMessage);
+        }
+
+        message_document.close_poll(&self.keypair)?;
+
+        let closed = message_document.poll_closed.expect("poll to be closed");
+        let signature = message_document.signature.expect("message to be signed");
+
+        let message_cid = conversation
+            .update_message_document(&self.ipfs, message_document)
+            .await?;
+
+        let recipients = conversation.recipients();
+
+        self.set_document(conversation).await?;
+
+        _ = tx.send(MessageEventKind::PollClosed {
+            conversation_id,
+            message_id,
+        });
+
+        let event = MessagingEvents::PollClose {
+            conversation_id,
+            message_id,
+            closed,
+            signature: signature.into(),
+        };
+
+        if !recipients.is_empty() {
+            if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
+                for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                    let _ = self
+                        .message_command
+                        .clone()
+                        .send(shuttle::message::client::MessageCommand::InsertMessage {
+                            peer_id,
+                            conversation_id,
+                            recipients: recipients.clone(),
+                            message_id,
+                            message_cid,
+                            expire,
+                        })
+                        .await;
+                }
+            }
+        }
+
+        self.publish(conversation_id, None, event, true).await
+    }
+
+    pub async fn set_embeds(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        embeds: Vec<EmbedDocument>,
+    ) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+        let tx = self.subscribe(conversation_id).await?;
+
+        let mut message_document = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
+
+        if message_document.sender.to_did().ne(&*self.keypair) {
+            return Err(Error::InvalidMessage);
+        }
+
+        if embeds.is_empty() && message_document.embeds.is_none() {
+            return Ok(());
+        }
+
+        let previous = self.own_embeds(conversation_id, &message_document).await;
+
+        let keystore = pubkey_or_keystore(self, conversation_id, &self.keypair).await?;
+
+        message_document
+            .set_embeds(&self.ipfs, &self.keypair, &embeds, keystore.as_ref())
+            .await?;
+
+        let signature = message_document.signature.expect("message to be signed");
+
+        let message_cid = conversation
+            .update_message_document(&self.ipfs, message_document)
+            .await?;
+
+        let recipients = conversation.recipients();
+
+        self.set_document(conversation).await?;
+
+        remove_thumbnails(&self.ipfs, &previous, &embeds).await;
+
+        let embeds = message_document.embeds;
+
+        _ = tx.send(MessageEventKind::MessageEmbedsUpdated {
+            conversation_id,
+            message_id,
+        });
+
+        let event = MessagingEvents::Embeds {
+            conversation_id,
+            message_id,
+            embeds,
+            signature: signature.into(),
+        };
+
+        if !recipients.is_empty() {
+            if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
+                for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
+                    let _ = self
+                        .message_command
+                        .clone()
+                        .send(shuttle::message::client::MessageCommand::InsertMessage {
+                            peer_id,
+                            conversation_id,
+                            recipients: recipients.clone(),
+                            message_id,
+                            message_cid,
+                            expire,
+                        })
+                        .await;
+                }
+            }
+        }
+
+        self.publish(conversation_id, None, event, true).await
+    }
+
     pub async fn edit_message(
         &mut self,
         conversation_id: Uuid,
@@ -1624,6 +2921,8 @@ impl ConversationInner {
         message.lines_mut().clone_from(&messages);
         message.set_modified(Utc::now());
 
+        let history = conversation.settings.keep_edit_history();
+
         message_document
             .update(
                 &self.ipfs,
@@ -1632,6 +2931,7 @@ impl ConversationInner {
                 None,
                 keystore.as_ref(),
                 None,
+                history,
             )
             .await?;
 
@@ -1646,6 +2946,14 @@ impl ConversationInner {
 
         self.set_document(conversation).await?;
 
+        self.index_message(
+            conversation_id,
+            message_id,
+            message_document.date,
+            &messages,
+        )
+        .await;
+
         _ = tx.send(MessageEventKind::MessageEdited {
             conversation_id,
             message_id,
@@ -1658,10 +2966,12 @@ impl ConversationInner {
             lines: messages,
             nonce: nonce.to_vec(),
             signature: signature.into(),
+            history,
         };
 
         if !recipients.is_empty() {
             if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
                 for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                     let _ = self
                         .message_command
@@ -1672,6 +2982,7 @@ impl ConversationInner {
                             recipients: recipients.clone(),
                             message_id,
                             message_cid,
+                            expire,
                         })
                         .await;
                 }
@@ -1686,6 +2997,7 @@ impl ConversationInner {
         conversation_id: Uuid,
         message_id: Uuid,
         messages: Vec<String>,
+        embeds: Vec<EmbedDocument>,
     ) -> Result<Uuid, Error> {
         let mut conversation = self.get(conversation_id).await?;
         let tx = self.subscribe(conversation_id).await?;
@@ -1715,29 +3027,48 @@ impl ConversationInner {
             });
         }
 
+        let parent = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await?;
+
+        // Replies to a message within a thread will belong to the same thread
+        let thread_id = parent.thread.unwrap_or(parent.id);
+
         let own_did = &*self.keypair;
 
         let mut message = warp::raygun::Message::default();
         message.set_conversation_id(conversation.id());
         message.set_sender(own_did.clone());
-        message.set_lines(messages);
+        message.set_lines(messages.clone());
         message.set_replied(Some(message_id));
+        message.set_thread(Some(thread_id));
 
         let keystore = pubkey_or_keystore(self, conversation.id(), &self.keypair).await?;
 
-        let message =
+        let mut message =
             MessageDocument::new(&self.ipfs, &self.keypair, message, keystore.as_ref()).await?;
 
+        if !embeds.is_empty() {
+            message
+                .set_embeds(&self.ipfs, &self.keypair, &embeds, keystore.as_ref())
+                .await?;
+        }
+
         let message_id = message.id;
 
         let message_cid = conversation
             .insert_message_document(&self.ipfs, message)
             .await?;
 
+        self.queue_expiry(&conversation, &message);
+
         let recipients = conversation.recipients();
 
         self.set_document(conversation).await?;
 
+        self.index_message(conversation_id, message_id, message.date, &messages)
+            .await;
+
         let event = MessageEventKind::MessageSent {
             conversation_id,
             message_id,
@@ -1747,10 +3078,17 @@ impl ConversationInner {
             error!(%conversation_id, error = %e, "Error broadcasting event");
         }
 
+        _ = tx.send(MessageEventKind::ThreadReplyAdded {
+            conversation_id,
+            thread_id,
+            message_id,
+        });
+
         let event = MessagingEvents::New { message };
 
         if !recipients.is_empty() {
             if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
                 for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                     let _ = self
                         .message_command
@@ -1761,6 +3099,7 @@ impl ConversationInner {
                             recipients: recipients.clone(),
                             message_id,
                             message_cid,
+                            expire,
                         })
                         .await;
                 }
@@ -1786,10 +3125,22 @@ impl ConversationInner {
             message_id,
         };
 
-        conversation.delete_message(&self.ipfs, message_id).await?;
+        let embeds = match conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await
+        {
+            Ok(document) => self.own_embeds(conversation_id, &document).await,
+            Err(_) => vec![],
+        };
+
+        let thread = conversation.delete_message(&self.ipfs, message_id).await?;
 
         self.set_document(conversation).await?;
 
+        self.unindex_message(conversation_id, message_id).await;
+
+        remove_thumbnails(&self.ipfs, &embeds, &[]).await;
+
         if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
             for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                 let _ = self
@@ -1809,6 +3160,14 @@ impl ConversationInner {
             message_id,
         });
 
+        if let Some(thread_id) = thread {
+            _ = tx.send(MessageEventKind::ThreadReplyRemoved {
+                conversation_id,
+                thread_id,
+                message_id,
+            });
+        }
+
         if broadcast {
             self.publish(conversation_id, None, event, true).await?;
         }
@@ -1816,6 +3175,147 @@ impl ConversationInner {
         Ok(())
     }
 
+    /// Previews of the links within a message sent by this node, whose thumbnails are pinned by this node
+    async fn own_embeds(
+        &self,
+        conversation_id: Uuid,
+        message: &MessageDocument,
+    ) -> Vec<EmbedDocument> {
+        if message.embeds.is_none() || message.sender.to_did().ne(&*self.keypair) {
+            return vec![];
+        }
+
+        let keystore = match pubkey_or_keystore(self, conversation_id, &self.keypair).await {
+            Ok(keystore) => keystore,
+            Err(_) => return vec![],
+        };
+
+        message
+            .embed_documents(&self.ipfs, &self.keypair, true, keystore.as_ref())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::debug!(%conversation_id, message_id = %message.id, error = %e, "unable to obtain previews of message");
+                vec![]
+            })
+    }
+
+    /// Removes the message, along with its attachments, from this node without notifying the other members
+    /// since each member will remove the message once it expires
+    async fn expire_message(
+        &mut self,
+        conversation_id: Uuid,
+        message: MessageDocument,
+    ) -> Result<(), Error> {
+        self.remove_attachments(conversation_id, &message).await;
+        self.delete_message(conversation_id, message.id, false)
+            .await
+    }
+
+    /// Removes the data of the attachments from this node. Files uploaded through the conversation are
+    /// removed from constellation while files received are removed from the blockstore
+    async fn remove_attachments(&mut self, conversation_id: Uuid, message: &MessageDocument) {
+        let attachments = message.attachments(&self.ipfs).await;
+
+        if attachments.is_empty() {
+            return;
+        }
+
+        let media_path = format!("/{CHAT_DIRECTORY}/{conversation_id}");
+
+        let media_items = self
+            .file
+            .root_directory()
+            .get_item_by_path(&media_path)
+            .and_then(|item| item.get_directory())
+            .map(|directory| directory.get_items())
+            .unwrap_or_default();
+
+        for attachment in attachments {
+            let uploaded = media_items.iter().find(|item| {
+                item.get_file()
+                    .ok()
+                    .and_then(|file| file.reference())
+                    .as_deref()
+                    == Some(attachment.data.as_str())
+            });
+
+            if let Some(item) = uploaded {
+                let path = format!("{media_path}/{}", item.name());
+                if let Err(e) = self.file.remove(path, false).await {
+                    tracing::warn!(%conversation_id, error = %e, "unable to remove attachment");
+                }
+                continue;
+            }
+
+            let data = attachment
+                .data
+                .parse::<IpfsPath>()
+                .ok()
+                .and_then(|path| path.root().cid().copied());
+
+            for cid in [data, attachment.thumbnail].into_iter().flatten() {
+                // Note: Pinned blocks belong to constellation so we should leave them be
+                if self.ipfs.is_pinned(&cid).await.unwrap_or_default() {
+                    continue;
+                }
+
+                if let Err(e) = self.ipfs.remove_block(cid, true).await {
+                    tracing::debug!(%conversation_id, %cid, error = %e, "unable to remove attachment block");
+                }
+            }
+        }
+    }
+
+    /// Date in which the message will expire, if messages are set to expire in the conversation
+    async fn message_expire(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Option<DateTime<Utc>> {
+        let conversation = self.get(conversation_id).await.ok()?;
+        let message = conversation
+            .get_message_document(&self.ipfs, message_id)
+            .await
+            .ok()?;
+        conversation.message_expire(&message)
+    }
+
+    /// Track the message so it is removed once it expires, if messages are set to expire in the conversation
+    fn queue_expiry(&mut self, conversation: &ConversationDocument, message: &MessageDocument) {
+        let Some(expire) = conversation.message_expire(message) else {
+            return;
+        };
+
+        self.expiring
+            .entry(conversation.id())
+            .or_default()
+            .insert((expire, message.id));
+    }
+
+    /// Rebuild the messages tracked for expiry within the conversation, such as when the settings of the
+    /// conversation changed
+    async fn refresh_expiry(&mut self, conversation: &ConversationDocument) {
+        let conversation_id = conversation.id();
+
+        self.expiring.remove(&conversation_id);
+
+        if conversation.settings.message_expiry().is_none() {
+            return;
+        }
+
+        let list = match conversation.get_message_list(&self.ipfs).await {
+            Ok(list) => list,
+            Err(e) => {
+                tracing::warn!(%conversation_id, error = %e, "unable to load messages for expiry");
+                return;
+            }
+        };
+
+        for message in list {
+            self.queue_expiry(conversation, &message);
+        }
+    }
+
     pub async fn pin_message(
         &mut self,
         conversation_id: Uuid,
@@ -1866,6 +3366,7 @@ impl ConversationInner {
                 None,
                 keystore.as_ref(),
                 None,
+                false,
             )
             .await?;
 
@@ -1881,6 +3382,7 @@ impl ConversationInner {
 
         if !recipients.is_empty() {
             if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
                 for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                     let _ = self
                         .message_command
@@ -1891,6 +3393,7 @@ impl ConversationInner {
                             recipients: recipients.clone(),
                             message_id,
                             message_cid,
+                            expire,
                         })
                         .await;
                 }
@@ -1951,6 +3454,7 @@ impl ConversationInner {
                         None,
                         keystore.as_ref(),
                         None,
+                        false,
                     )
                     .await?;
 
@@ -1991,6 +3495,7 @@ impl ConversationInner {
                         None,
                         keystore.as_ref(),
                         None,
+                        false,
                     )
                     .await?;
 
@@ -2019,6 +3524,7 @@ impl ConversationInner {
 
         if !recipients.is_empty() {
             if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
                 for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                     let _ = self
                         .message_command
@@ -2029,6 +3535,7 @@ impl ConversationInner {
                             recipients: recipients.clone(),
                             message_id,
                             message_cid,
+                            expire,
                         })
                         .await;
                 }
@@ -2284,10 +3791,14 @@ impl ConversationInner {
             .insert_message_document(&self.ipfs, message)
             .await?;
 
+        self.queue_expiry(&conversation, &message);
+
         let recipients = conversation.recipients();
 
         self.set_document(conversation).await?;
 
+        self.index_message_document(conversation_id, &message).await;
+
         let event = MessageEventKind::MessageSent {
             conversation_id,
             message_id,
@@ -2301,6 +3812,7 @@ impl ConversationInner {
 
         if !recipients.is_empty() {
             if let config::Discovery::Shuttle { addresses } = self.discovery.discovery_config() {
+                let expire = self.message_expire(conversation_id, message_id).await;
                 for peer_id in addresses.iter().filter_map(|addr| addr.peer_id()) {
                     let _ = self
                         .message_command
@@ -2311,6 +3823,7 @@ impl ConversationInner {
                             recipients: recipients.clone(),
                             message_id,
                             message_cid,
+                            expire,
                         })
                         .await;
                 }
@@ -2403,26 +3916,27 @@ impl ConversationInner {
             return Err(Error::InvalidConversation);
         }
 
-        let Some(creator) = conversation.creator.clone() else {
+        let Some(owner) = conversation.owner() else {
             return Err(Error::InvalidConversation);
         };
 
         let own_did = &*self.keypair;
 
-        if creator.ne(own_did) {
+        if owner.ne(own_did) {
             return Err(Error::PublicKeyInvalid);
         }
 
-        if creator.eq(did_key) {
+        if owner.eq(did_key) {
             return Err(Error::PublicKeyInvalid);
         }
 
-        if !self.root.is_blocked(did_key).await? {
-            return Err(Error::PublicKeyIsntBlocked);
+        if conversation.recipients.contains(did_key) {
+            return Err(Error::IdentityExist);
         }
 
-        debug_assert!(!conversation.recipients.contains(did_key));
-        debug_assert!(!conversation.restrict.contains(did_key));
+        if conversation.restrict.contains(did_key) {
+            return Err(Error::PublicKeyIsBlocked);
+        }
 
         conversation.restrict.push(did_key.clone());
 
@@ -2451,26 +3965,24 @@ impl ConversationInner {
             return Err(Error::InvalidConversation);
         }
 
-        let Some(creator) = conversation.creator.clone() else {
+        let Some(owner) = conversation.owner() else {
             return Err(Error::InvalidConversation);
         };
 
         let own_did = &*self.keypair;
 
-        if creator.ne(own_did) {
+        if owner.ne(own_did) {
             return Err(Error::PublicKeyInvalid);
         }
 
-        if creator.eq(did_key) {
+        if owner.eq(did_key) {
             return Err(Error::PublicKeyInvalid);
         }
 
-        if self.root.is_blocked(did_key).await? {
-            return Err(Error::PublicKeyIsBlocked);
+        if !conversation.restrict.contains(did_key) {
+            return Err(Error::PublicKeyIsntBlocked);
         }
 
-        debug_assert!(conversation.restrict.contains(did_key));
-
         conversation
             .restrict
             .retain(|restricted| restricted != did_key);
@@ -2515,163 +4027,396 @@ impl ConversationInner {
         };
         assert_eq!(conversation.conversation_type(), ConversationType::Group);
 
-        let Some(creator) = conversation.creator.clone() else {
+        let own_did = &*self.keypair;
+
+        if !settings.members_can_change_name() && conversation.role(own_did) < GroupRole::Moderator
+        {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        conversation.name = (!name.is_empty()).then_some(name.to_string());
+
+        self.set_document(conversation).await?;
+
+        let conversation = self.get(conversation_id).await?;
+
+        let new_name = conversation.name();
+
+        let event = MessagingEvents::UpdateConversation {
+            conversation,
+            kind: ConversationUpdateKind::ChangeName { name: new_name },
+        };
+
+        let _ = tx.send(MessageEventKind::ConversationNameUpdated {
+            conversation_id,
+            name: name.to_string(),
+        });
+
+        self.publish(conversation_id, None, event, true).await
+    }
+
+    pub async fn add_recipient(
+        &mut self,
+        conversation_id: Uuid,
+        did_key: &DID,
+    ) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+
+        let settings = match conversation.settings {
+            ConversationSettings::Group(settings) => settings,
+            ConversationSettings::Direct(_) => return Err(Error::InvalidConversation),
+        };
+        assert_eq!(conversation.conversation_type(), ConversationType::Group);
+
+        let own_did = &*self.keypair;
+
+        let role = conversation.role(own_did);
+
+        if !settings.members_can_add_participants() && role < GroupRole::Moderator {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        if conversation.owner().as_ref() == Some(did_key) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        if self.root.is_blocked(did_key).await? {
+            return Err(Error::PublicKeyIsBlocked);
+        }
+
+        if conversation.restrict.contains(did_key) || conversation.banned.contains(did_key) {
+            return Err(Error::PublicKeyIsBlocked);
+        }
+
+        if conversation.recipients.contains(did_key) {
+            return Err(Error::IdentityExist);
+        }
+
+        conversation.recipients.push(did_key.clone());
+
+        if role >= GroupRole::Moderator {
+            conversation.sign(own_did)?;
+        }
+
+        self.set_document(conversation).await?;
+
+        let conversation = self.get(conversation_id).await?;
+
+        let event = MessagingEvents::UpdateConversation {
+            conversation: conversation.clone(),
+            kind: ConversationUpdateKind::AddParticipant {
+                did: did_key.clone(),
+            },
+        };
+
+        let tx = self.subscribe(conversation_id).await?;
+        let _ = tx.send(MessageEventKind::RecipientAdded {
+            conversation_id,
+            recipient: did_key.clone(),
+        });
+
+        self.publish(conversation_id, None, event, true).await?;
+
+        let new_event = ConversationEvents::NewGroupConversation { conversation };
+
+        self.send_single_conversation_event(conversation_id, did_key, new_event)
+            .await?;
+        if let Err(_e) = self.request_key(conversation_id, did_key).await {}
+        Ok(())
+    }
+
+    pub async fn remove_recipient(
+        &mut self,
+        conversation_id: Uuid,
+        did_key: &DID,
+        broadcast: bool,
+    ) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+
+        if matches!(conversation.conversation_type(), ConversationType::Direct) {
+            return Err(Error::InvalidConversation);
+        }
+
+        let own_did = &*self.keypair;
+
+        let role = conversation.role(own_did);
+
+        if role < GroupRole::Moderator || conversation.role(did_key) >= role {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        if !conversation.recipients.contains(did_key) {
+            return Err(Error::IdentityDoesntExist);
+        }
+
+        conversation.recipients.retain(|did| did.ne(did_key));
+        conversation
+            .roles
+            .retain(|assignment| assignment.member.to_did().ne(did_key));
+        conversation.sign(own_did)?;
+        self.set_document(conversation).await?;
+
+        let conversation = self.get(conversation_id).await?;
+
+        let event = MessagingEvents::UpdateConversation {
+            conversation: conversation.clone(),
+            kind: ConversationUpdateKind::RemoveParticipant {
+                did: did_key.clone(),
+            },
+        };
+
+        let tx = self.subscribe(conversation_id).await?;
+        let _ = tx.send(MessageEventKind::RecipientRemoved {
+            conversation_id,
+            recipient: did_key.clone(),
+        });
+
+        self.publish(conversation_id, None, event, true).await?;
+
+        if broadcast {
+            let new_event = ConversationEvents::DeleteConversation { conversation_id };
+
+            self.send_single_conversation_event(conversation_id, did_key, new_event)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_role(
+        &mut self,
+        conversation_id: Uuid,
+        did_key: &DID,
+        role: GroupRole,
+    ) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+
+        if matches!(conversation.conversation_type(), ConversationType::Direct) {
             return Err(Error::InvalidConversation);
-        };
+        }
 
         let own_did = &*self.keypair;
 
-        if !settings.members_can_change_name() && creator.ne(own_did) {
-            return Err(Error::PublicKeyInvalid);
+        validate_role_change(&conversation, own_did, did_key, role)?;
+
+        if conversation.role(did_key) == role {
+            return Ok(());
         }
 
-        conversation.name = (!name.is_empty()).then_some(name.to_string());
+        conversation
+            .roles
+            .retain(|assignment| assignment.member.to_did().ne(did_key));
+
+        if role != GroupRole::Member {
+            conversation
+                .roles
+                .push(RoleDocument::new(own_did, conversation_id, did_key, role)?);
+        }
 
+        conversation.sign(own_did)?;
         self.set_document(conversation).await?;
 
         let conversation = self.get(conversation_id).await?;
 
-        let new_name = conversation.name();
-
         let event = MessagingEvents::UpdateConversation {
             conversation,
-            kind: ConversationUpdateKind::ChangeName { name: new_name },
+            kind: ConversationUpdateKind::ChangeRole {
+                did: did_key.clone(),
+                role,
+            },
         };
 
-        let _ = tx.send(MessageEventKind::ConversationNameUpdated {
+        let tx = self.subscribe(conversation_id).await?;
+        let _ = tx.send(MessageEventKind::RecipientRoleUpdated {
             conversation_id,
-            name: name.to_string(),
+            recipient: did_key.clone(),
+            role,
         });
 
         self.publish(conversation_id, None, event, true).await
     }
 
-    pub async fn add_recipient(
+    pub async fn transfer_ownership(
         &mut self,
         conversation_id: Uuid,
         did_key: &DID,
     ) -> Result<(), Error> {
         let mut conversation = self.get(conversation_id).await?;
 
-        let settings = match conversation.settings {
-            ConversationSettings::Group(settings) => settings,
-            ConversationSettings::Direct(_) => return Err(Error::InvalidConversation),
-        };
-        assert_eq!(conversation.conversation_type(), ConversationType::Group);
-
-        let Some(creator) = conversation.creator.clone() else {
+        if matches!(conversation.conversation_type(), ConversationType::Direct) {
             return Err(Error::InvalidConversation);
-        };
+        }
 
         let own_did = &*self.keypair;
 
-        if !settings.members_can_add_participants() && creator.ne(own_did) {
+        if conversation.owner().as_ref() != Some(own_did) || own_did.eq(did_key) {
             return Err(Error::PublicKeyInvalid);
         }
 
-        if creator.eq(did_key) {
-            return Err(Error::PublicKeyInvalid);
+        if !conversation.recipients().contains(did_key) {
+            return Err(Error::IdentityDoesntExist);
         }
 
-        if self.root.is_blocked(did_key).await? {
-            return Err(Error::PublicKeyIsBlocked);
+        conversation.ownership.push(RoleDocument::new(
+            own_did,
+            conversation_id,
+            did_key,
+            GroupRole::Owner,
+        )?);
+
+        // The previous owner remains as an admin of the conversation
+        conversation.roles.retain(|assignment| {
+            let member = assignment.member.to_did();
+            member.ne(did_key) && member.ne(own_did)
+        });
+        conversation.roles.push(RoleDocument::new(
+            own_did,
+            conversation_id,
+            own_did,
+            GroupRole::Admin,
+        )?);
+
+        conversation.sign(own_did)?;
+        self.set_document(conversation).await?;
+
+        let conversation = self.get(conversation_id).await?;
+
+        let event = MessagingEvents::UpdateConversation {
+            conversation,
+            kind: ConversationUpdateKind::TransferOwnership {
+                did: did_key.clone(),
+            },
+        };
+
+        let tx = self.subscribe(conversation_id).await?;
+        for (recipient, role) in [
+            (did_key.clone(), GroupRole::Owner),
+            (own_did.clone(), GroupRole::Admin),
+        ] {
+            let _ = tx.send(MessageEventKind::RecipientRoleUpdated {
+                conversation_id,
+                recipient,
+                role,
+            });
         }
 
-        if conversation.restrict.contains(did_key) {
-            return Err(Error::PublicKeyIsBlocked);
+        self.publish(conversation_id, None, event, true).await
+    }
+
+    pub async fn ban(&mut self, conversation_id: Uuid, did_key: &DID) -> Result<(), Error> {
+        let mut conversation = self.get(conversation_id).await?;
+
+        if matches!(conversation.conversation_type(), ConversationType::Direct) {
+            return Err(Error::InvalidConversation);
         }
 
-        if conversation.recipients.contains(did_key) {
-            return Err(Error::IdentityExist);
+        let own_did = &*self.keypair;
+
+        let role = conversation.role(own_did);
+
+        if role < GroupRole::Moderator || conversation.role(did_key) >= role {
+            return Err(Error::PublicKeyInvalid);
         }
 
-        conversation.recipients.push(did_key.clone());
+        if conversation.banned.contains(did_key) {
+            return Err(Error::PublicKeyIsBlocked);
+        }
+
+        let removed = conversation.recipients.contains(did_key);
 
+        conversation.recipients.retain(|did| did.ne(did_key));
+        conversation.excluded.remove(did_key);
+        conversation
+            .roles
+            .retain(|assignment| assignment.member.to_did().ne(did_key));
+        conversation.banned.push(did_key.clone());
+
+        conversation.sign(own_did)?;
         self.set_document(conversation).await?;
 
         let conversation = self.get(conversation_id).await?;
 
         let event = MessagingEvents::UpdateConversation {
-            conversation: conversation.clone(),
-            kind: ConversationUpdateKind::AddParticipant {
+            conversation,
+            kind: ConversationUpdateKind::Ban {
                 did: did_key.clone(),
             },
         };
 
         let tx = self.subscribe(conversation_id).await?;
-        let _ = tx.send(MessageEventKind::RecipientAdded {
+
+        if removed {
+            let _ = tx.send(MessageEventKind::RecipientRemoved {
+                conversation_id,
+                recipient: did_key.clone(),
+            });
+        }
+
+        let _ = tx.send(MessageEventKind::RecipientBanned {
             conversation_id,
             recipient: did_key.clone(),
         });
 
         self.publish(conversation_id, None, event, true).await?;
 
-        let new_event = ConversationEvents::NewGroupConversation { conversation };
+        if removed {
+            let new_event = ConversationEvents::DeleteConversation { conversation_id };
+
+            self.send_single_conversation_event(conversation_id, did_key, new_event)
+                .await?;
+        }
 
-        self.send_single_conversation_event(conversation_id, did_key, new_event)
-            .await?;
-        if let Err(_e) = self.request_key(conversation_id, did_key).await {}
         Ok(())
     }
 
-    pub async fn remove_recipient(
-        &mut self,
-        conversation_id: Uuid,
-        did_key: &DID,
-        broadcast: bool,
-    ) -> Result<(), Error> {
+    pub async fn unban(&mut self, conversation_id: Uuid, did_key: &DID) -> Result<(), Error> {
         let mut conversation = self.get(conversation_id).await?;
 
         if matches!(conversation.conversation_type(), ConversationType::Direct) {
             return Err(Error::InvalidConversation);
         }
 
-        let Some(creator) = conversation.creator.as_ref() else {
-            return Err(Error::InvalidConversation);
-        };
-
         let own_did = &*self.keypair;
 
-        if creator.ne(own_did) {
+        if conversation.role(own_did) < GroupRole::Moderator {
             return Err(Error::PublicKeyInvalid);
         }
 
-        if creator.eq(did_key) {
-            return Err(Error::PublicKeyInvalid);
+        if !conversation.banned.contains(did_key) {
+            return Err(Error::PublicKeyIsntBlocked);
         }
 
-        if !conversation.recipients.contains(did_key) {
-            return Err(Error::IdentityDoesntExist);
-        }
+        conversation.banned.retain(|did| did.ne(did_key));
 
-        conversation.recipients.retain(|did| did.ne(did_key));
+        conversation.sign(own_did)?;
         self.set_document(conversation).await?;
 
         let conversation = self.get(conversation_id).await?;
 
         let event = MessagingEvents::UpdateConversation {
-            conversation: conversation.clone(),
-            kind: ConversationUpdateKind::RemoveParticipant {
+            conversation,
+            kind: ConversationUpdateKind::Unban {
                 did: did_key.clone(),
             },
         };
 
         let tx = self.subscribe(conversation_id).await?;
-        let _ = tx.send(MessageEventKind::RecipientRemoved {
+        let _ = tx.send(MessageEventKind::RecipientUnbanned {
             conversation_id,
             recipient: did_key.clone(),
         });
 
-        self.publish(conversation_id, None, event, true).await?;
+        self.publish(conversation_id, None, event, true).await
+    }
 
-        if broadcast {
-            let new_event = ConversationEvents::DeleteConversation { conversation_id };
+    pub async fn list_banned(&self, conversation_id: Uuid) -> Result<Vec<DID>, Error> {
+        let conversation = self.get(conversation_id).await?;
 
-            self.send_single_conversation_event(conversation_id, did_key, new_event)
-                .await?;
+        if matches!(conversation.conversation_type(), ConversationType::Direct) {
+            return Err(Error::InvalidConversation);
         }
 
-        Ok(())
+        Ok(conversation.banned)
     }
 
     pub async fn delete_conversation(
@@ -2683,6 +4428,28 @@ impl ConversationInner {
 
         let document_type = self.delete(conversation_id).await?;
 
+        if let Err(e) = self.search_index.delete(conversation_id).await {
+            tracing::warn!(%conversation_id, error = %e, "unable to remove search index");
+        }
+
+        self.expiring.remove(&conversation_id);
+
+        let scheduled_count = self.scheduled.len();
+        self.scheduled
+            .retain(|scheduled| scheduled.conversation_id() != conversation_id);
+
+        if self.scheduled.len() != scheduled_count {
+            self.save_scheduled().await;
+        }
+
+        if let Ok(mut drafts) = self.root.get_drafts().await {
+            if drafts.remove(&conversation_id).is_some() {
+                if let Err(e) = self.root.set_drafts(drafts).await {
+                    tracing::warn!(%conversation_id, error = %e, "unable to remove drafts");
+                }
+            }
+        }
+
         if broadcast {
             let recipients = document_type.recipients();
 
@@ -2690,21 +4457,18 @@ impl ConversationInner {
 
             if matches!(document_type.conversation_type(), ConversationType::Group) {
                 let own_did = &*self.keypair;
-                let creator = document_type
-                    .creator
-                    .as_ref()
-                    .ok_or(Error::InvalidConversation)?;
+                let owner = document_type.owner().ok_or(Error::InvalidConversation)?;
 
-                if creator.ne(own_did) {
+                if owner.ne(own_did) {
                     can_broadcast = false;
                     let recipients = recipients
                         .iter()
                         .filter(|did| own_did.ne(did))
-                        .filter(|did| creator.ne(did))
+                        .filter(|did| owner.ne(did))
                         .cloned()
                         .collect::<Vec<_>>();
                     if let Err(e) = self
-                        .leave_group_conversation(creator, &recipients, conversation_id)
+                        .leave_group_conversation(&owner, &recipients, conversation_id)
                         .await
                     {
                         error!(%conversation_id, error = %e, "Error leaving conversation");
@@ -2790,7 +4554,7 @@ impl ConversationInner {
 
     async fn leave_group_conversation(
         &mut self,
-        creator: &DID,
+        owner: &DID,
         list: &[DID],
         conversation_id: Uuid,
     ) -> Result<(), Error> {
@@ -2806,7 +4570,7 @@ impl ConversationInner {
             signature,
         };
 
-        //We want to send the event to the recipients until the creator can remove them from the conversation directly
+        //We want to send the event to the recipients until the owner can remove them from the conversation directly
 
         for did in list.iter() {
             if let Err(e) = self
@@ -2818,7 +4582,7 @@ impl ConversationInner {
             }
         }
 
-        self.send_single_conversation_event(conversation_id, creator, event)
+        self.send_single_conversation_event(conversation_id, owner, event)
             .await
     }
 
@@ -2826,7 +4590,19 @@ impl ConversationInner {
         &mut self,
         conversation_id: Uuid,
         event: MessageEvent,
+        ttl: Duration,
     ) -> Result<(), Error> {
+        validate_message_event(&event)?;
+
+        if ttl.is_zero() || ttl > MAX_EVENT_TTL {
+            return Err(Error::InvalidLength {
+                context: "ttl".into(),
+                current: ttl.as_secs() as _,
+                minimum: Some(1),
+                maximum: Some(MAX_EVENT_TTL.as_secs() as _),
+            });
+        }
+
         let own_did = &*self.keypair;
 
         let event = MessagingEvents::Event {
@@ -2834,6 +4610,7 @@ impl ConversationInner {
             member: own_did.clone(),
             event,
             cancelled: false,
+            ttl: Some(ttl.as_secs()),
         };
         self.send_message_event(conversation_id, event).await
     }
@@ -2843,6 +4620,8 @@ impl ConversationInner {
         conversation_id: Uuid,
         event: MessageEvent,
     ) -> Result<(), Error> {
+        validate_message_event(&event)?;
+
         let own_did = &*self.keypair;
 
         let event = MessagingEvents::Event {
@@ -2850,6 +4629,7 @@ impl ConversationInner {
             member: own_did.clone(),
             event,
             cancelled: true,
+            ttl: None,
         };
         self.send_message_event(conversation_id, event).await
     }
@@ -2896,18 +4676,26 @@ impl ConversationInner {
     ) -> Result<(), Error> {
         let mut conversation = self.get(conversation_id).await?;
         let own_did = &*self.keypair;
-        let Some(creator) = &conversation.creator else {
-            return Err(Error::InvalidConversation);
-        };
 
-        if creator != own_did {
-            return Err(Error::PublicKeyInvalid);
+        match (conversation.conversation_type(), settings) {
+            // Note: Either member of a direct conversation can change the settings
+            (ConversationType::Direct, ConversationSettings::Direct(_)) => {}
+            (ConversationType::Group, ConversationSettings::Group(_)) => {
+                if conversation.role(own_did) < GroupRole::Admin {
+                    return Err(Error::PublicKeyInvalid);
+                }
+            }
+            _ => return Err(Error::InvalidConversation),
         }
 
         conversation.settings = settings;
+        if let ConversationType::Group = conversation.conversation_type() {
+            conversation.sign(own_did)?;
+        }
         self.set_document(conversation).await?;
 
         let conversation = self.get(conversation_id).await?;
+        self.refresh_expiry(&conversation).await;
         let event = MessagingEvents::UpdateConversation {
             conversation: conversation.clone(),
             kind: ConversationUpdateKind::ChangeSettings {
@@ -2924,6 +4712,219 @@ impl ConversationInner {
         self.publish(conversation_id, None, event, true).await
     }
 
+    pub async fn export_conversation(
+        &self,
+        conversation_id: Uuid,
+        path: PathBuf,
+        passphrase: Option<&str>,
+    ) -> Result<(), Error> {
+        let conversation = self.get(conversation_id).await?;
+
+        // The keystore is only exported into archives encrypted with a passphrase
+        let keystore = match (conversation.conversation_type(), passphrase) {
+            (ConversationType::Group, Some(_)) => Some(self.get_keystore(conversation_id).await?),
+            _ => None,
+        };
+
+        let key = pubkey_or_keystore(self, conversation_id, &self.keypair).await?;
+
+        let members = conversation
+            .recipients()
+            .iter()
+            .filter_map(|did| did.to_peer_id().ok())
+            .collect::<Vec<_>>();
+
+        let mut writer = ArchiveWriter::create(&path, passphrase).await?;
+
+        writer
+            .write_conversation(&ArchivedConversation {
+                conversation: Conversation::from(&conversation),
+                document: conversation.clone(),
+                keystore,
+            })
+            .await?;
+
+        for document in conversation.get_message_list(&self.ipfs).await? {
+            // Deleted messages no longer have any contents to export
+            if document.message.is_none() {
+                continue;
+            }
+
+            let message = match document
+                .resolve(&self.ipfs, &self.keypair, true, key.as_ref())
+                .await
+            {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!(%conversation_id, message_id = %document.id, "unable to resolve message: {e}");
+                    continue;
+                }
+            };
+
+            let archived = ArchivedMessage::new(&self.ipfs, message, document).await?;
+
+            for attachment in &archived.attachments {
+                let Ok(cid) = Cid::from_str(&attachment.data) else {
+                    continue;
+                };
+
+                let mut stream =
+                    attachment.download_stream(&self.ipfs, &members, Some(Duration::from_secs(30)));
+
+                let mut data = Vec::with_capacity(attachment.size);
+                let mut failed = false;
+
+                while let Some(result) = stream.next().await {
+                    match result {
+                        Ok(bytes) => data.extend(bytes),
+                        Err(e) => {
+                            warn!(%conversation_id, name = %attachment.name, "unable to obtain attachment: {e}");
+                            failed = true;
+                            break;
+                        }
+                    }
+                }
+
+                if !failed {
+                    writer.write_blob(&cid, &data).await?;
+                }
+            }
+
+            for image in &archived.thumbnails {
+                let data = self
+                    .ipfs
+                    .unixfs()
+                    .cat(image.link)
+                    .set_local(true)
+                    .timeout(Duration::from_secs(10))
+                    .await
+                    .unwrap_or_default();
+
+                if !data.is_empty() {
+                    writer.write_blob(&image.link, &data).await?;
+                }
+            }
+
+            writer.push_message(&archived)?;
+        }
+
+        writer.finish(conversation_id).await
+    }
+
+    pub async fn import_conversation(
+        &mut self,
+        path: PathBuf,
+        passphrase: Option<&str>,
+    ) -> Result<Uuid, Error> {
+        let own_did = &*(self.keypair.clone());
+
+        let reader = ArchiveReader::open(&path, passphrase).await?;
+        let archived = reader.conversation().await?;
+        let conversation_id = archived.document.id();
+
+        if !archived.document.recipients().contains(own_did) {
+            return Err(Error::InvalidConversation);
+        }
+
+        // Note: Deleted conversations are not returned and would be restored from the archive
+        let exist = self.get(conversation_id).await.is_ok();
+
+        if !exist {
+            let mut document = archived.document.clone();
+            document.messages = None;
+            document.deleted = false;
+            document.modified = Utc::now();
+            self.set_document(document).await?;
+
+            if let ConversationType::Group = archived.document.conversation_type() {
+                let keystore = match archived.keystore.clone() {
+                    Some(keystore) => keystore,
+                    None => {
+                        let mut keystore = Keystore::new(conversation_id);
+                        keystore.insert(own_did, own_did, warp::crypto::generate::<64>())?;
+                        keystore
+                    }
+                };
+
+                self.set_keystore(conversation_id, keystore).await?;
+            }
+
+            self.create_conversation_task(conversation_id).await?;
+        }
+
+        let mut conversation = self.get(conversation_id).await?;
+        let mut imported = vec![];
+
+        for message in reader.messages().await? {
+            let message_id = message.document.id;
+
+            if conversation.contains(&self.ipfs, message_id).await? {
+                continue;
+            }
+
+            let document = match message.restore(&self.ipfs).await {
+                Ok(document) => document,
+                Err(e) => {
+                    warn!(%conversation_id, %message_id, "unable to restore message: {e}");
+                    continue;
+                }
+            };
+
+            for cid in message.blobs() {
+                let data = match reader.blob(&cid).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        warn!(%conversation_id, %message_id, %cid, "blob is unavailable: {e}");
+                        continue;
+                    }
+                };
+
+                let path = self
+                    .ipfs
+                    .add_unixfs(data)
+                    .await
+                    .map_err(anyhow::Error::from)?;
+
+                if path.root().cid() != Some(&cid) {
+                    warn!(%conversation_id, %message_id, %cid, "blob does not match the referenced content");
+                }
+            }
+
+            conversation
+                .insert_message_document(&self.ipfs, document)
+                .await?;
+
+            self.queue_expiry(&conversation, &document);
+
+            imported.push((message_id, document.date, message.message.lines()));
+        }
+
+        self.set_document(conversation).await?;
+
+        for (message_id, date, lines) in imported {
+            self.index_message(conversation_id, message_id, date, &lines)
+                .await;
+        }
+
+        if !exist {
+            let conversation = self.get(conversation_id).await?;
+
+            if let ConversationType::Group = conversation.conversation_type() {
+                for recipient in conversation.recipients().iter().filter(|d| own_did.ne(d)) {
+                    if let Err(e) = self.request_key(conversation_id, recipient).await {
+                        tracing::warn!("Failed to send exchange request to {recipient}: {e}");
+                    }
+                }
+            }
+
+            self.event
+                .emit(RayGunEventKind::ConversationCreated { conversation_id })
+                .await;
+        }
+
+        Ok(conversation_id)
+    }
+
     pub async fn publish(
         &mut self,
         conversation_id: Uuid,
@@ -3256,15 +5257,15 @@ async fn process_conversation(
                 return Err(anyhow::anyhow!("Can only leave from a group conversation").into());
             }
 
-            let Some(creator) = conversation.creator.as_ref() else {
+            let Some(owner) = conversation.owner() else {
                 return Err(anyhow::anyhow!("Group conversation requires a creator").into());
             };
 
             let own_did = &*this.keypair;
 
             // Precaution
-            if recipient.eq(creator) {
-                return Err(anyhow::anyhow!("Cannot remove the creator of the group").into());
+            if recipient.eq(&owner) {
+                return Err(anyhow::anyhow!("Cannot remove the owner of the group").into());
             }
 
             if !conversation.recipients.contains(&recipient) {
@@ -3275,7 +5276,7 @@ async fn process_conversation(
 
             tracing::info!("{recipient} is leaving group conversation {conversation_id}");
 
-            if creator.eq(own_did) {
+            if owner.eq(own_did) {
                 this.remove_recipient(conversation_id, &recipient, false)
                     .await?;
             } else {
@@ -3327,7 +5328,8 @@ async fn process_conversation(
                     if conversation.recipients().contains(&sender)
                         && matches!(conversation.conversation_type(), ConversationType::Direct)
                         || matches!(conversation.conversation_type(), ConversationType::Group)
-                            && matches!(&conversation.creator, Some(creator) if creator.eq(&sender)) =>
+                            && conversation.role(&sender) >= GroupRole::Moderator
+                            && conversation.role(&sender) > conversation.role(&this.keypair) =>
                 {
                     conversation
                 }
@@ -3349,6 +5351,7 @@ async fn process_conversation(
 async fn message_event(
     this: &mut ConversationInner,
     conversation_id: Uuid,
+    sender: &DID,
     events: MessagingEvents,
 ) -> Result<(), Error> {
     let mut document = this.get(conversation_id).await?;
@@ -3376,6 +5379,11 @@ async fn message_event(
                 return Err(Error::MessageFound);
             }
 
+            if matches!(document.message_expire(&message), Some(expire) if expire <= Utc::now()) {
+                tracing::debug!(%conversation_id, %message_id, "message already expired. Skipping");
+                return Ok(());
+            }
+
             let resolved_message = message
                 .resolve(&this.ipfs, &this.keypair, false, keystore.as_ref())
                 .await?;
@@ -3407,14 +5415,39 @@ async fn message_event(
                 .insert_message_document(&this.ipfs, message)
                 .await?;
 
+            this.queue_expiry(&document, &message);
+
             this.set_document(document).await?;
 
+            this.index_message(
+                conversation_id,
+                message_id,
+                message.date,
+                &resolved_message.lines(),
+            )
+            .await;
+
             if let Err(e) = tx.send(MessageEventKind::MessageReceived {
                 conversation_id,
                 message_id,
             }) {
                 tracing::warn!(%conversation_id, "Error broadcasting event: {e}");
             }
+
+            if let Some(thread_id) = message.thread {
+                _ = tx.send(MessageEventKind::ThreadReplyAdded {
+                    conversation_id,
+                    thread_id,
+                    message_id,
+                });
+            }
+
+            if let Err(e) = this
+                .send_receipt(conversation_id, ReceiptKind::Delivered, &message)
+                .await
+            {
+                tracing::warn!(%conversation_id, %message_id, error = %e, "unable to send delivery receipt");
+            }
         }
         MessagingEvents::Edit {
             conversation_id,
@@ -3423,11 +5456,20 @@ async fn message_event(
             lines,
             nonce,
             signature,
+            history,
         } => {
+            // Revisions are only kept, and required, when the conversation keeps the history of edits
+            if history != document.settings.keep_edit_history() {
+                tracing::warn!(%conversation_id, %message_id, "edit does not match the history setting of the conversation");
+                return Err(Error::InvalidMessage);
+            }
+
             let mut message_document = document
                 .get_message_document(&this.ipfs, message_id)
                 .await?;
 
+            let previous = message_document;
+
             let mut message = message_document
                 .resolve(&this.ipfs, &this.keypair, true, keystore.as_ref())
                 .await?;
@@ -3455,7 +5497,7 @@ async fn message_event(
 
             let sender = message.sender();
 
-            *message.lines_mut() = lines;
+            *message.lines_mut() = lines.clone();
             message.set_modified(modified);
 
             message_document
@@ -3466,15 +5508,31 @@ async fn message_event(
                     (!signature.is_empty() && sender.ne(&this.keypair)).then_some(signature),
                     keystore.as_ref(),
                     Some(nonce.as_slice()),
+                    history,
                 )
                 .await?;
 
+            if message_document.message != previous.message {
+                let linked = match history {
+                    true => message_document.links_to(&this.ipfs, &previous).await,
+                    false => message_document.history == previous.history,
+                };
+
+                if !linked {
+                    tracing::warn!(%conversation_id, %message_id, "edit does not link to the previous revision");
+                    return Err(Error::InvalidMessage);
+                }
+            }
+
             document
                 .update_message_document(&this.ipfs, message_document)
                 .await?;
 
             this.set_document(document).await?;
 
+            this.index_message(conversation_id, message_id, message_document.date, &lines)
+                .await;
+
             if let Err(e) = tx.send(MessageEventKind::MessageEdited {
                 conversation_id,
                 message_id,
@@ -3500,16 +5558,26 @@ async fn message_event(
             //     }
             // }
 
-            document.delete_message(&this.ipfs, message_id).await?;
+            let thread = document.delete_message(&this.ipfs, message_id).await?;
 
             this.set_document(document).await?;
 
+            this.unindex_message(conversation_id, message_id).await;
+
             if let Err(e) = tx.send(MessageEventKind::MessageDeleted {
                 conversation_id,
                 message_id,
             }) {
                 tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
             }
+
+            if let Some(thread_id) = thread {
+                _ = tx.send(MessageEventKind::ThreadReplyRemoved {
+                    conversation_id,
+                    thread_id,
+                    message_id,
+                });
+            }
         }
         MessagingEvents::Pin {
             conversation_id,
@@ -3556,6 +5624,7 @@ async fn message_event(
                     None,
                     keystore.as_ref(),
                     None,
+                    false,
                 )
                 .await?;
 
@@ -3604,6 +5673,7 @@ async fn message_event(
                             None,
                             keystore.as_ref(),
                             None,
+                            false,
                         )
                         .await?;
 
@@ -3647,6 +5717,7 @@ async fn message_event(
                             None,
                             keystore.as_ref(),
                             None,
+                            false,
                         )
                         .await?;
 
@@ -3672,12 +5743,33 @@ async fn message_event(
             kind,
         } => {
             conversation.verify()?;
+
+            // Permissions are checked against the state of the conversation prior to the update
+            match document.settings {
+                ConversationSettings::Group(settings) => {
+                    if document.role(sender) < required_role(&kind, &settings) {
+                        return Err(Error::PublicKeyInvalid);
+                    }
+
+                    validate_conversation_update(&document, &conversation, sender, &kind)?;
+                }
+                ConversationSettings::Direct(_) => {
+                    if !matches!(kind, ConversationUpdateKind::ChangeSettings { .. }) {
+                        return Err(Error::InvalidConversation);
+                    }
+                }
+            }
+
             match kind {
                 ConversationUpdateKind::AddParticipant { did } => {
                     if document.recipients.contains(&did) {
                         return Ok(());
                     }
 
+                    if document.banned.contains(&did) {
+                        return Err(Error::PublicKeyIsBlocked);
+                    }
+
                     if !this.discovery.contains(&did).await {
                         let _ = this.discovery.insert(&did).await.ok();
                     }
@@ -3702,6 +5794,10 @@ async fn message_event(
                         return Err(Error::IdentityDoesntExist);
                     }
 
+                    if document.role(&did) >= document.role(sender) {
+                        return Err(Error::PublicKeyInvalid);
+                    }
+
                     //Maybe remove participant from discovery?
 
                     let can_emit = !document.excluded.contains_key(&did);
@@ -3772,19 +5868,286 @@ async fn message_event(
                     //      but for now, we can leave this as a silent update since the block list would be for internal handling for now
                 }
                 ConversationUpdateKind::ChangeSettings { settings } => {
+                    match (document.conversation_type(), settings) {
+                        // Since direct conversations are not signed, we only accept the settings from the update
+                        (ConversationType::Direct, ConversationSettings::Direct(_)) => {
+                            document.settings = settings;
+                            this.set_document(document).await?;
+                        }
+                        (ConversationType::Group, ConversationSettings::Group(_)) => {
+                            conversation.excluded = document.excluded;
+                            conversation.messages = document.messages;
+                            this.set_document(conversation).await?;
+                        }
+                        _ => return Err(Error::InvalidConversation),
+                    }
+
+                    let conversation = this.get(conversation_id).await?;
+                    this.refresh_expiry(&conversation).await;
+
+                    if let Err(e) = tx.send(MessageEventKind::ConversationSettingsUpdated {
+                        conversation_id,
+                        settings,
+                    }) {
+                        tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+                    }
+                }
+                ConversationUpdateKind::ChangeRole { did, role } => {
+                    validate_role_change(&document, sender, &did, role)?;
+
+                    if conversation.role(&did) != role {
+                        return Err(Error::InvalidConversation);
+                    }
+
                     conversation.excluded = document.excluded;
                     conversation.messages = document.messages;
                     this.set_document(conversation).await?;
 
-                    if let Err(e) = tx.send(MessageEventKind::ConversationSettingsUpdated {
+                    if let Err(e) = tx.send(MessageEventKind::RecipientRoleUpdated {
                         conversation_id,
-                        settings,
+                        recipient: did,
+                        role,
+                    }) {
+                        tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+                    }
+                }
+                ConversationUpdateKind::TransferOwnership { did } => {
+                    if !document.recipients.contains(&did) {
+                        return Err(Error::IdentityDoesntExist);
+                    }
+
+                    if conversation.owner().as_ref() != Some(&did) {
+                        return Err(Error::InvalidConversation);
+                    }
+
+                    let previous_role = conversation.role(sender);
+
+                    conversation.excluded = document.excluded;
+                    conversation.messages = document.messages;
+                    this.set_document(conversation).await?;
+
+                    for (recipient, role) in
+                        [(did, GroupRole::Owner), (sender.clone(), previous_role)]
+                    {
+                        if let Err(e) = tx.send(MessageEventKind::RecipientRoleUpdated {
+                            conversation_id,
+                            recipient,
+                            role,
+                        }) {
+                            tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+                        }
+                    }
+                }
+                ConversationUpdateKind::Ban { did } => {
+                    if document.banned.contains(&did) {
+                        return Ok(());
+                    }
+
+                    if document.role(&did) >= document.role(sender) {
+                        return Err(Error::PublicKeyInvalid);
+                    }
+
+                    if !conversation.banned.contains(&did) || conversation.recipients.contains(&did)
+                    {
+                        return Err(Error::InvalidConversation);
+                    }
+
+                    let removed = document.recipients.contains(&did);
+
+                    document.excluded.remove(&did);
+
+                    conversation.excluded = document.excluded;
+                    conversation.messages = document.messages;
+                    this.set_document(conversation).await?;
+
+                    if removed {
+                        if let Err(e) = tx.send(MessageEventKind::RecipientRemoved {
+                            conversation_id,
+                            recipient: did.clone(),
+                        }) {
+                            tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+                        }
+                    }
+
+                    if let Err(e) = tx.send(MessageEventKind::RecipientBanned {
+                        conversation_id,
+                        recipient: did,
+                    }) {
+                        tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+                    }
+                }
+                ConversationUpdateKind::Unban { did } => {
+                    if !document.banned.contains(&did) {
+                        return Ok(());
+                    }
+
+                    if conversation.banned.contains(&did) {
+                        return Err(Error::InvalidConversation);
+                    }
+
+                    conversation.excluded = document.excluded;
+                    conversation.messages = document.messages;
+                    this.set_document(conversation).await?;
+
+                    if let Err(e) = tx.send(MessageEventKind::RecipientUnbanned {
+                        conversation_id,
+                        recipient: did,
                     }) {
                         tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
                     }
                 }
             }
         }
+        MessagingEvents::Receipt { receipt } => {
+            if !receipt.verify() {
+                return Err(Error::InvalidSignature);
+            }
+
+            if document.id != receipt.conversation_id {
+                return Err(Error::InvalidConversation);
+            }
+
+            let member = receipt.member.to_did();
+
+            if !document.recipients().contains(&member) || (*this.keypair).eq(&member) {
+                return Err(Error::IdentityDoesntExist);
+            }
+
+            if !document.insert_receipt(&this.ipfs, receipt).await? {
+                return Ok(());
+            }
+
+            this.set_document(document).await?;
+
+            if matches!(receipt.kind, ReceiptKind::Read) {
+                if let Err(e) = tx.send(MessageEventKind::MessageRead {
+                    conversation_id,
+                    message_id: receipt.message_id,
+                    did_key: member,
+                }) {
+                    tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+                }
+            }
+        }
+        MessagingEvents::PollVote { vote } => {
+            if document.id != vote.conversation_id {
+                return Err(Error::InvalidConversation);
+            }
+
+            let voter = vote.voter.to_did();
+
+            if !document.recipients().contains(&voter) {
+                return Err(Error::IdentityDoesntExist);
+            }
+
+            let message_id = vote.message_id;
+
+            let mut message_document = document
+                .get_message_document(&this.ipfs, message_id)
+                .await?;
+
+            let message = message_document
+                .resolve(&this.ipfs, &this.keypair, true, keystore.as_ref())
+                .await?;
+
+            let poll = message.poll().ok_or(Error::InvalidMessage)?;
+
+            let received = Utc::now();
+
+            if matches!(poll.close_at(), Some(date) if received > date) {
+                return Err(Error::PollClosed);
+            }
+
+            validate_poll_choices(&poll, &vote.choices)?;
+
+            if !message_document
+                .insert_poll_vote(&this.ipfs, vote, received)
+                .await?
+            {
+                return Ok(());
+            }
+
+            document
+                .update_message_document(&this.ipfs, message_document)
+                .await?;
+
+            this.set_document(document).await?;
+
+            if let Err(e) = tx.send(MessageEventKind::PollVoted {
+                conversation_id,
+                message_id,
+                did_key: voter,
+            }) {
+                tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+            }
+        }
+        MessagingEvents::PollClose {
+            conversation_id,
+            message_id,
+            closed,
+            signature,
+        } => {
+            if document.id != conversation_id {
+                return Err(Error::InvalidConversation);
+            }
+
+            let mut message_document = document
+                .get_message_document(&this.ipfs, message_id)
+                .await?;
+
+            if message_document.poll_closed.is_some() {
+                return Ok(());
+            }
+
+            message_document.set_poll_closed(closed, signature)?;
+
+            document
+                .update_message_document(&this.ipfs, message_document)
+                .await?;
+
+            this.set_document(document).await?;
+
+            if let Err(e) = tx.send(MessageEventKind::PollClosed {
+                conversation_id,
+                message_id,
+            }) {
+                tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+            }
+        }
+        MessagingEvents::Embeds {
+            conversation_id,
+            message_id,
+            embeds,
+            signature,
+        } => {
+            if document.id != conversation_id {
+                return Err(Error::InvalidConversation);
+            }
+
+            let mut message_document = document
+                .get_message_document(&this.ipfs, message_id)
+                .await?;
+
+            message_document.set_embeds_signed(embeds, signature)?;
+
+            // resolving the message will fetch the previews and their thumbnails
+            message_document
+                .resolve(&this.ipfs, &this.keypair, false, keystore.as_ref())
+                .await?;
+
+            document
+                .update_message_document(&this.ipfs, message_document)
+                .await?;
+
+            this.set_document(document).await?;
+
+            if let Err(e) = tx.send(MessageEventKind::MessageEmbedsUpdated {
+                conversation_id,
+                message_id,
+            }) {
+                tracing::warn!(%conversation_id, error = %e, "Error broadcasting event");
+            }
+        }
         _ => {}
     }
     Ok(())
@@ -3822,7 +6185,7 @@ async fn process_identity_events(
                         }
                     }
                     ConversationType::Group => {
-                        if conversation.creator != Some((*this.keypair).clone()) {
+                        if conversation.role(&this.keypair) < GroupRole::Moderator {
                             continue;
                         }
 
@@ -3845,9 +6208,9 @@ async fn process_identity_events(
             for conversation in list
                 .iter()
                 .filter(|c| {
-                    c.creator
+                    c.owner()
                         .as_ref()
-                        .map(|creator| own_did.eq(creator))
+                        .map(|owner| own_did.eq(owner))
                         .unwrap_or_default()
                 })
                 .filter(|c| c.conversation_type() == ConversationType::Group)
@@ -3874,20 +6237,101 @@ async fn process_identity_events(
                         }
                     }
                     ConversationType::Group => {
-                        if conversation.creator != Some((*this.keypair).clone()) {
+                        if conversation.role(&this.keypair) < GroupRole::Moderator {
+                            continue;
+                        }
+
+                        if let Err(e) = this.remove_recipient(id, &did, true).await {
+                            tracing::warn!(conversation_id = %id, error = %e, "Failed to remove {did} from conversation");
+                            continue;
+                        }
+                    }
+                }
+            }
+        }
+        MultiPassEventKind::IdentityOnline { .. } => {
+            //TODO: Check queue and process any entry once peer is subscribed to the respective topics.
+        }
+        MultiPassEventKind::IdentityRotated { old, new } => {
+            let own_did = (*this.keypair).clone();
+            let list = this.list().await;
+
+            for conversation in list.iter().filter(|c| c.recipients().contains(&old)) {
+                let id = conversation.id();
+                match conversation.conversation_type() {
+                    ConversationType::Direct => {
+                        // Direct conversations are derived from both identities so a conversation is started with the new identity instead
+                        match this.create_conversation(&new).await {
+                            Ok(_) | Err(Error::ConversationExist { .. }) => {}
+                            Err(e) => {
+                                warn!(conversation_id = %id, error = %e, "Failed to create conversation with {new}");
+                            }
+                        }
+                    }
+                    ConversationType::Group => {
+                        if let Ok(mut keystore) = this.get_keystore(id).await {
+                            if keystore.migrate(&own_did, &old, &new).unwrap_or_default() {
+                                if let Err(e) = this.set_keystore(id, keystore).await {
+                                    warn!(conversation_id = %id, error = %e, "Failed to migrate keystore");
+                                }
+                            }
+                        }
+
+                        if conversation.recipients().contains(&new)
+                            || conversation.role(&own_did) < GroupRole::Moderator
+                        {
                             continue;
                         }
 
-                        if let Err(e) = this.remove_recipient(id, &did, true).await {
-                            tracing::warn!(conversation_id = %id, error = %e, "Failed to remove {did} from conversation");
+                        if let Err(e) = this.remove_recipient(id, &old, false).await {
+                            warn!(conversation_id = %id, error = %e, "Failed to remove {old} from conversation");
                             continue;
                         }
+
+                        if let Err(e) = this.add_recipient(id, &new).await {
+                            warn!(conversation_id = %id, error = %e, "Failed to add {new} to conversation");
+                        }
                     }
                 }
             }
         }
-        MultiPassEventKind::IdentityOnline { .. } => {
-            //TODO: Check queue and process any entry once peer is subscribed to the respective topics.
+        MultiPassEventKind::IdentitySynced { .. } => {
+            // Start or stop the conversations that were created, joined or deleted on another device
+            let list = this
+                .list_stream()
+                .await
+                .map(|conversation| conversation.id())
+                .collect::<HashSet<_>>()
+                .await;
+
+            let removed = this
+                .conversation_task
+                .keys()
+                .filter(|id| !list.contains(id))
+                .copied()
+                .collect::<Vec<_>>();
+
+            for conversation_id in removed {
+                this.destroy_conversation(conversation_id).await;
+                this.event
+                    .emit(RayGunEventKind::ConversationDeleted { conversation_id })
+                    .await;
+            }
+
+            for conversation_id in list {
+                if this.conversation_task.contains_key(&conversation_id) {
+                    continue;
+                }
+
+                if let Err(e) = this.create_conversation_task(conversation_id).await {
+                    warn!(%conversation_id, error = %e, "Failed to load conversation");
+                    continue;
+                }
+
+                this.event
+                    .emit(RayGunEventKind::ConversationCreated { conversation_id })
+                    .await;
+            }
         }
         _ => {}
     }
@@ -3943,52 +6387,7 @@ async fn process_request_response_event(
                     }
                 };
 
-                let key = ecdh_encrypt(&this.keypair, Some(&sender), raw_key)?;
-
-                let response = ConversationRequestResponse::Response {
-                    conversation_id,
-                    kind: ConversationResponseKind::Key { key },
-                };
-
-                let topic = conversation.reqres_topic(&sender);
-
-                let bytes =
-                    ecdh_encrypt(&this.keypair, Some(&sender), serde_json::to_vec(&response)?)?;
-                let signature = sign_serde(&this.keypair, &bytes)?;
-
-                let payload = Payload::new(&this.keypair, &bytes, &signature);
-
-                let peers = this.ipfs.pubsub_peers(Some(topic.clone())).await?;
-
-                let peer_id = sender.to_peer_id()?;
-
-                let bytes = payload.to_bytes()?;
-
-                tracing::trace!(%conversation_id, "Payload size: {} bytes", bytes.len());
-
-                tracing::info!(%conversation_id, "Responding to {sender}");
-
-                if !peers.contains(&peer_id)
-                    || (peers.contains(&peer_id)
-                        && this
-                            .ipfs
-                            .pubsub_publish(topic.clone(), bytes)
-                            .await
-                            .is_err())
-                {
-                    warn!(%conversation_id, "Unable to publish to topic. Queuing event");
-                    this.queue_event(
-                        sender.clone(),
-                        Queue::direct(
-                            conversation_id,
-                            None,
-                            peer_id,
-                            topic.clone(),
-                            payload.data().into(),
-                        ),
-                    )
-                    .await;
-                }
+                this.send_key(conversation_id, &sender, &raw_key).await?;
             }
             _ => {
                 tracing::info!(%conversation_id, "Unimplemented/Unsupported Event");
@@ -4063,7 +6462,7 @@ async fn process_pending_payload(this: &mut ConversationInner) {
                 let key = store.get_latest(&this.keypair, &sender)?;
                 let data = Cipher::direct_decrypt(&data, &key)?;
                 let event = serde_json::from_slice(&data)?;
-                message_event(this, conversation_id, event).await
+                message_event(this, conversation_id, &sender, event).await
             };
 
             if let Err(e) = fut.await {
@@ -4099,19 +6498,62 @@ async fn process_conversation_event(
         member,
         event,
         cancelled,
+        ttl,
     } = event
     {
+        if member != sender {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        validate_message_event(&event)?;
+
+        // Remove any previous instance of the event since it will either be cancelled or refreshed
+        this.active_events.retain(|active| {
+            !(active.conversation_id == conversation_id
+                && active.member == member
+                && is_same_event(&active.event, &event))
+        });
+
+        let active = this
+            .active_events
+            .iter()
+            .filter(|active| active.conversation_id == conversation_id && active.member == member)
+            .count();
+
+        if !cancelled && active >= MAX_ACTIVE_EVENTS {
+            return Err(Error::InvalidLength {
+                context: "events".into(),
+                current: active + 1,
+                minimum: None,
+                maximum: Some(MAX_ACTIVE_EVENTS),
+            });
+        }
+
         let ev = match cancelled {
             true => MessageEventKind::EventCancelled {
                 conversation_id,
                 did_key: member,
                 event,
             },
-            false => MessageEventKind::EventReceived {
-                conversation_id,
-                did_key: member,
-                event,
-            },
+            false => {
+                let ttl = ttl
+                    .map(Duration::from_secs)
+                    .unwrap_or_else(|| event.default_ttl())
+                    .min(MAX_EVENT_TTL);
+
+                this.active_events.push(ActiveEvent {
+                    conversation_id,
+                    member: member.clone(),
+                    event: event.clone(),
+                    expire: Instant::now() + ttl,
+                });
+
+                MessageEventKind::EventReceived {
+                    conversation_id,
+                    did_key: member,
+                    event,
+                }
+            }
         };
 
         if let Err(e) = tx.send(ev) {
@@ -4122,6 +6564,440 @@ async fn process_conversation_event(
     Ok(())
 }
 
+fn validate_message_event(event: &MessageEvent) -> Result<(), Error> {
+    if let MessageEvent::Custom { namespace, payload } = event {
+        if namespace.is_empty() || namespace.len() > MAX_EVENT_NAMESPACE_LENGTH {
+            return Err(Error::InvalidLength {
+                context: "namespace".into(),
+                current: namespace.len(),
+                minimum: Some(1),
+                maximum: Some(MAX_EVENT_NAMESPACE_LENGTH),
+            });
+        }
+
+        let size = serde_json::to_vec(payload)?.len();
+
+        if size > MAX_EVENT_PAYLOAD_SIZE {
+            return Err(Error::InvalidLength {
+                context: "payload".into(),
+                current: size,
+                minimum: None,
+                maximum: Some(MAX_EVENT_PAYLOAD_SIZE),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Events are identified by their kind, along with the namespace for custom events, so that a refreshed
+/// event replaces the previous instance even if its payload changed
+fn is_same_event(a: &MessageEvent, b: &MessageEvent) -> bool {
+    match (a, b) {
+        (MessageEvent::Custom { namespace: a, .. }, MessageEvent::Custom { namespace: b, .. }) => {
+            a == b
+        }
+        _ => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+}
+
+/// Event received from a member that is considered active until it expires or is cancelled
+struct ActiveEvent {
+    conversation_id: Uuid,
+    member: DID,
+    event: MessageEvent,
+    expire: Instant,
+}
+
+/// Emit a cancellation for any event that has not been refreshed or cancelled by the sender before it expired
+async fn process_expired_events(this: &mut ConversationInner) {
+    let now = Instant::now();
+
+    let (expired, active): (Vec<_>, Vec<_>) = std::mem::take(&mut this.active_events)
+        .into_iter()
+        .partition(|active| active.expire <= now);
+
+    this.active_events = active;
+
+    for ActiveEvent {
+        conversation_id,
+        member,
+        event,
+        ..
+    } in expired
+    {
+        let Ok(tx) = this.subscribe(conversation_id).await else {
+            continue;
+        };
+
+        _ = tx.send(MessageEventKind::EventCancelled {
+            conversation_id,
+            did_key: member,
+            event,
+        });
+    }
+}
+
+async fn process_expired_messages(this: &mut ConversationInner) {
+    let now = Utc::now();
+
+    let mut expired: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+
+    for (conversation_id, queue) in this.expiring.iter_mut() {
+        while let Some((expire, message_id)) = queue.first().copied() {
+            if expire > now {
+                break;
+            }
+            queue.pop_first();
+            expired
+                .entry(*conversation_id)
+                .or_default()
+                .push(message_id);
+        }
+    }
+
+    this.expiring.retain(|_, queue| !queue.is_empty());
+
+    for (conversation_id, list) in expired {
+        let Ok(conversation) = this.get(conversation_id).await else {
+            continue;
+        };
+
+        let Ok(messages) = conversation.get_message_list(&this.ipfs).await else {
+            continue;
+        };
+
+        // Messages that were already removed or whose expiry no longer applies are skipped
+        let messages = messages.into_iter().filter(|message| {
+            list.contains(&message.id)
+                && matches!(conversation.message_expire(message), Some(expire) if expire <= now)
+        });
+
+        for message in messages {
+            let message_id = message.id;
+            if let Err(e) = this.expire_message(conversation_id, message).await {
+                tracing::warn!(%conversation_id, %message_id, error = %e, "unable to remove expired message");
+            }
+        }
+    }
+}
+
+/// Send any scheduled message that is due. Messages that fail to send remain scheduled and are retried
+/// after [`SCHEDULED_RETRY_DELAY`]. The previews of the messages are built without holding the lock
+async fn process_scheduled_messages(inner: &tokio::sync::RwLock<ConversationInner>) {
+    let now = Utc::now();
+    let instant = Instant::now();
+
+    let (due, builder) = {
+        let this = &*inner.read().await;
+
+        let due = this
+            .scheduled
+            .iter()
+            .filter(|scheduled| scheduled.send_at() <= now)
+            .filter(|scheduled| {
+                this.scheduled_retry
+                    .get(&scheduled.id())
+                    .map(|retry| *retry <= instant)
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        (due, this.embeds.clone())
+    };
+
+    if due.is_empty() {
+        return;
+    }
+
+    let mut pending = Vec::with_capacity(due.len());
+
+    for scheduled in due {
+        let lines = scheduled.lines();
+        let embeds = match &builder {
+            Some(builder) => builder.build(&lines).await,
+            None => vec![],
+        };
+        pending.push((scheduled, lines, embeds));
+    }
+
+    let this = &mut *inner.write().await;
+
+    let mut sent = vec![];
+
+    for (scheduled, lines, embeds) in pending {
+        let conversation_id = scheduled.conversation_id();
+        let scheduled_id = scheduled.id();
+
+        // the message may have been cancelled or updated while its previews were built,
+        // in which case an updated message is left for the next check
+        if !this.scheduled.contains(&scheduled) {
+            remove_thumbnails(&this.ipfs, &embeds, &[]).await;
+            continue;
+        }
+
+        let event = match this
+            .send_message(conversation_id, lines, embeds.clone())
+            .await
+        {
+            Ok(message_id) => {
+                sent.push(scheduled_id);
+                MessageEventKind::ScheduledMessageSent {
+                    conversation_id,
+                    scheduled_id,
+                    message_id,
+                }
+            }
+            Err(e) => {
+                tracing::warn!(%conversation_id, %scheduled_id, error = %e, "unable to send scheduled message");
+                remove_thumbnails(&this.ipfs, &embeds, &[]).await;
+                this.scheduled_retry
+                    .insert(scheduled_id, instant + SCHEDULED_RETRY_DELAY);
+                MessageEventKind::ScheduledMessageFailed {
+                    conversation_id,
+                    scheduled_id,
+                }
+            }
+        };
+
+        let Ok(tx) = this.subscribe(conversation_id).await else {
+            continue;
+        };
+
+        _ = tx.send(event);
+    }
+
+    if sent.is_empty() {
+        return;
+    }
+
+    this.scheduled
+        .retain(|scheduled| !sent.contains(&scheduled.id()));
+
+    let scheduled = &this.scheduled;
+    this.scheduled_retry
+        .retain(|id, _| scheduled.iter().any(|scheduled| scheduled.id() == *id));
+
+    this.save_scheduled().await;
+}
+
+/// Minimum role a member must hold to apply an update to a group conversation
+fn required_role(kind: &ConversationUpdateKind, settings: &GroupSettings) -> GroupRole {
+    match kind {
+        ConversationUpdateKind::AddParticipant { .. }
+            if settings.members_can_add_participants() =>
+        {
+            GroupRole::Member
+        }
+        ConversationUpdateKind::ChangeName { .. } if settings.members_can_change_name() => {
+            GroupRole::Member
+        }
+        ConversationUpdateKind::AddParticipant { .. }
+        | ConversationUpdateKind::RemoveParticipant { .. }
+        | ConversationUpdateKind::ChangeName { .. }
+        | ConversationUpdateKind::Ban { .. }
+        | ConversationUpdateKind::Unban { .. } => GroupRole::Moderator,
+        ConversationUpdateKind::ChangeSettings { .. }
+        | ConversationUpdateKind::ChangeRole { .. } => GroupRole::Admin,
+        ConversationUpdateKind::AddRestricted { .. }
+        | ConversationUpdateKind::RemoveRestricted { .. }
+        | ConversationUpdateKind::TransferOwnership { .. } => GroupRole::Owner,
+    }
+}
+
+/// Validate that `assigner` is able to assign `role` to `did` within the conversation
+fn validate_role_change(
+    document: &ConversationDocument,
+    assigner: &DID,
+    did: &DID,
+    role: GroupRole,
+) -> Result<(), Error> {
+    if !document.recipients.contains(did) {
+        return Err(Error::IdentityDoesntExist);
+    }
+
+    // Ownership can only be changed through a transfer
+    if role == GroupRole::Owner {
+        return Err(Error::PublicKeyInvalid);
+    }
+
+    let assigner_role = document.role(assigner);
+    let current_role = document.role(did);
+
+    if assigner_role < GroupRole::Admin || current_role >= assigner_role {
+        return Err(Error::PublicKeyInvalid);
+    }
+
+    // Only the owner is able to assign or revoke admins
+    if role == GroupRole::Admin && assigner_role != GroupRole::Owner {
+        return Err(Error::PublicKeyInvalid);
+    }
+
+    Ok(())
+}
+
+/// Validate that an update to a group conversation only changes what the kind of update covers, and only
+/// for the member the update refers to, so the rest of the document cannot be replaced along with it
+fn validate_conversation_update(
+    current: &ConversationDocument,
+    update: &ConversationDocument,
+    sender: &DID,
+    kind: &ConversationUpdateKind,
+) -> Result<(), Error> {
+    use ConversationUpdateKind::*;
+
+    let member = match kind {
+        AddParticipant { did }
+        | RemoveParticipant { did }
+        | AddRestricted { did }
+        | RemoveRestricted { did }
+        | ChangeRole { did, .. }
+        | TransferOwnership { did }
+        | Ban { did }
+        | Unban { did } => Some(did),
+        ChangeName { .. } | ChangeSettings { .. } => None,
+    };
+
+    let affected = |did: &DID| member == Some(did);
+
+    // Compares the lists while ignoring any entries for the member affected by the update
+    fn unchanged_except<T: PartialEq>(
+        current: &[T],
+        update: &[T],
+        affected: impl Fn(&T) -> bool,
+    ) -> bool {
+        current
+            .iter()
+            .filter(|item| !affected(item))
+            .eq(update.iter().filter(|item| !affected(item)))
+    }
+
+    let recipients_changed = matches!(
+        kind,
+        AddParticipant { .. } | RemoveParticipant { .. } | Ban { .. }
+    );
+    let roles_changed = matches!(
+        kind,
+        RemoveParticipant { .. } | ChangeRole { .. } | TransferOwnership { .. } | Ban { .. }
+    );
+    let restrict_changed = matches!(kind, AddRestricted { .. } | RemoveRestricted { .. });
+    let banned_changed = matches!(kind, Ban { .. } | Unban { .. });
+
+    let valid = update.id == current.id
+        && update.creator == current.creator
+        && update.created == current.created
+        && match kind {
+            ChangeName { name } => update.name == *name,
+            _ => update.name == current.name,
+        }
+        && match kind {
+            ChangeSettings { settings } => update.settings == *settings,
+            _ => update.settings == current.settings,
+        }
+        && match recipients_changed {
+            true => unchanged_except(&current.recipients, &update.recipients, affected),
+            false => update.recipients == current.recipients,
+        }
+        && match restrict_changed {
+            true => unchanged_except(&current.restrict, &update.restrict, affected),
+            false => update.restrict == current.restrict,
+        }
+        && match banned_changed {
+            true => unchanged_except(&current.banned, &update.banned, affected),
+            false => update.banned == current.banned,
+        }
+        && match roles_changed {
+            // The previous owner is assigned a new role when transferring ownership
+            true => unchanged_except(&current.roles, &update.roles, |assignment| {
+                let did = assignment.member.to_did();
+                affected(&did) || (matches!(kind, TransferOwnership { .. }) && did.eq(sender))
+            }),
+            false => update.roles == current.roles,
+        }
+        && match kind {
+            TransferOwnership { .. } => {
+                update.ownership.len() == current.ownership.len() + 1
+                    && update.ownership.starts_with(&current.ownership)
+            }
+            _ => update.ownership == current.ownership,
+        };
+
+    if !valid {
+        return Err(Error::InvalidConversation);
+    }
+
+    // The update has to apply the change it describes
+    let applied = match kind {
+        AddParticipant { did } => update.recipients.contains(did),
+        RemoveParticipant { did } => !update.recipients.contains(did),
+        AddRestricted { did } => update.restrict.contains(did),
+        RemoveRestricted { did } => !update.restrict.contains(did),
+        Ban { did } => update.banned.contains(did) && !update.recipients.contains(did),
+        Unban { did } => !update.banned.contains(did),
+        ChangeName { .. }
+        | ChangeSettings { .. }
+        | ChangeRole { .. }
+        | TransferOwnership { .. } => true,
+    };
+
+    match applied {
+        true => Ok(()),
+        false => Err(Error::InvalidConversation),
+    }
+}
+
+/// Validate the choices of a vote against the options of the poll.
+/// An empty list of choices is valid as it removes the vote
+fn validate_poll_choices(poll: &Poll, choices: &[usize]) -> Result<(), Error> {
+    if !poll.multiple_choice() && choices.len() > 1 {
+        return Err(Error::InvalidPollChoice);
+    }
+
+    let options = poll.options().len();
+
+    let mut seen = HashSet::new();
+
+    if choices
+        .iter()
+        .any(|choice| *choice >= options || !seen.insert(*choice))
+    {
+        return Err(Error::InvalidPollChoice);
+    }
+
+    Ok(())
+}
+
+/// Validate the lines of a message prior to it being sent
+fn validate_message_lines(messages: &[String]) -> Result<(), Error> {
+    if messages.is_empty() {
+        return Err(Error::EmptyMessage);
+    }
+
+    let lines_value_length: usize = messages
+        .iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim())
+        .map(|s| s.chars().count())
+        .sum();
+
+    if lines_value_length == 0 || lines_value_length > MAX_MESSAGE_SIZE {
+        tracing::error!(
+            current_size = lines_value_length,
+            max = MAX_MESSAGE_SIZE,
+            "length of message is invalid"
+        );
+        return Err(Error::InvalidLength {
+            context: "message".into(),
+            current: lines_value_length,
+            minimum: Some(MIN_MESSAGE_SIZE),
+            maximum: Some(MAX_MESSAGE_SIZE),
+        });
+    }
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
 struct Queue {
     id: Uuid,
@@ -4245,3 +7121,76 @@ async fn pubkey_or_keystore(
 
     Ok(keystore)
 }
+
+#[cfg(test)]
+mod test {
+    use super::validate_conversation_update;
+    use crate::store::{
+        conversation::{ConversationDocument, RoleDocument},
+        ConversationUpdateKind,
+    };
+    use warp::{
+        crypto::DID,
+        raygun::{GroupRole, GroupSettings},
+    };
+
+    #[test]
+    fn conversation_update_limited_to_kind() -> anyhow::Result<()> {
+        let owner = DID::default();
+        let moderator = DID::default();
+        let member = DID::default();
+
+        let mut current = ConversationDocument::new_group(
+            &owner,
+            None,
+            [moderator.clone(), member.clone()],
+            &[],
+            GroupSettings::default(),
+        )?;
+
+        let id = current.id();
+
+        current.roles.push(RoleDocument::new(
+            &owner,
+            id,
+            &moderator,
+            GroupRole::Moderator,
+        )?);
+        current.sign(&owner)?;
+
+        let name = Some("Renamed".to_string());
+        let mut update = current.clone();
+        update.name = name.clone();
+
+        let kind = ConversationUpdateKind::ChangeName { name };
+        validate_conversation_update(&current, &update, &owner, &kind)?;
+
+        // A rename that also bans a member is rejected
+        update.banned.push(member.clone());
+        assert!(validate_conversation_update(&current, &update, &owner, &kind).is_err());
+
+        // A ban that also promotes the moderator is rejected
+        let mut update = current.clone();
+        update.recipients.retain(|did| did.ne(&member));
+        update.banned.push(member.clone());
+
+        let kind = ConversationUpdateKind::Ban {
+            did: member.clone(),
+        };
+        validate_conversation_update(&current, &update, &moderator, &kind)?;
+
+        update.roles.push(RoleDocument::new(
+            &moderator,
+            id,
+            &moderator,
+            GroupRole::Admin,
+        )?);
+        assert!(validate_conversation_update(&current, &update, &moderator, &kind).is_err());
+
+        // A ban that was never applied is rejected
+        let update = current.clone();
+        assert!(validate_conversation_update(&current, &update, &moderator, &kind).is_err());
+
+        Ok(())
+    }
+}
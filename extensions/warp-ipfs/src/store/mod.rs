@@ -1,3 +1,4 @@
+pub mod archive;
 pub mod conversation;
 pub mod discovery;
 pub mod document;
@@ -10,8 +11,10 @@ pub mod payload;
 pub mod phonebook;
 pub mod queue;
 pub mod request;
+pub mod search;
 
 use chrono::{DateTime, Utc};
+use libipld::Cid;
 use rust_ipfs as ipfs;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -22,14 +25,17 @@ use warp::{
     crypto::{
         cipher::Cipher,
         did_key::{CoreSign, Generate, ECDH},
+        ed25519_dalek::KEYPAIR_LENGTH,
         hash::sha256_hash,
+        keypair::{derived_keypair_from_tesseract, derived_keypair_into_tesseract, KeyPurpose},
         zeroize::Zeroizing,
         DIDKey, Ed25519KeyPair, KeyMaterial, DID,
     },
     error::Error,
     multipass::identity::IdentityStatus,
     raygun::{
-        ConversationSettings, DirectConversationSettings, MessageEvent, PinState, ReactionState,
+        ConversationSettings, DirectConversationSettings, GroupRole, MessageEvent, PinState,
+        ReactionState,
     },
     tesseract::Tesseract,
 };
@@ -46,6 +52,14 @@ pub const MIN_ATTACHMENT: usize = 1;
 pub const MAX_CONVERSATIONS: usize = 1_000;
 pub const MAX_FRIENDS: usize = 1_000;
 pub const MAX_REQUEST: usize = 1_000;
+pub const MAX_EVENT_PAYLOAD_SIZE: usize = 4_096;
+pub const MAX_EVENT_NAMESPACE_LENGTH: usize = 128;
+pub const MAX_EVENT_TTL: Duration = Duration::from_secs(300);
+pub const MAX_ACTIVE_EVENTS: usize = 32;
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 32;
+/// Amount of time a vote is allowed to be dated ahead of the time it was received, to account for clock differences
+pub const MAX_VOTE_CLOCK_SKEW: Duration = Duration::from_secs(60);
 
 pub(super) mod topics {
     use std::fmt::Display;
@@ -66,6 +80,9 @@ pub(super) mod topics {
         fn messaging(&self) -> String {
             format!("{self}/messaging")
         }
+        fn shares(&self) -> String {
+            format!("{self}/shares")
+        }
     }
 
     impl PeerTopic for DID {}
@@ -93,6 +110,18 @@ pub(super) mod ds_key {
         fn request_queue(&self) -> String {
             self.base() + "/request_queue"
         }
+
+        fn search_index(&self) -> String {
+            self.base() + "/search_index"
+        }
+
+        fn scheduled_messages(&self) -> String {
+            self.base() + "/scheduled_messages"
+        }
+
+        fn rotations(&self) -> String {
+            self.base() + "/rotations"
+        }
     }
 
     impl DataStoreKey for Ipfs {
@@ -129,7 +158,6 @@ pub(crate) async fn migrate_to_ds<P: AsRef<std::path::Path>>(
     path: P,
 ) -> Result<(), Error> {
     use ds_key::DataStoreKey;
-    use libipld::Cid;
 
     let path = path.as_ref();
     let ds = ipfs.repo().data_store();
@@ -184,9 +212,48 @@ pub(crate) async fn migrate_to_ds<P: AsRef<std::path::Path>>(
     Ok(())
 }
 
+/// Unpins the documents referenced by the identity in the datastore and removes their keys
+pub(crate) async fn clear_ds(ipfs: &rust_ipfs::Ipfs) -> Result<(), Error> {
+    use ds_key::DataStoreKey;
+
+    let ds = ipfs.repo().data_store();
+
+    for key in [
+        ipfs.root(),
+        ipfs.cache(),
+        ipfs.request_queue(),
+        ipfs.messaging_queue(),
+        ipfs.search_index(),
+        ipfs.scheduled_messages(),
+        ipfs.rotations(),
+    ] {
+        let cid = ds
+            .get(key.as_bytes())
+            .await
+            .unwrap_or_default()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+            .and_then(|cid_str| cid_str.parse::<Cid>().ok());
+
+        if let Some(cid) = cid {
+            if ipfs.is_pinned(&cid).await.unwrap_or_default() {
+                if let Err(e) = ipfs.remove_pin(&cid).recursive().await {
+                    tracing::warn!(%cid, "Failed to unpin {key}: {e}");
+                }
+            }
+        }
+
+        ds.remove(key.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
 const SHUTTLE_TIMEOUT: Duration = Duration::from_secs(60);
 
-use self::conversation::{ConversationDocument, MessageDocument};
+use self::{
+    conversation::{ConversationDocument, MessageDocument, PollVoteDocument, ReceiptDocument},
+    document::{files::FileDocument, share::ShareDocument},
+};
 
 pub trait PeerIdExt {
     fn to_public_key(&self) -> Result<PublicKey, anyhow::Error>;
@@ -293,6 +360,21 @@ pub enum ConversationEvents {
     },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ShareEvents {
+    /// Share was created or updated by its owner
+    Update { document: ShareDocument },
+    /// Access to the share was revoked by its owner
+    Revoke { share_id: Uuid },
+    /// File uploaded into the shared directory by a recipient with read-write access
+    Put {
+        share_id: Uuid,
+        path: String,
+        document: FileDocument,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum ConversationRequestResponse {
@@ -350,6 +432,9 @@ pub enum MessagingEvents {
         lines: Vec<String>,
         nonce: Vec<u8>,
         signature: Vec<u8>,
+        /// Previous contents were kept as a revision of the message
+        #[serde(default)]
+        history: bool,
     },
     Delete {
         conversation_id: Uuid,
@@ -377,6 +462,28 @@ pub enum MessagingEvents {
         member: DID,
         event: MessageEvent,
         cancelled: bool,
+        /// Duration, in seconds, the event is active for
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<u64>,
+    },
+    Receipt {
+        receipt: ReceiptDocument,
+    },
+    PollVote {
+        vote: PollVoteDocument,
+    },
+    PollClose {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        closed: DateTime<Utc>,
+        signature: Vec<u8>,
+    },
+    Embeds {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        /// Encrypted previews of the message. Previews are removed if `None`
+        embeds: Option<Cid>,
+        signature: Vec<u8>,
     },
 }
 
@@ -389,6 +496,10 @@ pub enum ConversationUpdateKind {
     RemoveRestricted { did: DID },
     ChangeName { name: Option<String> },
     ChangeSettings { settings: ConversationSettings },
+    ChangeRole { did: DID, role: GroupRole },
+    TransferOwnership { did: DID },
+    Ban { did: DID },
+    Unban { did: DID },
 }
 
 // Note that this are temporary
@@ -446,6 +557,35 @@ fn did_keypair(tesseract: &Tesseract) -> anyhow::Result<DID> {
     Ok(did.into())
 }
 
+/// Keypair unique to the device, stored in tesseract on first use.
+/// If the chain code of the identity was saved along with its mnemonic phrase, the key is derived
+/// from the identity under [`KeyPurpose::Device`] so it can be recovered from the phrase. Otherwise,
+/// as is the case on linked devices, a random key is generated
+pub(crate) fn device_keypair(tesseract: &Tesseract) -> Result<DID, Error> {
+    if !tesseract.exist("device") {
+        if tesseract.exist("chain") {
+            let path = KeyPurpose::Device.path(0)?;
+            let mut tesseract = tesseract.clone();
+            return derived_keypair_into_tesseract(&mut tesseract, "device", &path, false);
+        }
+
+        let secret = Zeroizing::new(DID::default().private_key_bytes());
+        let encoded = Zeroizing::new(bs58::encode(&*secret).into_string());
+        tesseract.set("device", &encoded)?;
+    }
+
+    let encoded = Zeroizing::new(tesseract.retrieve("device")?);
+    let secret = Zeroizing::new(bs58::decode(&*encoded).into_vec()?);
+
+    // derived keys are stored as the full keypair
+    if secret.len() == KEYPAIR_LENGTH {
+        return derived_keypair_from_tesseract(tesseract, "device");
+    }
+
+    let did = DIDKey::Ed25519(Ed25519KeyPair::from_secret_key(&secret));
+    Ok(did.into())
+}
+
 pub(crate) fn ecdh_shared_key(did: &DID, recipient: Option<&DID>) -> Result<Vec<u8>, Error> {
     let prikey = Ed25519KeyPair::from_secret_key(&did.private_key_bytes()).get_x25519();
     let did_pubkey = match recipient {
@@ -0,0 +1,821 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::{
+    constellation::{
+        directory::Directory,
+        item::Item,
+        search::{SearchQuery as ItemQuery, SearchResult},
+    },
+    crypto::DID,
+    error::Error,
+};
+
+use super::{ds_key::DataStoreKey, ecdh_decrypt, ecdh_encrypt};
+
+/// Inverted index of the messages within a single conversation.
+///
+/// Each term maps to the messages containing it along with the positions the term
+/// appears in, which allows for phrase queries without resolving the messages again.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConversationIndex {
+    terms: BTreeMap<String, BTreeMap<Uuid, Vec<u32>>>,
+    documents: HashMap<Uuid, IndexedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct IndexedMessage {
+    date: DateTime<Utc>,
+    length: u32,
+    terms: BTreeSet<String>,
+}
+
+impl ConversationIndex {
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Index the lines of a message. If the message was previously indexed, the old entry
+    /// is replaced.
+    pub fn insert(&mut self, message_id: Uuid, date: DateTime<Utc>, lines: &[String]) {
+        self.remove(message_id);
+
+        let tokens = tokenize(&lines.join("\n"));
+
+        if tokens.is_empty() {
+            return;
+        }
+
+        let mut terms = BTreeSet::new();
+
+        for (position, token) in tokens.iter().enumerate() {
+            self.terms
+                .entry(token.clone())
+                .or_default()
+                .entry(message_id)
+                .or_default()
+                .push(position as u32);
+            terms.insert(token.clone());
+        }
+
+        self.documents.insert(
+            message_id,
+            IndexedMessage {
+                date,
+                length: tokens.len() as u32,
+                terms,
+            },
+        );
+    }
+
+    /// Remove a message from the index
+    pub fn remove(&mut self, message_id: Uuid) -> bool {
+        let Some(document) = self.documents.remove(&message_id) else {
+            return false;
+        };
+
+        for term in document.terms {
+            if let std::collections::btree_map::Entry::Occupied(mut entry) = self.terms.entry(term)
+            {
+                entry.get_mut().remove(&message_id);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Execute a query against the index, returning the score and date of each matching message.
+    /// Every clause of the query must match for a message to be returned.
+    pub fn search(&self, query: &SearchQuery) -> Vec<(Uuid, DateTime<Utc>, f32)> {
+        if query.is_empty() || self.documents.is_empty() {
+            return vec![];
+        }
+
+        let total = self.documents.len() as f32;
+
+        let mut scores: Option<HashMap<Uuid, f32>> = None;
+
+        for clause in &query.clauses {
+            let matches = match clause {
+                QueryClause::Term(term) => self.term_matches(term),
+                QueryClause::Prefix(prefix) => self.prefix_matches(prefix),
+                QueryClause::Phrase(terms) => self.phrase_matches(terms),
+            };
+
+            let document_frequency = matches.len() as f32;
+
+            if document_frequency == 0.0 {
+                return vec![];
+            }
+
+            let idf = (1.0 + total / document_frequency).ln();
+
+            let clause_scores = matches
+                .into_iter()
+                .map(|(id, frequency)| {
+                    let length = self
+                        .documents
+                        .get(&id)
+                        .map(|document| document.length.max(1) as f32)
+                        .unwrap_or(1.0);
+                    (id, (frequency as f32 / length.sqrt()) * idf)
+                })
+                .collect::<HashMap<_, _>>();
+
+            scores = Some(match scores {
+                None => clause_scores,
+                Some(current) => current
+                    .into_iter()
+                    .filter_map(|(id, score)| clause_scores.get(&id).map(|s| (id, score + s)))
+                    .collect(),
+            });
+        }
+
+        scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, score)| {
+                self.documents
+                    .get(&id)
+                    .map(|document| (id, document.date, score))
+            })
+            .collect()
+    }
+
+    fn term_matches(&self, term: &str) -> HashMap<Uuid, usize> {
+        self.terms
+            .get(term)
+            .map(|list| {
+                list.iter()
+                    .map(|(id, positions)| (*id, positions.len()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn prefix_matches(&self, prefix: &str) -> HashMap<Uuid, usize> {
+        let mut matches: HashMap<Uuid, usize> = HashMap::new();
+        for (_, list) in self
+            .terms
+            .range(prefix.to_string()..)
+            .take_while(|(term, _)| term.starts_with(prefix))
+        {
+            for (id, positions) in list {
+                *matches.entry(*id).or_default() += positions.len();
+            }
+        }
+        matches
+    }
+
+    fn phrase_matches(&self, terms: &[String]) -> HashMap<Uuid, usize> {
+        let Some((first, rest)) = terms.split_first() else {
+            return HashMap::new();
+        };
+
+        let Some(candidates) = self.terms.get(first) else {
+            return HashMap::new();
+        };
+
+        let mut matches = HashMap::new();
+
+        for (id, positions) in candidates {
+            let count = positions
+                .iter()
+                .filter(|start| {
+                    rest.iter().enumerate().all(|(offset, term)| {
+                        self.terms
+                            .get(term)
+                            .and_then(|list| list.get(id))
+                            .map(|list| list.contains(&(**start + offset as u32 + 1)))
+                            .unwrap_or_default()
+                    })
+                })
+                .count();
+
+            if count > 0 {
+                matches.insert(*id, count);
+            }
+        }
+
+        matches
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QueryClause {
+    /// Exact term
+    Term(String),
+    /// Term ending with `*`
+    Prefix(String),
+    /// Terms wrapped in quotes that must appear in order
+    Phrase(Vec<String>),
+}
+
+/// Parsed search query.
+///
+/// Terms are separated by whitespace, a term ending with `*` matches any term starting with it,
+/// and terms wrapped in double quotes are matched as a phrase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    clauses: Vec<QueryClause>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut clauses = vec![];
+
+        for (index, section) in query.split('"').enumerate() {
+            // Any odd section is between quotes
+            if index % 2 == 1 {
+                let terms = tokenize(section);
+                match terms.len() {
+                    0 => {}
+                    1 => clauses.push(QueryClause::Term(terms[0].clone())),
+                    _ => clauses.push(QueryClause::Phrase(terms)),
+                }
+                continue;
+            }
+
+            for word in section.split_whitespace() {
+                let prefix = word.ends_with('*');
+                let terms = tokenize(word);
+                let count = terms.len();
+                for (position, term) in terms.into_iter().enumerate() {
+                    match prefix && position == count - 1 {
+                        true => clauses.push(QueryClause::Prefix(term)),
+                        false => clauses.push(QueryClause::Term(term)),
+                    }
+                }
+            }
+        }
+
+        Self { clauses }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_lowercase())
+        .collect()
+}
+
+/// Amount of changes kept in the journal of an index before it is compacted into a new snapshot
+const JOURNAL_LIMIT: usize = 128;
+
+/// Location of a stored conversation index.
+///
+/// The index is stored as a snapshot along with a journal of the changes made since the snapshot
+/// was taken, so indexing a message only stores the change rather than the whole index.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct IndexPointer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snapshot: Option<Cid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    journal: Option<Cid>,
+    #[serde(default)]
+    entries: usize,
+}
+
+/// Entry within the journal of an index, linking to the entry before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<Cid>,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum IndexChange {
+    Insert {
+        id: Uuid,
+        date: DateTime<Utc>,
+        lines: Vec<String>,
+    },
+    Remove {
+        id: Uuid,
+    },
+}
+
+/// Store for the conversation indexes. Each index is encrypted with the
+/// local identity before being stored and a map of the conversation to
+/// the index is pinned and referenced from the datastore.
+///
+/// Changes appended to the journals are only referenced from the stored map once
+/// [`MessageSearchIndex::flush`] is called, so indexing a batch of messages stores the map once.
+pub struct MessageSearchIndex {
+    ipfs: Ipfs,
+    keypair: Arc<DID>,
+    map: BTreeMap<String, IndexPointer>,
+    indexes: HashMap<Uuid, ConversationIndex>,
+    /// Set when the map has changes that are not stored yet
+    pending: bool,
+}
+
+impl MessageSearchIndex {
+    pub async fn new(ipfs: &Ipfs, keypair: Arc<DID>) -> Self {
+        let key = ipfs.search_index();
+
+        let map = match ipfs
+            .repo()
+            .data_store()
+            .get(key.as_bytes())
+            .await
+            .unwrap_or_default()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+            .and_then(|cid_str| cid_str.parse::<Cid>().ok())
+        {
+            Some(cid) => ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized::<BTreeMap<String, IndexPointer>>()
+                .await
+                .unwrap_or_default(),
+            None => BTreeMap::new(),
+        };
+
+        Self {
+            ipfs: ipfs.clone(),
+            keypair,
+            map,
+            indexes: HashMap::new(),
+            pending: false,
+        }
+    }
+
+    /// Returns true if an index exist for the conversation
+    pub fn exist(&self, conversation_id: Uuid) -> bool {
+        self.indexes.contains_key(&conversation_id)
+            || self.map.contains_key(&conversation_id.to_string())
+    }
+
+    /// Obtain the index for the conversation, loading it from the store if needed
+    pub async fn get(&mut self, conversation_id: Uuid) -> Result<&mut ConversationIndex, Error> {
+        if !self.indexes.contains_key(&conversation_id) {
+            let index = match self.map.get(&conversation_id.to_string()) {
+                Some(pointer) => self.load(pointer).await?,
+                None => ConversationIndex::default(),
+            };
+            self.indexes.insert(conversation_id, index);
+        }
+
+        Ok(self
+            .indexes
+            .get_mut(&conversation_id)
+            .expect("index inserted"))
+    }
+
+    async fn load(&self, pointer: &IndexPointer) -> Result<ConversationIndex, Error> {
+        let mut index = match pointer.snapshot {
+            Some(cid) => {
+                let bytes = self
+                    .ipfs
+                    .get_dag(cid)
+                    .local()
+                    .deserialized::<Vec<u8>>()
+                    .await?;
+                let bytes = ecdh_decrypt(&self.keypair, None, bytes)?;
+                serde_json::from_slice(&bytes)?
+            }
+            None => ConversationIndex::default(),
+        };
+
+        let mut changes = Vec::with_capacity(pointer.entries);
+        let mut next = pointer.journal;
+
+        while let Some(cid) = next {
+            let entry = self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized::<JournalEntry>()
+                .await?;
+            let bytes = ecdh_decrypt(&self.keypair, None, entry.data)?;
+            changes.push(serde_json::from_slice::<IndexChange>(&bytes)?);
+            next = entry.previous;
+        }
+
+        for change in changes.into_iter().rev() {
+            match change {
+                IndexChange::Insert { id, date, lines } => index.insert(id, date, &lines),
+                IndexChange::Remove { id } => {
+                    index.remove(id);
+                }
+            }
+        }
+
+        Ok(index)
+    }
+
+    pub async fn insert(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        date: DateTime<Utc>,
+        lines: &[String],
+    ) -> Result<(), Error> {
+        self.get(conversation_id)
+            .await?
+            .insert(message_id, date, lines);
+        self.append(
+            conversation_id,
+            IndexChange::Insert {
+                id: message_id,
+                date,
+                lines: lines.to_vec(),
+            },
+        )
+        .await
+    }
+
+    pub async fn remove(&mut self, conversation_id: Uuid, message_id: Uuid) -> Result<(), Error> {
+        if !self.exist(conversation_id) {
+            return Ok(());
+        }
+
+        if self.get(conversation_id).await?.remove(message_id) {
+            self.append(conversation_id, IndexChange::Remove { id: message_id })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the index for the conversation entirely
+    pub async fn delete(&mut self, conversation_id: Uuid) -> Result<(), Error> {
+        self.indexes.remove(&conversation_id);
+        if let Some(pointer) = self.map.remove(&conversation_id.to_string()) {
+            self.save_map().await?;
+            self.discard(&pointer).await;
+        }
+        Ok(())
+    }
+
+    /// Persist a snapshot of the index of the conversation, replacing the previous snapshot and journal
+    pub async fn save(&mut self, conversation_id: Uuid) -> Result<(), Error> {
+        let Some(index) = self.indexes.get(&conversation_id) else {
+            return Ok(());
+        };
+
+        let bytes = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(index)?)?;
+        let cid = self.ipfs.dag().put().serialize(bytes).await?;
+
+        let pointer = IndexPointer {
+            snapshot: Some(cid),
+            journal: None,
+            entries: 0,
+        };
+
+        let previous = self.map.insert(conversation_id.to_string(), pointer);
+        self.save_map().await?;
+
+        if let Some(previous) = previous {
+            self.discard(&previous).await;
+        }
+
+        Ok(())
+    }
+
+    /// Store a change to the index of the conversation in its journal, compacting the journal into a
+    /// new snapshot once it grows past [`JOURNAL_LIMIT`]
+    async fn append(&mut self, conversation_id: Uuid, change: IndexChange) -> Result<(), Error> {
+        let pointer = self
+            .map
+            .get(&conversation_id.to_string())
+            .cloned()
+            .unwrap_or_default();
+
+        if pointer.entries + 1 >= JOURNAL_LIMIT {
+            return self.save(conversation_id).await;
+        }
+
+        let data = ecdh_encrypt(&self.keypair, None, serde_json::to_vec(&change)?)?;
+
+        let entry = JournalEntry {
+            previous: pointer.journal,
+            data,
+        };
+
+        let cid = self.ipfs.dag().put().serialize(entry).await?;
+
+        self.map.insert(
+            conversation_id.to_string(),
+            IndexPointer {
+                snapshot: pointer.snapshot,
+                journal: Some(cid),
+                entries: pointer.entries + 1,
+            },
+        );
+
+        self.pending = true;
+        Ok(())
+    }
+
+    /// Store the map of the indexes if it has changed since it was last stored
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        if !self.pending {
+            return Ok(());
+        }
+
+        self.save_map().await
+    }
+
+    /// Remove the blocks of an index that has been replaced
+    async fn discard(&self, pointer: &IndexPointer) {
+        for (cid, recursive) in [(pointer.snapshot, false), (pointer.journal, true)] {
+            let Some(cid) = cid else {
+                continue;
+            };
+
+            if self.ipfs.is_pinned(&cid).await.unwrap_or_default() {
+                continue;
+            }
+
+            if let Err(e) = self.ipfs.remove_block(cid, recursive).await {
+                tracing::debug!(%cid, error = %e, "unable to remove search index block");
+            }
+        }
+    }
+
+    async fn save_map(&mut self) -> Result<(), Error> {
+        let key = self.ipfs.search_index();
+
+        let current_cid = self
+            .ipfs
+            .repo()
+            .data_store()
+            .get(key.as_bytes())
+            .await
+            .unwrap_or_default()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+            .and_then(|cid_str| cid_str.parse::<Cid>().ok());
+
+        let cid = self.ipfs.dag().put().serialize(&self.map).pin(true).await?;
+
+        let cid_str = cid.to_string();
+
+        if let Err(e) = self
+            .ipfs
+            .repo()
+            .data_store()
+            .put(key.as_bytes(), cid_str.as_bytes())
+            .await
+        {
+            tracing::error!(error = %e, "unable to store search index");
+            return Ok(());
+        }
+
+        self.pending = false;
+
+        if let Some(old_cid) = current_cid {
+            if old_cid != cid && self.ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                _ = self.ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Search the given conversations, returning the message ids ordered by relevance
+    pub async fn search(
+        &mut self,
+        conversations: &[Uuid],
+        query: &SearchQuery,
+    ) -> Result<Vec<(Uuid, Uuid)>, Error> {
+        let mut results = vec![];
+
+        for conversation_id in conversations {
+            let index = self.get(*conversation_id).await?;
+            results.extend(
+                index
+                    .search(query)
+                    .into_iter()
+                    .map(|(id, date, score)| (*conversation_id, id, date, score)),
+            );
+        }
+
+        results.sort_by(|(_, _, date_a, score_a), (_, _, date_b, score_b)| {
+            score_b.total_cmp(score_a).then_with(|| date_b.cmp(date_a))
+        });
+
+        Ok(results
+            .into_iter()
+            .map(|(conversation_id, message_id, _, _)| (conversation_id, message_id))
+            .collect())
+    }
+}
+
+/// Flattened index of the items within the filesystem along with their paths.
+///
+/// The index is rebuilt by the file task whenever the tree is exported, so it reflects
+/// the items after every mutation.
+#[derive(Default, Debug, Clone)]
+pub struct ItemIndex {
+    entries: Vec<(String, Item)>,
+}
+
+impl ItemIndex {
+    pub fn new(root: &Directory) -> Self {
+        let mut entries = vec![];
+        flatten(root, "", &mut entries);
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Items matching the query, ordered by their path
+    pub fn search(&self, query: &ItemQuery) -> Vec<SearchResult> {
+        self.entries
+            .iter()
+            .filter(|(_, item)| query.matches(item))
+            .map(|(path, item)| SearchResult::new(path.clone(), item.clone()))
+            .collect()
+    }
+}
+
+fn flatten(directory: &Directory, parent: &str, entries: &mut Vec<(String, Item)>) {
+    let mut items = directory.get_items();
+    items.sort_by_key(|item| item.name());
+
+    for item in items {
+        let path = match parent.is_empty() {
+            true => item.name(),
+            false => format!("{parent}/{}", item.name()),
+        };
+
+        if let Item::Directory(directory) = &item {
+            entries.push((path.clone(), item.clone()));
+            flatten(directory, &path, entries);
+            continue;
+        }
+
+        entries.push((path, item));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use warp::constellation::{directory::Directory, file::File, search::SearchQuery as ItemQuery};
+
+    use std::sync::Arc;
+
+    use rust_ipfs::UninitializedIpfsNoop;
+    use warp::crypto::DID;
+
+    use super::{ConversationIndex, ItemIndex, MessageSearchIndex, SearchQuery};
+
+    fn index() -> (ConversationIndex, [Uuid; 3]) {
+        let mut index = ConversationIndex::default();
+        let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let now = Utc::now();
+        index.insert(ids[0], now, &["Hello, World".into()]);
+        index.insert(
+            ids[1],
+            now + Duration::seconds(1),
+            &["The world is big".into(), "hello again".into()],
+        );
+        index.insert(
+            ids[2],
+            now + Duration::seconds(2),
+            &["Helicopters are loud".into()],
+        );
+        (index, ids)
+    }
+
+    fn ids(list: Vec<(Uuid, chrono::DateTime<Utc>, f32)>) -> Vec<Uuid> {
+        let mut list = list.into_iter().map(|(id, _, _)| id).collect::<Vec<_>>();
+        list.sort();
+        list
+    }
+
+    #[tokio::test]
+    async fn journal_stored_on_flush() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfsNoop::new().start().await?;
+        let keypair = Arc::new(DID::default());
+
+        let conversation_id = Uuid::new_v4();
+        let (message_a, message_b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let mut store = MessageSearchIndex::new(&ipfs, keypair.clone()).await;
+        store
+            .insert(conversation_id, message_a, Utc::now(), &["hello".into()])
+            .await?;
+        store
+            .insert(conversation_id, message_b, Utc::now(), &["world".into()])
+            .await?;
+
+        // the changes are not referenced from the stored map until flushed
+        let reloaded = MessageSearchIndex::new(&ipfs, keypair.clone()).await;
+        assert!(!reloaded.exist(conversation_id));
+
+        store.flush().await?;
+
+        let mut reloaded = MessageSearchIndex::new(&ipfs, keypair).await;
+        let results = reloaded
+            .search(&[conversation_id], &SearchQuery::parse("hello"))
+            .await?;
+        assert_eq!(results, vec![(conversation_id, message_a)]);
+        Ok(())
+    }
+
+    #[test]
+    fn term_query() {
+        let (index, msg) = index();
+        let mut expected = vec![msg[0], msg[1]];
+        expected.sort();
+        assert_eq!(ids(index.search(&SearchQuery::parse("WORLD"))), expected);
+        assert_eq!(
+            ids(index.search(&SearchQuery::parse("world big"))),
+            vec![msg[1]]
+        );
+        assert!(index.search(&SearchQuery::parse("missing")).is_empty());
+    }
+
+    #[test]
+    fn prefix_query() {
+        let (index, msg) = index();
+        let mut expected = vec![msg[0], msg[1], msg[2]];
+        expected.sort();
+        assert_eq!(ids(index.search(&SearchQuery::parse("hel*"))), expected);
+    }
+
+    #[test]
+    fn phrase_query() {
+        let (index, msg) = index();
+        assert_eq!(
+            ids(index.search(&SearchQuery::parse("\"hello world\""))),
+            vec![msg[0]]
+        );
+        assert_eq!(
+            ids(index.search(&SearchQuery::parse("\"world is\""))),
+            vec![msg[1]]
+        );
+        assert!(index
+            .search(&SearchQuery::parse("\"world hello\""))
+            .is_empty());
+    }
+
+    #[test]
+    fn update_and_remove() {
+        let (mut index, msg) = index();
+        index.insert(msg[0], Utc::now(), &["Goodbye".into()]);
+        assert_eq!(
+            ids(index.search(&SearchQuery::parse("hello"))),
+            vec![msg[1]]
+        );
+        assert!(index.remove(msg[1]));
+        assert!(index.search(&SearchQuery::parse("hello")).is_empty());
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn item_index_paths() {
+        let root = Directory::new("root");
+        let photos = Directory::new("photos");
+        photos.add_item(File::new("beach.png")).unwrap();
+        photos.add_item(File::new("notes.txt")).unwrap();
+        root.add_item(photos).unwrap();
+        root.add_item(File::new("beach.txt")).unwrap();
+
+        let index = ItemIndex::new(&root);
+        assert_eq!(index.len(), 4);
+
+        let paths = |query: &str| {
+            index
+                .search(&ItemQuery::parse(query).unwrap())
+                .into_iter()
+                .map(|result| result.path())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(paths("beach"), ["beach.txt", "photos/beach.png"]);
+        assert_eq!(paths("*.txt"), ["beach.txt", "photos/notes.txt"]);
+        assert_eq!(paths("is:directory"), ["photos"]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+use lofty::{file::TaggedFileExt, picture::PictureType, probe::Probe};
+use mediatype::MediaTypeBuf;
+use warp::error::Error;
+
+use super::{ThumbnailBackend, ThumbnailSource};
+
+/// Extracts the cover art embedded within the tags of an audio file
+pub struct AudioBackend;
+
+impl ThumbnailBackend for AudioBackend {
+    fn supports(&self, media: &MediaTypeBuf) -> bool {
+        media.ty().as_str() == "audio"
+    }
+
+    fn render(
+        &self,
+        source: &ThumbnailSource,
+        _: u32,
+        _: u32,
+    ) -> Result<(DynamicImage, Option<ImageFormat>), Error> {
+        // Files are read from disk as needed so the audio itself is not loaded into memory
+        let tagged_file = match source {
+            ThumbnailSource::Path(path) => Probe::open(path)
+                .map_err(anyhow::Error::from)?
+                .guess_file_type()?
+                .read(),
+            ThumbnailSource::Buffer(buffer) => {
+                Probe::new(Cursor::new(buffer)).guess_file_type()?.read()
+            }
+        }
+        .map_err(anyhow::Error::from)?;
+
+        let pictures = tagged_file
+            .tags()
+            .iter()
+            .flat_map(|tag| tag.pictures())
+            .collect::<Vec<_>>();
+
+        // Prefer the front cover, falling back to whichever picture is embedded first
+        let picture = pictures
+            .iter()
+            .find(|picture| picture.pic_type() == PictureType::CoverFront)
+            .or_else(|| pictures.first())
+            .ok_or(Error::ObjectNotFound)?;
+
+        let image = image::load_from_memory(picture.data()).map_err(anyhow::Error::from)?;
+
+        Ok((image, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use lofty::{
+        config::WriteOptions,
+        picture::{MimeType, Picture, PictureType},
+        tag::{Tag, TagExt, TagType},
+    };
+
+    use super::AudioBackend;
+    use crate::thumbnail::{ThumbnailBackend, ThumbnailSource};
+
+    /// Silent wav file with a single sample
+    fn wav() -> Vec<u8> {
+        let data: &[u8] = &[0, 0];
+        [
+            &b"RIFF"[..],
+            &(36 + data.len() as u32).to_le_bytes(),
+            b"WAVE",
+            b"fmt ",
+            &16u32.to_le_bytes(),
+            &1u16.to_le_bytes(),
+            &1u16.to_le_bytes(),
+            &8000u32.to_le_bytes(),
+            &16000u32.to_le_bytes(),
+            &2u16.to_le_bytes(),
+            &16u16.to_le_bytes(),
+            b"data",
+            &(data.len() as u32).to_le_bytes(),
+            data,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn render_cover_art_from_file() -> anyhow::Result<()> {
+        let backend = AudioBackend;
+        assert!(backend.supports(&"audio/wav".parse()?));
+        assert!(!backend.supports(&"video/mp4".parse()?));
+
+        let path = std::env::temp_dir().join(format!("{}.wav", uuid::Uuid::new_v4()));
+        std::fs::write(&path, wav())?;
+
+        let source = ThumbnailSource::Path(path.clone());
+
+        // the file does not contain any cover art
+        assert!(backend.render(&source, 128, 128).is_err());
+
+        let mut cover = std::io::Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(RgbaImage::new(4, 4)).write_to(&mut cover, ImageFormat::Png)?;
+
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.push_picture(Picture::new_unchecked(
+            PictureType::CoverFront,
+            Some(MimeType::Png),
+            None,
+            cover.into_inner(),
+        ));
+        tag.save_to_path(&path, WriteOptions::default())?;
+
+        let result = backend.render(&source, 128, 128);
+        _ = std::fs::remove_file(&path);
+
+        let (image, _) = result?;
+        assert_eq!((image.width(), image.height()), (4, 4));
+        Ok(())
+    }
+}
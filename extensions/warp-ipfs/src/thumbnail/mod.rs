@@ -0,0 +1,267 @@
+#[cfg(feature = "thumbnail-audio")]
+mod audio;
+#[cfg(feature = "thumbnail-pdf")]
+mod pdf;
+mod raster;
+#[cfg(feature = "thumbnail-video")]
+mod video;
+
+use image::{DynamicImage, ImageFormat};
+use mediatype::MediaTypeBuf;
+use rust_ipfs::{Ipfs, IpfsPath};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fmt::Display,
+    hash::Hash,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use warp::{
+    constellation::{file::FileType, mime::HEADER_LENGTH},
+    error::Error,
+};
+use web_time::Instant;
+
+use crate::{store::document::image_dag::ImageDag, utils::ExtensionType};
+
+static GLOBAL_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ThumbnailId(usize);
+
+impl Display for ThumbnailId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl core::ops::Deref for ThumbnailId {
+    type Target = usize;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Default for ThumbnailId {
+    fn default() -> Self {
+        ThumbnailId(GLOBAL_ID.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+/// Contents of the file a thumbnail is generated from
+pub enum ThumbnailSource {
+    Path(PathBuf),
+    Buffer(Vec<u8>),
+}
+
+/// Backend rendering the image a thumbnail is made from for a kind of media.
+///
+/// Backends other than the one for images are enabled through their cargo feature
+pub trait ThumbnailBackend: Send + Sync + 'static {
+    /// Returns true if the backend is able to render the media type
+    fn supports(&self, media: &MediaTypeBuf) -> bool;
+
+    /// Renders the source into an image, which is at most `width` by `height` when the backend is
+    /// able to scale while rendering. The format of the image is returned when it should be kept for
+    /// the thumbnail if an exact format is requested.
+    fn render(
+        &self,
+        source: &ThumbnailSource,
+        width: u32,
+        height: u32,
+    ) -> Result<(DynamicImage, Option<ImageFormat>), Error>;
+}
+
+fn backends() -> Vec<Box<dyn ThumbnailBackend>> {
+    vec![
+        Box::new(raster::ImageBackend),
+        #[cfg(feature = "thumbnail-video")]
+        Box::new(video::VideoBackend),
+        #[cfg(feature = "thumbnail-pdf")]
+        Box::new(pdf::PdfBackend),
+        #[cfg(feature = "thumbnail-audio")]
+        Box::new(audio::AudioBackend),
+    ]
+}
+
+type TaskMap = BTreeMap<ThumbnailId, JoinHandle<Result<(ExtensionType, IpfsPath, Vec<u8>), Error>>>;
+
+#[derive(Clone)]
+pub struct ThumbnailGenerator {
+    ipfs: Ipfs,
+    backends: Arc<Vec<Box<dyn ThumbnailBackend>>>,
+    tasks: Arc<Mutex<TaskMap>>,
+}
+
+impl ThumbnailGenerator {
+    pub fn new(ipfs: Ipfs) -> Self {
+        Self {
+            ipfs,
+            backends: Arc::new(backends()),
+            tasks: Arc::default(),
+        }
+    }
+
+    pub async fn insert<P: AsRef<Path>>(
+        &self,
+        path: P,
+        width: u32,
+        height: u32,
+        output_exact: bool,
+    ) -> Result<ThumbnailId, Error> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(io::Error::from(ErrorKind::NotFound).into());
+        }
+
+        let header = fs::read_header(path, HEADER_LENGTH).await?;
+        let file_type = file_type(path, &header);
+
+        let source = ThumbnailSource::Path(path.to_path_buf());
+
+        Ok(self
+            .spawn(source, file_type, width, height, output_exact)
+            .await)
+    }
+
+    pub async fn insert_buffer<S: AsRef<str>>(
+        &self,
+        name: S,
+        buffer: &[u8],
+        width: u32,
+        height: u32,
+        output_exact: bool,
+    ) -> ThumbnailId {
+        let file_type = file_type(Path::new(name.as_ref()), buffer);
+
+        let source = ThumbnailSource::Buffer(buffer.to_vec());
+
+        self.spawn(source, file_type, width, height, output_exact)
+            .await
+    }
+
+    async fn spawn(
+        &self,
+        source: ThumbnailSource,
+        file_type: FileType,
+        width: u32,
+        height: u32,
+        output_exact: bool,
+    ) -> ThumbnailId {
+        let id = ThumbnailId::default();
+
+        let ipfs = self.ipfs.clone();
+        let backends = self.backends.clone();
+
+        let task = tokio::spawn(async move {
+            let instance = Instant::now();
+
+            let result = match file_type {
+                FileType::Mime(media) => tokio::task::spawn_blocking(move || {
+                    let backend = backends
+                        .iter()
+                        .find(|backend| backend.supports(&media))
+                        .ok_or(Error::Unimplemented)?;
+
+                    let (image, format) = backend.render(&source, width, height)?;
+
+                    let width = width.min(image.width());
+                    let height = height.min(image.height());
+
+                    let thumbnail = image.thumbnail(width, height);
+
+                    let mut t_buffer = std::io::Cursor::new(vec![]);
+                    let output_format = match (output_exact, format) {
+                        (false, _) | (true, None) => ImageFormat::Jpeg,
+                        (true, Some(ImageFormat::WebP)) if cfg!(not(feature = "webp")) => {
+                            ImageFormat::Jpeg
+                        }
+                        (true, Some(format)) => format,
+                    };
+
+                    // Jpeg does not support an alpha channel
+                    let thumbnail = match output_format {
+                        ImageFormat::Jpeg => DynamicImage::ImageRgb8(thumbnail.into_rgb8()),
+                        _ => thumbnail,
+                    };
+
+                    thumbnail
+                        .write_to(&mut t_buffer, output_format)
+                        .map_err(anyhow::Error::from)?;
+                    Ok::<_, Error>((
+                        ExtensionType::try_from(output_format)?,
+                        t_buffer.into_inner(),
+                    ))
+                })
+                .await
+                .map_err(anyhow::Error::from)?,
+                _ => Err(Error::Other),
+            };
+
+            let stop = instance.elapsed();
+
+            tracing::trace!("Took: {}ms to complete task for {}", stop.as_millis(), id);
+
+            let (ty, data) = result?;
+
+            let size = data.len();
+
+            let path = ipfs.add_unixfs(data.clone()).await?;
+
+            let link = *path.root().cid().expect("valid cid");
+
+            let image_dag = ImageDag {
+                link,
+                size: size as _,
+                mime: ty.into(),
+            };
+
+            let cid = ipfs.dag().put().serialize(image_dag).await?;
+
+            Ok((ty, IpfsPath::from(cid), data))
+        });
+
+        self.tasks.lock().await.insert(id, task);
+
+        id
+    }
+
+    #[allow(dead_code)]
+    pub async fn cancel(&self, id: ThumbnailId) {
+        let task = self.tasks.lock().await.remove(&id);
+        if let Some(task) = task {
+            task.abort();
+        }
+    }
+
+    pub async fn get(&self, id: ThumbnailId) -> Result<(ExtensionType, IpfsPath, Vec<u8>), Error> {
+        let task = self.tasks.lock().await.remove(&id);
+        let task = task.ok_or(Error::Other)?;
+        task.await.map_err(anyhow::Error::from)?
+    }
+
+    #[allow(dead_code)]
+    pub async fn is_finished(&self, id: ThumbnailId) -> Result<bool, Error> {
+        if let Some(task) = self.tasks.lock().await.get(&id) {
+            return Ok(task.is_finished());
+        }
+        Err(Error::Other)
+    }
+}
+
+fn file_type(path: &Path, header: &[u8]) -> FileType {
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(ExtensionType::from)
+        .unwrap_or(ExtensionType::Other);
+
+    FileType::from_content(header, extension.into())
+}
@@ -0,0 +1,67 @@
+use image::{DynamicImage, ImageFormat};
+use mediatype::MediaTypeBuf;
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+use warp::error::Error;
+
+use super::{ThumbnailBackend, ThumbnailSource};
+
+/// Renders the first page of a PDF using the pdfium library.
+///
+/// Note: The library is bound from the system at runtime, so it has to be installed for PDFs to have a thumbnail
+pub struct PdfBackend;
+
+impl ThumbnailBackend for PdfBackend {
+    fn supports(&self, media: &MediaTypeBuf) -> bool {
+        media.ty().as_str() == "application" && media.subty().as_str() == "pdf"
+    }
+
+    fn render(
+        &self,
+        source: &ThumbnailSource,
+        width: u32,
+        height: u32,
+    ) -> Result<(DynamicImage, Option<ImageFormat>), Error> {
+        let bindings = Pdfium::bind_to_system_library().map_err(anyhow::Error::from)?;
+        let pdfium = Pdfium::new(bindings);
+
+        // pdfium reads the pages it needs from the file rather than the whole document
+        let document = match source {
+            ThumbnailSource::Path(path) => pdfium.load_pdf_from_file(path, None),
+            ThumbnailSource::Buffer(buffer) => pdfium.load_pdf_from_byte_slice(buffer, None),
+        }
+        .map_err(anyhow::Error::from)?;
+
+        let page = document.pages().first().map_err(anyhow::Error::from)?;
+
+        let config = PdfRenderConfig::new()
+            .set_target_width(width as _)
+            .set_maximum_height(height as _);
+
+        let image = page
+            .render_with_config(&config)
+            .map_err(anyhow::Error::from)?
+            .as_image();
+
+        Ok((image, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PdfBackend;
+    use crate::thumbnail::{ThumbnailBackend, ThumbnailSource};
+
+    #[test]
+    fn supports_pdf() -> anyhow::Result<()> {
+        let backend = PdfBackend;
+        assert!(backend.supports(&"application/pdf".parse()?));
+        assert!(!backend.supports(&"application/json".parse()?));
+        Ok(())
+    }
+
+    #[test]
+    fn render_invalid_document() {
+        let source = ThumbnailSource::Buffer(b"not a pdf".to_vec());
+        assert!(PdfBackend.render(&source, 128, 128).is_err());
+    }
+}
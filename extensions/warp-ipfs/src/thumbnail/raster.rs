@@ -0,0 +1,62 @@
+use image::{io::Reader as ImageReader, DynamicImage, ImageFormat};
+use mediatype::MediaTypeBuf;
+use warp::error::Error;
+
+use super::{ThumbnailBackend, ThumbnailSource};
+
+/// Decodes images supported by the `image` crate
+pub struct ImageBackend;
+
+impl ThumbnailBackend for ImageBackend {
+    fn supports(&self, media: &MediaTypeBuf) -> bool {
+        media.ty().as_str() == "image"
+    }
+
+    fn render(
+        &self,
+        source: &ThumbnailSource,
+        _: u32,
+        _: u32,
+    ) -> Result<(DynamicImage, Option<ImageFormat>), Error> {
+        let (format, image) = match source {
+            ThumbnailSource::Path(path) => {
+                let reader = ImageReader::open(path)?.with_guessed_format()?;
+                (reader.format(), reader.decode())
+            }
+            ThumbnailSource::Buffer(buffer) => {
+                let reader =
+                    ImageReader::new(std::io::Cursor::new(buffer)).with_guessed_format()?;
+                (reader.format(), reader.decode())
+            }
+        };
+
+        let format = format.ok_or(Error::InvalidDataType)?;
+        let image = image.map_err(anyhow::Error::from)?;
+
+        Ok((image, Some(format)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+
+    use super::ImageBackend;
+    use crate::thumbnail::{ThumbnailBackend, ThumbnailSource};
+
+    #[test]
+    fn render_guessed_format() -> anyhow::Result<()> {
+        let mut buffer = std::io::Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(RgbaImage::new(4, 4)).write_to(&mut buffer, ImageFormat::Png)?;
+
+        let backend = ImageBackend;
+        assert!(backend.supports(&"image/jpeg".parse()?));
+        assert!(!backend.supports(&"video/mp4".parse()?));
+
+        let source = ThumbnailSource::Buffer(buffer.into_inner());
+        let (image, format) = backend.render(&source, 128, 128)?;
+        assert_eq!(format, Some(ImageFormat::Png));
+        assert_eq!((image.width(), image.height()), (4, 4));
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    io::{Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+use image::{DynamicImage, ImageFormat};
+use mediatype::MediaTypeBuf;
+use warp::error::Error;
+
+use super::{ThumbnailBackend, ThumbnailSource};
+
+/// Duration `ffmpeg` is given to extract a frame before it is killed
+const FFMPEG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Grabs the first frame of a video using the `ffmpeg` executable.
+///
+/// Note: `ffmpeg` has to be available in `PATH` at runtime, otherwise videos will not have a thumbnail
+pub struct VideoBackend;
+
+impl ThumbnailBackend for VideoBackend {
+    fn supports(&self, media: &MediaTypeBuf) -> bool {
+        media.ty().as_str() == "video"
+    }
+
+    fn render(
+        &self,
+        source: &ThumbnailSource,
+        width: u32,
+        height: u32,
+    ) -> Result<(DynamicImage, Option<ImageFormat>), Error> {
+        let input = match source {
+            ThumbnailSource::Path(path) => path.to_string_lossy().to_string(),
+            ThumbnailSource::Buffer(_) => "pipe:0".to_string(),
+        };
+
+        let scale = format!("scale={width}:{height}:force_original_aspect_ratio=decrease");
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error", "-i", &input])
+            .args(["-frames:v", "1", "-vf", &scale])
+            .args(["-f", "image2pipe", "-vcodec", "png", "pipe:1"])
+            .stdin(match source {
+                ThumbnailSource::Path(_) => Stdio::null(),
+                ThumbnailSource::Buffer(_) => Stdio::piped(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // The buffer is written from another thread so ffmpeg is able to write its output while reading the input
+        let writer = match (source, child.stdin.take()) {
+            (ThumbnailSource::Buffer(buffer), Some(mut stdin)) => {
+                let buffer = buffer.clone();
+                Some(std::thread::spawn(move || {
+                    // ffmpeg closes its input once the frame is decoded, which is not an error
+                    _ = stdin.write_all(&buffer);
+                }))
+            }
+            _ => None,
+        };
+
+        let reader = child.stdout.take().map(|mut stdout| {
+            std::thread::spawn(move || {
+                let mut output = vec![];
+                stdout.read_to_end(&mut output).map(|_| output)
+            })
+        });
+
+        let status = wait_with_timeout(&mut child, FFMPEG_TIMEOUT);
+
+        // Both pipes are closed once the process exits or is killed, ending the threads
+        if let Some(writer) = writer {
+            _ = writer.join();
+        }
+
+        let output = reader
+            .and_then(|reader| reader.join().ok())
+            .transpose()?
+            .unwrap_or_default();
+
+        if !status?.success() || output.is_empty() {
+            return Err(Error::OtherWithContext(
+                "Unable to extract frame from video".into(),
+            ));
+        }
+
+        let image = image::load_from_memory_with_format(&output, ImageFormat::Png)
+            .map_err(anyhow::Error::from)?;
+
+        Ok((image, None))
+    }
+}
+
+/// Waits for the process to exit, killing it if it does not exit within the timeout
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<ExitStatus, Error> {
+    let start = Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+
+        if start.elapsed() >= timeout {
+            _ = child.kill();
+            _ = child.wait();
+            return Err(Error::OtherWithContext(
+                "ffmpeg did not complete in time".into(),
+            ));
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{process::Command, time::Duration};
+
+    use super::{wait_with_timeout, VideoBackend};
+    use crate::thumbnail::ThumbnailBackend;
+
+    #[test]
+    fn supports_video() -> anyhow::Result<()> {
+        let backend = VideoBackend;
+        assert!(backend.supports(&"video/mp4".parse()?));
+        assert!(!backend.supports(&"image/png".parse()?));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn process_killed_after_timeout() -> anyhow::Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+
+        assert!(wait_with_timeout(&mut child, Duration::from_millis(100)).is_err());
+
+        // the process has been reaped after being killed
+        assert!(child.try_wait()?.is_some());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn process_completes_within_timeout() -> anyhow::Result<()> {
+        let mut child = Command::new("true").spawn()?;
+
+        let status = wait_with_timeout(&mut child, Duration::from_secs(10))?;
+        assert!(status.success());
+        Ok(())
+    }
+}
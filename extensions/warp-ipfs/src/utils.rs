@@ -32,6 +32,18 @@ pub enum ExtensionType {
     WEBM,
     #[display(fmt = "video/x-matroska")]
     MKV,
+    #[display(fmt = "video/quicktime")]
+    MOV,
+    #[display(fmt = "audio/mpeg")]
+    MP3,
+    #[display(fmt = "audio/mp4")]
+    M4A,
+    #[display(fmt = "audio/ogg")]
+    OGG,
+    #[display(fmt = "audio/flac")]
+    FLAC,
+    #[display(fmt = "audio/wav")]
+    WAV,
     #[display(fmt = "application/pdf")]
     PDF,
     #[display(fmt = "application/msword")]
@@ -58,6 +70,12 @@ impl From<&str> for ExtensionType {
             "webp" => Self::WEBP,
             "webm" => Self::WEBM,
             "mkv" => Self::MKV,
+            "mov" => Self::MOV,
+            "mp3" => Self::MP3,
+            "m4a" => Self::M4A,
+            "ogg" | "oga" => Self::OGG,
+            "flac" => Self::FLAC,
+            "wav" => Self::WAV,
             "pdf" => Self::PDF,
             "txt" => Self::TXT,
             "docx" => Self::DOCX,
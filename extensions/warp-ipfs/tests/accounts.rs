@@ -2,13 +2,20 @@ pub mod common;
 
 #[cfg(test)]
 mod test {
-    use std::time::Duration;
+    use std::{path::PathBuf, time::Duration};
 
     use crate::common::{self, create_account, create_accounts};
     use futures::StreamExt;
+    use rust_ipfs::{Keypair, Multiaddr, Protocol};
+    use shuttle::server::ShuttleServer;
     use warp::constellation::file::FileType;
+    use warp::crypto::keypair::{did_from_mnemonic_with_path, KeyPurpose};
+    use warp::crypto::DID;
+    use warp::error::Error;
     use warp::multipass::identity::{IdentityStatus, IdentityUpdate, Platform};
+    use warp::multipass::{IdentityImportOption, MultiPassEventKind};
     use warp::tesseract::Tesseract;
+    use warp_ipfs::config::Bootstrap;
     use warp_ipfs::WarpIpfsBuilder;
 
     #[tokio::test]
@@ -392,4 +399,222 @@ mod test {
         assert_eq!(platform_b, Platform::Desktop);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn link_and_revoke_device() -> anyhow::Result<()> {
+        let (mut account, _, _, _) =
+            create_account(None, None, Some("test::link_and_revoke_device".into())).await?;
+
+        let own_device = account.device_key().await?;
+        let device = DID::default();
+
+        let bundle = account.link_device(&device, "mobile").await?;
+        assert!(!bundle.is_empty());
+
+        assert!(matches!(
+            account.link_device(&device, "mobile").await,
+            Err(Error::DeviceExist)
+        ));
+
+        let devices = account.list_devices().await?;
+        assert_eq!(devices.len(), 2);
+        assert!(devices
+            .iter()
+            .any(|item| item.did() == own_device && item.is_current()));
+        assert!(devices
+            .iter()
+            .any(|item| item.did() == device && item.name() == "mobile" && !item.is_current()));
+
+        assert!(matches!(
+            account.revoke_device(&own_device).await,
+            Err(Error::CannotRevokeOwnDevice)
+        ));
+
+        account.revoke_device(&device).await?;
+
+        let devices = account.list_devices().await?;
+        assert_eq!(devices.len(), 1);
+
+        assert!(matches!(
+            account.revoke_device(&device).await,
+            Err(Error::DeviceDoesntExist)
+        ));
+
+        // a revoked device key cannot be linked again
+        assert!(account.link_device(&device, "mobile").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_identity() -> anyhow::Result<()> {
+        let (mut account, _, did, _) =
+            create_account(Some("JohnDoe"), None, Some("test::delete_identity".into())).await?;
+
+        account.delete_identity().await?;
+
+        assert!(matches!(
+            account.get_own_identity().await,
+            Err(Error::IdentityNotCreated)
+        ));
+
+        // the stores of the deleted identity are cleared so a new identity can be created
+        let profile = account.create_identity(Some("JaneDoe"), None).await?;
+        assert_ne!(profile.identity().did_key(), did);
+        assert_eq!(account.get_own_identity().await?.username(), "JaneDoe");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sync_linked_devices() -> anyhow::Result<()> {
+        let keypair = Keypair::generate_ed25519();
+        let shuttle = ShuttleServer::new(
+            &keypair,
+            None::<PathBuf>,
+            false,
+            true,
+            &[Multiaddr::empty().with(Protocol::Memory(0))],
+            false,
+            None,
+        )
+        .await?;
+
+        let peer_id = keypair.public().to_peer_id();
+        let addresses = shuttle
+            .listening_addresses()
+            .await
+            .map(|addr| addr.with(Protocol::P2p(peer_id)))
+            .collect::<Vec<_>>();
+
+        let (mut account_a, mut chat_a, _) =
+            common::create_node_with_shuttle(addresses.clone()).await;
+        account_a.create_identity(Some("JohnDoe"), None).await?;
+
+        let (mut account_b, mut chat_b, _) =
+            common::create_node_with_shuttle(addresses.clone()).await;
+        let device = account_b.device_key().await?;
+        let bundle = account_a.link_device(&device, "mobile").await?;
+        account_b
+            .import_identity(IdentityImportOption::Device { bundle })
+            .await?;
+
+        let (mut account_c, _, _) = common::create_node_with_shuttle(addresses).await;
+        let did_c = account_c
+            .create_identity(Some("JaneDoe"), None)
+            .await?
+            .identity()
+            .did_key();
+
+        let conversation_id = chat_a.create_conversation(&did_c).await?.id();
+        let message_id = chat_a
+            .send(conversation_id, vec!["Hello, World".into()])
+            .await?;
+
+        // the message sent from one device shows up on the linked device
+        tokio::time::timeout(Duration::from_secs(60), async {
+            while chat_b
+                .get_message(conversation_id, message_id)
+                .await
+                .is_err()
+            {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        })
+        .await?;
+
+        // drafts are merged instead of being replaced by the newest root
+        let draft_id = chat_b
+            .save_draft(conversation_id, vec!["draft".into()])
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Ok(drafts) = chat_a.list_drafts(conversation_id).await {
+                    if drafts.iter().any(|draft| draft.id() == draft_id) {
+                        break;
+                    }
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        })
+        .await?;
+
+        // deleting the message on one device removes it on the other
+        chat_b.delete(conversation_id, Some(message_id)).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            while chat_a
+                .get_message(conversation_id, message_id)
+                .await
+                .is_ok()
+            {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        })
+        .await?;
+
+        // only the primary device is able to link and revoke devices
+        let primary = account_a.device_key().await?;
+        assert!(matches!(
+            account_b.revoke_device(&primary).await,
+            Err(Error::DeviceNotPrimary)
+        ));
+        assert!(matches!(
+            account_b.link_device(&DID::default(), "tablet").await,
+            Err(Error::DeviceNotPrimary)
+        ));
+        assert!(matches!(
+            account_b.remove_revoked_device().await,
+            Err(Error::DeviceNotRevoked)
+        ));
+
+        let mut subscribe_b = account_b.multipass_subscribe().await?;
+        account_a.revoke_device(&device).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MultiPassEventKind::DeviceRevoked { did }) = subscribe_b.next().await {
+                    if did == device {
+                        break;
+                    }
+                }
+            }
+        })
+        .await?;
+
+        // the identity is only removed from the revoked device once confirmed
+        assert!(account_b.get_own_identity().await.is_ok());
+        account_b.remove_revoked_device().await?;
+        assert!(matches!(
+            account_b.get_own_identity().await,
+            Err(Error::IdentityNotCreated)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn device_key_derived_from_phrase() -> anyhow::Result<()> {
+        let phrase = "morning caution dose lab six actress pond humble pause enact virtual train";
+        let tesseract = Tesseract::default();
+        tesseract.unlock(b"internal pass").unwrap();
+        let mut config = warp_ipfs::config::Config::development();
+        *config.listen_on_mut() = vec![Multiaddr::empty().with(Protocol::Memory(0))];
+        config.ipfs_setting_mut().memory_transport = true;
+        config.ipfs_setting_mut().relay_client.relay_address = vec![];
+        *config.bootstrap_mut() = Bootstrap::None;
+        *config.save_phrase_mut() = true;
+
+        let (mut account, _, _) = WarpIpfsBuilder::default()
+            .set_tesseract(tesseract)
+            .set_config(config)
+            .finalize()
+            .await;
+
+        account.create_identity(None, Some(phrase)).await?;
+
+        // the key of the device is recovered along with the identity from the phrase
+        let expected = did_from_mnemonic_with_path(phrase, None, &KeyPurpose::Device.path(0)?)?;
+        assert_eq!(account.device_key().await?, expected);
+        Ok(())
+    }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use futures::{stream, StreamExt};
 use rust_ipfs::{Ipfs, Multiaddr, PeerId, Protocol};
 use warp::{
@@ -8,7 +10,7 @@ use warp::{
     tesseract::Tesseract,
 };
 use warp_ipfs::{
-    config::{Bootstrap, Discovery},
+    config::{Bootstrap, Discovery, StoreSetting},
     WarpIpfsBuilder,
 };
 
@@ -116,6 +118,22 @@ pub async fn create_account_and_chat(
     Box<dyn Constellation>,
     DID,
     Identity,
+)> {
+    create_account_and_chat_with_setting(username, passphrase, context, |_| {}).await
+}
+
+#[allow(dead_code)]
+pub async fn create_account_and_chat_with_setting(
+    username: Option<&str>,
+    passphrase: Option<&str>,
+    context: Option<String>,
+    setting: impl FnOnce(&mut StoreSetting),
+) -> anyhow::Result<(
+    Box<dyn MultiPass>,
+    Box<dyn RayGun>,
+    Box<dyn Constellation>,
+    DID,
+    Identity,
 )> {
     let tesseract = Tesseract::default();
     tesseract.unlock(b"internal pass").unwrap();
@@ -129,6 +147,7 @@ pub async fn create_account_and_chat(
     config.store_setting_mut().share_platform = true;
     config.ipfs_setting_mut().relay_client.relay_address = vec![];
     *config.bootstrap_mut() = Bootstrap::None;
+    setting(config.store_setting_mut());
 
     let (mut account, raygun, fs) = WarpIpfsBuilder::default()
         .set_tesseract(tesseract)
@@ -142,6 +161,28 @@ pub async fn create_account_and_chat(
     Ok((account, raygun, fs, identity.did_key(), identity))
 }
 
+/// Node using the shuttle at the addresses for discovery, without an identity
+#[allow(dead_code)]
+pub async fn create_node_with_shuttle(
+    addresses: Vec<Multiaddr>,
+) -> (Box<dyn MultiPass>, Box<dyn RayGun>, Box<dyn Constellation>) {
+    let tesseract = Tesseract::default();
+    tesseract.unlock(b"internal pass").unwrap();
+    let mut config = warp_ipfs::config::Config::development();
+    *config.listen_on_mut() = vec![Multiaddr::empty().with(Protocol::Memory(0))];
+    config.ipfs_setting_mut().memory_transport = true;
+    config.store_setting_mut().discovery = Discovery::Shuttle { addresses };
+    config.store_setting_mut().device_sync = Some(Duration::from_secs(1));
+    config.ipfs_setting_mut().relay_client.relay_address = vec![];
+    *config.bootstrap_mut() = Bootstrap::None;
+
+    WarpIpfsBuilder::default()
+        .set_tesseract(tesseract)
+        .set_config(config)
+        .finalize()
+        .await
+}
+
 #[allow(dead_code)]
 pub async fn create_accounts_and_chat(
     infos: Vec<(Option<&str>, Option<&str>, Option<String>)>,
@@ -153,6 +194,22 @@ pub async fn create_accounts_and_chat(
         DID,
         Identity,
     )>,
+> {
+    create_accounts_and_chat_with_setting(infos, |_| {}).await
+}
+
+#[allow(dead_code)]
+pub async fn create_accounts_and_chat_with_setting(
+    infos: Vec<(Option<&str>, Option<&str>, Option<String>)>,
+    setting: impl Fn(&mut StoreSetting),
+) -> anyhow::Result<
+    Vec<(
+        Box<dyn MultiPass>,
+        Box<dyn RayGun>,
+        Box<dyn Constellation>,
+        DID,
+        Identity,
+    )>,
 > {
     let _ = tracing_subscriber::registry()
         .with(fmt::layer().pretty())
@@ -162,7 +219,8 @@ pub async fn create_accounts_and_chat(
     let mut accounts = vec![];
     let mut nodes = vec![];
     for (username, passphrase, context) in infos {
-        let account = create_account_and_chat(username, passphrase, context).await?;
+        let account =
+            create_account_and_chat_with_setting(username, passphrase, context, &setting).await?;
         let ipfs = account
             .0
             .handle()
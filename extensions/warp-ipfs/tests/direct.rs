@@ -2,18 +2,25 @@ mod common;
 
 #[cfg(test)]
 mod test {
+    use chrono::Utc;
     use futures::{StreamExt, TryStreamExt};
-    use std::time::Duration;
+    use std::{sync::Arc, time::Duration};
     use warp::{
         constellation::Progression,
+        error::Error,
         multipass::MultiPassEventKind,
         raygun::{
-            AttachmentKind, ConversationType, Location, MessageEvent, MessageEventKind,
-            MessageType, PinState, RayGunEventKind, ReactionState,
+            AttachmentKind, ConversationSettings, ConversationType, DirectConversationSettings,
+            EmbedState, Location, MessageEvent, MessageEventKind, MessageOptions, MessageStatus,
+            MessageType, PinState, Poll, RayGunEventKind, ReactionState,
         },
     };
 
-    use crate::common::{create_accounts_and_chat, PROFILE_IMAGE};
+    use warp_ipfs::embed::{EmbedFetcher, EmbedResponse};
+
+    use crate::common::{
+        create_accounts_and_chat, create_accounts_and_chat_with_setting, PROFILE_IMAGE,
+    };
 
     #[tokio::test]
     async fn create_conversation() -> anyhow::Result<()> {
@@ -567,22 +574,22 @@ mod test {
     }
 
     #[tokio::test]
-    async fn react_message_in_conversation() -> anyhow::Result<()> {
+    async fn search_message_in_conversation() -> anyhow::Result<()> {
         let accounts = create_accounts_and_chat(vec![
             (
                 None,
                 None,
-                Some("test::react_message_in_conversation".into()),
+                Some("test::search_message_in_conversation".into()),
             ),
             (
                 None,
                 None,
-                Some("test::react_message_in_conversation".into()),
+                Some("test::search_message_in_conversation".into()),
             ),
         ])
         .await?;
 
-        let (_account_a, mut chat_a, _, did_a, _) = accounts.first().cloned().unwrap();
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
         let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
 
         let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
@@ -612,168 +619,165 @@ mod test {
         })
         .await?;
 
-        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
         let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
 
-        chat_a.send(id_a, vec!["Hello, World".into()]).await?;
+        let message_id_1 = chat_a.send(id_a, vec!["Hello, World".into()]).await?;
+        let message_id_2 = chat_a
+            .send(id_a, vec!["The weather is nice today".into()])
+            .await?;
 
-        let message_a = tokio::time::timeout(Duration::from_secs(60), async {
+        tokio::time::timeout(Duration::from_secs(60), async {
+            let mut received = 0;
             loop {
-                if let Some(MessageEventKind::MessageSent {
-                    conversation_id,
-                    message_id,
-                }) = conversation_a.next().await
+                if let Some(MessageEventKind::MessageReceived { .. }) = conversation_b.next().await
                 {
-                    break chat_a.get_message(conversation_id, message_id).await;
+                    received += 1;
+                    if received == 2 {
+                        break;
+                    }
                 }
             }
         })
-        .await??;
+        .await?;
 
-        let message_b = tokio::time::timeout(Duration::from_secs(60), async {
-            loop {
-                if let Some(MessageEventKind::MessageReceived {
-                    conversation_id,
-                    message_id,
-                }) = conversation_b.next().await
-                {
-                    break chat_b.get_message(conversation_id, message_id).await;
-                }
-            }
-        })
-        .await??;
+        let results = chat_a.search_messages(Some(id_a), "world").await?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id(), message_id_1);
 
-        assert_eq!(message_a, message_b);
+        let results = chat_b.search_messages(None, "weath*").await?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id(), message_id_2);
+
+        let results = chat_b.search_messages(None, "\"nice today\"").await?;
+        assert_eq!(results.len(), 1);
+
+        assert!(chat_b
+            .search_messages(None, "\"today nice\"")
+            .await?
+            .is_empty());
 
         chat_a
-            .react(id_a, message_a.id(), ReactionState::Add, ":smile:".into())
+            .edit(id_a, message_id_1, vec!["Goodbye".into()])
             .await?;
 
-        tokio::time::timeout(Duration::from_secs(60), async {
+        assert!(chat_a
+            .search_messages(Some(id_a), "world")
+            .await?
+            .is_empty());
+
+        chat_a.delete(id_a, Some(message_id_2)).await?;
+
+        assert!(chat_a.search_messages(None, "weather").await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_receipt_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::read_receipt_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::read_receipt_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessageReactionAdded {
-                    conversation_id,
-                    message_id,
-                    did_key,
-                    reaction,
-                }) = conversation_a.next().await
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
                 {
-                    assert_eq!(id_a, conversation_id);
-                    assert_eq!(message_id, message_a.id());
-                    assert_eq!(did_key, did_a);
-                    assert_eq!(reaction, ":smile:");
-                    //Check the actual message itself
-                    let message = chat_a
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    let reactions = message.reactions();
-
-                    let reactors = reactions.get(":smile:").expect("Reaction exist");
-                    assert!(reactors.contains(&did_a));
-                    assert!(reactions.contains_key(":smile:"));
-                    break;
+                    break conversation_id;
                 }
             }
         })
         .await?;
 
-        tokio::time::timeout(Duration::from_secs(60), async {
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessageReactionAdded {
-                    conversation_id,
-                    message_id,
-                    did_key,
-                    reaction,
-                }) = conversation_b.next().await
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
                 {
-                    assert_eq!(id_b, conversation_id);
-                    assert_eq!(message_id, message_b.id());
-                    assert_eq!(did_key, did_a);
-                    assert_eq!(reaction, ":smile:");
-                    //Check the actual message itself
-                    let message = chat_b
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    let reactions = message.reactions();
-
-                    let reactors = reactions.get(":smile:").expect("Reaction exist");
-                    assert!(reactors.contains(&did_a));
-                    assert!(reactions.contains_key(":smile:"));
-                    break;
+                    break conversation_id;
                 }
             }
         })
         .await?;
 
-        chat_a
-            .react(
-                id_a,
-                message_a.id(),
-                ReactionState::Remove,
-                ":smile:".into(),
-            )
-            .await?;
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let message_id = chat_a.send(id_a, vec!["Hello, World".into()]).await?;
 
         tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessageReactionRemoved {
-                    conversation_id,
-                    message_id,
-                    did_key,
-                    reaction,
-                }) = conversation_a.next().await
+                if let Some(MessageEventKind::MessageReceived { .. }) = conversation_b.next().await
                 {
-                    assert_eq!(id_a, conversation_id);
-                    assert_eq!(message_id, message_a.id());
-                    assert_eq!(did_key, did_a);
-                    assert_eq!(reaction, ":smile:");
-                    //Check the actual message itself
-                    let message = chat_a
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    assert!(message.reactions().is_empty());
                     break;
                 }
             }
         })
         .await?;
 
+        assert_eq!(chat_b.unread_count(id_b).await?, 1);
+        assert_eq!(chat_a.unread_count(id_a).await?, 0);
+
+        chat_b.mark_as_read(id_b, message_id).await?;
+
+        assert_eq!(chat_b.unread_count(id_b).await?, 0);
+
         tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessageReactionRemoved {
+                if let Some(MessageEventKind::MessageRead {
                     conversation_id,
-                    message_id,
+                    message_id: m_id,
                     did_key,
-                    reaction,
-                }) = conversation_b.next().await
+                }) = conversation_a.next().await
                 {
-                    assert_eq!(id_b, conversation_id);
-                    assert_eq!(message_id, message_b.id());
-                    assert_eq!(did_key, did_a);
-                    assert_eq!(reaction, ":smile:");
-                    //Check the actual message itself
-                    let message = chat_b
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    assert!(message.reactions().is_empty());
+                    assert_eq!(conversation_id, id_a);
+                    assert_eq!(m_id, message_id);
+                    assert_eq!(did_key, did_b);
                     break;
                 }
             }
         })
         .await?;
 
+        let receipts = chat_a.message_receipts(id_a, message_id).await?;
+        assert_eq!(receipts.get(&did_b), Some(&MessageStatus::Read));
+        assert_eq!(
+            chat_a.message_status(id_a, message_id).await?,
+            MessageStatus::Read
+        );
         Ok(())
     }
 
     #[tokio::test]
-    async fn pin_message_in_conversation() -> anyhow::Result<()> {
+    async fn disappearing_message_in_conversation() -> anyhow::Result<()> {
         let accounts = create_accounts_and_chat(vec![
-            (None, None, Some("test::pin_message_in_conversation".into())),
-            (None, None, Some("test::pin_message_in_conversation".into())),
+            (
+                None,
+                None,
+                Some("test::disappearing_message_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::disappearing_message_in_conversation".into()),
+            ),
         ])
         .await?;
 
@@ -810,94 +814,47 @@ mod test {
         let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
         let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
 
-        chat_a.send(id_a, vec!["Hello, World".into()]).await?;
-
-        let message_a = tokio::time::timeout(Duration::from_secs(60), async {
-            loop {
-                if let Some(MessageEventKind::MessageSent {
-                    conversation_id,
-                    message_id,
-                }) = conversation_a.next().await
-                {
-                    break chat_a.get_message(conversation_id, message_id).await;
-                }
-            }
-        })
-        .await??;
-
-        let message_b = tokio::time::timeout(Duration::from_secs(60), async {
-            loop {
-                if let Some(MessageEventKind::MessageReceived {
-                    conversation_id,
-                    message_id,
-                }) = conversation_b.next().await
-                {
-                    break chat_b.get_message(conversation_id, message_id).await;
-                }
-            }
-        })
-        .await??;
-
-        assert_eq!(message_a, message_b);
+        let mut settings = DirectConversationSettings::default();
+        settings.set_message_expiry(Some(5));
 
-        chat_a.pin(id_a, message_a.id(), PinState::Pin).await?;
+        chat_a
+            .update_conversation_settings(id_a, ConversationSettings::Direct(settings))
+            .await?;
 
         tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessagePinned {
+                if let Some(MessageEventKind::ConversationSettingsUpdated {
                     conversation_id,
-                    message_id,
-                }) = conversation_a.next().await
+                    settings,
+                }) = conversation_b.next().await
                 {
-                    assert_eq!(id_a, conversation_id);
-                    assert_eq!(message_id, message_a.id());
-                    let message = chat_a
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    assert!(message.pinned());
+                    assert_eq!(conversation_id, id_b);
+                    assert_eq!(settings.message_expiry(), Some(5));
                     break;
                 }
             }
         })
         .await?;
 
+        let message_id = chat_a.send(id_a, vec!["Hello, World".into()]).await?;
+
         tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessagePinned {
-                    conversation_id,
-                    message_id,
-                }) = conversation_b.next().await
+                if let Some(MessageEventKind::MessageReceived { .. }) = conversation_b.next().await
                 {
-                    assert_eq!(id_b, conversation_id);
-                    assert_eq!(message_id, message_b.id());
-                    let message = chat_a
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    assert!(message.pinned());
                     break;
                 }
             }
         })
         .await?;
 
-        chat_a.pin(id_a, message_a.id(), PinState::Unpin).await?;
-
         tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessageUnpinned {
-                    conversation_id,
-                    message_id,
+                if let Some(MessageEventKind::MessageDeleted {
+                    message_id: m_id, ..
                 }) = conversation_a.next().await
                 {
-                    assert_eq!(id_a, conversation_id);
-                    assert_eq!(message_id, message_a.id());
-                    let message = chat_a
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    assert!(!message.pinned());
+                    assert_eq!(m_id, message_id);
                     break;
                 }
             }
@@ -906,48 +863,42 @@ mod test {
 
         tokio::time::timeout(Duration::from_secs(60), async {
             loop {
-                if let Some(MessageEventKind::MessageUnpinned {
-                    conversation_id,
-                    message_id,
+                if let Some(MessageEventKind::MessageDeleted {
+                    message_id: m_id, ..
                 }) = conversation_b.next().await
                 {
-                    assert_eq!(id_b, conversation_id);
-                    assert_eq!(message_id, message_b.id());
-                    let message = chat_a
-                        .get_message(conversation_id, message_id)
-                        .await
-                        .expect("Message exist");
-                    assert!(!message.pinned());
+                    assert_eq!(m_id, message_id);
                     break;
                 }
             }
         })
         .await?;
 
+        assert!(chat_a.get_message(id_a, message_id).await.is_err());
+        assert!(chat_b.get_message(id_b, message_id).await.is_err());
         Ok(())
     }
 
     #[tokio::test]
-    async fn event_in_conversation() -> anyhow::Result<()> {
+    async fn draft_message_in_conversation() -> anyhow::Result<()> {
         let accounts = create_accounts_and_chat(vec![
             (
                 None,
                 None,
-                Some("test::event_message_in_conversation".into()),
+                Some("test::draft_message_in_conversation".into()),
             ),
             (
                 None,
                 None,
-                Some("test::event_message_in_conversation".into()),
+                Some("test::draft_message_in_conversation".into()),
             ),
         ])
         .await?;
 
-        let (_account_a, mut chat_a, _, did_a, _) = accounts.first().cloned().unwrap();
-        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, _, _, did_b, _) = accounts.last().cloned().unwrap();
 
         let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
-        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
 
         chat_a.create_conversation(&did_b).await?;
 
@@ -962,8 +913,1155 @@ mod test {
         })
         .await?;
 
-        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
-            loop {
+        let draft_id = chat_a.save_draft(id_a, vec!["Hello".into()]).await?;
+
+        let drafts = chat_a.list_drafts(id_a).await?;
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].id(), draft_id);
+        assert_eq!(drafts[0].lines(), vec!["Hello".to_string()]);
+
+        chat_a
+            .update_draft(id_a, draft_id, vec!["Hello, World".into()])
+            .await?;
+
+        let drafts = chat_a.list_drafts(id_a).await?;
+        assert_eq!(drafts[0].lines(), vec!["Hello, World".to_string()]);
+
+        chat_a.delete_draft(id_a, draft_id).await?;
+        assert!(chat_a.list_drafts(id_a).await?.is_empty());
+        assert!(chat_a.delete_draft(id_a, draft_id).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scheduled_message_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::scheduled_message_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::scheduled_message_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        assert!(chat_a
+            .schedule_message(
+                id_a,
+                vec!["Hello, World".into()],
+                Utc::now() - chrono::Duration::seconds(1)
+            )
+            .await
+            .is_err());
+
+        let cancelled_id = chat_a
+            .schedule_message(
+                id_a,
+                vec!["Goodbye".into()],
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await?;
+
+        let scheduled_id = chat_a
+            .schedule_message(
+                id_a,
+                vec!["Hello".into()],
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await?;
+
+        assert_eq!(chat_a.list_scheduled_messages(id_a).await?.len(), 2);
+
+        chat_a.cancel_scheduled_message(id_a, cancelled_id).await?;
+
+        chat_a
+            .update_scheduled_message(
+                id_a,
+                scheduled_id,
+                vec!["Hello, World".into()],
+                Utc::now() + chrono::Duration::seconds(2),
+            )
+            .await?;
+
+        let list = chat_a.list_scheduled_messages(id_a).await?;
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].id(), scheduled_id);
+        assert_eq!(list[0].lines(), vec!["Hello, World".to_string()]);
+
+        let message_id = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::ScheduledMessageSent {
+                    conversation_id,
+                    scheduled_id: s_id,
+                    message_id,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(conversation_id, id_a);
+                    assert_eq!(s_id, scheduled_id);
+                    break message_id;
+                }
+            }
+        })
+        .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived {
+                    message_id: m_id, ..
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(m_id, message_id);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let message = chat_b.get_message(id_b, message_id).await?;
+        assert_eq!(message.lines(), vec!["Hello, World".to_string()]);
+        assert!(chat_a.list_scheduled_messages(id_a).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn thread_reply_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::thread_reply_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::thread_reply_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let root_id = chat_a.send(id_a, vec!["Root message".into()]).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived { message_id, .. }) =
+                    conversation_b.next().await
+                {
+                    if message_id == root_id {
+                        break;
+                    }
+                }
+            }
+        })
+        .await?;
+
+        let reply_id = chat_b
+            .reply(id_b, root_id, vec!["First reply".into()])
+            .await?;
+
+        // Replying to a reply keeps the message within the same thread
+        let nested_reply_id = chat_b
+            .reply(id_b, reply_id, vec!["Second reply".into()])
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            let mut received = vec![];
+            loop {
+                if let Some(MessageEventKind::ThreadReplyAdded {
+                    thread_id,
+                    message_id,
+                    ..
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(thread_id, root_id);
+                    received.push(message_id);
+                    if received.len() == 2 {
+                        break;
+                    }
+                }
+            }
+        })
+        .await?;
+
+        let thread = chat_a.get_thread(id_a, nested_reply_id).await?;
+        assert_eq!(
+            thread
+                .iter()
+                .map(|message| message.id())
+                .collect::<Vec<_>>(),
+            vec![root_id, reply_id, nested_reply_id]
+        );
+
+        let reference = chat_a.get_message_reference(id_a, root_id).await?;
+        assert_eq!(reference.reply_count(), 2);
+        assert_eq!(reference.last_reply(), Some(thread[2].date()));
+
+        let timeline = chat_a
+            .get_message_references(id_a, MessageOptions::default().set_exclude_threads())
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].id(), root_id);
+
+        chat_b.delete(id_b, Some(nested_reply_id)).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::ThreadReplyRemoved { message_id, .. }) =
+                    conversation_a.next().await
+                {
+                    assert_eq!(message_id, nested_reply_id);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let reference = chat_a.get_message_reference(id_a, root_id).await?;
+        assert_eq!(reference.reply_count(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn poll_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (None, None, Some("test::poll_in_conversation".into())),
+            (None, None, Some("test::poll_in_conversation".into())),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, did_a, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let poll = Poll::new("Favourite colour?", vec!["Red".into(), "Blue".into()]);
+
+        let message_id = chat_a.send_poll(id_a, poll).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived {
+                    message_id: m_id, ..
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(m_id, message_id);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let message = chat_b.get_message(id_b, message_id).await?;
+        assert_eq!(message.message_type(), MessageType::Poll);
+        let poll = message.poll().expect("poll to exist");
+        assert_eq!(poll.question(), "Favourite colour?");
+        assert_eq!(poll.options().len(), 2);
+
+        assert!(chat_b.vote(id_b, message_id, vec![0, 1]).await.is_err());
+        assert!(chat_b.vote(id_b, message_id, vec![2]).await.is_err());
+
+        chat_b.vote(id_b, message_id, vec![1]).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::PollVoted {
+                    message_id: m_id,
+                    did_key,
+                    ..
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(m_id, message_id);
+                    assert_eq!(did_key, did_b);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        chat_a.vote(id_a, message_id, vec![1]).await?;
+
+        let poll = chat_a
+            .get_message(id_a, message_id)
+            .await?
+            .poll()
+            .expect("poll to exist");
+        assert_eq!(poll.tally(), vec![0, 2]);
+        assert_eq!(poll.votes().get(&did_a), Some(&vec![1]));
+
+        assert!(chat_b.close_poll(id_b, message_id).await.is_err());
+
+        chat_a.close_poll(id_a, message_id).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::PollClosed {
+                    message_id: m_id, ..
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(m_id, message_id);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let poll = chat_b
+            .get_message(id_b, message_id)
+            .await?
+            .poll()
+            .expect("poll to exist");
+        assert!(poll.is_closed());
+        assert!(chat_b.vote(id_b, message_id, vec![0]).await.is_err());
+        Ok(())
+    }
+
+    /// Serves a page with OpenGraph metadata on a local address, standing in for a website
+    fn serve_page() -> std::io::Result<String> {
+        use std::io::{Read, Write};
+
+        const PAGE: &str = r#"<html><head>
+            <title>Fallback</title>
+            <meta property="og:title" content="Local Page">
+            <meta property="og:description" content="A page served for the tests">
+            <meta property="og:site_name" content="Warp">
+            </head><body></body></html>"#;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buffer = [0u8; 1024];
+                _ = stream.read(&mut buffer);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{PAGE}",
+                    PAGE.len()
+                );
+                _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        Ok(format!("http://{addr}/page"))
+    }
+
+    /// Fetcher making plain HTTP requests, which is enough to reach the local page
+    struct LocalFetcher;
+
+    #[async_trait::async_trait]
+    impl EmbedFetcher for LocalFetcher {
+        async fn fetch(&self, url: &str) -> Result<EmbedResponse, Error> {
+            use std::io::{Read, Write};
+
+            let url = url.to_string();
+
+            tokio::task::spawn_blocking(move || {
+                let (host, path) = url
+                    .strip_prefix("http://")
+                    .and_then(|rest| rest.split_once('/'))
+                    .ok_or(Error::InvalidConversion)?;
+
+                let mut stream = std::net::TcpStream::connect(host)?;
+                write!(
+                    stream,
+                    "GET /{path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n"
+                )?;
+
+                let mut response = vec![];
+                stream.read_to_end(&mut response)?;
+
+                let response = String::from_utf8_lossy(&response).to_string();
+                let (head, body) = response
+                    .split_once("\r\n\r\n")
+                    .ok_or(Error::InvalidConversion)?;
+
+                let content_type = head.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-type")
+                        .then(|| value.trim().to_string())
+                });
+
+                Ok(EmbedResponse {
+                    content_type,
+                    body: body.as_bytes().to_vec(),
+                })
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+        }
+    }
+
+    #[tokio::test]
+    async fn embeds_in_conversation() -> anyhow::Result<()> {
+        let url = serve_page()?;
+
+        let accounts = create_accounts_and_chat_with_setting(
+            vec![
+                (None, None, Some("test::embeds_in_conversation".into())),
+                (None, None, Some("test::embeds_in_conversation".into())),
+            ],
+            |setting| setting.embed_fetcher = Some(Arc::new(LocalFetcher)),
+        )
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let message_id = chat_a
+            .send(id_a, vec![format!("Have a look at {url}")])
+            .await?;
+
+        let message_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived {
+                    conversation_id,
+                    message_id,
+                }) = conversation_b.next().await
+                {
+                    break chat_b.get_message(conversation_id, message_id).await;
+                }
+            }
+        })
+        .await??;
+
+        assert_eq!(message_b.id(), message_id);
+
+        let embeds = message_b.embeds();
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(embeds[0].url(), url);
+        assert_eq!(embeds[0].title().as_deref(), Some("Local Page"));
+        assert_eq!(
+            embeds[0].description().as_deref(),
+            Some("A page served for the tests")
+        );
+        assert_eq!(embeds[0].site().as_deref(), Some("Warp"));
+
+        assert!(chat_b
+            .embeds(id_b, message_id, EmbedState::Disable)
+            .await
+            .is_err());
+
+        chat_a.embeds(id_a, message_id, EmbedState::Disable).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageEmbedsUpdated {
+                    message_id: m_id, ..
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(m_id, message_id);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        assert!(chat_a
+            .get_message(id_a, message_id)
+            .await?
+            .embeds()
+            .is_empty());
+        assert!(chat_b
+            .get_message(id_b, message_id)
+            .await?
+            .embeds()
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn embeds_rebuilt_on_edit() -> anyhow::Result<()> {
+        let url = serve_page()?;
+
+        let accounts = create_accounts_and_chat_with_setting(
+            vec![
+                (None, None, Some("test::embeds_rebuilt_on_edit".into())),
+                (None, None, Some("test::embeds_rebuilt_on_edit".into())),
+            ],
+            |setting| setting.embed_fetcher = Some(Arc::new(LocalFetcher)),
+        )
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, _, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let message_id = chat_a.send(id_a, vec!["Nothing to see".into()]).await?;
+
+        assert!(chat_a
+            .get_message(id_a, message_id)
+            .await?
+            .embeds()
+            .is_empty());
+
+        chat_a
+            .edit(id_a, message_id, vec![format!("Have a look at {url}")])
+            .await?;
+
+        let embeds = chat_a.get_message(id_a, message_id).await?.embeds();
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(embeds[0].url(), url);
+
+        chat_a
+            .edit(id_a, message_id, vec!["Never mind".into()])
+            .await?;
+
+        assert!(chat_a
+            .get_message(id_a, message_id)
+            .await?
+            .embeds()
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn export_and_import_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::export_and_import_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::export_and_import_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let first_id = chat_a.send(id_a, vec!["Hello, World".into()]).await?;
+        let second_id = chat_a.send(id_a, vec!["Goodbye".into()]).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived { message_id, .. }) =
+                    conversation_b.next().await
+                {
+                    if message_id == second_id {
+                        break;
+                    }
+                }
+            }
+        })
+        .await?;
+
+        let path = std::env::temp_dir().join(format!("archive-{id_a}"));
+
+        chat_a
+            .export_conversation(id_a, path.clone(), Some("passphrase"))
+            .await?;
+
+        assert!(chat_a
+            .import_conversation(path.clone(), Some("invalid"))
+            .await
+            .is_err());
+
+        chat_a.delete_conversation(id_a).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationDeleted { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    assert_eq!(conversation_id, id_a);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        assert!(chat_a.get_conversation(id_a).await.is_err());
+
+        let conversation_id = chat_a
+            .import_conversation(path.clone(), Some("passphrase"))
+            .await?;
+
+        assert_eq!(conversation_id, id_a);
+        assert_eq!(chat_a.get_message_count(id_a).await?, 2);
+
+        let message = chat_a.get_message(id_a, first_id).await?;
+        assert_eq!(message.lines(), vec!["Hello, World".to_string()]);
+
+        let message = chat_a.get_message(id_a, second_id).await?;
+        assert_eq!(message.lines(), vec!["Goodbye".to_string()]);
+
+        std::fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn react_message_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::react_message_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::react_message_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, did_a, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        chat_a.send(id_a, vec!["Hello, World".into()]).await?;
+
+        let message_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageSent {
+                    conversation_id,
+                    message_id,
+                }) = conversation_a.next().await
+                {
+                    break chat_a.get_message(conversation_id, message_id).await;
+                }
+            }
+        })
+        .await??;
+
+        let message_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived {
+                    conversation_id,
+                    message_id,
+                }) = conversation_b.next().await
+                {
+                    break chat_b.get_message(conversation_id, message_id).await;
+                }
+            }
+        })
+        .await??;
+
+        assert_eq!(message_a, message_b);
+
+        chat_a
+            .react(id_a, message_a.id(), ReactionState::Add, ":smile:".into())
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReactionAdded {
+                    conversation_id,
+                    message_id,
+                    did_key,
+                    reaction,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(id_a, conversation_id);
+                    assert_eq!(message_id, message_a.id());
+                    assert_eq!(did_key, did_a);
+                    assert_eq!(reaction, ":smile:");
+                    //Check the actual message itself
+                    let message = chat_a
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    let reactions = message.reactions();
+
+                    let reactors = reactions.get(":smile:").expect("Reaction exist");
+                    assert!(reactors.contains(&did_a));
+                    assert!(reactions.contains_key(":smile:"));
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReactionAdded {
+                    conversation_id,
+                    message_id,
+                    did_key,
+                    reaction,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(id_b, conversation_id);
+                    assert_eq!(message_id, message_b.id());
+                    assert_eq!(did_key, did_a);
+                    assert_eq!(reaction, ":smile:");
+                    //Check the actual message itself
+                    let message = chat_b
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    let reactions = message.reactions();
+
+                    let reactors = reactions.get(":smile:").expect("Reaction exist");
+                    assert!(reactors.contains(&did_a));
+                    assert!(reactions.contains_key(":smile:"));
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        chat_a
+            .react(
+                id_a,
+                message_a.id(),
+                ReactionState::Remove,
+                ":smile:".into(),
+            )
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReactionRemoved {
+                    conversation_id,
+                    message_id,
+                    did_key,
+                    reaction,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(id_a, conversation_id);
+                    assert_eq!(message_id, message_a.id());
+                    assert_eq!(did_key, did_a);
+                    assert_eq!(reaction, ":smile:");
+                    //Check the actual message itself
+                    let message = chat_a
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    assert!(message.reactions().is_empty());
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReactionRemoved {
+                    conversation_id,
+                    message_id,
+                    did_key,
+                    reaction,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(id_b, conversation_id);
+                    assert_eq!(message_id, message_b.id());
+                    assert_eq!(did_key, did_a);
+                    assert_eq!(reaction, ":smile:");
+                    //Check the actual message itself
+                    let message = chat_b
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    assert!(message.reactions().is_empty());
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pin_message_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (None, None, Some("test::pin_message_in_conversation".into())),
+            (None, None, Some("test::pin_message_in_conversation".into())),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        chat_a.send(id_a, vec!["Hello, World".into()]).await?;
+
+        let message_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageSent {
+                    conversation_id,
+                    message_id,
+                }) = conversation_a.next().await
+                {
+                    break chat_a.get_message(conversation_id, message_id).await;
+                }
+            }
+        })
+        .await??;
+
+        let message_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived {
+                    conversation_id,
+                    message_id,
+                }) = conversation_b.next().await
+                {
+                    break chat_b.get_message(conversation_id, message_id).await;
+                }
+            }
+        })
+        .await??;
+
+        assert_eq!(message_a, message_b);
+
+        chat_a.pin(id_a, message_a.id(), PinState::Pin).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessagePinned {
+                    conversation_id,
+                    message_id,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(id_a, conversation_id);
+                    assert_eq!(message_id, message_a.id());
+                    let message = chat_a
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    assert!(message.pinned());
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessagePinned {
+                    conversation_id,
+                    message_id,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(id_b, conversation_id);
+                    assert_eq!(message_id, message_b.id());
+                    let message = chat_a
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    assert!(message.pinned());
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        chat_a.pin(id_a, message_a.id(), PinState::Unpin).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageUnpinned {
+                    conversation_id,
+                    message_id,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(id_a, conversation_id);
+                    assert_eq!(message_id, message_a.id());
+                    let message = chat_a
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    assert!(!message.pinned());
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageUnpinned {
+                    conversation_id,
+                    message_id,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(id_b, conversation_id);
+                    assert_eq!(message_id, message_b.id());
+                    let message = chat_a
+                        .get_message(conversation_id, message_id)
+                        .await
+                        .expect("Message exist");
+                    assert!(!message.pinned());
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn event_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::event_message_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::event_message_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, did_a, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
                 if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
                     chat_subscribe_b.next().await
                 {
@@ -1016,6 +2114,123 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn custom_event_expires_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::custom_event_expires_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::custom_event_expires_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, did_a, _) = accounts.first().cloned().unwrap();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        chat_a.create_conversation(&did_b).await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let custom_event = MessageEvent::Custom {
+            namespace: "test.warp.poll".into(),
+            payload: serde_json::json!({ "question": "lunch?" }),
+        };
+
+        chat_a
+            .send_event_with_ttl(id_a, custom_event.clone(), Duration::from_secs(2))
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::EventReceived {
+                    conversation_id,
+                    did_key,
+                    event,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(conversation_id, id_b);
+                    assert_eq!(did_key, did_a);
+                    assert_eq!(event, custom_event);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        // Refreshing the event with a different payload should replace the previous instance
+        let refreshed_event = MessageEvent::Custom {
+            namespace: "test.warp.poll".into(),
+            payload: serde_json::json!({ "question": "dinner?" }),
+        };
+
+        chat_a
+            .send_event_with_ttl(id_a, refreshed_event.clone(), Duration::from_secs(2))
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::EventReceived { event, .. }) =
+                    conversation_b.next().await
+                {
+                    assert_eq!(event, refreshed_event);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        // Event should be cancelled on its own once the ttl elapsed
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Some(MessageEventKind::EventCancelled {
+                    conversation_id,
+                    did_key,
+                    event,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(conversation_id, id_b);
+                    assert_eq!(did_key, did_a);
+                    assert_eq!(event, refreshed_event);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn delete_conversation_when_blocked() -> anyhow::Result<()> {
         let accounts = create_accounts_and_chat(vec![
@@ -3,9 +3,15 @@ mod common;
 #[cfg(test)]
 mod test {
 
+    use std::time::Duration;
+
     use futures::{stream, StreamExt, TryStreamExt};
+    use warp::constellation::{
+        file::{FileType, PruneVersions},
+        ConstellationEventKind, Progression, SharePermission, TransferKind,
+    };
 
-    use crate::common::{create_account, PROFILE_IMAGE};
+    use crate::common::{create_account, create_accounts, PROFILE_IMAGE};
 
     #[tokio::test]
     async fn create_directory() -> anyhow::Result<()> {
@@ -17,6 +23,22 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn create_directory_within_existing_directory() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+        fs.create_directory("data", false).await?;
+        fs.create_directory("data/images/2024", true).await?;
+
+        let data = root_directory.get_item("data")?.get_directory()?;
+        assert_eq!(data.get_items().len(), 1);
+        assert!(root_directory
+            .get_item_by_path("data/images/2024")?
+            .is_directory());
+        assert!(fs.create_directory("data/images", true).await.is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn upload_file() -> anyhow::Result<()> {
         let (_, mut fs, _, _) = create_account(None, None, None).await?;
@@ -136,6 +158,101 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn move_file_into_directory() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+        let mut subscribe = fs.constellation_subscribe().await?;
+
+        fs.create_directory("images", false).await?;
+        fs.put_buffer("image.png", PROFILE_IMAGE).await?;
+
+        fs.move_item("image.png", "images").await?;
+
+        assert!(!root_directory.has_item("image.png"));
+        let item = root_directory.get_item_by_path("images/image.png")?;
+        assert!(item.is_file());
+        assert_eq!(item.path(), "/images/");
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Some(ConstellationEventKind::Moved {
+                    item_name,
+                    from,
+                    to,
+                }) = subscribe.next().await
+                {
+                    assert_eq!(item_name, "image.png");
+                    assert_eq!(from, "");
+                    assert_eq!(to, "images");
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        fs.move_item("images/image.png", "/").await?;
+
+        assert!(root_directory.has_item("image.png"));
+        assert!(root_directory
+            .get_item_by_path("images")?
+            .get_directory()?
+            .get_items()
+            .is_empty());
+        assert_eq!(fs.get_buffer("image.png").await?, PROFILE_IMAGE);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn move_directory_across_paths() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+
+        fs.create_directory("data/2024", true).await?;
+        fs.create_directory("archive/old", true).await?;
+        fs.put_buffer("data/2024/notes.txt", &b"hello, world!"[..])
+            .await?;
+
+        fs.move_item("data/2024", "archive/old").await?;
+
+        assert!(root_directory.get_item_by_path("data/2024").is_err());
+
+        let item = root_directory.get_item_by_path("archive/old/2024/notes.txt")?;
+        assert_eq!(item.path(), "/archive/old/2024/");
+
+        assert_eq!(
+            fs.get_buffer("archive/old/2024/notes.txt").await?,
+            b"hello, world!"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn move_item_is_rejected() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+
+        fs.create_directory("data/nested", true).await?;
+        fs.create_directory("images", false).await?;
+        fs.put_buffer("image.png", PROFILE_IMAGE).await?;
+        fs.put_buffer("images/image.png", PROFILE_IMAGE).await?;
+
+        // a directory cannot be moved within itself
+        assert!(fs.move_item("data", "data/nested").await.is_err());
+        assert!(fs.move_item("data", "data").await.is_err());
+        // the destination must be an existing directory
+        assert!(fs.move_item("data", "image.png").await.is_err());
+        assert!(fs.move_item("data", "missing").await.is_err());
+        // an item with the same name already exists within the destination
+        assert!(fs.move_item("image.png", "images").await.is_err());
+        assert!(fs.move_item("missing.png", "images").await.is_err());
+
+        assert!(root_directory.has_item("data"));
+        assert!(root_directory.has_item("image.png"));
+        assert!(root_directory.get_item_by_path("data/nested").is_ok());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn remove_file() -> anyhow::Result<()> {
         let (_, mut fs, _, _) = create_account(None, None, None).await?;
@@ -150,6 +267,78 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn restore_file_from_trash() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+        fs.create_directory("data", false).await?;
+        fs.put_buffer("data/image.png", PROFILE_IMAGE).await?;
+
+        fs.remove("data/image.png", false).await?;
+        assert!(root_directory.get_item_by_path("data/image.png").is_err());
+
+        let trash = fs.list_trash().await?;
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].path(), "data");
+        assert_eq!(trash[0].item().name(), "image.png");
+
+        fs.restore(trash[0].id()).await?;
+        assert!(root_directory.get_item_by_path("data/image.png").is_ok());
+        assert!(fs.list_trash().await?.is_empty());
+
+        let data = fs.get_buffer("data/image.png").await?;
+        assert_eq!(data, PROFILE_IMAGE);
+
+        // items in the trash count towards the storage used until the trash is emptied
+        fs.remove("data/image.png", false).await?;
+        assert_eq!(fs.current_size(), PROFILE_IMAGE.len());
+
+        fs.empty_trash().await?;
+        assert_eq!(fs.current_size(), 0);
+        assert!(fs.list_trash().await?.is_empty());
+        assert!(root_directory.get_item_by_path("data/image.png").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn file_versions() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        fs.put_buffer("data.txt", &b"first"[..]).await?;
+        fs.put_buffer("data.txt", &b"second"[..]).await?;
+        fs.put_buffer("data.txt", &b"third"[..]).await?;
+
+        let versions = fs.list_versions("data.txt").await?;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].size(), 5);
+
+        // previous versions count towards the storage used
+        assert_eq!(fs.current_size(), 5 + 6 + 5);
+
+        // the file is modified once its contents are replaced
+        let file = fs.root_directory().get_item("data.txt")?.get_file()?;
+        assert!(versions[1].modified() < file.modified());
+
+        let data = fs.get_version_buffer("data.txt", versions[0].id()).await?;
+        assert_eq!(data, b"first");
+
+        fs.restore_version("data.txt", versions[0].id()).await?;
+        assert_eq!(fs.get_buffer("data.txt").await?, b"first");
+
+        let versions = fs.list_versions("data.txt").await?;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            fs.get_version_buffer("data.txt", versions[1].id()).await?,
+            b"third"
+        );
+
+        fs.prune_versions("data.txt", PruneVersions::KeepLast(1))
+            .await?;
+        let pruned = fs.list_versions("data.txt").await?;
+        assert_eq!(pruned, versions[1..]);
+        assert_eq!(fs.current_size(), 5 + 5);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn check_thumbnail_of_file() -> anyhow::Result<()> {
         let (_, mut fs, _, _) = create_account(None, None, None).await?;
@@ -166,4 +355,314 @@ mod test {
         assert!(item.thumbnail().is_empty());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn detect_file_type_from_content() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+        fs.put_buffer("image.jpg", PROFILE_IMAGE).await?;
+        fs.put_buffer("image.txt", &b"hello, world!"[..]).await?;
+
+        let stream = stream::once(async { PROFILE_IMAGE.to_vec() }).boxed();
+        let mut status = fs.put_stream("image", None, stream).await?;
+        while status.next().await.is_some() {}
+
+        let png = FileType::Mime("image/png".parse()?);
+
+        let file = root_directory.get_item("image.jpg")?.get_file()?;
+        assert_eq!(file.file_type(), png);
+        assert!(!file.thumbnail().is_empty());
+
+        let file = root_directory.get_item("image")?.get_file()?;
+        assert_eq!(file.file_type(), png);
+
+        let file = root_directory.get_item("image.txt")?.get_file()?;
+        assert_eq!(file.file_type(), FileType::Mime("text/plain".parse()?));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resume_upload_stream() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        let root_directory = fs.root_directory();
+
+        let data = (0..5 * 1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<u8>>();
+
+        let chunks = data
+            .chunks(64 * 1024)
+            .map(<[u8]>::to_vec)
+            .collect::<Vec<_>>();
+
+        // Interrupt the upload once most of the contents were stored
+        let stream = stream::iter(chunks.clone()).boxed();
+        let mut status = fs.put_stream("data.bin", Some(data.len()), stream).await?;
+        while let Some(progress) = status.next().await {
+            if let Progression::CurrentProgress { current, .. } = progress {
+                if current >= 4 * 1024 * 1024 + 512 * 1024 {
+                    break;
+                }
+            }
+        }
+        drop(status);
+
+        assert!(!root_directory.has_item("data.bin"));
+
+        let transfer = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let transfers = fs.list_transfers().await?;
+                match transfers.first() {
+                    Some(transfer) if transfer.current() >= 4 * 1024 * 1024 + 512 * 1024 => {
+                        return Ok::<_, anyhow::Error>(transfer.clone())
+                    }
+                    _ => tokio::time::sleep(Duration::from_millis(100)).await,
+                }
+            }
+        })
+        .await??;
+
+        assert_eq!(transfer.kind(), TransferKind::Upload);
+        assert_eq!(transfer.name(), "data.bin");
+        assert_eq!(transfer.total(), Some(data.len()));
+
+        // Chunks stored by the first attempt are reused rather than being stored again
+        let stream = stream::iter(chunks).boxed();
+        let mut status = fs.put_stream("data.bin", Some(data.len()), stream).await?;
+        let mut resumed = None;
+        while let Some(progress) = status.next().await {
+            match progress {
+                Progression::CurrentProgress { current, .. } if resumed.is_none() => {
+                    resumed = Some(current)
+                }
+                Progression::ProgressFailed { error, .. } => anyhow::bail!(error),
+                _ => {}
+            }
+        }
+
+        assert_eq!(resumed, Some(transfer.current()));
+
+        assert!(root_directory.has_item("data.bin"));
+        assert_eq!(fs.get_buffer("data.bin").await?, data);
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !fs.list_transfers().await?.is_empty() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resume_download() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+
+        let data = (0..5 * 1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<u8>>();
+
+        fs.put_buffer("data.bin", &data).await?;
+
+        let path = std::env::temp_dir().join(format!("{}.bin", uuid::Uuid::new_v4()));
+        let location = path.to_string_lossy().to_string();
+
+        // Interrupt the download once some of the contents were written
+        let mut status = fs.get("data.bin", &location).await?;
+        while let Some(progress) = status.next().await {
+            match progress {
+                Progression::CurrentProgress { current, .. } if current >= 1024 * 1024 => break,
+                Progression::ProgressFailed { error, .. } => anyhow::bail!(error),
+                _ => {}
+            }
+        }
+        drop(status);
+
+        let transfer = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let transfers = fs.list_transfers().await?;
+                match transfers.first() {
+                    Some(transfer) if transfer.current() >= 1024 * 1024 => {
+                        return Ok::<_, anyhow::Error>(transfer.clone())
+                    }
+                    _ => tokio::time::sleep(Duration::from_millis(100)).await,
+                }
+            }
+        })
+        .await??;
+
+        assert_eq!(transfer.kind(), TransferKind::Download);
+        assert_eq!(transfer.name(), "data.bin");
+        assert_eq!(transfer.total(), Some(data.len()));
+
+        // Contents written by the first attempt are kept rather than being downloaded again
+        let mut status = fs.get("data.bin", &location).await?;
+        let mut resumed = None;
+        while let Some(progress) = status.next().await {
+            match progress {
+                Progression::CurrentProgress { current, .. } if resumed.is_none() => {
+                    resumed = Some(current)
+                }
+                Progression::ProgressFailed { error, .. } => anyhow::bail!(error),
+                _ => {}
+            }
+        }
+
+        assert!(resumed >= Some(transfer.current()));
+
+        let contents = tokio::fs::read(&path).await?;
+        tokio::fs::remove_file(&path).await?;
+        assert_eq!(contents, data);
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !fs.list_transfers().await?.is_empty() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cancel_transfer() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+
+        let stream = stream::once(async { vec![0; 1024 * 1024] }).boxed();
+        let status = fs.put_stream("data.bin", None, stream).await?;
+        drop(status);
+
+        let transfers = fs.list_transfers().await?;
+        assert_eq!(transfers.len(), 1);
+
+        fs.cancel_transfer(transfers[0].id()).await?;
+        assert!(fs.list_transfers().await?.is_empty());
+        assert!(fs.cancel_transfer(transfers[0].id()).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn publish_requires_shuttle() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        fs.put_buffer("hello.txt", &b"hello, world"[..]).await?;
+
+        // only shuttle nodes serve published files
+        assert!(fs.publish("hello.txt").await.is_err());
+        assert!(fs.publish("missing.txt").await.is_err());
+        assert!(fs.unpublish("hello.txt").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_items() -> anyhow::Result<()> {
+        let (_, mut fs, _, _) = create_account(None, None, None).await?;
+        fs.put_buffer("images/image.png", PROFILE_IMAGE).await?;
+        fs.put_buffer("notes/todo.txt", &b"hello, world!"[..])
+            .await?;
+        fs.put_buffer("notes/done.txt", &b"hello"[..]).await?;
+
+        let paths = |results: Vec<warp::constellation::search::SearchResult>| {
+            results
+                .iter()
+                .map(|result| result.path())
+                .collect::<Vec<_>>()
+        };
+
+        let results = fs.search("*.txt").await?.collect::<Vec<_>>().await;
+        assert_eq!(paths(results), ["notes/done.txt", "notes/todo.txt"]);
+
+        let results = fs.search("type:image").await?.collect::<Vec<_>>().await;
+        assert_eq!(paths(results), ["images/image.png"]);
+
+        let results = fs
+            .search("is:file size:>5")
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(paths(results), ["images/image.png", "notes/todo.txt"]);
+
+        let results = fs.search("is:directory").await?.collect::<Vec<_>>().await;
+        assert_eq!(paths(results), ["images", "notes"]);
+
+        assert!(fs.search("size:>big").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn share_file() -> anyhow::Result<()> {
+        let accounts = create_accounts(vec![
+            (Some("JohnDoe"), None, Some("test::share_file".into())),
+            (Some("JaneDoe"), None, Some("test::share_file".into())),
+        ])
+        .await?;
+
+        let (_, mut fs_a, did_a, _) = accounts.first().cloned().unwrap();
+        let (_, mut fs_b, did_b, _) = accounts.last().cloned().unwrap();
+
+        let mut subscribe_b = fs_b.constellation_subscribe().await?;
+
+        fs_a.put_buffer("data.txt", &b"hello, world!"[..]).await?;
+
+        let share_id = fs_a
+            .share("data.txt", &did_b, SharePermission::Read)
+            .await?;
+
+        let shares = fs_a.list_shares().await?;
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].permission(&did_b), Some(SharePermission::Read));
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(ConstellationEventKind::ShareReceived { share_id: id, .. }) =
+                    subscribe_b.next().await
+                {
+                    if id == share_id {
+                        break;
+                    }
+                }
+            }
+        })
+        .await?;
+
+        let shares = fs_b.shared_with_me().await?;
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].owner(), did_a);
+        assert_eq!(shares[0].name(), "data.txt");
+
+        // shared items are addressed by the id of their share
+        let path = format!("{share_id}/data.txt");
+
+        let directory = fs_b.shared_directory().await?;
+        assert!(directory.get_item_by_path(&path).is_ok());
+
+        let data = fs_b.get_shared_buffer(&path).await?;
+        assert_eq!(data, b"hello, world!");
+        assert!(fs_b.get_shared_buffer("data.txt").await.is_err());
+
+        assert!(fs_b
+            .put_shared_buffer(&format!("{path}/other.txt"), &b"hello"[..])
+            .await
+            .is_err());
+
+        fs_a.revoke_share("data.txt", &did_b).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(ConstellationEventKind::ShareRemoved { share_id: id, .. }) =
+                    subscribe_b.next().await
+                {
+                    if id == share_id {
+                        break;
+                    }
+                }
+            }
+        })
+        .await?;
+
+        assert!(fs_a.list_shares().await?.is_empty());
+        assert!(fs_b.shared_with_me().await?.is_empty());
+        assert!(fs_b.get_shared_buffer(&path).await.is_err());
+        Ok(())
+    }
 }
@@ -375,4 +375,72 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn rotate_identity_keeps_friends() -> anyhow::Result<()> {
+        let accounts = create_accounts(vec![
+            (
+                Some("JohnDoe"),
+                None,
+                Some("test::rotate_identity_keeps_friends".into()),
+            ),
+            (
+                Some("JaneDoe"),
+                None,
+                Some("test::rotate_identity_keeps_friends".into()),
+            ),
+            (
+                Some("JohnDoe"),
+                None,
+                Some("test::rotate_identity_keeps_friends".into()),
+            ),
+        ])
+        .await?;
+
+        let (mut account_a, _, _, _) = accounts[0].clone();
+        let (mut account_b, _, did_b, _) = accounts[1].clone();
+        let (mut account_c, _, did_c, _) = accounts[2].clone();
+
+        let mut subscribe_a = account_a.multipass_subscribe().await?;
+        let mut subscribe_b = account_b.multipass_subscribe().await?;
+
+        account_a.send_request(&did_b).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            let did = loop {
+                if let Some(MultiPassEventKind::FriendRequestReceived { from }) =
+                    subscribe_b.next().await
+                {
+                    break from;
+                }
+            };
+            account_b.accept_request(&did).await
+        })
+        .await??;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MultiPassEventKind::FriendAdded { .. }) = subscribe_a.next().await {
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        // the identity of `account_a` is rotated to the identity of `account_c`
+        let statement = account_a.issue_rotation(&did_c).await?;
+        account_c.publish_rotation(&statement).await?;
+
+        assert!(account_c.has_friend(&did_b).await?);
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            while !account_b.has_friend(&did_c).await? {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+
+        Ok(())
+    }
 }
@@ -6,9 +6,10 @@ mod test {
     use crate::common::create_accounts_and_chat;
     use futures::StreamExt;
     use warp::{
+        crypto::DID,
         multipass::MultiPassEventKind,
         raygun::{
-            ConversationSettings, ConversationType, GroupSettings, MessageEventKind,
+            ConversationSettings, ConversationType, GroupRole, GroupSettings, MessageEventKind,
             RayGunEventKind,
         },
     };
@@ -1553,4 +1554,460 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn roles_and_bans_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::roles_and_bans_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::roles_and_bans_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::roles_and_bans_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, did_a, _) = accounts[0].clone();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts[1].clone();
+        let (_account_c, mut chat_c, _, did_c, _) = accounts[2].clone();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+        let mut chat_subscribe_c = chat_c.raygun_subscribe().await?;
+
+        chat_a
+            .create_group_conversation(
+                None,
+                vec![did_b.clone(), did_c.clone()],
+                GroupSettings::default(),
+            )
+            .await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_c = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_c.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let conversation = chat_a.get_conversation(id_a).await?;
+        assert_eq!(conversation.owner(), Some(did_a.clone()));
+        assert_eq!(conversation.role(&did_b), GroupRole::Member);
+
+        // Members are unable to manage the conversation
+        assert!(chat_c.ban(id_c, &did_b).await.is_err());
+        assert!(chat_b
+            .set_role(id_b, &did_c, GroupRole::Moderator)
+            .await
+            .is_err());
+
+        chat_a.set_role(id_a, &did_b, GroupRole::Moderator).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::RecipientRoleUpdated {
+                    conversation_id,
+                    recipient,
+                    role,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(conversation_id, id_b);
+                    assert_eq!(recipient, did_b);
+                    assert_eq!(role, GroupRole::Moderator);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let conversation = chat_b.get_conversation(id_b).await?;
+        assert_eq!(conversation.role(&did_b), GroupRole::Moderator);
+
+        // Moderators are unable to act on the owner
+        assert!(chat_b.ban(id_b, &did_a).await.is_err());
+
+        chat_b.ban(id_b, &did_c).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::RecipientBanned {
+                    conversation_id,
+                    recipient,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(conversation_id, id_a);
+                    assert_eq!(recipient, did_c);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationDeleted { conversation_id }) =
+                    chat_subscribe_c.next().await
+                {
+                    assert_eq!(conversation_id, id_c);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        assert_eq!(chat_a.list_banned(id_a).await?, vec![did_c.clone()]);
+        let conversation = chat_a.get_conversation(id_a).await?;
+        assert!(!conversation.recipients().contains(&did_c));
+
+        // Banned identities cannot be added back until they are unbanned
+        assert!(chat_a.add_recipient(id_a, &did_c).await.is_err());
+
+        chat_b.unban(id_b, &did_c).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::RecipientUnbanned {
+                    conversation_id,
+                    recipient,
+                }) = conversation_a.next().await
+                {
+                    assert_eq!(conversation_id, id_a);
+                    assert_eq!(recipient, did_c);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        assert!(chat_a.list_banned(id_a).await?.is_empty());
+
+        chat_a.transfer_ownership(id_a, &did_b).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::RecipientRoleUpdated {
+                    conversation_id,
+                    recipient,
+                    role: GroupRole::Owner,
+                }) = conversation_b.next().await
+                {
+                    assert_eq!(conversation_id, id_b);
+                    assert_eq!(recipient, did_b);
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let conversation = chat_b.get_conversation(id_b).await?;
+        assert_eq!(conversation.owner(), Some(did_b.clone()));
+        assert_eq!(conversation.role(&did_a), GroupRole::Admin);
+
+        // The previous owner is unable to manage admins after the transfer
+        assert!(chat_a
+            .set_role(id_a, &did_b, GroupRole::Member)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unauthorized_updates_rejected_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::unauthorized_updates_rejected_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::unauthorized_updates_rejected_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::unauthorized_updates_rejected_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, did_a, _) = accounts[0].clone();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts[1].clone();
+        let (_account_c, mut chat_c, _, did_c, _) = accounts[2].clone();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+        let mut chat_subscribe_c = chat_c.raygun_subscribe().await?;
+
+        chat_a
+            .create_group_conversation(
+                None,
+                vec![did_b.clone(), did_c.clone()],
+                GroupSettings::default(),
+            )
+            .await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_c = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_c.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+        let mut conversation_c = chat_c.get_conversation_stream(id_c).await?;
+
+        chat_a.set_role(id_a, &did_b, GroupRole::Moderator).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::RecipientRoleUpdated {
+                    role: GroupRole::Moderator,
+                    ..
+                }) = conversation_b.next().await
+                {
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        // Members are unable to rename the conversation unless the settings allow it
+        assert!(chat_c
+            .update_conversation_name(id_c, "Member Rename")
+            .await
+            .is_err());
+
+        // Moderators are unable to act beyond their role
+        let mut settings = GroupSettings::default();
+        settings.set_members_can_change_name(true);
+        assert!(chat_b
+            .update_conversation_settings(id_b, ConversationSettings::Group(settings))
+            .await
+            .is_err());
+        assert!(chat_b.transfer_ownership(id_b, &did_b).await.is_err());
+        assert!(chat_b
+            .set_role(id_b, &did_c, GroupRole::Admin)
+            .await
+            .is_err());
+        assert!(chat_b
+            .set_role(id_b, &did_b, GroupRole::Admin)
+            .await
+            .is_err());
+        assert!(chat_b.add_restricted(id_b, &DID::default()).await.is_err());
+
+        // The owner is able to restrict identities that are not recipients
+        let restricted = DID::default();
+        assert!(chat_a.add_restricted(id_a, &did_c).await.is_err());
+        chat_a.add_restricted(id_a, &restricted).await?;
+        assert!(chat_a.add_restricted(id_a, &restricted).await.is_err());
+
+        chat_a
+            .update_conversation_name(id_a, "Owner Rename")
+            .await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::ConversationNameUpdated { name, .. }) =
+                    conversation_c.next().await
+                {
+                    assert_eq!(name, "Owner Rename");
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        let conversation = chat_c.get_conversation(id_c).await?;
+        assert_eq!(conversation.owner(), Some(did_a.clone()));
+        assert_eq!(conversation.role(&did_b), GroupRole::Moderator);
+        assert_eq!(conversation.role(&did_c), GroupRole::Member);
+        assert_eq!(
+            conversation.settings(),
+            ConversationSettings::Group(GroupSettings::default())
+        );
+
+        // Restricted identities cannot be added until the restriction is lifted
+        assert!(chat_a.add_recipient(id_a, &restricted).await.is_err());
+
+        chat_a.remove_restricted(id_a, &restricted).await?;
+        assert!(chat_a.remove_restricted(id_a, &restricted).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn edit_history_in_conversation() -> anyhow::Result<()> {
+        let accounts = create_accounts_and_chat(vec![
+            (
+                None,
+                None,
+                Some("test::edit_history_in_conversation".into()),
+            ),
+            (
+                None,
+                None,
+                Some("test::edit_history_in_conversation".into()),
+            ),
+        ])
+        .await?;
+
+        let (_account_a, mut chat_a, _, _, _) = accounts[0].clone();
+        let (_account_b, mut chat_b, _, did_b, _) = accounts[1].clone();
+
+        let mut chat_subscribe_a = chat_a.raygun_subscribe().await?;
+        let mut chat_subscribe_b = chat_b.raygun_subscribe().await?;
+
+        let mut settings = GroupSettings::default();
+        settings.set_keep_edit_history(true);
+
+        chat_a
+            .create_group_conversation(None, vec![did_b.clone()], settings)
+            .await?;
+
+        let id_a = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_a.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let id_b = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(RayGunEventKind::ConversationCreated { conversation_id }) =
+                    chat_subscribe_b.next().await
+                {
+                    break conversation_id;
+                }
+            }
+        })
+        .await?;
+
+        let mut conversation_a = chat_a.get_conversation_stream(id_a).await?;
+        let mut conversation_b = chat_b.get_conversation_stream(id_b).await?;
+
+        let message_id = chat_a.send(id_a, vec!["First".into()]).await?;
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                if let Some(MessageEventKind::MessageReceived { .. }) = conversation_b.next().await
+                {
+                    break;
+                }
+            }
+        })
+        .await?;
+
+        assert!(chat_a.message_revisions(id_a, message_id).await?.is_empty());
+
+        for lines in [vec!["Second".to_string()], vec!["Third".to_string()]] {
+            chat_a.edit(id_a, message_id, lines).await?;
+
+            tokio::time::timeout(Duration::from_secs(60), async {
+                loop {
+                    if let Some(MessageEventKind::MessageEdited { .. }) =
+                        conversation_a.next().await
+                    {
+                        break;
+                    }
+                }
+            })
+            .await?;
+
+            tokio::time::timeout(Duration::from_secs(60), async {
+                loop {
+                    if let Some(MessageEventKind::MessageEdited { .. }) =
+                        conversation_b.next().await
+                    {
+                        break;
+                    }
+                }
+            })
+            .await?;
+        }
+
+        let revisions_a = chat_a.message_revisions(id_a, message_id).await?;
+        let revisions_b = chat_b.message_revisions(id_b, message_id).await?;
+
+        assert_eq!(revisions_a, revisions_b);
+        assert_eq!(revisions_a.len(), 2);
+        assert_eq!(revisions_a[0].lines(), vec!["First".to_string()]);
+        assert_eq!(revisions_a[1].lines(), vec!["Second".to_string()]);
+        assert!(revisions_a[0].date() < revisions_a[1].date());
+
+        let message = chat_b.get_message(id_b, message_id).await?;
+        assert_eq!(message.lines(), vec!["Third".to_string()]);
+
+        Ok(())
+    }
 }
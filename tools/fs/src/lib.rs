@@ -67,3 +67,85 @@ pub async fn file_size(path: impl AsRef<Path>) -> io::Result<usize> {
     #[cfg(target_arch = "wasm32")]
     Ok(read(path).await?.len())
 }
+
+/// Read up to `len` bytes from the start of the file at path
+pub async fn read_header(path: impl AsRef<Path>, len: usize) -> io::Result<Vec<u8>> {
+    read_range(path, 0, len).await
+}
+
+/// Read up to `len` bytes of the file at path, starting at `offset`
+pub async fn read_range(path: impl AsRef<Path>, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let path = path.as_ref().to_path_buf();
+        return tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut buffer = Vec::with_capacity(len);
+            file.take(len as u64).read_to_end(&mut buffer)?;
+            Ok(buffer)
+        })
+        .await
+        .map_err(io::Error::other)?;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let contents = read(path).await?;
+        let start = (offset as usize).min(contents.len());
+        let end = start.saturating_add(len).min(contents.len());
+        Ok(contents[start..end].to_vec())
+    }
+}
+
+/// Append the contents to the end of the file at path, creating the file if it does not exist
+pub async fn append(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::io::Write;
+
+        let path = path.as_ref().to_path_buf();
+        let contents = contents.as_ref().to_vec();
+        return tokio::task::spawn_blocking(move || {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(&contents)
+        })
+        .await
+        .map_err(io::Error::other)?;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let mut existing = read(&path).await.unwrap_or_default();
+        existing.extend_from_slice(contents.as_ref());
+        write(path, existing).await
+    }
+}
+
+/// Truncate or extend the file at path to `len` bytes
+pub async fn truncate(path: impl AsRef<Path>, len: u64) -> io::Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = path.as_ref().to_path_buf();
+        return tokio::task::spawn_blocking(move || {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_len(len)
+        })
+        .await
+        .map_err(io::Error::other)?;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let mut contents = read(&path).await?;
+        contents.resize(len as usize, 0);
+        write(path, contents).await
+    }
+}
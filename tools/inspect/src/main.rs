@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use comfy_table::Table;
 
 use warp::constellation::Constellation;
@@ -13,6 +13,8 @@ use warp::tesseract::Tesseract;
 use warp_ipfs::config::Discovery;
 use warp_ipfs::WarpIpfsBuilder;
 
+use uuid::Uuid;
+
 #[derive(Debug, Parser)]
 #[clap(name = "inspect")]
 struct Opt {
@@ -27,6 +29,34 @@ struct Opt {
     /// Password to unlock keystore
     #[clap(long)]
     password: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Export a conversation to an archive
+    Export {
+        /// Id of the conversation
+        conversation_id: Uuid,
+
+        /// Path to the directory of the archive
+        output: PathBuf,
+
+        /// Passphrase to encrypt the archive with
+        #[clap(long)]
+        passphrase: Option<String>,
+    },
+    /// Import a conversation from an archive
+    Import {
+        /// Path to the directory of the archive
+        input: PathBuf,
+
+        /// Passphrase the archive was encrypted with
+        #[clap(long)]
+        passphrase: Option<String>,
+    },
 }
 
 async fn setup<P: AsRef<Path>>(
@@ -71,7 +101,7 @@ async fn main() -> anyhow::Result<()> {
     });
 
     let start_time = Instant::now();
-    let (account, rg, _) = setup(&opt.path, opt.keystore.clone(), password).await?;
+    let (account, mut rg, _) = setup(&opt.path, opt.keystore.clone(), password).await?;
     let end_time = start_time.elapsed();
     println!(
         "Took {}ms to load the account, messaging and filesystem",
@@ -85,6 +115,38 @@ async fn main() -> anyhow::Result<()> {
 
     println!("Username: {}#{}", identity.username(), identity.short_id());
 
+    match opt.command {
+        Some(Command::Export {
+            conversation_id,
+            output,
+            passphrase,
+        }) => {
+            let start_time = Instant::now();
+            rg.export_conversation(conversation_id, output.clone(), passphrase.as_deref())
+                .await?;
+            let end_time = start_time.elapsed();
+            println!(
+                "Took {}ms to export conversation {conversation_id} to {}",
+                end_time.as_millis(),
+                output.display()
+            );
+            return Ok(());
+        }
+        Some(Command::Import { input, passphrase }) => {
+            let start_time = Instant::now();
+            let conversation_id = rg.import_conversation(input, passphrase.as_deref()).await?;
+            let end_time = start_time.elapsed();
+            println!(
+                "Took {}ms to import conversation {conversation_id}",
+                end_time.as_millis()
+            );
+            let count = rg.get_message_count(conversation_id).await?;
+            println!("Total Messages: {count}");
+            return Ok(());
+        }
+        None => {}
+    }
+
     let start_time = Instant::now();
     let mut friends = account.list_friends().await?;
     let end_time = start_time.elapsed();
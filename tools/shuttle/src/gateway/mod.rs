@@ -1 +1,147 @@
+//! HTTP gateway for the content identities explicitly made public: the picture and banner referenced by
+//! their identity, and the files they published to the node.
+//!
+//! Content is only read from blocks stored on the node, and only when its root is a unixfs file, so that
+//! documents such as encrypted messages or shares are never exposed even if their cid is known.
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+
+use chrono::{DateTime, Utc};
+use libipld::{Cid, Ipld};
+use rust_ipfs::Ipfs;
+use serde::{Deserialize, Serialize};
+use warp::{constellation::file::FileType, crypto::DID};
+
+pub(crate) const DAG_PB: u64 = 0x70;
+pub(crate) const RAW: u64 = 0x55;
+
+const UNIXFS_RAW: u64 = 0;
+const UNIXFS_FILE: u64 = 2;
+
+/// File published by an identity to be served by the gateway
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PublishedDocument {
+    /// Root of the unencrypted unixfs contents
+    pub cid: Cid,
+    pub owner: DID,
+    pub name: String,
+    pub size: u64,
+    pub file_type: FileType,
+    pub published: DateTime<Utc>,
+}
+
+impl PublishedDocument {
+    pub fn new(cid: Cid, owner: DID, name: &str, size: u64, file_type: FileType) -> Self {
+        Self {
+            cid,
+            owner,
+            name: name.to_string(),
+            size,
+            file_type,
+            published: Utc::now(),
+        }
+    }
+}
+
+/// Returns true if the cid points to the contents of a unixfs file stored on the node, which is the only
+/// kind of content the gateway serves. Other dag-pb nodes, such as directories, are not served
+pub async fn is_unixfs(ipfs: &Ipfs, cid: &Cid) -> bool {
+    match cid.codec() {
+        RAW => true,
+        DAG_PB => {
+            let Ok(Ipld::Map(node)) = ipfs.get_dag(*cid).local().await else {
+                return false;
+            };
+
+            match node.get("Data") {
+                Some(Ipld::Bytes(data)) => {
+                    matches!(unixfs_type(data), Some(UNIXFS_RAW | UNIXFS_FILE))
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Type of the unixfs `Data` message within a dag-pb node
+fn unixfs_type(mut data: &[u8]) -> Option<u64> {
+    while !data.is_empty() {
+        let key = read_varint(&mut data)?;
+        match (key >> 3, key & 0x7) {
+            (1, 0) => return read_varint(&mut data),
+            (_, 0) => {
+                read_varint(&mut data)?;
+            }
+            (_, 2) => {
+                let length = usize::try_from(read_varint(&mut data)?).ok()?;
+                data = data.get(length..)?;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for (index, byte) in data.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            *data = &data[index + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use libipld::{
+        multihash::{Code, MultihashDigest},
+        Cid, IpldCodec,
+    };
+    use rust_ipfs::{Block, UninitializedIpfs};
+
+    use super::{is_unixfs, unixfs_type, UNIXFS_FILE};
+
+    #[test]
+    fn unixfs_types() {
+        // file with a size, directory and symlink
+        assert_eq!(unixfs_type(&[0x08, 0x02, 0x18, 0x05]), Some(UNIXFS_FILE));
+        assert_eq!(unixfs_type(&[0x18, 0x05, 0x08, 0x02]), Some(UNIXFS_FILE));
+        assert_eq!(unixfs_type(&[0x08, 0x01]), Some(1));
+        assert_eq!(unixfs_type(&[0x08, 0x04]), Some(4));
+        assert_eq!(
+            unixfs_type(&[0x12, 0x01, 0xff, 0x08, 0x02]),
+            Some(UNIXFS_FILE)
+        );
+        assert_eq!(unixfs_type(&[]), None);
+        assert_eq!(unixfs_type(&[0x12, 0x05, 0x00]), None);
+        assert_eq!(unixfs_type(&[0x08, 0x80]), None);
+    }
+
+    #[tokio::test]
+    async fn only_unixfs_files_served() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+
+        let path = ipfs.add_unixfs(b"hello, world".to_vec()).await?;
+        let file = path.root().cid().copied().expect("valid cid");
+        assert!(is_unixfs(&ipfs, &file).await);
+
+        // dag-pb node of a directory, holding only its unixfs `Data`
+        let node = vec![0x0a, 0x02, 0x08, 0x01];
+        let directory = Cid::new_v1(IpldCodec::DagPb.into(), Code::Sha2_256.digest(&node));
+        ipfs.put_block(Block::new(directory, node)?).await?;
+        assert!(!is_unixfs(&ipfs, &directory).await);
+
+        let document = ipfs.dag().put().serialize("message").await?;
+        assert!(!is_unixfs(&ipfs, &document).await);
+
+        // dag-pb nodes that are not stored on the node are not served
+        let missing = Cid::new_v1(IpldCodec::DagPb.into(), Code::Sha2_256.digest(b"missing"));
+        assert!(!is_unixfs(&ipfs, &missing).await);
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{
+        header::{
+            ACCEPT_RANGES, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_SECURITY_POLICY,
+            CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE, X_CONTENT_TYPE_OPTIONS,
+        },
+        HeaderMap, StatusCode,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use futures::{future, StreamExt};
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use warp::{constellation::file::FileType, crypto::DID};
+
+use crate::{
+    identity::protocol::Lookup,
+    store::{identity::IdentityStorage, published::PublishedStorage},
+};
+
+use super::is_unixfs;
+
+#[derive(Clone, Debug)]
+pub struct GatewayConfig {
+    pub listen_addr: SocketAddr,
+    /// Largest content, in bytes, that can be published and served
+    pub max_size: u64,
+    /// Duration published content can be cached for. Since content is addressed by its cid, it never changes
+    pub max_age: Duration,
+    /// Duration the picture or banner of an identity can be cached for
+    pub identity_max_age: Duration,
+}
+
+impl GatewayConfig {
+    pub fn new(listen_addr: SocketAddr) -> Self {
+        Self {
+            listen_addr,
+            max_size: 100 * 1024 * 1024,
+            max_age: Duration::from_secs(365 * 24 * 60 * 60),
+            identity_max_age: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Picture or banner of an identity, as stored by warp-ipfs
+#[derive(Deserialize)]
+struct ImageDag {
+    link: Cid,
+    size: u64,
+    mime: FileType,
+}
+
+struct Content {
+    link: Cid,
+    size: u64,
+    file_type: FileType,
+    /// Cid identifying the version of the content
+    tag: Cid,
+}
+
+#[derive(Clone)]
+pub struct Gateway {
+    ipfs: Ipfs,
+    identity_storage: IdentityStorage,
+    published_storage: PublishedStorage,
+    config: GatewayConfig,
+}
+
+impl Gateway {
+    pub fn new(
+        ipfs: &Ipfs,
+        identity_storage: &IdentityStorage,
+        published_storage: &PublishedStorage,
+        config: GatewayConfig,
+    ) -> Self {
+        Self {
+            ipfs: ipfs.clone(),
+            identity_storage: identity_storage.clone(),
+            published_storage: published_storage.clone(),
+            config,
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/ipfs/:cid", get(published))
+            .route("/identity/:did/picture", get(picture))
+            .route("/identity/:did/banner", get(banner))
+            .with_state(self.clone())
+    }
+
+    pub async fn serve(self) -> io::Result<()> {
+        let listener = TcpListener::bind(self.config.listen_addr).await?;
+        tracing::info!(address = %listener.local_addr()?, "gateway listening");
+        axum::serve(listener, self.router()).await
+    }
+
+    async fn identity_image(&self, did: &str, banner: bool, headers: &HeaderMap) -> Response {
+        let Ok(did) = did.parse::<DID>() else {
+            return status(StatusCode::BAD_REQUEST);
+        };
+
+        let document = match self
+            .identity_storage
+            .lookup(Lookup::PublicKey { did })
+            .await
+        {
+            Ok(list) => list.into_iter().next(),
+            Err(_) => None,
+        };
+
+        let cid = document.and_then(|document| match banner {
+            true => document.metadata.profile_banner,
+            false => document.metadata.profile_picture,
+        });
+
+        let Some(cid) = cid else {
+            return status(StatusCode::NOT_FOUND);
+        };
+
+        let Ok(image) = self
+            .ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized::<ImageDag>()
+            .await
+        else {
+            return status(StatusCode::NOT_FOUND);
+        };
+
+        let content = Content {
+            link: image.link,
+            size: image.size,
+            file_type: image.mime,
+            tag: cid,
+        };
+
+        self.respond(content, false, headers).await
+    }
+
+    async fn respond(&self, content: Content, immutable: bool, headers: &HeaderMap) -> Response {
+        if !is_unixfs(&self.ipfs, &content.link).await {
+            return status(StatusCode::NOT_FOUND);
+        }
+
+        if content.size > self.config.max_size {
+            return status(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        let etag = format!("\"{}\"", content.tag);
+        let cache_control = match immutable {
+            true => format!(
+                "public, max-age={}, immutable",
+                self.config.max_age.as_secs()
+            ),
+            false => format!("public, max-age={}", self.config.identity_max_age.as_secs()),
+        };
+
+        let matches_etag = headers
+            .get(IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(',')
+                    .any(|tag| tag.trim() == etag || tag.trim() == "*")
+            })
+            .unwrap_or_default();
+
+        let builder = Response::builder()
+            .header(ETAG, &etag)
+            .header(CACHE_CONTROL, cache_control);
+
+        if matches_etag {
+            return builder
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+                .unwrap_or_default();
+        }
+
+        let range = match headers.get(RANGE).and_then(|value| value.to_str().ok()) {
+            Some(value) => match parse_range(value, content.size) {
+                Ok(range) => range,
+                Err(_) => {
+                    return Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(CONTENT_RANGE, format!("bytes */{}", content.size))
+                        .body(Body::empty())
+                        .unwrap_or_default();
+                }
+            },
+            None => None,
+        };
+
+        let (start, end) = range.unwrap_or((0, content.size));
+
+        let content_type = match content.file_type {
+            FileType::Mime(media) => media.to_string(),
+            FileType::Generic => "application/octet-stream".into(),
+        };
+
+        let mut builder = builder
+            .header(CONTENT_TYPE, content_type)
+            .header(CONTENT_LENGTH, end - start)
+            .header(ACCEPT_RANGES, "bytes")
+            .header(X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(CONTENT_SECURITY_POLICY, "sandbox");
+
+        builder = match range {
+            Some((start, end)) => builder.status(StatusCode::PARTIAL_CONTENT).header(
+                CONTENT_RANGE,
+                format!("bytes {start}-{}/{}", end - 1, content.size),
+            ),
+            None => builder.status(StatusCode::OK),
+        };
+
+        // only blocks stored on the node are read, so the gateway never fetches content from the network
+        let stream = self
+            .ipfs
+            .cat_unixfs(content.link)
+            .max_length(content.size as _)
+            .set_local(true)
+            .scan(0u64, move |offset, data| {
+                let item = match data {
+                    Ok(_) if *offset >= end => None,
+                    Ok(data) => {
+                        let position = *offset;
+                        let length = data.len() as u64;
+                        *offset += length;
+
+                        let from = start.saturating_sub(position).min(length) as usize;
+                        let to = end.saturating_sub(position).min(length) as usize;
+                        Some(Ok(data.slice(from..to)))
+                    }
+                    Err(e) => Some(Err(io::Error::new(io::ErrorKind::Other, e.to_string()))),
+                };
+                future::ready(item)
+            });
+
+        builder.body(Body::from_stream(stream)).unwrap_or_default()
+    }
+}
+
+async fn published(
+    State(gateway): State<Gateway>,
+    Path(cid): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Ok(cid) = cid.parse::<Cid>() else {
+        return status(StatusCode::BAD_REQUEST);
+    };
+
+    // content that is not published is reported the same way as content that does not exist
+    let Ok(document) = gateway.published_storage.get(&cid).await else {
+        return status(StatusCode::NOT_FOUND);
+    };
+
+    let content = Content {
+        link: document.cid,
+        size: document.size,
+        file_type: document.file_type,
+        tag: document.cid,
+    };
+
+    gateway.respond(content, true, &headers).await
+}
+
+async fn picture(
+    State(gateway): State<Gateway>,
+    Path(did): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    gateway.identity_image(&did, false, &headers).await
+}
+
+async fn banner(
+    State(gateway): State<Gateway>,
+    Path(did): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    gateway.identity_image(&did, true, &headers).await
+}
+
+fn status(status: StatusCode) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+/// Parses the `Range` header into the start and exclusive end of the requested bytes.
+/// Returns `None` when the whole content should be served, such as for units other than bytes or
+/// for several ranges, which would otherwise need a multipart response.
+fn parse_range(value: &str, size: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(ranges) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+
+    if ranges.contains(',') {
+        return Ok(None);
+    }
+
+    let (start, end) = ranges.split_once('-').ok_or(())?;
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return Err(()),
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().map_err(|_| ())?;
+            if suffix == 0 {
+                return Err(());
+            }
+            (size.saturating_sub(suffix), size)
+        }
+        (start, "") => (start.parse().map_err(|_| ())?, size),
+        (start, end) => {
+            let start = start.parse().map_err(|_| ())?;
+            let end = end.parse::<u64>().map_err(|_| ())?;
+            if end < start {
+                return Err(());
+            }
+            (start, end.saturating_add(1).min(size))
+        }
+    };
+
+    if start >= size || start >= end {
+        return Err(());
+    }
+
+    Ok(Some((start, end)))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+        http::{
+            header::{CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE},
+            StatusCode,
+        },
+        response::Response,
+        Router,
+    };
+    use rust_ipfs::UninitializedIpfs;
+    use tower::ServiceExt;
+    use warp::{constellation::file::FileType, crypto::DID};
+
+    use crate::{
+        gateway::PublishedDocument,
+        store::{identity::IdentityStorage, published::PublishedStorage, root::RootStorage},
+    };
+
+    use super::{parse_range, Gateway, GatewayConfig};
+
+    async fn get(router: &Router, path: &str, header: Option<(&str, &str)>) -> Response {
+        let mut request = Request::builder().uri(path);
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        let request = request.body(Body::empty()).expect("valid request");
+        router.clone().oneshot(request).await.expect("infallible")
+    }
+
+    #[test]
+    fn range_requests() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some((0, 100))));
+        assert_eq!(parse_range("bytes=500-", 1000), Ok(Some((500, 1000))));
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some((900, 1000))));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Ok(Some((900, 1000))));
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), Ok(None));
+        assert_eq!(parse_range("items=0-1", 1000), Ok(None));
+        assert!(parse_range("bytes=1000-", 1000).is_err());
+        assert!(parse_range("bytes=9-5", 1000).is_err());
+        assert!(parse_range("bytes=-0", 1000).is_err());
+        assert!(parse_range("bytes=a-b", 1000).is_err());
+    }
+
+    #[tokio::test]
+    async fn published_content_served() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+        let root = RootStorage::new(&ipfs, None).await;
+        let identity = IdentityStorage::new(&ipfs, &root).await;
+        let published = PublishedStorage::new(&ipfs, &root).await;
+
+        let config = GatewayConfig::new(([127, 0, 0, 1], 0).into());
+        let router = Gateway::new(&ipfs, &identity, &published, config).router();
+
+        let contents = b"hello, world".to_vec();
+        let cid = ipfs
+            .add_unixfs(contents.clone())
+            .await?
+            .root()
+            .cid()
+            .copied()
+            .expect("valid cid");
+        let path = format!("/ipfs/{cid}");
+
+        // content stored on the node is only served once it was published
+        let response = get(&router, &path, None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = get(&router, "/ipfs/content", None).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let document = PublishedDocument::new(
+            cid,
+            DID::default(),
+            "hello.txt",
+            contents.len() as _,
+            FileType::Generic,
+        );
+        published.publish(document, 1024).await?;
+
+        let response = get(&router, &path, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/octet-stream");
+        let etag = response.headers()[ETAG].to_str()?.to_string();
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await?, contents);
+
+        let response = get(&router, &path, Some((RANGE.as_str(), "bytes=7-"))).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 7-11/12");
+        assert_eq!(
+            to_bytes(response.into_body(), usize::MAX).await?,
+            &b"world"[..]
+        );
+
+        let response = get(&router, &path, Some((RANGE.as_str(), "bytes=12-"))).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let response = get(&router, &path, Some((IF_NONE_MATCH.as_str(), &etag))).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        Ok(())
+    }
+}
@@ -22,11 +22,17 @@ use rust_ipfs::{
 use rust_ipfs::libp2p::request_response;
 use warp::crypto::DID;
 
-use crate::{identity::protocol::payload_message_construct, PayloadRequest, PeerIdExt};
+use crate::{
+    gateway::PublishedDocument, identity::protocol::payload_message_construct, PayloadRequest,
+    PeerIdExt,
+};
 
-use super::document::IdentityDocument;
+use super::document::{IdentityDocument, RotationDocument};
 use super::{
-    protocol::{Lookup, LookupResponse, Message, Register, RegisterResponse, Request, Response},
+    protocol::{
+        Lookup, LookupResponse, Message, Publish, PublishResponse, Register, RegisterResponse,
+        Request, Response, Rotation, RotationResponse,
+    },
     RequestPayload,
 };
 
@@ -62,6 +68,10 @@ pub enum IdentityCommand {
         root_cid: Cid,
         response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
     },
+    Unregister {
+        peer_id: PeerId,
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
     Lookup {
         peer_id: PeerId,
         kind: Lookup,
@@ -86,6 +96,27 @@ pub enum IdentityCommand {
         peer_id: PeerId,
         response: futures::channel::oneshot::Sender<Result<Cid, warp::error::Error>>,
     },
+    Publish {
+        peer_id: PeerId,
+        document: PublishedDocument,
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
+    Unpublish {
+        peer_id: PeerId,
+        cid: Cid,
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
+    PublishRotation {
+        peer_id: PeerId,
+        document: RotationDocument,
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
+    FetchRotations {
+        peer_id: PeerId,
+        dids: Vec<DID>,
+        response:
+            futures::channel::oneshot::Sender<Result<Vec<RotationDocument>, warp::error::Error>>,
+    },
 }
 
 #[allow(dead_code)]
@@ -93,6 +124,9 @@ enum IdentityResponse {
     Register {
         response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
     },
+    Unregister {
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
     Lookup {
         response:
             futures::channel::oneshot::Sender<Result<Vec<IdentityDocument>, warp::error::Error>>,
@@ -116,6 +150,16 @@ enum IdentityResponse {
     Fetch {
         response: futures::channel::oneshot::Sender<Result<Cid, warp::error::Error>>,
     },
+    Publish {
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
+    Rotation {
+        response: futures::channel::oneshot::Sender<Result<(), warp::error::Error>>,
+    },
+    Rotations {
+        response:
+            futures::channel::oneshot::Sender<Result<Vec<RotationDocument>, warp::error::Error>>,
+    },
 }
 
 impl Behaviour {
@@ -198,14 +242,15 @@ impl Behaviour {
                 tracing::debug!(?response, id = ?id, sender = %sender, "Received response");
                 match response {
                     Response::RegisterResponse(response) => {
-                        let res = match self.waiting_on_response.remove(&id) {
-                            Some(IdentityResponse::Register { response }) => response,
+                        let (res, unregister) = match self.waiting_on_response.remove(&id) {
+                            Some(IdentityResponse::Register { response }) => (response, false),
+                            Some(IdentityResponse::Unregister { response }) => (response, true),
                             _ => return,
                         };
 
                         match response {
                             RegisterResponse::Ok => {
-                                if self.external_addresses.iter().count() > 0 {
+                                if !unregister && self.external_addresses.iter().count() > 0 {
                                     self.send_record();
                                 }
                                 let _ = res.send(Ok(()));
@@ -371,6 +416,70 @@ impl Behaviour {
                             };
                         }
                     },
+                    Response::PublishResponse(response) => {
+                        let Some(IdentityResponse::Publish { response: res }) =
+                            self.waiting_on_response.remove(&id)
+                        else {
+                            return;
+                        };
+
+                        let result = match response {
+                            PublishResponse::Ok => Ok(()),
+                            PublishResponse::Error(e) => Err(match e {
+                                super::protocol::PublishError::NotRegistered => {
+                                    warp::error::Error::IdentityNotCreated
+                                }
+                                super::protocol::PublishError::Unavailable => {
+                                    warp::error::Error::OtherWithContext(
+                                        "Gateway is not enabled on the node".into(),
+                                    )
+                                }
+                                super::protocol::PublishError::Forbidden => {
+                                    warp::error::Error::SenderMismatch
+                                }
+                                super::protocol::PublishError::DoesntExist => {
+                                    warp::error::Error::ObjectNotFound
+                                }
+                                super::protocol::PublishError::Invalid { msg } => {
+                                    warp::error::Error::OtherWithContext(msg)
+                                }
+                            }),
+                        };
+
+                        _ = res.send(result);
+                    }
+                    Response::RotationResponse(response) => {
+                        let Some(waiting) = self.waiting_on_response.remove(&id) else {
+                            return;
+                        };
+
+                        let result = match response {
+                            RotationResponse::Ok => Ok(vec![]),
+                            RotationResponse::Documents { list } => Ok(list),
+                            RotationResponse::Error(e) => Err(match e {
+                                super::protocol::RotationError::NotRegistered => {
+                                    warp::error::Error::IdentityDoesntExist
+                                }
+                                super::protocol::RotationError::Forbidden => {
+                                    warp::error::Error::SenderMismatch
+                                }
+                                super::protocol::RotationError::Rotated => {
+                                    warp::error::Error::IdentityRotated
+                                }
+                                super::protocol::RotationError::Invalid => {
+                                    warp::error::Error::InvalidSignature
+                                }
+                            }),
+                        };
+
+                        match waiting {
+                            IdentityResponse::Rotation { response } => {
+                                _ = response.send(result.map(|_| ()))
+                            }
+                            IdentityResponse::Rotations { response } => _ = response.send(result),
+                            _ => {}
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -520,6 +629,22 @@ impl NetworkBehaviour for Behaviour {
                         self.waiting_on_response
                             .insert(id, IdentityResponse::Register { response });
                     }
+                    IdentityCommand::Unregister { peer_id, response } => {
+                        tracing::info!("Unregistering from {peer_id}");
+                        let payload = payload_message_construct(
+                            &self.keypair,
+                            self.primary_keypair.as_ref(),
+                            Request::Register(Register::Unregister { shuttle: peer_id }),
+                        )
+                        .expect("Valid construction of payload");
+
+                        let id = self.inner.send_request(&peer_id, payload);
+
+                        tracing::debug!(?id, "Request sent");
+
+                        self.waiting_on_response
+                            .insert(id, IdentityResponse::Unregister { response });
+                    }
                     IdentityCommand::Lookup {
                         peer_id,
                         kind,
@@ -623,6 +748,82 @@ impl NetworkBehaviour for Behaviour {
                         self.waiting_on_response
                             .insert(id, IdentityResponse::RequestsReceived { response });
                     }
+                    IdentityCommand::Publish {
+                        peer_id,
+                        document,
+                        response,
+                    } => {
+                        tracing::info!(cid = %document.cid, "Publishing content to {peer_id}");
+                        let payload = payload_message_construct(
+                            &self.keypair,
+                            self.primary_keypair.as_ref(),
+                            Request::Publish(Publish::File { document }),
+                        )
+                        .expect("Valid construction of payload");
+
+                        let id = self.inner.send_request(&peer_id, payload);
+                        tracing::debug!(?id, "Request sent");
+
+                        self.waiting_on_response
+                            .insert(id, IdentityResponse::Publish { response });
+                    }
+                    IdentityCommand::Unpublish {
+                        peer_id,
+                        cid,
+                        response,
+                    } => {
+                        tracing::info!(%cid, "Removing published content from {peer_id}");
+                        let payload = payload_message_construct(
+                            &self.keypair,
+                            self.primary_keypair.as_ref(),
+                            Request::Publish(Publish::Remove { cid }),
+                        )
+                        .expect("Valid construction of payload");
+
+                        let id = self.inner.send_request(&peer_id, payload);
+                        tracing::debug!(?id, "Request sent");
+
+                        self.waiting_on_response
+                            .insert(id, IdentityResponse::Publish { response });
+                    }
+                    IdentityCommand::PublishRotation {
+                        peer_id,
+                        document,
+                        response,
+                    } => {
+                        tracing::info!(old = %document.old, new = %document.new, "Publishing rotation to {peer_id}");
+                        let payload = payload_message_construct(
+                            &self.keypair,
+                            self.primary_keypair.as_ref(),
+                            Request::Rotation(Rotation::Publish { document }),
+                        )
+                        .expect("Valid construction of payload");
+
+                        let id = self.inner.send_request(&peer_id, payload);
+                        tracing::debug!(?id, "Request sent");
+
+                        self.waiting_on_response
+                            .insert(id, IdentityResponse::Rotation { response });
+                    }
+                    IdentityCommand::FetchRotations {
+                        peer_id,
+                        dids,
+                        response,
+                    } => {
+                        tracing::info!("Fetching rotations from {peer_id}");
+                        let payload = payload_message_construct(
+                            &self.keypair,
+                            self.primary_keypair.as_ref(),
+                            Request::Rotation(Rotation::Fetch { dids }),
+                        )
+                        .expect("Valid construction of payload");
+
+                        let id = self.inner.send_request(&peer_id, payload);
+                        tracing::debug!(?id, "Request sent");
+
+                        self.waiting_on_response
+                            .insert(id, IdentityResponse::Rotations { response });
+                    }
                 },
                 Poll::Ready(None) => {
                     //There is no point in keeping a stream if it already closed, though we should probably panic here
@@ -666,6 +867,10 @@ impl NetworkBehaviour for Behaviour {
                                 let _ =
                                     response.send(Err(warp::error::Error::Boxed(Box::new(error))));
                             }
+                            IdentityResponse::Unregister { response } => {
+                                let _ =
+                                    response.send(Err(warp::error::Error::Boxed(Box::new(error))));
+                            }
                             IdentityResponse::Lookup { response } => {
                                 let _ =
                                     response.send(Err(warp::error::Error::Boxed(Box::new(error))));
@@ -690,6 +895,15 @@ impl NetworkBehaviour for Behaviour {
                             IdentityResponse::RequestsReceived { response } => {
                                 _ = response.send(Err(warp::error::Error::Boxed(Box::new(error))))
                             }
+                            IdentityResponse::Publish { response } => {
+                                _ = response.send(Err(warp::error::Error::Boxed(Box::new(error))))
+                            }
+                            IdentityResponse::Rotation { response } => {
+                                _ = response.send(Err(warp::error::Error::Boxed(Box::new(error))))
+                            }
+                            IdentityResponse::Rotations { response } => {
+                                _ = response.send(Err(warp::error::Error::Boxed(Box::new(error))))
+                            }
                         }
                     }
                     continue;
@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use libipld::Cid;
 use serde::{Deserialize, Serialize};
 use warp::crypto::did_key::CoreSign;
+use warp::crypto::hash::sha256_iter;
 use warp::crypto::{Fingerprint, DID};
 use warp::error::Error;
 use warp::multipass::identity::{IdentityStatus, Platform, SHORT_ID_SIZE};
@@ -33,6 +34,10 @@ pub struct IdentityDocument {
     #[serde(default)]
     pub version: IdentityDocumentVersion,
 
+    /// Recovery key able to rotate the identity to a new key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<DID>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
 }
@@ -79,6 +84,7 @@ impl IdentityDocument {
         self.username != other.username
             || self.status_message != other.status_message
             || self.metadata != other.metadata
+            || self.recovery != other.recovery
     }
 }
 
@@ -137,3 +143,192 @@ impl IdentityDocument {
         Ok(())
     }
 }
+
+/// Statement rotating an identity to a new key, issued by the previous key or by the recovery key
+/// the identity has committed to, and accepted by the new key
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RotationDocument {
+    pub old: DID,
+    pub new: DID,
+    pub created: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<DID>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_signature: Option<String>,
+}
+
+impl RotationDocument {
+    /// Statement issued by the previous key of the identity
+    pub fn new(old: &DID, new: &DID) -> Result<Self, Error> {
+        Self::construct(old, new, None)?.sign(old)
+    }
+
+    /// Statement issued by the recovery key the identity has committed to
+    pub fn new_with_recovery(old: &DID, recovery: &DID, new: &DID) -> Result<Self, Error> {
+        Self::construct(old, new, Some(recovery.clone()))?.sign(recovery)
+    }
+
+    fn construct(old: &DID, new: &DID, recovery: Option<DID>) -> Result<Self, Error> {
+        if old.eq(new) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        Ok(RotationDocument {
+            old: old.clone(),
+            new: new.clone(),
+            created: Utc::now(),
+            recovery,
+            signature: None,
+            new_signature: None,
+        })
+    }
+
+    /// Key that issued the statement
+    pub fn issuer(&self) -> &DID {
+        self.recovery.as_ref().unwrap_or(&self.old)
+    }
+
+    /// Whether the statement takes precedence over another statement rotating the same identity.
+    /// A statement issued by the recovery key supersedes one issued by the previous key, allowing
+    /// the owner to take the identity back after the previous key was compromised
+    pub fn supersedes(&self, other: &RotationDocument) -> bool {
+        self.old.eq(&other.old) && self.recovery.is_some() && other.recovery.is_none()
+    }
+
+    /// Sign the statement using the new key, showing the new key accepts the rotation
+    pub fn accept(mut self, new: &DID) -> Result<Self, Error> {
+        if self.new.ne(new) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let signature = new.sign(&self.hash());
+        self.new_signature = Some(bs58::encode(signature).into_string());
+        Ok(self)
+    }
+
+    /// Verify the statement, using the recovery key the previous identity committed to if the statement was
+    /// issued by a recovery key
+    pub fn verify(&self, recovery: Option<&DID>) -> Result<(), Error> {
+        if self.old.eq(&self.new) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        if let Some(key) = &self.recovery {
+            match recovery {
+                Some(committed) if committed.eq(key) => {}
+                Some(_) => return Err(Error::PublicKeyInvalid),
+                None => return Err(Error::RecoveryKeyNotSet),
+            }
+        }
+
+        let hash = self.hash();
+
+        let signature = self.signature.as_ref().ok_or(Error::InvalidSignature)?;
+        let signature = bs58::decode(signature).into_vec()?;
+
+        self.issuer()
+            .verify(&hash, &signature)
+            .map_err(|_| Error::InvalidSignature)?;
+
+        let signature = self.new_signature.as_ref().ok_or(Error::InvalidSignature)?;
+        let signature = bs58::decode(signature).into_vec()?;
+
+        self.new
+            .verify(&hash, &signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self).map_err(Error::from)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        serde_json::from_slice(bytes).map_err(Error::from)
+    }
+
+    fn sign(mut self, issuer: &DID) -> Result<Self, Error> {
+        if self.issuer().ne(issuer) {
+            return Err(Error::PublicKeyInvalid);
+        }
+
+        let signature = issuer.sign(&self.hash());
+        self.signature = Some(bs58::encode(signature).into_string());
+        Ok(self)
+    }
+
+    fn hash(&self) -> Vec<u8> {
+        sha256_iter(
+            [
+                Some(self.old.public_key_bytes()),
+                Some(self.new.public_key_bytes()),
+                Some(self.created.to_string().into_bytes()),
+                self.recovery.as_ref().map(|key| key.public_key_bytes()),
+            ]
+            .into_iter(),
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use warp::{crypto::DID, error::Error};
+
+    use super::RotationDocument;
+
+    #[test]
+    fn rotate_with_previous_key() -> Result<(), Error> {
+        let old = DID::default();
+        let new = DID::default();
+
+        let statement = RotationDocument::new(&old, &new)?;
+        assert!(statement.verify(None).is_err());
+
+        let statement = statement.accept(&new)?;
+        statement.verify(None)?;
+
+        let statement = RotationDocument::from_bytes(&statement.to_bytes()?)?;
+        statement.verify(None)?;
+
+        let mut tampered = statement.clone();
+        tampered.new = DID::default();
+        assert!(tampered.verify(None).is_err());
+
+        assert!(RotationDocument::new(&old, &old).is_err());
+        assert!(RotationDocument::new(&old, &new)?
+            .accept(&DID::default())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rotate_with_recovery_key() -> Result<(), Error> {
+        let old = DID::default();
+        let recovery = DID::default();
+        let new = DID::default();
+
+        let statement = RotationDocument::new_with_recovery(&old, &recovery, &new)?.accept(&new)?;
+        assert_eq!(statement.issuer(), &recovery);
+
+        statement.verify(Some(&recovery))?;
+        assert!(matches!(
+            statement.verify(None),
+            Err(Error::RecoveryKeyNotSet)
+        ));
+        assert!(statement.verify(Some(&DID::default())).is_err());
+
+        // a key that was not committed to cannot issue the statement
+        let forged =
+            RotationDocument::new_with_recovery(&old, &DID::default(), &new)?.accept(&new)?;
+        assert!(forged.verify(Some(&recovery)).is_err());
+
+        // the statement of the recovery key takes precedence over the one of the previous key
+        let previous = RotationDocument::new(&old, &DID::default())?;
+        assert!(statement.supersedes(&previous));
+        assert!(!previous.supersedes(&statement));
+        assert!(!statement.supersedes(&statement));
+        Ok(())
+    }
+}
@@ -1,14 +1,21 @@
+use chrono::{DateTime, Utc};
 use libipld::Cid;
-use rust_ipfs::{libp2p::StreamProtocol, Keypair};
+use rust_ipfs::{libp2p::StreamProtocol, Keypair, PeerId};
 use serde::{Deserialize, Serialize};
 use warp::{crypto::DID, multipass::identity::ShortId};
 
-use crate::PayloadRequest;
+use crate::{gateway::PublishedDocument, PayloadRequest};
 
-use super::{document::IdentityDocument, RequestPayload};
+use super::{
+    document::{IdentityDocument, RotationDocument},
+    RequestPayload,
+};
 
 pub const PROTOCOL: StreamProtocol = StreamProtocol::new("/shuttle/identity/0.0.1");
 
+/// Seconds a request to unregister an identity remains valid, limiting the window in which it could be replayed
+pub const UNREGISTER_VALIDITY: i64 = 300;
+
 pub fn payload_message_construct(
     keypair: &Keypair,
     cosigner: Option<&Keypair>,
@@ -45,6 +52,8 @@ pub enum Request {
     Mailbox(Mailbox),
     Synchronized(Synchronized),
     Lookup(Lookup),
+    Publish(Publish),
+    Rotation(Rotation),
 }
 
 impl From<Register> for Request {
@@ -71,6 +80,18 @@ impl From<Lookup> for Request {
     }
 }
 
+impl From<Publish> for Request {
+    fn from(publish: Publish) -> Self {
+        Request::Publish(publish)
+    }
+}
+
+impl From<Rotation> for Request {
+    fn from(rotation: Rotation) -> Self {
+        Request::Rotation(rotation)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Response {
@@ -78,6 +99,8 @@ pub enum Response {
     SynchronizedResponse(SynchronizedResponse),
     MailboxResponse(MailboxResponse),
     LookupResponse(LookupResponse),
+    PublishResponse(PublishResponse),
+    RotationResponse(RotationResponse),
     Ack,
     Error(String),
 }
@@ -106,6 +129,18 @@ impl From<LookupResponse> for Response {
     }
 }
 
+impl From<PublishResponse> for Response {
+    fn from(res: PublishResponse) -> Self {
+        Response::PublishResponse(res)
+    }
+}
+
+impl From<RotationResponse> for Response {
+    fn from(res: RotationResponse) -> Self {
+        Response::RotationResponse(res)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Mailbox {
@@ -173,7 +208,25 @@ pub enum LookupResponse {
 #[serde(rename_all = "snake_case")]
 pub enum Register {
     IsRegistered,
-    RegisterIdentity { root_cid: Cid },
+    RegisterIdentity {
+        root_cid: Cid,
+    },
+    /// Unregister the identity from the shuttle node it is addressed to
+    Unregister {
+        shuttle: PeerId,
+    },
+}
+
+impl Register {
+    /// Whether a request to unregister, created at the given date, is addressed to the local node and recent
+    /// enough to not be a replay of a previous request
+    pub fn valid_unregister(&self, date: DateTime<Utc>, local: &PeerId) -> bool {
+        let Register::Unregister { shuttle } = self else {
+            return false;
+        };
+
+        shuttle.eq(local) && (Utc::now() - date).num_seconds().abs() <= UNREGISTER_VALIDITY
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -220,3 +273,77 @@ pub enum SynchronizedError {
     InvalidPayload { msg: String },
     InvalodRecord { msg: String },
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Publish {
+    File { document: PublishedDocument },
+    Remove { cid: Cid },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishResponse {
+    Ok,
+    Error(PublishError),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishError {
+    NotRegistered,
+    Unavailable,
+    Forbidden,
+    DoesntExist,
+    Invalid { msg: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    Publish { document: RotationDocument },
+    Fetch { dids: Vec<DID> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationResponse {
+    Ok,
+    Documents { list: Vec<RotationDocument> },
+    Error(RotationError),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationError {
+    NotRegistered,
+    Forbidden,
+    Rotated,
+    Invalid,
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use rust_ipfs::{Keypair, PeerId};
+
+    use super::{Register, UNREGISTER_VALIDITY};
+
+    #[test]
+    fn unregister_request_validity() {
+        let local = Keypair::generate_ed25519().public().to_peer_id();
+        let request = Register::Unregister { shuttle: local };
+
+        assert!(request.valid_unregister(Utc::now(), &local));
+
+        // the request cannot be replayed once it has expired or against another node
+        let expired = Utc::now() - Duration::seconds(UNREGISTER_VALIDITY + 60);
+        assert!(!request.valid_unregister(expired, &local));
+
+        let future = Utc::now() + Duration::seconds(UNREGISTER_VALIDITY + 60);
+        assert!(!request.valid_unregister(future, &local));
+
+        assert!(!request.valid_unregister(Utc::now(), &PeerId::random()));
+        assert!(!Register::IsRegistered.valid_unregister(Utc::now(), &local));
+    }
+}
@@ -1,6 +1,6 @@
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use std::path::PathBuf;
+use std::{net::SocketAddr, path::PathBuf};
 
 use base64::{
     alphabet::STANDARD,
@@ -54,6 +54,14 @@ struct Opt {
 
     #[clap(long)]
     enable_relay_server: bool,
+
+    /// Address for the http gateway serving published content. The gateway is disabled if not provided
+    #[clap(long)]
+    gateway_addr: Option<SocketAddr>,
+
+    /// Largest content, in bytes, that can be published to the gateway
+    #[clap(long)]
+    gateway_max_size: Option<u64>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -110,6 +118,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let local_peer_id = keypair.public().to_peer_id();
     println!("Local PeerID: {local_peer_id}");
 
+    let gateway = opts.gateway_addr.map(|addr| {
+        let mut config = shuttle::gateway::server::GatewayConfig::new(addr);
+        if let Some(max_size) = opts.gateway_max_size {
+            config.max_size = max_size;
+        }
+        config
+    });
+
     let _ = shuttle::server::ShuttleServer::new(
         &keypair,
         path,
@@ -117,6 +133,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         false,
         &opts.listen_addr,
         true,
+        gateway,
     )
     .await?;
 
@@ -4,6 +4,7 @@ use std::{
     time::Duration,
 };
 
+use chrono::{DateTime, Utc};
 use futures::{channel::oneshot, FutureExt, StreamExt};
 use libipld::Cid;
 use rust_ipfs::{
@@ -61,6 +62,7 @@ pub enum MessageCommand {
         recipients: Vec<DID>,
         message_id: Uuid,
         message_cid: Cid,
+        expire: Option<DateTime<Utc>>,
     },
     RemoveMessage {
         peer_id: PeerId,
@@ -315,6 +317,7 @@ impl NetworkBehaviour for Behaviour {
                                 recipients,
                                 message_id,
                                 message_cid,
+                                expire,
                             } => {
                                 tracing::info!("sending message to {peer_id}");
 
@@ -323,6 +326,7 @@ impl NetworkBehaviour for Behaviour {
                                     message_id,
                                     recipients,
                                     message_cid,
+                                    expire,
                                 });
 
                                 let payload = payload_message_construct(
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use chrono::{DateTime, Utc};
 use libipld::Cid;
 use rust_ipfs::{libp2p::StreamProtocol, Keypair};
 use serde::{Deserialize, Serialize};
@@ -69,6 +70,9 @@ pub enum MessageUpdate {
         message_id: Uuid,
         recipients: Vec<DID>,
         message_cid: Cid,
+        /// Date in which the message should be removed from the mailbox
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expire: Option<DateTime<Utc>>,
     },
     Delivered {
         conversation_id: Uuid,
@@ -19,12 +19,14 @@ use rust_ipfs::{
 use tokio::task::JoinHandle;
 
 use crate::{
+    gateway::server::{Gateway, GatewayConfig},
     identity::{
         self,
         document::IdentityDocument,
         protocol::{
-            payload_message_construct, Lookup, LookupResponse, Message, Register, RegisterResponse,
-            Response, Synchronized, SynchronizedError, SynchronizedResponse,
+            payload_message_construct, Lookup, LookupResponse, Message, Publish, PublishError,
+            PublishResponse, Register, RegisterResponse, Response, Rotation, RotationError,
+            RotationResponse, Synchronized, SynchronizedError, SynchronizedResponse,
         },
     },
     message::{
@@ -68,6 +70,7 @@ struct Behaviour {
 pub struct ShuttleServer {
     ipfs: Ipfs,
     task: JoinHandle<()>,
+    gateway_task: Option<JoinHandle<()>>,
 }
 
 #[allow(clippy::type_complexity)]
@@ -77,6 +80,9 @@ struct ShuttleTask {
     root_storage: crate::store::root::RootStorage,
     identity_storage: crate::store::identity::IdentityStorage,
     message_storage: crate::store::messages::MessageStorage,
+    published_storage: crate::store::published::PublishedStorage,
+    /// Largest content that can be published, if the gateway is enabled
+    max_publish_size: Option<u64>,
     subscriptions: crate::subscription_stream::Subscriptions,
     identity_rx: mpsc::Receiver<IdentityReceiver>,
     message_rx: mpsc::Receiver<MessageReceiver>,
@@ -92,6 +98,7 @@ impl ShuttleServer {
         memory_transport: bool,
         listen_addrs: &[Multiaddr],
         ext: bool,
+        gateway: Option<GatewayConfig>,
     ) -> anyhow::Result<Self> {
         let path = path.map(|p| p.as_ref().to_path_buf());
 
@@ -167,6 +174,18 @@ impl ShuttleServer {
         let identity = crate::store::identity::IdentityStorage::new(&ipfs, &root).await;
         let message =
             crate::store::messages::MessageStorage::new(&ipfs, &root, &identity, None).await;
+        let published = crate::store::published::PublishedStorage::new(&ipfs, &root).await;
+
+        let max_publish_size = gateway.as_ref().map(|config| config.max_size);
+
+        let gateway_task = gateway.map(|config| {
+            let gateway = Gateway::new(&ipfs, &identity, &published, config);
+            tokio::spawn(async move {
+                if let Err(e) = gateway.serve().await {
+                    tracing::error!(error = %e, "gateway stopped");
+                }
+            })
+        });
 
         println!(
             "Identities Registered: {}",
@@ -183,6 +202,8 @@ impl ShuttleServer {
             root_storage: root,
             identity_storage: identity,
             message_storage: message,
+            published_storage: published,
+            max_publish_size,
             identity_rx: id_event_rx,
             message_rx: msg_event_rx,
             precord_tx,
@@ -196,13 +217,30 @@ impl ShuttleServer {
             server_event.start().await;
         });
 
-        Ok(ShuttleServer { ipfs, task })
+        Ok(ShuttleServer {
+            ipfs,
+            task,
+            gateway_task,
+        })
     }
 
     pub async fn addresses(&self) -> impl Iterator<Item = Multiaddr> {
         let addresses = self.ipfs.external_addresses().await.unwrap_or_default();
         addresses.into_iter()
     }
+
+    pub async fn listening_addresses(&self) -> impl Iterator<Item = Multiaddr> {
+        let addresses = self.ipfs.listening_addresses().await.unwrap_or_default();
+        addresses.into_iter()
+    }
+}
+
+fn publish_error(error: WarpError) -> PublishError {
+    match error {
+        WarpError::SenderMismatch => PublishError::Forbidden,
+        WarpError::ObjectNotFound => PublishError::DoesntExist,
+        e => PublishError::Invalid { msg: e.to_string() },
+    }
 }
 
 impl ShuttleTask {
@@ -211,6 +249,8 @@ impl ShuttleTask {
         // TODO: Track long running task (or futures) and abort/terminate them if they exceed a specific (TBD) duration
         //       (i.e if we are pinning a file from a user, the duration can be ignored while if the user is updating their profile, it shouldnt exceed maybe 5min (though other factors may have to be taken into account))
 
+        let mut expiry_interval = tokio::time::interval(Duration::from_secs(30));
+
         loop {
             tokio::select! {
                 Some((id, ch, payload, resp)) = self.identity_rx.next() => {
@@ -220,6 +260,14 @@ impl ShuttleTask {
                     self.process_message_events(id, ch, payload, resp).await
                 }
                 _ = self.requests.next() => {}
+                _ = expiry_interval.tick() => {
+                    let message_storage = self.message_storage.clone();
+                    self.requests.push(async move {
+                        if let Err(e) = message_storage.remove_expired_messages().await {
+                            tracing::error!(error = %e, "unable to remove expired messages");
+                        }
+                    }.boxed());
+                }
             }
         }
     }
@@ -233,6 +281,9 @@ impl ShuttleTask {
     ) {
         let ipfs = self.ipfs.clone();
         let identity_storage = self.identity_storage.clone();
+        let message_storage = self.message_storage.clone();
+        let published_storage = self.published_storage.clone();
+        let max_publish_size = self.max_publish_size;
         let mut subscriptions = self.subscriptions.clone();
 
         let fut = async move {
@@ -290,6 +341,102 @@ impl ShuttleTask {
                             let _ = resp.send((ch, payload));
                         }
                     }
+                    identity::protocol::Request::Register(
+                        request @ Register::Unregister { .. },
+                    ) => {
+                        let peer_id = payload.sender();
+                        let Ok(did) = peer_id.to_did() else {
+                            tracing::warn!(%peer_id, "Could not convert to did key");
+                            let payload = payload_message_construct(
+                                keypair,
+                                None,
+                                Response::RegisterResponse(RegisterResponse::Error(
+                                    identity::protocol::RegisterError::IdentityVerificationFailed,
+                                )),
+                            )
+                            .expect("Valid payload construction");
+
+                            if let (Some(ch), Some(resp)) = (ch, resp) {
+                                let _ = resp.send((ch, payload));
+                            }
+
+                            return;
+                        };
+
+                        // the request has to be addressed to this node and recent so it cannot be replayed
+                        if !request.valid_unregister(payload.date(), &keypair.public().to_peer_id())
+                        {
+                            tracing::warn!(%did, date = %payload.date(), "Rejecting stale or misaddressed unregister request");
+                            let payload = payload_message_construct(
+                                keypair,
+                                None,
+                                Response::RegisterResponse(RegisterResponse::Error(
+                                    identity::protocol::RegisterError::IdentityVerificationFailed,
+                                )),
+                            )
+                            .expect("Valid payload construction");
+
+                            if let (Some(ch), Some(resp)) = (ch, resp) {
+                                let _ = resp.send((ch, payload));
+                            }
+
+                            return;
+                        }
+
+                        tracing::info!(%did, "Receive unregister request");
+
+                        if let Err(e) = identity_storage.remove(&did).await {
+                            tracing::warn!(%did, error = %e, "Unable to unregister identity");
+                            let res_error = match e {
+                                WarpError::IdentityDoesntExist => {
+                                    identity::protocol::RegisterError::NotRegistered
+                                }
+                                _ => identity::protocol::RegisterError::InternalError,
+                            };
+
+                            let payload = payload_message_construct(
+                                keypair,
+                                None,
+                                Response::RegisterResponse(RegisterResponse::Error(res_error)),
+                            )
+                            .expect("Valid payload construction");
+
+                            if let (Some(ch), Some(resp)) = (ch, resp) {
+                                let _ = resp.send((ch, payload));
+                            }
+
+                            return;
+                        }
+
+                        // the identity is already removed so any leftover would no longer be reachable by the user
+                        if let Err(e) = message_storage.remove_member(&did).await {
+                            tracing::warn!(%did, error = %e, "Unable to remove conversation mailboxes");
+                        }
+
+                        if let Err(e) = published_storage.remove_owner(&did).await {
+                            tracing::warn!(%did, error = %e, "Unable to remove published content");
+                        }
+
+                        if let Err(e) = subscriptions.unsubscribe(did.inbox()).await {
+                            tracing::warn!(%did, "Unable to unsubscribe from given topic: {e}. ignoring...");
+                        }
+
+                        if let Err(e) = subscriptions.unsubscribe(did.messaging()).await {
+                            tracing::warn!(%did, "Unable to unsubscribe from given topic: {e}. ignoring...");
+                        }
+
+                        tracing::info!(%did, "identity unregistered");
+                        let payload = payload_message_construct(
+                            keypair,
+                            None,
+                            Response::RegisterResponse(RegisterResponse::Ok),
+                        )
+                        .expect("Valid payload construction");
+
+                        if let (Some(ch), Some(resp)) = (ch, resp) {
+                            let _ = resp.send((ch, payload));
+                        }
+                    }
                     identity::protocol::Request::Register(Register::RegisterIdentity {
                         root_cid,
                     }) => {
@@ -848,6 +995,93 @@ impl ShuttleTask {
                         let payload = payload_message_construct(keypair, None, event)
                             .expect("Valid payload construction");
 
+                        if let (Some(ch), Some(resp)) = (ch, resp) {
+                            let _ = resp.send((ch, payload));
+                        }
+                    }
+                    identity::protocol::Request::Publish(publish) => {
+                        let peer_id = payload.sender();
+
+                        let result = match (peer_id.to_did(), max_publish_size) {
+                            (_, None) => Err(PublishError::Unavailable),
+                            (Err(_), _) => Err(PublishError::Forbidden),
+                            (Ok(did), Some(_)) if !identity_storage.contains(&did).await => {
+                                Err(PublishError::NotRegistered)
+                            }
+                            (Ok(did), Some(max_size)) => match publish {
+                                Publish::File { document } if document.owner != did => {
+                                    Err(PublishError::Forbidden)
+                                }
+                                Publish::File { document } => {
+                                    tracing::info!(%did, cid = %document.cid, "publishing content");
+                                    published_storage
+                                        .publish(document.clone(), max_size)
+                                        .await
+                                        .map_err(publish_error)
+                                }
+                                Publish::Remove { cid } => {
+                                    tracing::info!(%did, %cid, "removing published content");
+                                    published_storage
+                                        .unpublish(&did, cid)
+                                        .await
+                                        .map_err(publish_error)
+                                }
+                            },
+                        };
+
+                        if let Err(e) = &result {
+                            tracing::warn!(%peer_id, error = ?e, "unable to process publish request");
+                        }
+
+                        let event = Response::PublishResponse(match result {
+                            Ok(()) => PublishResponse::Ok,
+                            Err(e) => PublishResponse::Error(e),
+                        });
+
+                        let payload = payload_message_construct(keypair, None, event)
+                            .expect("Valid payload construction");
+
+                        if let (Some(ch), Some(resp)) = (ch, resp) {
+                            let _ = resp.send((ch, payload));
+                        }
+                    }
+                    identity::protocol::Request::Rotation(rotation) => {
+                        let peer_id = payload.sender();
+
+                        let response = match (peer_id.to_did(), rotation) {
+                            (Err(_), _) => RotationResponse::Error(RotationError::Forbidden),
+                            // Only the new identity is able to publish the statement
+                            (Ok(did), Rotation::Publish { document }) if document.new != did => {
+                                RotationResponse::Error(RotationError::Forbidden)
+                            }
+                            (Ok(did), Rotation::Publish { document }) => {
+                                tracing::info!(%did, old = %document.old, "Receive rotation statement");
+                                match identity_storage.rotate(document).await {
+                                    Ok(()) => RotationResponse::Ok,
+                                    Err(e) => {
+                                        tracing::warn!(%did, old = %document.old, error = %e, "Unable to store rotation statement");
+                                        RotationResponse::Error(match e {
+                                            WarpError::IdentityDoesntExist => {
+                                                RotationError::NotRegistered
+                                            }
+                                            WarpError::IdentityRotated => RotationError::Rotated,
+                                            _ => RotationError::Invalid,
+                                        })
+                                    }
+                                }
+                            }
+                            (Ok(_), Rotation::Fetch { dids }) => RotationResponse::Documents {
+                                list: identity_storage.rotations(dids).await,
+                            },
+                        };
+
+                        let payload = payload_message_construct(
+                            keypair,
+                            None,
+                            Response::RotationResponse(response),
+                        )
+                        .expect("Valid payload construction");
+
                         if let (Some(ch), Some(resp)) = (ch, resp) {
                             let _ = resp.send((ch, payload));
                         }
@@ -903,11 +1137,13 @@ impl ShuttleTask {
                             message_id,
                             recipients,
                             message_cid,
+                            expire,
                         } => {
                             let conversation_id = *conversation_id;
                             let message_id = *message_id;
                             let recipients = recipients.to_owned();
                             let message_cid = *message_cid;
+                            let expire = *expire;
 
                             tracing::info!(%conversation_id, %message_id, %did, "inserting message into mailbox");
                             if let Err(e) = message_storage
@@ -917,6 +1153,7 @@ impl ShuttleTask {
                                     conversation_id,
                                     message_id,
                                     message_cid,
+                                    expire,
                                 )
                                 .await
                             {
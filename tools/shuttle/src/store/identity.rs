@@ -10,7 +10,11 @@ use tokio::sync::RwLock;
 use warp::{crypto::DID, error::Error};
 
 use crate::{
-    identity::{document::IdentityDocument, protocol::Lookup, RequestPayload, RootDocument},
+    identity::{
+        document::{IdentityDocument, RotationDocument},
+        protocol::Lookup,
+        RequestPayload, RootDocument,
+    },
     DidExt,
 };
 
@@ -27,12 +31,14 @@ impl IdentityStorage {
 
         let users = root_dag.users;
         let mailbox = root_dag.mailbox;
+        let rotations = root_dag.rotations;
 
         let inner = Arc::new(RwLock::new(IdentityStorageInner {
             ipfs: ipfs.clone(),
             root: root.clone(),
             mailbox,
             users,
+            rotations,
         }));
 
         Self { inner }
@@ -78,20 +84,25 @@ impl IdentityStorage {
         inner.list().await
     }
 
-    // pub async fn remove(&self, did: &DID) -> Result<(), Error> {
-    //     let (tx, rx) = futures::channel::oneshot::channel();
+    /// Removes the identity, its root document and its mailbox, along with any request it has sent that
+    /// is still waiting in the mailbox of another identity
+    pub async fn remove(&self, did: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.remove(did).await
+    }
 
-    //     let _ = self
-    //         .tx
-    //         .clone()
-    //         .send(IdentityStorageCommand::Remove {
-    //             did: did.clone(),
-    //             response: tx,
-    //         })
-    //         .await;
+    /// Stores a statement rotating a registered identity to a new key. The statement is verified against
+    /// the recovery key the registered identity has committed to
+    pub async fn rotate(&self, document: &RotationDocument) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.rotate(document).await
+    }
 
-    //     rx.await.map_err(anyhow::Error::from)?
-    // }
+    /// Statements rotating any of the identities to a new key
+    pub async fn rotations(&self, dids: &[DID]) -> Vec<RotationDocument> {
+        let inner = &*self.inner.read().await;
+        inner.rotations(dids).await
+    }
 }
 
 //Note: Maybe migrate to using a map where the public key points to the cid of the identity document instead
@@ -100,6 +111,7 @@ struct IdentityStorageInner {
     ipfs: Ipfs,
     users: Option<Cid>,
     mailbox: Option<Cid>,
+    rotations: Option<Cid>,
     root: RootStorage,
 }
 
@@ -488,44 +500,350 @@ impl IdentityStorageInner {
         Ok(())
     }
 
-    // TODO: We should have the option for users to deregister their identity from shuttle
-    //       which would be an act of preserving privacy to those who wish to opt out
-    // async fn remove(&mut self, did: DID) -> Result<(), Error> {
-    //     let mut list: HashSet<IdentityDocument> = match self.list {
-    //         Some(cid) => self
-    //             .ipfs
-    //             .get_dag(cid)
-    //             .local()
-    //             .deserialized()
-    //             .await
-    //             .unwrap_or_default(),
-    //         None => {
-    //             return Err(Error::IdentityDoesntExist);
-    //         }
-    //     };
-
-    //     let old_document = list.iter().find(|document| document.did == did).cloned();
-
-    //     if old_document.is_none() {
-    //         return Err(Error::IdentityDoesntExist);
-    //     }
-
-    //     let document = old_document.expect("Exist");
-
-    //     if !list.remove(&document) {
-    //         return Err(Error::IdentityDoesntExist);
-    //     }
-
-    //     let cid = self.ipfs.dag().put().serialize(list)?.await?;
-
-    //     let old_cid = self.list.replace(cid);
-
-    //     if let Some(old_cid) = old_cid {
-    //         if old_cid != cid && self.ipfs.is_pinned(&old_cid).await? {
-    //             self.ipfs.remove_pin(&old_cid, false).await?;
-    //         }
-    //     }
-
-    //     Ok(())
-    // }
+    async fn remove(&mut self, did: &DID) -> Result<(), Error> {
+        if !self.contains(did).await {
+            return Err(Error::IdentityDoesntExist);
+        }
+
+        let mut list: BTreeMap<String, Cid> = match self.users {
+            Some(cid) => self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized()
+                .await
+                .unwrap_or_default(),
+            None => return Err(Error::IdentityDoesntExist),
+        };
+
+        let did_str = did.to_string();
+
+        let root_cid = list.remove(&did_str).ok_or(Error::IdentityDoesntExist)?;
+
+        let cid = self.ipfs.dag().put().serialize(list).pin(true).await?;
+
+        let old_cid = self.users.replace(cid);
+        if let Some(old_cid) = old_cid {
+            if old_cid != cid && self.ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                tracing::debug!(cid = %old_cid, "unpinning identity package block");
+                _ = self.ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+        self.root.set_user_documents(cid).await?;
+
+        if self.ipfs.is_pinned(&root_cid).await.unwrap_or_default() {
+            tracing::debug!(%did, cid = %root_cid, "unpinning root document");
+            _ = self.ipfs.remove_pin(&root_cid).recursive().await;
+        }
+
+        let Some(mailbox_cid) = self.mailbox else {
+            return Ok(());
+        };
+
+        let mut mailbox_list: BTreeMap<String, Cid> = self
+            .ipfs
+            .get_dag(mailbox_cid)
+            .local()
+            .deserialized()
+            .await
+            .unwrap_or_default();
+
+        let mut changed = mailbox_list.remove(&did_str).is_some();
+
+        // requests sent by the identity would otherwise be delivered after the account no longer exist
+        for cid in mailbox_list.values_mut() {
+            let Ok(mut mailbox) = self
+                .ipfs
+                .get_dag(*cid)
+                .local()
+                .deserialized::<Vec<RequestPayload>>()
+                .await
+            else {
+                continue;
+            };
+
+            let len = mailbox.len();
+            mailbox.retain(|request| request.sender.ne(did));
+
+            if mailbox.len() == len {
+                continue;
+            }
+
+            *cid = self.ipfs.dag().put().serialize(mailbox).await?;
+            changed = true;
+        }
+
+        if !changed {
+            return Ok(());
+        }
+
+        let cid = self
+            .ipfs
+            .dag()
+            .put()
+            .serialize(mailbox_list)
+            .pin(true)
+            .await?;
+
+        let old_cid = self.mailbox.replace(cid);
+
+        if let Some(old_cid) = old_cid {
+            if old_cid != cid && self.ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                tracing::debug!(cid = %old_cid, "unpinning identity mailbox block");
+                _ = self.ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+
+        self.root.set_mailbox(cid).await?;
+
+        Ok(())
+    }
+
+    async fn rotate(&mut self, document: &RotationDocument) -> Result<(), Error> {
+        let identity = self
+            .lookup(Lookup::PublicKey {
+                did: document.old.clone(),
+            })
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(Error::IdentityDoesntExist)?;
+
+        document.verify(identity.recovery.as_ref())?;
+
+        let mut list: BTreeMap<String, Cid> = match self.rotations {
+            Some(cid) => self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized()
+                .await
+                .unwrap_or_default(),
+            None => BTreeMap::new(),
+        };
+
+        let did_str = document.old.to_string();
+
+        if let Some(cid) = list.get(&did_str).copied() {
+            // a statement only replaces a previous one if it was issued by the recovery key
+            // while the previous one was issued by the previous key
+            let current = self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized::<RotationDocument>()
+                .await
+                .ok();
+
+            if !current.map_or(false, |current| document.supersedes(&current)) {
+                return Err(Error::IdentityRotated);
+            }
+        }
+
+        let document_cid = self.ipfs.dag().put().serialize(document).await?;
+
+        list.insert(did_str, document_cid);
+
+        let cid = self.ipfs.dag().put().serialize(list).pin(true).await?;
+
+        let old_cid = self.rotations.replace(cid);
+
+        if let Some(old_cid) = old_cid {
+            if old_cid != cid && self.ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                tracing::debug!(cid = %old_cid, "unpinning identity rotation block");
+                _ = self.ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+
+        self.root.set_rotations(cid).await?;
+
+        Ok(())
+    }
+
+    async fn rotations(&self, dids: &[DID]) -> Vec<RotationDocument> {
+        let Some(cid) = self.rotations else {
+            return vec![];
+        };
+
+        let list: BTreeMap<String, Cid> = self
+            .ipfs
+            .get_dag(cid)
+            .local()
+            .deserialized()
+            .await
+            .unwrap_or_default();
+
+        FuturesUnordered::from_iter(
+            dids.iter()
+                .filter_map(|did| list.get(&did.to_string()).copied())
+                .map(|cid| {
+                    let ipfs = self.ipfs.clone();
+                    async move {
+                        ipfs.get_dag(cid)
+                            .local()
+                            .deserialized::<RotationDocument>()
+                            .await
+                            .ok()
+                    }
+                }),
+        )
+        .filter_map(futures::future::ready)
+        .collect()
+        .await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use chrono::Utc;
+    use libipld::Cid;
+    use rust_ipfs::{Ipfs, UninitializedIpfs};
+    use warp::{
+        crypto::{did_key::CoreSign, Fingerprint, DID},
+        error::Error,
+        multipass::identity::SHORT_ID_SIZE,
+    };
+
+    use crate::{
+        identity::{
+            document::{IdentityDocument, RotationDocument},
+            RequestEvent, RequestPayload, RootDocument,
+        },
+        store::root::RootStorage,
+    };
+
+    use super::IdentityStorage;
+
+    /// Registers a new identity, returning it along with its root document
+    pub(crate) async fn register(
+        ipfs: &Ipfs,
+        storage: &IdentityStorage,
+        username: &str,
+    ) -> anyhow::Result<(DID, Cid)> {
+        register_with_recovery(ipfs, storage, username, None).await
+    }
+
+    /// Registers a new identity committed to the recovery key, returning it along with its root document
+    pub(crate) async fn register_with_recovery(
+        ipfs: &Ipfs,
+        storage: &IdentityStorage,
+        username: &str,
+        recovery: Option<&DID>,
+    ) -> anyhow::Result<(DID, Cid)> {
+        let did = DID::default();
+        let fingerprint = did.fingerprint();
+        let bytes = fingerprint.as_bytes();
+
+        let mut document = IdentityDocument {
+            username: username.into(),
+            short_id: bytes[bytes.len() - SHORT_ID_SIZE..].try_into()?,
+            did: did.clone(),
+            created: Utc::now(),
+            modified: Utc::now(),
+            status_message: None,
+            metadata: Default::default(),
+            version: Default::default(),
+            recovery: recovery.cloned(),
+            signature: None,
+        };
+
+        let signature = did.sign(&serde_json::to_vec(&document)?);
+        document.signature = Some(bs58::encode(signature).into_string());
+
+        let identity = ipfs.dag().put().serialize(document.clone()).await?;
+        let root = RootDocument {
+            identity,
+            created: Utc::now(),
+            modified: Utc::now(),
+        };
+        let root_cid = ipfs.dag().put().serialize(root).pin(true).await?;
+
+        storage.register(&document, root_cid).await?;
+
+        Ok((did, root_cid))
+    }
+
+    fn request(sender: &DID) -> anyhow::Result<RequestPayload> {
+        RequestPayload {
+            sender: sender.clone(),
+            event: RequestEvent::Request,
+            created: Utc::now(),
+            original_signature: vec![],
+            signature: vec![],
+        }
+        .sign(sender)
+        .map_err(|e| anyhow::anyhow!("{e}"))
+    }
+
+    #[tokio::test]
+    async fn remove_identity() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+        let root = RootStorage::new(&ipfs, None).await;
+        let storage = IdentityStorage::new(&ipfs, &root).await;
+
+        let (alice, alice_root) = register(&ipfs, &storage, "alice").await?;
+        let (bob, _) = register(&ipfs, &storage, "bobby").await?;
+
+        storage.deliver_request(&bob, &request(&alice)?).await?;
+        storage.deliver_request(&alice, &request(&bob)?).await?;
+
+        storage.remove(&alice).await?;
+
+        assert!(!storage.contains(&alice).await);
+        assert!(storage.contains(&bob).await);
+        assert!(!ipfs.is_pinned(&alice_root).await?);
+
+        // neither the mailbox of the identity nor the requests it sent remain
+        let (requests, remaining) = storage.fetch_mailbox(alice.clone()).await?;
+        assert!(requests.is_empty() && remaining == 0);
+        let (requests, remaining) = storage.fetch_mailbox(bob.clone()).await?;
+        assert!(requests.is_empty() && remaining == 0);
+
+        assert!(matches!(
+            storage.remove(&alice).await,
+            Err(Error::IdentityDoesntExist)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recovery_statement_supersedes_rotation() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+        let root = RootStorage::new(&ipfs, None).await;
+        let storage = IdentityStorage::new(&ipfs, &root).await;
+
+        let recovery = DID::default();
+        let (alice, _) = register_with_recovery(&ipfs, &storage, "alice", Some(&recovery)).await?;
+
+        // the previous key, possibly compromised, rotates the identity
+        let stolen = DID::default();
+        let statement = RotationDocument::new(&alice, &stolen)?.accept(&stolen)?;
+        storage.rotate(&statement).await?;
+
+        let next = DID::default();
+        let statement = RotationDocument::new(&alice, &next)?.accept(&next)?;
+        assert!(matches!(
+            storage.rotate(&statement).await,
+            Err(Error::IdentityRotated)
+        ));
+
+        // the owner takes the identity back using the recovery key
+        let new = DID::default();
+        let statement =
+            RotationDocument::new_with_recovery(&alice, &recovery, &new)?.accept(&new)?;
+        storage.rotate(&statement).await?;
+
+        let rotations = storage.rotations(&[alice.clone()]).await;
+        assert_eq!(rotations, vec![statement]);
+
+        // once issued by the recovery key, the statement is final
+        let other = DID::default();
+        let statement =
+            RotationDocument::new_with_recovery(&alice, &recovery, &other)?.accept(&other)?;
+        assert!(matches!(
+            storage.rotate(&statement).await,
+            Err(Error::IdentityRotated)
+        ));
+        Ok(())
+    }
 }
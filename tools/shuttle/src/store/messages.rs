@@ -2,6 +2,7 @@
 // about it being delivered. Messages that are new or updated will be inserted in the same manner
 use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
+use chrono::{DateTime, Utc};
 use futures::{stream, StreamExt};
 use libipld::Cid;
 use rust_ipfs::{Ipfs, IpfsPath};
@@ -23,6 +24,7 @@ struct MessageStorageInner {
     ipfs: Ipfs,
     _path: Option<PathBuf>,
     list: Option<Cid>,
+    expiry: Option<Cid>,
     identity: IdentityStorage,
     root: RootStorage,
 }
@@ -37,12 +39,14 @@ impl MessageStorage {
         let root_dag = root.get_root().await;
 
         let list = root_dag.conversation_mailbox;
+        let expiry = root_dag.message_expiry;
 
         let inner = Arc::new(RwLock::new(MessageStorageInner {
             ipfs: ipfs.clone(),
             root: root.clone(),
             identity: identity.clone(),
             list,
+            expiry,
             _path,
         }));
 
@@ -56,10 +60,18 @@ impl MessageStorage {
         conversation_id: Uuid,
         message_id: Uuid,
         message_cid: Cid,
+        expire: Option<DateTime<Utc>>,
     ) -> Result<(), Error> {
         let inner = &mut *self.inner.write().await;
         inner
-            .insert_or_update(member, recipients, conversation_id, message_id, message_cid)
+            .insert_or_update(
+                member,
+                recipients,
+                conversation_id,
+                message_id,
+                message_cid,
+                expire,
+            )
             .await
     }
 
@@ -95,6 +107,17 @@ impl MessageStorage {
             .message_delivered(member, conversation_id, message_id)
             .await
     }
+
+    pub async fn remove_expired_messages(&self) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.remove_expired_messages().await
+    }
+
+    /// Removes the mailbox of the member from every conversation
+    pub async fn remove_member(&self, member: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.remove_member(member).await
+    }
 }
 
 impl MessageStorageInner {
@@ -107,6 +130,7 @@ impl MessageStorageInner {
         conversation_id: Uuid,
         message_id: Uuid,
         message_cid: Cid,
+        expire: Option<DateTime<Utc>>,
     ) -> Result<(), Error> {
         let member_peer_id = member.to_peer_id()?;
 
@@ -219,9 +243,93 @@ impl MessageStorageInner {
 
         self.root.set_conversation_mailbox(root_cid).await?;
         tracing::info!(%conversation_id, %message_id, "message is stored in conversation mailbox");
+
+        if let Some(expire) = expire {
+            let mut expiry = self.expiry_list().await;
+            expiry
+                .entry(conversation_id.to_string())
+                .or_default()
+                .insert(message_id.to_string(), expire);
+            self.set_expiry_list(expiry).await?;
+        }
+
         Ok(())
     }
 
+    // note: <cid>/<conversation-id>/<message-id> will return the date in which the message expires
+    async fn expiry_list(&self) -> BTreeMap<String, BTreeMap<String, DateTime<Utc>>> {
+        match self.expiry {
+            Some(cid) => self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized()
+                .await
+                .unwrap_or_default(),
+            None => BTreeMap::new(),
+        }
+    }
+
+    async fn set_expiry_list(
+        &mut self,
+        list: BTreeMap<String, BTreeMap<String, DateTime<Utc>>>,
+    ) -> Result<(), Error> {
+        let root_cid = self.ipfs.dag().put().serialize(list).await?;
+
+        if !self.ipfs.is_pinned(&root_cid).await.unwrap_or_default() {
+            self.ipfs.insert_pin(&root_cid).recursive().local().await?;
+        }
+
+        let mut old_cid = self.expiry.replace(root_cid);
+
+        if let Some(cid) = old_cid.take() {
+            if cid != root_cid {
+                self.ipfs.remove_pin(&cid).recursive().await?;
+            }
+        }
+
+        self.root.set_message_expiry(root_cid).await?;
+
+        Ok(())
+    }
+
+    async fn remove_expired_messages(&mut self) -> Result<(), Error> {
+        let mut expiry = self.expiry_list().await;
+
+        let now = Utc::now();
+        let mut expired = vec![];
+
+        expiry.retain(|conversation_id, messages| {
+            messages.retain(|message_id, expire| {
+                if *expire > now {
+                    return true;
+                }
+                expired.push((conversation_id.clone(), message_id.clone()));
+                false
+            });
+            !messages.is_empty()
+        });
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        for (conversation_id, message_id) in expired {
+            let (Ok(conversation_id), Ok(message_id)) =
+                (conversation_id.parse(), message_id.parse())
+            else {
+                continue;
+            };
+
+            tracing::info!(%conversation_id, %message_id, "removing expired message from mailbox");
+            if let Err(e) = self.remove_message_entry(conversation_id, message_id).await {
+                tracing::debug!(%conversation_id, %message_id, error = %e, "unable to remove expired message");
+            }
+        }
+
+        self.set_expiry_list(expiry).await
+    }
+
     //TODO: Use to remove conversation specific mailbox depending on conversation type
     #[allow(dead_code)]
     async fn remove_mailbox(&mut self, creator: &DID, conversation_id: Uuid) -> Result<(), Error> {
@@ -261,6 +369,80 @@ impl MessageStorageInner {
         Ok(())
     }
 
+    async fn remove_member(&mut self, member: &DID) -> Result<(), Error> {
+        let mut list: BTreeMap<String, Cid> = match self.list {
+            Some(cid) => self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized()
+                .await
+                .unwrap_or_default(),
+            None => return Ok(()),
+        };
+
+        let member = member.to_string();
+        let mut changed = false;
+        let mut removed = vec![];
+
+        for (conversation_id, cid) in list.iter_mut() {
+            let Ok(mut conversation_mailbox) = self
+                .ipfs
+                .get_dag(*cid)
+                .local()
+                .deserialized::<BTreeMap<String, Cid>>()
+                .await
+            else {
+                continue;
+            };
+
+            if conversation_mailbox.remove(&member).is_none() {
+                continue;
+            }
+
+            changed = true;
+
+            if conversation_mailbox.is_empty() {
+                removed.push(conversation_id.clone());
+                continue;
+            }
+
+            *cid = self
+                .ipfs
+                .dag()
+                .put()
+                .serialize(conversation_mailbox)
+                .await?;
+        }
+
+        if !changed {
+            return Ok(());
+        }
+
+        for conversation_id in removed {
+            tracing::info!(%conversation_id, "removing empty conversation mailbox");
+            list.remove(&conversation_id);
+        }
+
+        let root_cid = self.ipfs.dag().put().serialize(list).await?;
+
+        if !self.ipfs.is_pinned(&root_cid).await.unwrap_or_default() {
+            self.ipfs.insert_pin(&root_cid).recursive().local().await?;
+        }
+
+        let mut old_cid = self.list.replace(root_cid);
+
+        if let Some(cid) = old_cid.take() {
+            if cid != root_cid {
+                self.ipfs.remove_pin(&cid).recursive().await?;
+            }
+        }
+
+        self.root.set_conversation_mailbox(root_cid).await?;
+
+        Ok(())
+    }
+
     async fn remove_message(
         &mut self,
         member: &DID,
@@ -271,6 +453,14 @@ impl MessageStorageInner {
             return Err(Error::IdentityDoesntExist);
         }
 
+        self.remove_message_entry(conversation_id, message_id).await
+    }
+
+    async fn remove_message_entry(
+        &mut self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), Error> {
         let mut list: BTreeMap<String, Cid> = match self.list {
             Some(cid) => self
                 .ipfs
@@ -361,13 +551,21 @@ impl MessageStorageInner {
             .sub_path(&conversation_id.to_string())?
             .sub_path(&member.to_string())?;
 
-        self.ipfs
+        let mut messages = self
+            .ipfs
             .get_dag(path)
             .local()
             .deserialized::<BTreeMap<String, Cid>>()
             .await
-            .map_err(anyhow::Error::from)
-            .map_err(Error::from)
+            .map_err(anyhow::Error::from)?;
+
+        // messages that expired, but have not been removed yet, should not be delivered
+        if let Some(expiry) = self.expiry_list().await.get(&conversation_id.to_string()) {
+            let now = Utc::now();
+            messages.retain(|id, _| !matches!(expiry.get(id), Some(expire) if *expire <= now));
+        }
+
+        Ok(messages)
     }
 
     async fn message_delivered(
@@ -451,3 +649,76 @@ impl MessageStorageInner {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use libipld::Cid;
+    use rust_ipfs::UninitializedIpfs;
+    use uuid::Uuid;
+
+    use crate::store::{
+        identity::{test::register, IdentityStorage},
+        root::RootStorage,
+    };
+
+    use super::MessageStorage;
+
+    #[tokio::test]
+    async fn remove_member_mailboxes() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+        let root = RootStorage::new(&ipfs, None).await;
+        let identity = IdentityStorage::new(&ipfs, &root).await;
+        let storage = MessageStorage::new(&ipfs, &root, &identity, None).await;
+
+        let (alice, _) = register(&ipfs, &identity, "alice").await?;
+        let (bob, _) = register(&ipfs, &identity, "bobby").await?;
+        let (carol, _) = register(&ipfs, &identity, "carol").await?;
+
+        let message = ipfs.dag().put().serialize("message").await?;
+
+        // a group conversation with both members, and a direct conversation with only one of them
+        let group = Uuid::new_v4();
+        let direct = Uuid::new_v4();
+        let recipients = vec![bob.clone(), carol.clone()];
+        storage
+            .insert_or_update(&alice, recipients, group, Uuid::new_v4(), message, None)
+            .await?;
+        storage
+            .insert_or_update(
+                &alice,
+                vec![bob.clone()],
+                direct,
+                Uuid::new_v4(),
+                message,
+                None,
+            )
+            .await?;
+
+        storage.remove_member(&bob).await?;
+
+        assert!(storage
+            .get_unsent_messages(bob.clone(), group)
+            .await
+            .is_err());
+        assert_eq!(
+            storage
+                .get_unsent_messages(carol.clone(), group)
+                .await?
+                .len(),
+            1
+        );
+
+        // conversations without any mailbox left are removed
+        let list = storage.inner.read().await.list.expect("list stored");
+        let list: BTreeMap<String, Cid> = ipfs.get_dag(list).local().deserialized().await?;
+        assert!(list.contains_key(&group.to_string()));
+        assert!(!list.contains_key(&direct.to_string()));
+
+        // members without a mailbox are ignored
+        storage.remove_member(&carol).await?;
+        storage.remove_member(&carol).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use libipld::Cid;
+use rust_ipfs::Ipfs;
+use tokio::sync::RwLock;
+use warp::{crypto::DID, error::Error};
+
+use crate::gateway::{is_unixfs, PublishedDocument, DAG_PB, RAW};
+
+use super::root::RootStorage;
+
+/// Longest time spent fetching the contents of a document being published
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone)]
+pub struct PublishedStorage {
+    inner: Arc<RwLock<PublishedStorageInner>>,
+}
+
+impl PublishedStorage {
+    pub async fn new(ipfs: &Ipfs, root: &RootStorage) -> Self {
+        let root_dag = root.get_root().await;
+
+        let inner = Arc::new(RwLock::new(PublishedStorageInner {
+            ipfs: ipfs.clone(),
+            root: root.clone(),
+            list: root_dag.published,
+        }));
+
+        Self { inner }
+    }
+
+    /// Stores the contents of the document on the node and makes them available through the gateway,
+    /// as long as they are no larger than `max_size`
+    pub async fn publish(&self, document: PublishedDocument, max_size: u64) -> Result<(), Error> {
+        if document.size > max_size {
+            return Err(Error::InvalidLength {
+                context: "published content".into(),
+                current: document.size as _,
+                minimum: None,
+                maximum: Some(max_size as _),
+            });
+        }
+
+        let ipfs = {
+            let inner = &*self.inner.read().await;
+            inner.check_owner(&document).await?;
+            inner.ipfs.clone()
+        };
+
+        // the contents are fetched without holding the lock, only reading up to the size of the document
+        fetch(&ipfs, &document).await?;
+
+        let inner = &mut *self.inner.write().await;
+        inner.publish(document).await
+    }
+
+    pub async fn unpublish(&self, owner: &DID, cid: &Cid) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.unpublish(owner, cid).await
+    }
+
+    /// Removes every document published by the owner
+    pub async fn remove_owner(&self, owner: &DID) -> Result<(), Error> {
+        let inner = &mut *self.inner.write().await;
+        inner.remove_owner(owner).await
+    }
+
+    pub async fn get(&self, cid: &Cid) -> Result<PublishedDocument, Error> {
+        let inner = &*self.inner.read().await;
+        inner.get(cid).await
+    }
+}
+
+#[derive(Debug)]
+struct PublishedStorageInner {
+    ipfs: Ipfs,
+    list: Option<Cid>,
+    root: RootStorage,
+}
+
+impl PublishedStorageInner {
+    async fn load(&self) -> BTreeMap<String, PublishedDocument> {
+        match self.list {
+            Some(cid) => self
+                .ipfs
+                .get_dag(cid)
+                .local()
+                .deserialized()
+                .await
+                .unwrap_or_default(),
+            None => BTreeMap::new(),
+        }
+    }
+
+    async fn check_owner(&self, document: &PublishedDocument) -> Result<(), Error> {
+        match self.load().await.get(&document.cid.to_string()) {
+            Some(existing) if existing.owner != document.owner => Err(Error::SenderMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Pins the contents of the document, which were fetched by [`fetch`], and adds it to the list
+    async fn publish(&mut self, document: PublishedDocument) -> Result<(), Error> {
+        self.check_owner(&document).await?;
+
+        self.ipfs
+            .insert_pin(&document.cid)
+            .recursive()
+            .local()
+            .await?;
+
+        let mut list = self.load().await;
+        list.insert(document.cid.to_string(), document);
+
+        self.save(list).await
+    }
+
+    async fn unpublish(&mut self, owner: &DID, cid: &Cid) -> Result<(), Error> {
+        let mut list = self.load().await;
+
+        let key = cid.to_string();
+
+        match list.get(&key) {
+            Some(document) if document.owner.eq(owner) => {}
+            Some(_) => return Err(Error::SenderMismatch),
+            None => return Err(Error::ObjectNotFound),
+        }
+
+        list.remove(&key);
+
+        self.save(list).await?;
+
+        if self.ipfs.is_pinned(cid).await.unwrap_or_default() {
+            tracing::debug!(%cid, "unpinning published content");
+            _ = self.ipfs.remove_pin(cid).recursive().await;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_owner(&mut self, owner: &DID) -> Result<(), Error> {
+        let (removed, list): (BTreeMap<_, _>, _) = self
+            .load()
+            .await
+            .into_iter()
+            .partition(|(_, document)| document.owner.eq(owner));
+
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        self.save(list).await?;
+
+        for document in removed.values() {
+            if self.ipfs.is_pinned(&document.cid).await.unwrap_or_default() {
+                tracing::debug!(cid = %document.cid, "unpinning published content");
+                _ = self.ipfs.remove_pin(&document.cid).recursive().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, cid: &Cid) -> Result<PublishedDocument, Error> {
+        self.load()
+            .await
+            .remove(&cid.to_string())
+            .ok_or(Error::ObjectNotFound)
+    }
+
+    async fn save(&mut self, list: BTreeMap<String, PublishedDocument>) -> Result<(), Error> {
+        let cid = self.ipfs.dag().put().serialize(list).pin(true).await?;
+
+        let old_cid = self.list.replace(cid);
+
+        if let Some(old_cid) = old_cid {
+            if old_cid != cid && self.ipfs.is_pinned(&old_cid).await.unwrap_or_default() {
+                tracing::debug!(cid = %old_cid, "unpinning published list block");
+                _ = self.ipfs.remove_pin(&old_cid).recursive().await;
+            }
+        }
+
+        self.root.set_published(cid).await?;
+
+        Ok(())
+    }
+}
+
+/// Fetches the contents of the document, confirming they are a unixfs file matching the size of the document.
+/// No more than the size of the document is read, and the fetch is abandoned after [`FETCH_TIMEOUT`]
+async fn fetch(ipfs: &Ipfs, document: &PublishedDocument) -> Result<(), Error> {
+    // the codec is checked before fetching anything, with the root checked once it was fetched
+    if !matches!(document.cid.codec(), DAG_PB | RAW) {
+        return Err(Error::InvalidFile);
+    }
+
+    let mut stream = ipfs
+        .cat_unixfs(document.cid)
+        .max_length(document.size as _)
+        .timeout(FETCH_TIMEOUT);
+
+    let mut size = 0;
+    while let Some(data) = stream.next().await {
+        match data {
+            Ok(data) => size += data.len() as u64,
+            Err(e) => return Err(Error::from(anyhow::anyhow!("{e}"))),
+        }
+    }
+
+    // the size is checked against the contents since it is served as the length of the content
+    if size != document.size || !is_unixfs(ipfs, &document.cid).await {
+        return Err(Error::InvalidFile);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use rust_ipfs::UninitializedIpfs;
+    use warp::{constellation::file::FileType, crypto::DID, error::Error};
+
+    use crate::{gateway::PublishedDocument, store::root::RootStorage};
+
+    use super::PublishedStorage;
+
+    #[tokio::test]
+    async fn publish_and_unpublish() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+        let root = RootStorage::new(&ipfs, None).await;
+        let storage = PublishedStorage::new(&ipfs, &root).await;
+
+        let contents = b"hello, world".to_vec();
+        let cid = ipfs
+            .add_unixfs(contents.clone())
+            .await?
+            .root()
+            .cid()
+            .copied()
+            .expect("valid cid");
+
+        // contents are pinned when added, while only publishing should keep them here
+        ipfs.remove_pin(&cid).recursive().await?;
+
+        let owner = DID::default();
+        let document = |owner: &DID, size: usize| {
+            PublishedDocument::new(
+                cid,
+                owner.clone(),
+                "hello.txt",
+                size as _,
+                FileType::Generic,
+            )
+        };
+
+        // the size has to match the contents that are served
+        assert!(storage
+            .publish(document(&owner, contents.len() - 1), 1024)
+            .await
+            .is_err());
+        assert!(matches!(
+            storage.publish(document(&owner, contents.len()), 8).await,
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(storage.get(&cid).await.is_err());
+
+        storage
+            .publish(document(&owner, contents.len()), 1024)
+            .await?;
+        assert_eq!(storage.get(&cid).await?.owner, owner);
+        assert!(ipfs.is_pinned(&cid).await?);
+
+        let other = DID::default();
+        assert!(matches!(
+            storage
+                .publish(document(&other, contents.len()), 1024)
+                .await,
+            Err(Error::SenderMismatch)
+        ));
+        assert!(matches!(
+            storage.unpublish(&other, &cid).await,
+            Err(Error::SenderMismatch)
+        ));
+
+        storage.unpublish(&owner, &cid).await?;
+        assert!(storage.get(&cid).await.is_err());
+        assert!(!ipfs.is_pinned(&cid).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_unixfs_published() -> anyhow::Result<()> {
+        let ipfs = UninitializedIpfs::new().start().await?;
+        let root = RootStorage::new(&ipfs, None).await;
+        let storage = PublishedStorage::new(&ipfs, &root).await;
+
+        let cid = ipfs.dag().put().serialize("message").await?;
+        let document = PublishedDocument::new(cid, DID::default(), "message", 9, FileType::Generic);
+
+        assert!(matches!(
+            storage.publish(document, 1024).await,
+            Err(Error::InvalidFile)
+        ));
+        assert!(!ipfs.is_pinned(&cid).await?);
+        Ok(())
+    }
+}
@@ -15,6 +15,12 @@ pub struct Root {
     pub mailbox: Option<Cid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub conversation_mailbox: Option<Cid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_expiry: Option<Cid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<Cid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotations: Option<Cid>,
 }
 
 #[derive(Debug)]
@@ -81,6 +87,21 @@ impl RootStorage {
         inner.set_conversation_mailbox(&self.ipfs, cid).await
     }
 
+    pub async fn set_message_expiry(&self, cid: Cid) -> Result<(), Error> {
+        let inner: &mut RootInner = &mut *self.inner.write().await;
+        inner.set_message_expiry(&self.ipfs, cid).await
+    }
+
+    pub async fn set_published(&self, cid: Cid) -> Result<(), Error> {
+        let inner: &mut RootInner = &mut *self.inner.write().await;
+        inner.set_published(&self.ipfs, cid).await
+    }
+
+    pub async fn set_rotations(&self, cid: Cid) -> Result<(), Error> {
+        let inner: &mut RootInner = &mut *self.inner.write().await;
+        inner.set_rotations(&self.ipfs, cid).await
+    }
+
     pub async fn get_root(&self) -> Root {
         let inner = &*self.inner.read().await;
         inner.root
@@ -111,6 +132,27 @@ impl RootInner {
         Ok(())
     }
 
+    async fn set_message_expiry(&mut self, ipfs: &Ipfs, cid: Cid) -> Result<(), Error> {
+        self.root.message_expiry.replace(cid);
+        tracing::debug!(%cid, "message expiry set");
+        self.save(ipfs).await?;
+        Ok(())
+    }
+
+    async fn set_published(&mut self, ipfs: &Ipfs, cid: Cid) -> Result<(), Error> {
+        self.root.published.replace(cid);
+        tracing::debug!(%cid, "published content set");
+        self.save(ipfs).await?;
+        Ok(())
+    }
+
+    async fn set_rotations(&mut self, ipfs: &Ipfs, cid: Cid) -> Result<(), Error> {
+        self.root.rotations.replace(cid);
+        tracing::debug!(%cid, "identity rotations set");
+        self.save(ipfs).await?;
+        Ok(())
+    }
+
     async fn save(&mut self, ipfs: &Ipfs) -> std::io::Result<()> {
         //TODO: Reenable ipns
         // self.ipfs
@@ -0,0 +1,708 @@
+//! WebDAV server exposing a [`Constellation`] filesystem, allowing ordinary applications to open
+//! the files within it.
+//!
+//! The server only listens on loopback addresses. Every request has to carry the token of the session,
+//! either as a bearer token or as the password of basic authentication, with any username.
+
+mod xml;
+
+use std::{
+    io::{self, ErrorKind},
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{
+        header::{
+            ALLOW, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, LAST_MODIFIED, WWW_AUTHENTICATE,
+        },
+        HeaderMap, StatusCode, Uri,
+    },
+    response::Response,
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{channel::oneshot, future, stream, StreamExt};
+use percent_encoding::percent_decode_str;
+use rand::RngCore;
+use tokio::net::TcpListener;
+use warp::{
+    constellation::{item::Item, Constellation, Progression, TransferKind},
+    error::Error,
+};
+
+const METHODS: &str = "OPTIONS, PROPFIND, GET, HEAD, PUT, MKCOL, MOVE, DELETE";
+
+#[derive(Clone)]
+pub struct WebDav {
+    constellation: Box<dyn Constellation>,
+    token: Arc<str>,
+}
+
+impl WebDav {
+    /// Creates a server for the filesystem with a new random token for the session
+    pub fn new(constellation: Box<dyn Constellation>) -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self::with_token(constellation, hex::encode(bytes))
+    }
+
+    pub fn with_token(constellation: Box<dyn Constellation>, token: impl Into<String>) -> Self {
+        Self {
+            constellation,
+            token: Arc::from(token.into()),
+        }
+    }
+
+    /// Token clients have to authenticate with
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new().fallback(handle).with_state(self.clone())
+    }
+
+    /// Serves the filesystem until the listener fails. The listener must be bound to a loopback address
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        if !listener.local_addr()?.ip().is_loopback() {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                "WebDAV server can only listen on a loopback address",
+            ));
+        }
+
+        axum::serve(listener, self.router()).await
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(value) = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return false;
+        };
+
+        let token = match value.split_once(' ') {
+            Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.to_string(),
+            Some((scheme, credentials)) if scheme.eq_ignore_ascii_case("basic") => {
+                let Some(credentials) = STANDARD
+                    .decode(credentials.trim())
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                else {
+                    return false;
+                };
+                match credentials.split_once(':') {
+                    Some((_, password)) => password.to_string(),
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+
+        constant_time_eq(token.trim().as_bytes(), self.token.as_bytes())
+    }
+
+    fn item(&self, path: &str) -> Result<Item, Error> {
+        let root = self.constellation.root_directory();
+        if path.is_empty() {
+            return Ok(Item::new_directory(root));
+        }
+
+        let item = root.get_item_by_path(path)?;
+
+        // the lookup stops at a file, even if the path continues past it
+        if split(path).1 != item.name() {
+            return Err(Error::InvalidItem);
+        }
+
+        Ok(item)
+    }
+
+    fn is_directory(&self, path: &str) -> bool {
+        matches!(self.item(path), Ok(Item::Directory(_)))
+    }
+
+    fn propfind(&self, path: &str, headers: &HeaderMap) -> Result<Response, Error> {
+        let item = self.item(path)?;
+
+        // an infinite depth is answered with the direct children, which clients handle by walking the tree
+        let depth = headers
+            .get("Depth")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("infinity");
+
+        let mut entries = vec![(path.to_string(), item.clone())];
+
+        if depth != "0" {
+            if let Item::Directory(directory) = &item {
+                entries.extend(
+                    directory
+                        .get_items()
+                        .into_iter()
+                        .map(|child| (join(path, &child.name()), child)),
+                );
+            }
+        }
+
+        response(
+            Response::builder()
+                .status(StatusCode::MULTI_STATUS)
+                .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+                .body(Body::from(xml::multistatus(&entries))),
+        )
+    }
+
+    async fn get(&self, path: &str, head: bool) -> Result<Response, Error> {
+        let file = self.item(path)?.get_file()?;
+
+        let builder = Response::builder()
+            .header(CONTENT_TYPE, xml::content_type(&file.file_type()))
+            .header(CONTENT_LENGTH, file.size())
+            .header(LAST_MODIFIED, xml::http_date(file.modified()));
+
+        if head {
+            return response(builder.body(Body::empty()));
+        }
+
+        let stream = self
+            .constellation
+            .get_stream(path)
+            .await?
+            .map(|result| result.map_err(|e| io::Error::new(ErrorKind::Other, e.to_string())));
+
+        response(builder.body(Body::from_stream(stream)))
+    }
+
+    async fn put(&mut self, path: &str, request: Request) -> Result<Response, Error> {
+        let existed = match self.item(path) {
+            Ok(Item::Directory(_)) => return Ok(status(StatusCode::METHOD_NOT_ALLOWED)),
+            Ok(Item::File(_)) => true,
+            Err(_) => false,
+        };
+
+        if !self.is_directory(parent(path)) {
+            return Ok(status(StatusCode::CONFLICT));
+        }
+
+        let size = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+
+        // the upload is stalled at the first error of the body and then aborted before the file is written,
+        // so an existing file is never replaced by a partial upload
+        let (failed_tx, mut failed_rx) = oneshot::channel::<()>();
+        let body = request.into_body().into_data_stream();
+        let stream = stream::unfold(
+            (body, Some(failed_tx)),
+            |(mut body, mut failed)| async move {
+                match body.next().await? {
+                    Ok(bytes) => Some((bytes.to_vec(), (body, failed))),
+                    Err(_) => {
+                        if let Some(tx) = failed.take() {
+                            _ = tx.send(());
+                        }
+                        future::pending().await
+                    }
+                }
+            },
+        )
+        .boxed();
+
+        let mut progress = self.constellation.put_stream(path, size, stream).await?;
+
+        loop {
+            tokio::select! {
+                progression = progress.next() => match progression {
+                    Some(Progression::CurrentProgress { .. }) => {}
+                    Some(Progression::ProgressComplete { .. }) | None => break,
+                    Some(Progression::ProgressFailed { error, .. }) => return Err(error),
+                },
+                Ok(()) = &mut failed_rx => {
+                    drop(progress);
+                    self.abort_upload(path).await;
+                    return Ok(status(StatusCode::BAD_REQUEST));
+                }
+            }
+        }
+
+        Ok(status(match existed {
+            true => StatusCode::NO_CONTENT,
+            false => StatusCode::CREATED,
+        }))
+    }
+
+    /// Cancels the transfer of an upload that was abandoned, discarding what was written
+    async fn abort_upload(&mut self, path: &str) {
+        let transfers = match self.constellation.list_transfers().await {
+            Ok(transfers) => transfers,
+            Err(e) => {
+                tracing::warn!(%path, error = %e, "Unable to list transfers");
+                return;
+            }
+        };
+
+        for transfer in transfers
+            .into_iter()
+            .filter(|transfer| transfer.kind() == TransferKind::Upload && transfer.name() == path)
+        {
+            if let Err(e) = self.constellation.cancel_transfer(transfer.id()).await {
+                tracing::warn!(%path, error = %e, "Unable to cancel upload");
+            }
+        }
+    }
+
+    async fn mkcol(&mut self, path: &str) -> Result<Response, Error> {
+        if self.item(path).is_ok() {
+            return Ok(status(StatusCode::METHOD_NOT_ALLOWED));
+        }
+
+        if !self.is_directory(parent(path)) {
+            return Ok(status(StatusCode::CONFLICT));
+        }
+
+        self.constellation.create_directory(path, true).await?;
+
+        Ok(status(StatusCode::CREATED))
+    }
+
+    async fn move_to(&mut self, path: &str, headers: &HeaderMap) -> Result<Response, Error> {
+        let Some(destination) = headers
+            .get("Destination")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Uri>().ok())
+            .and_then(|uri| decode_path(uri.path()).ok())
+        else {
+            return Ok(status(StatusCode::BAD_REQUEST));
+        };
+
+        let overwrite = headers
+            .get("Overwrite")
+            .map(|value| value.as_bytes() != b"F")
+            .unwrap_or(true);
+
+        // an item can't be replaced by one it contains
+        if path.is_empty()
+            || destination.is_empty()
+            || destination == path
+            || path.starts_with(&format!("{destination}/"))
+        {
+            return Ok(status(StatusCode::FORBIDDEN));
+        }
+
+        self.item(path)?;
+
+        if !self.is_directory(parent(&destination)) {
+            return Ok(status(StatusCode::CONFLICT));
+        }
+
+        let existed = self.item(&destination).is_ok();
+        if existed && !overwrite {
+            return Ok(status(StatusCode::PRECONDITION_FAILED));
+        }
+
+        // the destination is set aside rather than removed, so it is restored if the move fails
+        let (to, new_name) = split(&destination);
+        let displaced = match existed {
+            true => {
+                let mut bytes = [0u8; 8];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                let temporary = format!(".{new_name}.{}", hex::encode(bytes));
+                self.constellation.rename(&destination, &temporary).await?;
+                Some(join(to, &temporary))
+            }
+            false => None,
+        };
+
+        if let Err(e) = self.relocate(path, &destination).await {
+            if let Some(displaced) = &displaced {
+                if let Err(e) = self.constellation.rename(displaced, new_name).await {
+                    tracing::warn!(%displaced, error = %e, "Unable to restore destination");
+                }
+            }
+            return Err(e);
+        }
+
+        if let Some(displaced) = displaced {
+            self.constellation.remove(&displaced, true).await?;
+        }
+
+        Ok(status(match existed {
+            true => StatusCode::NO_CONTENT,
+            false => StatusCode::CREATED,
+        }))
+    }
+
+    /// Moves the item to the destination, which must not exist
+    async fn relocate(&mut self, path: &str, destination: &str) -> Result<(), Error> {
+        let (from, name) = split(path);
+        let (to, new_name) = split(destination);
+
+        if from != to {
+            let to = match to.is_empty() {
+                true => "/",
+                false => to,
+            };
+            self.constellation.move_item(path, to).await?;
+        }
+
+        if name != new_name {
+            self.constellation.rename(&join(to, name), new_name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&mut self, path: &str) -> Result<Response, Error> {
+        if path.is_empty() {
+            return Ok(status(StatusCode::FORBIDDEN));
+        }
+
+        self.item(path)?;
+        self.constellation.remove(path, true).await?;
+
+        Ok(status(StatusCode::NO_CONTENT))
+    }
+}
+
+async fn handle(State(mut dav): State<WebDav>, request: Request) -> Response {
+    if !dav.authorized(request.headers()) {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header(WWW_AUTHENTICATE, r#"Basic realm="warp""#)
+            .body(Body::empty())
+            .unwrap_or_default();
+    }
+
+    let Ok(path) = decode_path(request.uri().path()) else {
+        return status(StatusCode::BAD_REQUEST);
+    };
+
+    let result = match request.method().as_str() {
+        "OPTIONS" => response(
+            Response::builder()
+                .header("DAV", "1")
+                .header(ALLOW, METHODS)
+                .body(Body::empty()),
+        ),
+        "PROPFIND" => dav.propfind(&path, request.headers()),
+        "GET" => dav.get(&path, false).await,
+        "HEAD" => dav.get(&path, true).await,
+        "PUT" => dav.put(&path, request).await,
+        "MKCOL" => dav.mkcol(&path).await,
+        "MOVE" => dav.move_to(&path, request.headers()).await,
+        "DELETE" => dav.delete(&path).await,
+        _ => Ok(status(StatusCode::METHOD_NOT_ALLOWED)),
+    };
+
+    result.unwrap_or_else(|e| {
+        tracing::debug!(%path, error = %e, "Unable to complete request");
+        status(error_status(&e))
+    })
+}
+
+fn error_status(error: &Error) -> StatusCode {
+    match error {
+        Error::InvalidItem
+        | Error::InvalidFile
+        | Error::InvalidDirectory
+        | Error::FileNotFound
+        | Error::DirectoryNotFound
+        | Error::ObjectNotFound => StatusCode::NOT_FOUND,
+        Error::ItemNotFile | Error::ItemNotDirectory => StatusCode::METHOD_NOT_ALLOWED,
+        Error::DuplicateName
+        | Error::DirectoryExist
+        | Error::DirectoryNotEmpty
+        | Error::FileExist
+        | Error::DirParadox => StatusCode::CONFLICT,
+        Error::InvalidPath => StatusCode::BAD_REQUEST,
+        Error::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn response(response: Result<Response, axum::http::Error>) -> Result<Response, Error> {
+    response.map_err(anyhow::Error::from).map_err(Error::from)
+}
+
+fn status(status: StatusCode) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+/// Decodes the path of the url into a path of the filesystem, without leading or trailing slashes
+fn decode_path(path: &str) -> Result<String, Error> {
+    let path = percent_decode_str(path)
+        .decode_utf8()
+        .map_err(|_| Error::InvalidPath)?;
+
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>();
+
+    if segments.contains(&"..") {
+        return Err(Error::InvalidPath);
+    }
+
+    Ok(segments.join("/"))
+}
+
+fn parent(path: &str) -> &str {
+    split(path).0
+}
+
+fn split(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+fn join(parent: &str, name: &str) -> String {
+    match parent.is_empty() {
+        true => name.to_string(),
+        false => format!("{parent}/{name}"),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use axum::{
+        body::{to_bytes, Body, Bytes},
+        extract::Request,
+        http::{header::AUTHORIZATION, request::Builder, StatusCode},
+        response::Response,
+        Router,
+    };
+    use futures::stream;
+    use tower::ServiceExt;
+    use warp::{multipass::MultiPass, tesseract::Tesseract};
+    use warp_ipfs::WarpIpfsBuilder;
+
+    use super::{decode_path, split, WebDav};
+
+    async fn server() -> anyhow::Result<WebDav> {
+        let tesseract = Tesseract::default();
+        tesseract.unlock(b"internal pass")?;
+
+        let (mut account, _, constellation) = WarpIpfsBuilder::default()
+            .set_tesseract(tesseract)
+            .set_config(warp_ipfs::config::Config::minimal_testing())
+            .finalize()
+            .await;
+        account.create_identity(None, None).await?;
+
+        Ok(WebDav::with_token(constellation, "token"))
+    }
+
+    fn request(method: &str, path: &str) -> Builder {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .header(AUTHORIZATION, "Bearer token")
+    }
+
+    async fn send(router: &Router, request: Request) -> anyhow::Result<Response> {
+        Ok(router.clone().oneshot(request).await?)
+    }
+
+    async fn contents(router: &Router, path: &str) -> anyhow::Result<Vec<u8>> {
+        let response = send(router, request("GET", path).body(Body::empty())?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(to_bytes(response.into_body(), usize::MAX).await?.to_vec())
+    }
+
+    #[tokio::test]
+    async fn unauthorized_requests_rejected() -> anyhow::Result<()> {
+        let router = server().await?.router();
+
+        let response = send(&router, Request::builder().uri("/").body(Body::empty())?).await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(
+            &router,
+            Request::builder()
+                .uri("/")
+                .header(AUTHORIZATION, "Bearer other")
+                .body(Body::empty())?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn move_overwrites_destination() -> anyhow::Result<()> {
+        let router = server().await?.router();
+
+        let response = send(&router, request("MKCOL", "/notes").body(Body::empty())?).await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(
+            &router,
+            request("PUT", "/notes/todo.txt").body(Body::from("todo"))?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(
+            &router,
+            request("PUT", "/done.txt").body(Body::from("done"))?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(
+            &router,
+            request("MOVE", "/done.txt")
+                .header("Destination", "http://localhost/notes/todo.txt")
+                .header("Overwrite", "F")
+                .body(Body::empty())?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(contents(&router, "/notes/todo.txt").await?, b"todo");
+
+        let response = send(
+            &router,
+            request("MOVE", "/done.txt")
+                .header("Destination", "http://localhost/notes/todo.txt")
+                .header("Overwrite", "T")
+                .body(Body::empty())?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(contents(&router, "/notes/todo.txt").await?, b"done");
+
+        let response = send(&router, request("GET", "/done.txt").body(Body::empty())?).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_move_keeps_destination() -> anyhow::Result<()> {
+        let dav = server().await?;
+        let router = dav.router();
+
+        for path in ["/data", "/data/images"] {
+            let response = send(&router, request("MKCOL", path).body(Body::empty())?).await?;
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let response = send(
+            &router,
+            request("PUT", "/data/images/image.png").body(Body::from("image"))?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // a directory can't be moved into itself, so the destination it would replace is kept
+        let response = send(
+            &router,
+            request("MOVE", "/data")
+                .header("Destination", "http://localhost/data/images")
+                .body(Body::empty())?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(contents(&router, "/data/images/image.png").await?, b"image");
+
+        let data = dav
+            .constellation
+            .root_directory()
+            .get_item("data")?
+            .get_directory()?;
+        assert_eq!(data.get_items().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_upload_keeps_existing_file() -> anyhow::Result<()> {
+        let router = server().await?.router();
+
+        let response = send(
+            &router,
+            request("PUT", "/todo.txt").body(Body::from("todo"))?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = Body::from_stream(stream::iter([
+            Ok(Bytes::from_static(b"partial")),
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")),
+        ]));
+        let response = send(&router, request("PUT", "/todo.txt").body(body)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(contents(&router, "/todo.txt").await?, b"todo");
+
+        // nothing is left behind by an upload of a new file that failed
+        let body = Body::from_stream(stream::iter([
+            Ok(Bytes::from_static(b"partial")),
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")),
+        ]));
+        let response = send(&router, request("PUT", "/new.txt").body(body)?).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(&router, request("GET", "/new.txt").body(Body::empty())?).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_collection() -> anyhow::Result<()> {
+        let dav = server().await?;
+        let router = dav.router();
+
+        for path in ["/data", "/data/images"] {
+            let response = send(&router, request("MKCOL", path).body(Body::empty())?).await?;
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let response = send(
+            &router,
+            request("PUT", "/data/images/image.png").body(Body::from("image"))?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(&router, request("DELETE", "/data").body(Body::empty())?).await?;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = send(
+            &router,
+            request("GET", "/data/images/image.png").body(Body::empty())?,
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(!dav.constellation.root_directory().has_item("data"));
+        Ok(())
+    }
+
+    #[test]
+    fn decode_paths() {
+        assert_eq!(decode_path("/").unwrap(), "");
+        assert_eq!(decode_path("/notes/").unwrap(), "notes");
+        assert_eq!(
+            decode_path("/notes/my%20report.txt").unwrap(),
+            "notes/my report.txt"
+        );
+        assert_eq!(decode_path("//notes/./todo.txt").unwrap(), "notes/todo.txt");
+        assert!(decode_path("/notes/../../secret").is_err());
+    }
+
+    #[test]
+    fn split_paths() {
+        assert_eq!(split("todo.txt"), ("", "todo.txt"));
+        assert_eq!(split("notes/todo.txt"), ("notes", "todo.txt"));
+    }
+}
@@ -0,0 +1,86 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+
+use warp::constellation::Constellation;
+use warp::crypto::zeroize::Zeroizing;
+use warp::multipass::MultiPass;
+use warp::tesseract::Tesseract;
+use warp_ipfs::WarpIpfsBuilder;
+use webdav::WebDav;
+
+#[derive(Debug, Parser)]
+#[clap(name = "webdav")]
+struct Opt {
+    /// Path to directory
+    #[clap(long)]
+    path: PathBuf,
+
+    /// Name of the tesseract keystore
+    #[clap(long)]
+    keystore: Option<String>,
+
+    /// Password to unlock keystore
+    #[clap(long)]
+    password: Option<String>,
+
+    /// Loopback address to listen on
+    #[clap(long, default_value = "127.0.0.1:4918")]
+    listen_addr: SocketAddr,
+}
+
+async fn setup<P: AsRef<Path>>(
+    path: P,
+    keystore: Option<String>,
+    passphrase: Zeroizing<String>,
+) -> anyhow::Result<Box<dyn Constellation>> {
+    let path = path.as_ref();
+    let keystore_path = path.join(keystore.unwrap_or("tesseract_store".into()));
+
+    let tesseract = Tesseract::from_file(keystore_path)?;
+    tesseract.unlock(passphrase.as_bytes())?;
+
+    let config = warp_ipfs::config::Config::production(path);
+
+    let (identity, _, constellation) = WarpIpfsBuilder::default()
+        .set_tesseract(tesseract)
+        .set_config(config)
+        .finalize()
+        .await;
+
+    //validating that account exist
+    let _ = identity.get_own_identity().await?;
+    Ok(constellation)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let opt = Opt::parse();
+    //Just in case
+    if fdlimit::raise_fd_limit().is_none() {
+        //
+    }
+
+    let password = Zeroizing::new(match opt.password {
+        Some(password) => password,
+        None => rpassword::prompt_password("Enter A Password: ")?,
+    });
+
+    let constellation = setup(&opt.path, opt.keystore, password).await?;
+
+    let listener = TcpListener::bind(opt.listen_addr).await?;
+    let server = WebDav::new(constellation);
+
+    println!("Serving on http://{}", listener.local_addr()?);
+    println!("Session token: {}", server.token());
+
+    server.serve(listener).await?;
+    Ok(())
+}
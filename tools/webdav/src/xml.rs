@@ -0,0 +1,158 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use warp::constellation::{file::FileType, item::Item};
+
+/// Characters escaped within a segment of a path
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b']')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Renders the `multistatus` body answering a PROPFIND request for the items at their path
+pub fn multistatus(entries: &[(String, Item)]) -> String {
+    let mut body =
+        String::from(r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#);
+
+    for (path, item) in entries {
+        response(&mut body, path, item);
+    }
+
+    body.push_str("</D:multistatus>");
+    body
+}
+
+fn response(body: &mut String, path: &str, item: &Item) {
+    let collection = item.is_directory();
+
+    _ = write!(
+        body,
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>",
+        escape(&href(path, collection))
+    );
+    _ = write!(
+        body,
+        "<D:displayname>{}</D:displayname>",
+        escape(&item.name())
+    );
+    _ = write!(
+        body,
+        "<D:creationdate>{}</D:creationdate>",
+        item.creation().to_rfc3339()
+    );
+    _ = write!(
+        body,
+        "<D:getlastmodified>{}</D:getlastmodified>",
+        http_date(item.modified())
+    );
+
+    match item {
+        Item::Directory(_) => body.push_str("<D:resourcetype><D:collection/></D:resourcetype>"),
+        Item::File(file) => {
+            body.push_str("<D:resourcetype/>");
+            _ = write!(
+                body,
+                "<D:getcontentlength>{}</D:getcontentlength>",
+                file.size()
+            );
+            _ = write!(
+                body,
+                "<D:getcontenttype>{}</D:getcontenttype>",
+                escape(&content_type(&file.file_type()))
+            );
+        }
+    }
+
+    body.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>");
+}
+
+/// Url path of an item, with a trailing slash for collections
+pub fn href(path: &str, collection: bool) -> String {
+    let mut href = String::from("/");
+
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+        .collect::<Vec<_>>();
+
+    if !segments.is_empty() {
+        href.push_str(&segments.join("/"));
+        if collection {
+            href.push('/');
+        }
+    }
+
+    href
+}
+
+pub fn content_type(file_type: &FileType) -> String {
+    match file_type {
+        FileType::Mime(media) => media.to_string(),
+        FileType::Generic => "application/octet-stream".into(),
+    }
+}
+
+/// Formats the date as used by the `Last-Modified` header
+pub fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use warp::constellation::{directory::Directory, file::File, item::Item};
+
+    use super::{href, multistatus};
+
+    #[test]
+    fn href_of_items() {
+        assert_eq!(href("", true), "/");
+        assert_eq!(href("notes", true), "/notes/");
+        assert_eq!(href("notes/my report.txt", false), "/notes/my%20report.txt");
+        assert_eq!(href("a#b/c?d", false), "/a%23b/c%3Fd");
+    }
+
+    #[test]
+    fn multistatus_of_items() {
+        let directory = Directory::new("notes & more");
+        let file = File::new("todo.txt");
+        file.set_size(5);
+
+        let body = multistatus(&[
+            ("notes & more".into(), Item::new_directory(directory)),
+            ("notes & more/todo.txt".into(), Item::new_file(file)),
+        ]);
+
+        assert!(body.contains("<D:href>/notes%20&amp;%20more/</D:href>"));
+        assert!(body.contains("<D:displayname>notes &amp; more</D:displayname>"));
+        assert!(body.contains("<D:resourcetype><D:collection/></D:resourcetype>"));
+        assert!(body.contains("<D:href>/notes%20&amp;%20more/todo.txt</D:href>"));
+        assert!(body.contains("<D:getcontentlength>5</D:getcontentlength>"));
+    }
+}
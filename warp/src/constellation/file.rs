@@ -14,8 +14,6 @@ use super::item::FormatType;
 /// `FileType` describes all supported file types.
 /// This will be useful for applying icons to the tree later on
 /// if we don't have a supported file type, we can just default to generic.
-///
-/// TODO: Use mime to define the filetype
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, Display, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum FileType {
@@ -26,6 +24,27 @@ pub enum FileType {
     Mime(mediatype::MediaTypeBuf),
 }
 
+impl FileType {
+    /// Determines the type of the file from the start of its contents (see [`super::mime::detect`]),
+    /// using `fallback`, usually derived from the file extension, when the contents are not recognized
+    /// or only identify a container shared by several formats.
+    pub fn from_content(bytes: &[u8], fallback: FileType) -> FileType {
+        let Some(mime) = super::mime::detect(bytes) else {
+            return fallback;
+        };
+
+        if super::mime::is_container(mime) {
+            if let FileType::Mime(media) = &fallback {
+                if media.ty() == mediatype::names::APPLICATION {
+                    return fallback;
+                }
+            }
+        }
+
+        mime.parse().map(FileType::Mime).unwrap_or(fallback)
+    }
+}
+
 impl From<FileType> for FormatType {
     fn from(ty: FileType) -> Self {
         match ty {
@@ -79,6 +98,10 @@ pub struct File {
     /// External reference pointing to the source of the file
     reference: Arc<RwLock<Option<String>>>,
 
+    /// Prior revisions of the `File`, ordered from oldest to newest
+    #[serde(default)]
+    versions: Arc<RwLock<Vec<FileVersion>>>,
+
     /// Path to file
     #[serde(default)]
     path: Arc<String>,
@@ -135,6 +158,7 @@ impl Default for File {
             file_type: Default::default(),
             hash: Default::default(),
             reference: Default::default(),
+            versions: Default::default(),
             path: Arc::new("/".into()),
             signal: Arc::default(),
         }
@@ -320,6 +344,16 @@ impl File {
         self.file_type.read().clone()
     }
 
+    /// Prior revisions of the file, ordered from oldest to newest
+    pub fn versions(&self) -> Vec<FileVersion> {
+        self.versions.read().clone()
+    }
+
+    pub fn set_versions(&self, versions: Vec<FileVersion>) {
+        *self.versions.write() = versions;
+        self.signal();
+    }
+
     pub fn path(&self) -> &str {
         &self.path
     }
@@ -382,6 +416,77 @@ impl File {
     }
 }
 
+/// Prior revision of the contents of a `File`
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct FileVersion {
+    id: Uuid,
+    reference: String,
+    hash: Hash,
+    size: usize,
+    modified: DateTime<Utc>,
+}
+
+impl FileVersion {
+    pub fn new(reference: &str, hash: Hash, size: usize, modified: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            reference: reference.to_string(),
+            hash,
+            size,
+            modified,
+        }
+    }
+}
+
+impl FileVersion {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// External reference pointing to the contents of the revision
+    pub fn reference(&self) -> String {
+        self.reference.clone()
+    }
+
+    pub fn hash(&self) -> Hash {
+        self.hash.clone()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Timestamp of when the revision was the current contents of the `File`
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+}
+
+/// Versions of a `File` to remove
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PruneVersions {
+    /// Keep the given number of the most recent versions
+    KeepLast(usize),
+    /// Remove the versions that were replaced before the given timestamp
+    Before(DateTime<Utc>),
+}
+
+impl PruneVersions {
+    /// Splits the versions into those that are kept and those that are pruned
+    pub fn split(&self, versions: Vec<FileVersion>) -> (Vec<FileVersion>, Vec<FileVersion>) {
+        match *self {
+            PruneVersions::KeepLast(count) => {
+                let mut kept = versions;
+                let pruned = kept.drain(..kept.len().saturating_sub(count)).collect();
+                (kept, pruned)
+            }
+            PruneVersions::Before(date) => versions
+                .into_iter()
+                .partition(|version| version.modified >= date),
+        }
+    }
+}
+
 #[derive(Default, Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
 pub struct Hash {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -497,7 +602,30 @@ impl Hash {
 
 #[cfg(test)]
 mod test {
-    use super::File;
+    use chrono::{Duration, Utc};
+
+    use super::{File, FileType, FileVersion, Hash, PruneVersions};
+
+    #[test]
+    fn file_type_from_content() {
+        let png = FileType::Mime("image/png".parse().unwrap());
+        let jpeg = FileType::Mime("image/jpeg".parse().unwrap());
+        let docx = FileType::Mime(
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                .parse()
+                .unwrap(),
+        );
+
+        let content = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F'];
+        assert_eq!(FileType::from_content(&content, png.clone()), jpeg);
+        assert_eq!(FileType::from_content(&content, FileType::Generic), jpeg);
+        assert_eq!(FileType::from_content(b"plain text", png.clone()), png);
+        assert_eq!(FileType::from_content(b"PK\x03\x04", docx.clone()), docx);
+        assert_eq!(
+            FileType::from_content(b"PK\x03\x04", png),
+            FileType::Mime("application/zip".parse().unwrap())
+        );
+    }
 
     #[test]
     fn name_length() {
@@ -512,4 +640,31 @@ mod test {
         assert_eq!(long_file.name(), &long_name[..256]);
         assert_ne!(long_file.name(), &long_name[..255]);
     }
+
+    #[test]
+    fn prune_versions() {
+        let now = Utc::now();
+        let versions = (0..4)
+            .map(|i| {
+                FileVersion::new(
+                    &format!("ref{i}"),
+                    Hash::default(),
+                    i,
+                    now - Duration::days(4 - i as i64),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let (kept, pruned) = PruneVersions::KeepLast(1).split(versions.clone());
+        assert_eq!(kept, versions[3..]);
+        assert_eq!(pruned, versions[..3]);
+
+        let (kept, pruned) = PruneVersions::Before(now - Duration::days(2)).split(versions.clone());
+        assert_eq!(kept, versions[2..]);
+        assert_eq!(pruned, versions[..2]);
+
+        let (kept, pruned) = PruneVersions::KeepLast(10).split(versions.clone());
+        assert_eq!(kept, versions);
+        assert!(pruned.is_empty());
+    }
 }
@@ -0,0 +1,110 @@
+//! Detection of the media type of a file from the signature found at the start of its contents
+
+/// Number of bytes from the start of a file that is sufficient to detect its media type
+pub const HEADER_LENGTH: usize = 512;
+
+/// Detects the media type from the signature ("magic bytes") at the start of the contents
+///
+/// # Examples
+///
+/// ```
+/// use warp::constellation::mime::detect;
+///
+/// assert_eq!(detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+/// assert_eq!(detect(b"%PDF-1.7"), Some("application/pdf"));
+/// assert_eq!(detect(b"hello, world!"), None);
+/// ```
+pub fn detect(bytes: &[u8]) -> Option<&'static str> {
+    let mime = match bytes {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => "video/x-msvideo",
+        [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => "image/tiff",
+        [0x00, 0x00, 0x01, 0x00, ..] => "image/vnd.microsoft.icon",
+        [b'B', b'M', ..] if bytes.len() >= 14 => "image/bmp",
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => match brand {
+            [b'q', b't', b' ', b' ', ..] => "video/quicktime",
+            [b'M', b'4', b'A', b' ', ..] => "audio/mp4",
+            [b'h', b'e', b'i', b'c' | b'x', ..] | [b'm', b'i', b'f', b'1', ..] => "image/heic",
+            [b'a', b'v', b'i', b'f', ..] => "image/avif",
+            _ => "video/mp4",
+        },
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => match contains(bytes, b"webm") {
+            true => "video/webm",
+            false => "video/x-matroska",
+        },
+        [b'I', b'D', b'3', ..] | [0xFF, 0xFB | 0xF3 | 0xF2, ..] => "audio/mpeg",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        [b'%', b'P', b'D', b'F', b'-', ..] => "application/pdf",
+        [b'P', b'K', 0x03, 0x04, ..] => "application/zip",
+        [0x1F, 0x8B, ..] => "application/gzip",
+        [0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C, ..] => "application/x-7z-compressed",
+        [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1, ..] => "application/x-ole-storage",
+        _ if is_svg(bytes) => "image/svg+xml",
+        _ => return None,
+    };
+
+    Some(mime)
+}
+
+/// Returns true if the media type belongs to a container that is shared by several formats,
+/// such as `application/zip` for office documents, where the file extension is more specific
+pub fn is_container(mime: &str) -> bool {
+    matches!(mime, "application/zip" | "application/x-ole-storage")
+}
+
+fn is_svg(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(HEADER_LENGTH)]);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    (text.starts_with("<svg") || text.starts_with("<?xml")) && text.contains("<svg")
+}
+
+fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+    bytes[..bytes.len().min(HEADER_LENGTH)]
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect, is_container};
+
+    #[test]
+    fn detect_signatures() {
+        assert_eq!(
+            detect(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10]),
+            Some("image/jpeg")
+        );
+        assert_eq!(detect(b"GIF89a\x01\x00"), Some("image/gif"));
+        assert_eq!(detect(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(detect(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(detect(b"\0\0\0\x18ftypmp42\0\0\0\0"), Some("video/mp4"));
+        assert_eq!(
+            detect(b"\0\0\0\x14ftypqt  \0\0\0\0"),
+            Some("video/quicktime")
+        );
+        assert_eq!(
+            detect(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm"),
+            Some("video/webm")
+        );
+        assert_eq!(detect(b"ID3\x04\0\0"), Some("audio/mpeg"));
+        assert_eq!(detect(b"fLaC\0\0\0\x22"), Some("audio/flac"));
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(detect(b"PK\x03\x04\x14\0"), Some("application/zip"));
+        assert_eq!(detect(b"BM"), None);
+        assert_eq!(detect(&[]), None);
+    }
+
+    #[test]
+    fn containers() {
+        assert!(is_container("application/zip"));
+        assert!(!is_container("image/png"));
+    }
+}
@@ -2,18 +2,28 @@
 pub mod directory;
 pub mod file;
 pub mod item;
+pub mod mime;
+pub mod search;
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::crypto::DID;
 use crate::error::Error;
 use crate::{Extension, SingleHandle};
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
 
+use derive_more::Display;
 use directory::Directory;
 use dyn_clone::DynClone;
+use file::{FileVersion, PruneVersions};
 use futures::stream::BoxStream;
 use futures::Stream;
+use item::Item;
+use search::SearchResult;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub enum ConstellationEventKind {
@@ -29,10 +39,62 @@ pub enum ConstellationEventKind {
     Deleted {
         item_name: String,
     },
+    /// Item was restored from the trash
+    Restored {
+        item_name: String,
+    },
+    /// Item was permanently removed from the trash
+    Purged {
+        item_name: String,
+    },
+    /// Prior version of a file was restored as its current contents
+    VersionRestored {
+        item_name: String,
+        version: Uuid,
+    },
     Renamed {
         old_item_name: String,
         new_item_name: String,
     },
+    /// Item was moved into another directory
+    Moved {
+        item_name: String,
+        /// Path of the directory the item was moved from
+        from: String,
+        /// Path of the directory the item was moved into
+        to: String,
+    },
+    /// Item was shared with, or had its permission changed for, a recipient
+    Shared {
+        share_id: Uuid,
+        item_name: String,
+        recipient: DID,
+        permission: SharePermission,
+    },
+    /// Access of a recipient to a shared item was revoked
+    ShareRevoked {
+        share_id: Uuid,
+        item_name: String,
+        recipient: DID,
+    },
+    /// Item was shared by another identity
+    ShareReceived {
+        share_id: Uuid,
+        owner: DID,
+        item_name: String,
+    },
+    /// Item shared by another identity was updated by its owner
+    ShareUpdated {
+        share_id: Uuid,
+        owner: DID,
+        item_name: String,
+    },
+    /// Access to an item shared by another identity was revoked by its owner
+    ShareRemoved {
+        share_id: Uuid,
+        owner: DID,
+        item_name: String,
+    },
 }
 
 pub struct ConstellationEventStream(pub BoxStream<'static, ConstellationEventKind>);
@@ -80,6 +142,227 @@ pub enum Progression {
 
 pub type ConstellationProgressStream = BoxStream<'static, Progression>;
 
+pub type ConstellationSearchStream = BoxStream<'static, SearchResult>;
+
+/// Name of the virtual directory containing the items shared with the identity
+pub const SHARED_DIRECTORY: &str = "Shared with me";
+
+/// Access granted to a recipient of a shared item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum SharePermission {
+    /// Recipient is able to view and download the item
+    #[display(fmt = "read")]
+    Read,
+    /// Recipient is also able to upload files into the item, if it is a directory
+    #[display(fmt = "read-write")]
+    ReadWrite,
+}
+
+/// File or directory shared with other identities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Share {
+    id: Uuid,
+    owner: DID,
+    name: String,
+    path: Option<String>,
+    recipients: HashMap<DID, SharePermission>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+impl Share {
+    pub fn new(
+        id: Uuid,
+        owner: DID,
+        name: String,
+        recipients: HashMap<DID, SharePermission>,
+        created: DateTime<Utc>,
+        modified: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            owner,
+            name,
+            path: None,
+            recipients,
+            created,
+            modified,
+        }
+    }
+}
+
+impl Share {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn owner(&self) -> DID {
+        self.owner.clone()
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Path of the item within the filesystem. Only available to the owner of the share
+    pub fn path(&self) -> Option<String> {
+        self.path.clone()
+    }
+
+    pub fn recipients(&self) -> HashMap<DID, SharePermission> {
+        self.recipients.clone()
+    }
+
+    pub fn permission(&self, did: &DID) -> Option<SharePermission> {
+        self.recipients.get(did).copied()
+    }
+
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+}
+
+impl Share {
+    pub fn set_path(&mut self, path: Option<String>) {
+        self.path = path;
+    }
+}
+
+/// Item removed from the filesystem that is held within the trash until it is restored or purged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    id: Uuid,
+    item: Item,
+    path: String,
+    deleted: DateTime<Utc>,
+}
+
+impl TrashItem {
+    pub fn new(id: Uuid, item: Item, path: String, deleted: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            item,
+            path,
+            deleted,
+        }
+    }
+}
+
+impl TrashItem {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn item(&self) -> Item {
+        self.item.clone()
+    }
+
+    /// Path of the directory the item was removed from
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn deleted(&self) -> DateTime<Utc> {
+        self.deleted
+    }
+}
+
+/// Direction of a transfer between the filesystem and the local device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    /// Contents are being added to the filesystem
+    #[display(fmt = "upload")]
+    Upload,
+    /// Contents of a file are being written to a local path
+    #[display(fmt = "download")]
+    Download,
+}
+
+/// Transfer of a file that has not completed.
+///
+/// Uploads and downloads that are interrupted are able to resume from the last verified chunk
+/// by putting or getting the same file again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    id: Uuid,
+    kind: TransferKind,
+    name: String,
+    location: Option<String>,
+    current: usize,
+    total: Option<usize>,
+    started: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+impl Transfer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: Uuid,
+        kind: TransferKind,
+        name: String,
+        location: Option<String>,
+        current: usize,
+        total: Option<usize>,
+        started: DateTime<Utc>,
+        modified: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            kind,
+            name,
+            location,
+            current,
+            total,
+            started,
+            modified,
+        }
+    }
+}
+
+impl Transfer {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn kind(&self) -> TransferKind {
+        self.kind
+    }
+
+    /// Path of the file within the filesystem
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Local path the file is being written to or read from, if any
+    pub fn location(&self) -> Option<String> {
+        self.location.clone()
+    }
+
+    /// Amount of bytes that have been transferred and verified
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Total size of the file, if known
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    pub fn started(&self) -> DateTime<Utc> {
+        self.started
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+}
+
 /// Interface that would provide functionality around the filesystem.
 #[async_trait::async_trait]
 pub trait Constellation:
@@ -197,12 +480,14 @@ pub trait Constellation:
         Err(Error::Unimplemented)
     }
 
-    /// Used to remove data from the filesystem
+    /// Used to remove data from the filesystem.
+    /// A directory that is not empty is only removed, along with its contents, if `recursive` is true
     async fn remove(&mut self, _: &str, _: bool) -> Result<(), Error> {
         Err(Error::Unimplemented)
     }
 
-    /// Used to move data within the filesystem
+    /// Used to move a file or directory into another directory within the filesystem.
+    /// The destination is the path of the directory, with `/` being the root directory
     async fn move_item(&mut self, _: &str, _: &str) -> Result<(), Error> {
         Err(Error::Unimplemented)
     }
@@ -216,6 +501,113 @@ pub trait Constellation:
     async fn sync_ref(&mut self, _: &str) -> Result<(), Error> {
         Err(Error::Unimplemented)
     }
+
+    /// Share a file or directory with an identity, returning the id of the share.
+    /// Sharing the same item again with the identity updates its permission
+    async fn share(&mut self, _: &str, _: &DID, _: SharePermission) -> Result<Uuid, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Revoke the access of an identity to a shared file or directory.
+    /// Note: The contents of files are not encrypted. Revoking a share removes the item from the
+    ///       shared directory of the identity and stops further updates, but does not prevent
+    ///       access to contents the identity has already obtained the reference to
+    async fn revoke_share(&mut self, _: &str, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List items shared with other identities
+    async fn list_shares(&self) -> Result<Vec<Share>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List items shared by other identities
+    async fn shared_with_me(&self) -> Result<Vec<Share>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Virtual root directory, named [`SHARED_DIRECTORY`], containing the items shared by other identities.
+    /// Each item is held within a directory named after the id of its share
+    async fn shared_directory(&self) -> Result<Directory, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Used to download a file from the shared directory into a buffer.
+    /// The path starts with the id of the share, e.g. `<share id>/<item name>/<path within the item>`
+    async fn get_shared_buffer(&self, _: &str) -> Result<Vec<u8>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Used to upload a file into a directory from the shared directory with data from buffer.
+    /// The path starts with the id of the share, as for [`Constellation::get_shared_buffer`].
+    /// Requires [`SharePermission::ReadWrite`] on the shared directory
+    async fn put_shared_buffer(&mut self, _: &str, _: &[u8]) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List the items that were removed and are held within the trash
+    async fn list_trash(&self) -> Result<Vec<TrashItem>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Restore an item from the trash into the directory it was removed from
+    async fn restore(&mut self, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Permanently remove every item within the trash
+    async fn empty_trash(&mut self) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List the prior versions of a file, ordered from oldest to newest
+    async fn list_versions(&self, _: &str) -> Result<Vec<FileVersion>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Used to download a prior version of a file into a buffer
+    async fn get_version_buffer(&self, _: &str, _: Uuid) -> Result<Vec<u8>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Restore a prior version of a file as its current contents.
+    /// The contents being replaced are kept as the newest version
+    async fn restore_version(&mut self, _: &str, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Remove prior versions of a file
+    async fn prune_versions(&mut self, _: &str, _: PruneVersions) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List the uploads and downloads that have not completed
+    async fn list_transfers(&self) -> Result<Vec<Transfer>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Cancel a pending transfer, discarding the progress made
+    async fn cancel_transfer(&mut self, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Search the items within the filesystem, streaming back those that match the query along
+    /// with their paths. See [`search`] for the syntax of the query
+    async fn search(&self, _: &str) -> Result<ConstellationSearchStream, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Publish a file, making its contents available to anyone through the gateway of the nodes
+    /// storing the identity, under the reference of the file.
+    /// Note: Published contents are not encrypted
+    async fn publish(&mut self, _: &str) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Remove a file published through [`Constellation::publish`] from the gateway
+    async fn unpublish(&mut self, _: &str) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
 }
 
 dyn_clone::clone_trait_object!(Constellation);
@@ -0,0 +1,441 @@
+//! Query language used to search the items within the filesystem
+//!
+//! A query is made of clauses separated by whitespace, all of which have to match an item.
+//! Values containing whitespace can be wrapped in double quotes.
+//!
+//! | Clause                              | Matches                                                    |
+//! |-------------------------------------|------------------------------------------------------------|
+//! | `report`, `name:report`             | name containing the text                                   |
+//! | `*.png`, `name:img_??.jpg`          | name matching the glob, where `*` and `?` are wildcards    |
+//! | `type:image/png`, `type:image`      | media type of a file, or any subtype of the type           |
+//! | `size:>1mb`, `size:1kb..2mb`        | size within the range, with `b`, `kb`, `mb` and `gb` units |
+//! | `created:>=2024-01-01`              | creation within the range of dates                         |
+//! | `modified:2024-01-01..2024-02-01`   | modification within the range of dates                     |
+//! | `is:favorite`, `favorite:false`     | items marked, or not marked, as favorite                   |
+//! | `is:file`, `is:directory`           | kind of item                                               |
+//! | `desc:"quarterly numbers"`          | description containing the text                            |
+//!
+//! Ranges accept `<`, `<=`, `>`, `>=` or `start..end`, with `start` and `end` being inclusive.
+//! A single value matches exactly, or the entire day when it is a date.
+
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+use super::file::FileType;
+use super::item::Item;
+
+/// Parsed query to search the filesystem with. See the [module documentation](self) for the syntax
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    clauses: Vec<SearchClause>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchClause {
+    /// Name contains the text, ignoring case
+    Name(String),
+    /// Name matches the glob, ignoring case
+    NameGlob(String),
+    /// Media type of a file matches the essence, or the type alone
+    MediaType(String),
+    Size((Bound<usize>, Bound<usize>)),
+    Created((Bound<DateTime<Utc>>, Bound<DateTime<Utc>>)),
+    Modified((Bound<DateTime<Utc>>, Bound<DateTime<Utc>>)),
+    Favorite(bool),
+    File,
+    Directory,
+    /// Description contains the text, ignoring case
+    Description(String),
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Result<Self, Error> {
+        let clauses = split(query)?
+            .into_iter()
+            .map(|token| parse_clause(&token))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { clauses })
+    }
+
+    pub fn clauses(&self) -> &[SearchClause] {
+        &self.clauses
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Returns true if the item matches every clause of the query
+    pub fn matches(&self, item: &Item) -> bool {
+        self.clauses.iter().all(|clause| clause.matches(item))
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = Error;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        SearchQuery::parse(query)
+    }
+}
+
+impl SearchClause {
+    pub fn matches(&self, item: &Item) -> bool {
+        match self {
+            SearchClause::Name(text) => item.name().to_lowercase().contains(text),
+            SearchClause::NameGlob(pattern) => glob(pattern, &item.name().to_lowercase()),
+            SearchClause::MediaType(media) => match item.file().map(|file| file.file_type()) {
+                Some(FileType::Mime(mime)) => {
+                    let essence = mime.essence().to_string().to_lowercase();
+                    match media.contains('/') {
+                        true => glob(media, &essence),
+                        false => essence.split('/').next() == Some(media.as_str()),
+                    }
+                }
+                _ => false,
+            },
+            SearchClause::Size(range) => range.contains(&item.size()),
+            SearchClause::Created(range) => range.contains(&item.creation()),
+            SearchClause::Modified(range) => range.contains(&item.modified()),
+            SearchClause::Favorite(favorite) => item.favorite() == *favorite,
+            SearchClause::File => item.is_file(),
+            SearchClause::Directory => item.is_directory(),
+            SearchClause::Description(text) => item.description().to_lowercase().contains(text),
+        }
+    }
+}
+
+/// Item that matched a search along with its path from the root of the filesystem
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    path: String,
+    item: Item,
+}
+
+impl SearchResult {
+    pub fn new(path: String, item: Item) -> Self {
+        Self { path, item }
+    }
+}
+
+impl SearchResult {
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn item(&self) -> Item {
+        self.item.clone()
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidSearchQuery(reason.into())
+}
+
+/// Splits the query by whitespace, keeping text between double quotes together
+fn split(query: &str) -> Result<Vec<String>, Error> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(invalid("unterminated quote"));
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+fn parse_clause(token: &str) -> Result<SearchClause, Error> {
+    let (key, value) = match token.split_once(':') {
+        Some((key, value)) if !key.is_empty() => (key.to_lowercase(), value),
+        _ => return Ok(name_clause(token)),
+    };
+
+    if value.is_empty() {
+        return Err(invalid(format!("{key} requires a value")));
+    }
+
+    let clause = match key.as_str() {
+        "name" => name_clause(value),
+        "type" | "mime" => SearchClause::MediaType(value.to_lowercase()),
+        "size" => SearchClause::Size(parse_range(value, parse_size, |size| size.checked_add(1))?),
+        "created" => SearchClause::Created(parse_range(value, parse_date, next_day)?),
+        "modified" => SearchClause::Modified(parse_range(value, parse_date, next_day)?),
+        "desc" | "description" => SearchClause::Description(value.to_lowercase()),
+        "favorite" => match value.to_lowercase().as_str() {
+            "true" | "yes" => SearchClause::Favorite(true),
+            "false" | "no" => SearchClause::Favorite(false),
+            _ => return Err(invalid(format!("{value} is not a boolean"))),
+        },
+        "is" => match value.to_lowercase().as_str() {
+            "favorite" => SearchClause::Favorite(true),
+            "file" => SearchClause::File,
+            "directory" | "dir" => SearchClause::Directory,
+            _ => return Err(invalid(format!("unknown kind {value}"))),
+        },
+        _ => return Err(invalid(format!("unknown field {key}"))),
+    };
+
+    Ok(clause)
+}
+
+fn name_clause(value: &str) -> SearchClause {
+    let value = value.to_lowercase();
+    match value.contains(['*', '?']) {
+        true => SearchClause::NameGlob(value),
+        false => SearchClause::Name(value),
+    }
+}
+
+/// Parses a range of values. `end` returns the first value after the one parsed, which is used to
+/// treat a single value, or the end of the range, as inclusive of the entire span it represents.
+/// `end` returns `None` if there is no value after the one parsed
+fn parse_range<T: Copy + PartialOrd>(
+    value: &str,
+    parse: impl Fn(&str) -> Result<T, Error>,
+    end: impl Fn(T) -> Option<T>,
+) -> Result<(Bound<T>, Bound<T>), Error> {
+    // Bound excluding everything after the span of the value
+    let until = |value: T| match end(value) {
+        Some(end) => Bound::Excluded(end),
+        None => Bound::Included(value),
+    };
+
+    // Bound excluding the span of the value and everything before it
+    let after = |value: T| match end(value) {
+        Some(end) => Bound::Included(end),
+        None => Bound::Excluded(value),
+    };
+
+    let range = if let Some(value) = value.strip_prefix(">=") {
+        (Bound::Included(parse(value)?), Bound::Unbounded)
+    } else if let Some(value) = value.strip_prefix("<=") {
+        (Bound::Unbounded, until(parse(value)?))
+    } else if let Some(value) = value.strip_prefix('>') {
+        (after(parse(value)?), Bound::Unbounded)
+    } else if let Some(value) = value.strip_prefix('<') {
+        (Bound::Unbounded, Bound::Excluded(parse(value)?))
+    } else if let Some((start, stop)) = value.split_once("..") {
+        let start = parse(start)?;
+        let stop = parse(stop)?;
+        if start > stop {
+            return Err(invalid(format!("{value} is an empty range")));
+        }
+        (Bound::Included(start), until(stop))
+    } else {
+        let value = parse(value)?;
+        (Bound::Included(value), until(value))
+    };
+
+    Ok(range)
+}
+
+fn parse_size(value: &str) -> Result<usize, Error> {
+    let value = value.trim().to_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let multiplier = match unit {
+        "" | "b" => 1,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => return Err(invalid(format!("unknown unit {unit}"))),
+    };
+
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| invalid(format!("{value} is not a size")))?;
+
+    Ok((number * multiplier as f64) as usize)
+}
+
+fn parse_date(value: &str) -> Result<DateTime<Utc>, Error> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| Utc.from_utc_datetime(&date))
+        .ok_or_else(|| invalid(format!("{value} is not a date")))
+}
+
+fn next_day(date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    date.checked_add_signed(Duration::days(1))
+}
+
+/// Matches the text against the pattern, where `*` matches any sequence and `?` any single character
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` consume one more character
+                Some((star, position)) => {
+                    p = star + 1;
+                    t = position + 1;
+                    backtrack = Some((star, position + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Bound;
+
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::constellation::{
+        directory::Directory,
+        file::{File, FileType},
+        item::Item,
+    };
+
+    use super::{glob, SearchClause, SearchQuery};
+
+    fn file(name: &str, mime: &str, size: usize) -> Item {
+        let file = File::new(name);
+        file.set_file_type(FileType::Mime(mime.parse().unwrap()));
+        file.set_size(size);
+        Item::new_file(file)
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob("*.png", "image.png"));
+        assert!(glob("img_??.jpg", "img_01.jpg"));
+        assert!(!glob("img_??.jpg", "img_1.jpg"));
+        assert!(glob("*report*", "q1 report final.pdf"));
+        assert!(!glob("*.png", "image.png.txt"));
+        assert!(glob("*", ""));
+    }
+
+    #[test]
+    fn parse_clauses() {
+        let query = SearchQuery::parse(
+            r#"Report name:*.PDF size:1kb..2mb created:>=2024-01-01 is:favorite desc:"q1 numbers""#,
+        )
+        .unwrap();
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            query.clauses(),
+            &[
+                SearchClause::Name("report".into()),
+                SearchClause::NameGlob("*.pdf".into()),
+                SearchClause::Size((Bound::Included(1024), Bound::Excluded(2 * 1024 * 1024 + 1))),
+                SearchClause::Created((Bound::Included(start), Bound::Unbounded)),
+                SearchClause::Favorite(true),
+                SearchClause::Description("q1 numbers".into()),
+            ]
+        );
+
+        let query = SearchQuery::parse("modified:2024-01-01").unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[SearchClause::Modified((
+                Bound::Included(start),
+                Bound::Excluded(start + Duration::days(1))
+            ))]
+        );
+
+        assert!(SearchQuery::parse("size:>lots").is_err());
+        assert!(SearchQuery::parse("color:red").is_err());
+        assert!(SearchQuery::parse("desc:\"unterminated").is_err());
+        assert!(SearchQuery::parse("size:2mb..1mb").is_err());
+
+        // sizes past the largest representable size do not overflow
+        let query = SearchQuery::parse("size:<=99999999999999999999gb").unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[SearchClause::Size((
+                Bound::Unbounded,
+                Bound::Included(usize::MAX)
+            ))]
+        );
+
+        let query = SearchQuery::parse("size:>99999999999999999999gb").unwrap();
+        assert_eq!(
+            query.clauses(),
+            &[SearchClause::Size((
+                Bound::Excluded(usize::MAX),
+                Bound::Unbounded
+            ))]
+        );
+    }
+
+    #[test]
+    fn match_items() {
+        let image = file("Holiday.png", "image/png", 2048);
+        let document = file("report.pdf", "application/pdf", 4 * 1024 * 1024);
+        document.set_description("Quarterly numbers");
+        document.set_favorite(true);
+        let directory = Item::new_directory(Directory::new("reports"));
+
+        let search = |query: &str| {
+            let query = SearchQuery::parse(query).unwrap();
+            [&image, &document, &directory]
+                .into_iter()
+                .filter(|item| query.matches(item))
+                .map(|item| item.name())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search("report"), ["report.pdf", "reports"]);
+        assert_eq!(search("report is:file"), ["report.pdf"]);
+        assert_eq!(search("*.png"), ["Holiday.png"]);
+        assert_eq!(search("type:image"), ["Holiday.png"]);
+        assert_eq!(search("type:application/pdf"), ["report.pdf"]);
+        assert_eq!(search("size:>1mb"), ["report.pdf"]);
+        assert_eq!(search("size:<=2kb is:file"), ["Holiday.png"]);
+        assert_eq!(search("is:favorite"), ["report.pdf"]);
+        assert_eq!(search("desc:quarterly"), ["report.pdf"]);
+        assert_eq!(search("is:directory"), ["reports"]);
+        assert!(search("type:video").is_empty());
+    }
+}
@@ -6,11 +6,13 @@ use did_key::KeyMaterial;
 use ed25519_dalek::{Keypair, PublicKey, SecretKey, KEYPAIR_LENGTH, SECRET_KEY_LENGTH};
 use hmac::{Hmac, Mac};
 use sha2::Sha512;
+use std::{fmt, str::FromStr};
 use zeroize::Zeroizing;
 
 use super::DID;
 
 const ED25519_BIP32_NAME: &str = "ed25519 seed";
+const HARDENED_OFFSET: u32 = 0x8000_0000;
 type HmacSha512 = Hmac<Sha512>;
 
 #[derive(Clone, Display, Copy)]
@@ -45,29 +47,217 @@ pub fn generate_keypair(
     Ok((mnemonic.into_phrase(), did))
 }
 
+/// Path of a key derived from the root key of a mnemonic phrase (eg `m/1'/0'`).
+/// Only hardened indices are supported when deriving ed25519 keys with SLIP-0010
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(indices: impl IntoIterator<Item = u32>) -> Result<Self, Error> {
+        indices
+            .into_iter()
+            .try_fold(DerivationPath::default(), |path, index| path.push(index))
+    }
+
+    /// Append a hardened index to the path
+    pub fn push(mut self, index: u32) -> Result<Self, Error> {
+        if index >= HARDENED_OFFSET {
+            return Err(Error::InvalidDerivationPath);
+        }
+        self.0.push(index);
+        Ok(self)
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut segments = path.trim().split('/');
+
+        if segments.next() != Some("m") {
+            return Err(Error::InvalidDerivationPath);
+        }
+
+        segments.try_fold(DerivationPath::default(), |path, segment| {
+            let index = segment
+                .strip_suffix('\'')
+                .or_else(|| segment.strip_suffix('h'))
+                .ok_or(Error::InvalidDerivationPath)?
+                .parse()
+                .map_err(|_| Error::InvalidDerivationPath)?;
+            path.push(index)
+        })
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{index}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// Purpose of a derived key, used as the first index of its path
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[repr(C)]
+pub enum KeyPurpose {
+    #[display(fmt = "device")]
+    Device,
+    #[display(fmt = "conversation")]
+    Conversation,
+    #[display(fmt = "file")]
+    File,
+    #[display(fmt = "recovery")]
+    Recovery,
+}
+
+impl KeyPurpose {
+    /// Path of the key at `index` for this purpose, in the format of `m/<purpose>'/<index>'`
+    pub fn path(&self, index: u32) -> Result<DerivationPath, Error> {
+        let purpose = match self {
+            KeyPurpose::Device => 1,
+            KeyPurpose::Conversation => 2,
+            KeyPurpose::File => 3,
+            KeyPurpose::Recovery => 4,
+        };
+        DerivationPath::new([purpose, index])
+    }
+}
+
+/// Ed25519 secret key along with its chain code, derived using SLIP-0010
+#[derive(Clone)]
+pub struct ExtendedSecretKey {
+    depth: u8,
+    child_index: u32,
+    secret_key: Zeroizing<[u8; 32]>,
+    chain_code: Zeroizing<[u8; 32]>,
+}
+
+impl fmt::Debug for ExtendedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedSecretKey")
+            .field("depth", &self.depth)
+            .field("child_index", &self.child_index)
+            .finish()
+    }
+}
+
+impl ExtendedSecretKey {
+    /// Root key of the seed
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut mac = HmacSha512::new_from_slice(ED25519_BIP32_NAME.as_ref()).unwrap();
+        mac.update(seed);
+        Self::from_hmac(mac, 0, 0)
+    }
+
+    /// Root key of the mnemonic phrase
+    pub fn from_mnemonic(mnemonic: &str, passphrase: Option<&str>) -> Result<Self, Error> {
+        let mnemonic = Mnemonic::from_phrase(mnemonic, Language::English)?;
+        let seed = Seed::new(&mnemonic, passphrase.unwrap_or_default());
+        Ok(Self::from_seed(seed.as_bytes()))
+    }
+
+    /// Root key stored in tesseract by [`mnemonic_into_tesseract`].
+    /// Note: This requires the mnemonic phrase to have been saved along with the keypair
+    pub fn from_tesseract(tesseract: &Tesseract) -> Result<Self, Error> {
+        let keypair = Zeroizing::new(bs58::decode(tesseract.retrieve("keypair")?).into_vec()?);
+        let chain = Zeroizing::new(bs58::decode(tesseract.retrieve("chain")?).into_vec()?);
+
+        if keypair.len() != KEYPAIR_LENGTH {
+            return Err(Error::InvalidPrivateKeyLength);
+        }
+
+        if chain.len() != 32 {
+            return Err(Error::PrivateKeyInvalid);
+        }
+
+        let mut secret_key = Zeroizing::new([0; 32]);
+        let mut chain_code = Zeroizing::new([0; 32]);
+        secret_key.copy_from_slice(&keypair[..SECRET_KEY_LENGTH]);
+        chain_code.copy_from_slice(&chain);
+
+        Ok(Self {
+            depth: 0,
+            child_index: 0,
+            secret_key,
+            chain_code,
+        })
+    }
+
+    /// Derive the hardened child key at `index`
+    pub fn derive_child(&self, index: u32) -> Result<Self, Error> {
+        if index >= HARDENED_OFFSET {
+            return Err(Error::InvalidDerivationPath);
+        }
+
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or(Error::InvalidDerivationPath)?;
+
+        let mut mac = HmacSha512::new_from_slice(&*self.chain_code).unwrap();
+        mac.update(&[0]);
+        mac.update(&*self.secret_key);
+        mac.update(&(index | HARDENED_OFFSET).to_be_bytes());
+
+        Ok(Self::from_hmac(mac, depth, index))
+    }
+
+    /// Derive the key at the path, relative to this key
+    pub fn derive(&self, path: &DerivationPath) -> Result<Self, Error> {
+        path.indices()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Index of the key within its parent, without the hardened offset
+    pub fn child_index(&self) -> u32 {
+        self.child_index
+    }
+
+    pub fn chain_code(&self) -> [u8; 32] {
+        *self.chain_code
+    }
+
+    pub fn to_did(&self) -> Result<DID, Error> {
+        let secret = SecretKey::from_bytes(&*self.secret_key)?;
+        Ok(secret.into())
+    }
+
+    fn from_hmac(mac: HmacSha512, depth: u8, child_index: u32) -> Self {
+        let bytes = mac.finalize().into_bytes();
+        let mut secret_key = Zeroizing::new([0; 32]);
+        let mut chain_code = Zeroizing::new([0; 32]);
+        secret_key.copy_from_slice(&bytes[..32]);
+        chain_code.copy_from_slice(&bytes[32..]);
+        Self {
+            depth,
+            child_index,
+            secret_key,
+            chain_code,
+        }
+    }
+}
+
 /// Generate DID from mnemonic phrase, extending compatibility
 pub fn did_from_mnemonic_with_chain(
     mnemonic: &str,
     passphrase: Option<&str>,
 ) -> Result<(DID, [u8; 32]), Error> {
-    let mnemonic = Mnemonic::from_phrase(mnemonic, Language::English)?;
-    let seed = Seed::new(&mnemonic, passphrase.unwrap_or_default());
-    let mut mac = HmacSha512::new_from_slice(ED25519_BIP32_NAME.as_ref()).unwrap();
-    mac.update(seed.as_bytes());
-    let bytes = mac.finalize().into_bytes();
-    let secret = SecretKey::from_bytes(&bytes[..32])?;
-    // Note: This will allow extending to `ed25519-dalek-bip32` for path derivation, with this being the root of the `ExtendedSecretKey` in the following format
-    /*
-       ExtendedSecretKey {
-           depth: 0,
-           child_index: ChildIndex::Normal(0),
-           secret_key,
-           chain_code
-       }
-    */
-    let mut chain_code = [0; 32];
-    chain_code.copy_from_slice(&bytes[32..]);
-    Ok((secret.into(), chain_code))
+    let root = ExtendedSecretKey::from_mnemonic(mnemonic, passphrase)?;
+    Ok((root.to_did()?, root.chain_code()))
 }
 
 /// Generate DID from mnemonic phrase
@@ -75,6 +265,17 @@ pub fn did_from_mnemonic(mnemonic: &str, passphrase: Option<&str>) -> Result<DID
     did_from_mnemonic_with_chain(mnemonic, passphrase).map(|(did, _)| did)
 }
 
+/// Generate DID derived from the mnemonic phrase at the path
+pub fn did_from_mnemonic_with_path(
+    mnemonic: &str,
+    passphrase: Option<&str>,
+    path: &DerivationPath,
+) -> Result<DID, Error> {
+    ExtendedSecretKey::from_mnemonic(mnemonic, passphrase)?
+        .derive(path)?
+        .to_did()
+}
+
 pub fn mnemonic_into_tesseract(
     tesseract: &mut Tesseract,
     mnemonic: &str,
@@ -92,6 +293,58 @@ pub fn mnemonic_into_tesseract(
 
     let (did, chain) = did_from_mnemonic_with_chain(mnemonic, passphrase)?;
 
+    let encoded = encode_keypair(&did)?;
+
+    tesseract.set("keypair", &encoded)?;
+
+    if save_mnemonic {
+        let encoded_chain = Zeroizing::new(bs58::encode(&chain).into_string());
+        tesseract.set("chain", &encoded_chain)?;
+        tesseract.set("mnemonic", mnemonic)?;
+    }
+    Ok(())
+}
+
+/// Derive the key at the path from the root key stored in tesseract, storing it under `name`
+/// in the same format as the root keypair.
+/// Note: This requires the mnemonic phrase to have been saved along with the keypair
+pub fn derived_keypair_into_tesseract(
+    tesseract: &mut Tesseract,
+    name: &str,
+    path: &DerivationPath,
+    override_key: bool,
+) -> Result<DID, Error> {
+    if !tesseract.is_unlock() {
+        return Err(Error::TesseractLocked);
+    }
+
+    if ["keypair", "chain", "mnemonic"].contains(&name) {
+        return Err(Error::InvalidDerivationPath);
+    }
+
+    if tesseract.exist(name) && !override_key {
+        return Err(Error::Any(anyhow::anyhow!("Keypair already exist")));
+    }
+
+    let did = ExtendedSecretKey::from_tesseract(tesseract)?
+        .derive(path)?
+        .to_did()?;
+
+    let encoded = encode_keypair(&did)?;
+
+    tesseract.set(name, &encoded)?;
+
+    Ok(did)
+}
+
+/// Retrieve a keypair stored in tesseract by [`derived_keypair_into_tesseract`]
+pub fn derived_keypair_from_tesseract(tesseract: &Tesseract, name: &str) -> Result<DID, Error> {
+    let bytes = Zeroizing::new(bs58::decode(tesseract.retrieve(name)?).into_vec()?);
+    let keypair = Keypair::from_bytes(&bytes)?;
+    Ok(keypair.secret.into())
+}
+
+fn encode_keypair(did: &DID) -> Result<Zeroizing<String>, Error> {
     let bytes = Zeroizing::new(did.as_ref().private_key_bytes());
     let secret_key = SecretKey::from_bytes(&bytes)?;
     let public_key: PublicKey = (&secret_key).into();
@@ -102,21 +355,17 @@ pub fn mnemonic_into_tesseract(
 
     let kp = Keypair::from_bytes(&*bytes)?;
 
-    let encoded = Zeroizing::new(bs58::encode(&kp.to_bytes()).into_string());
-
-    tesseract.set("keypair", &encoded)?;
-
-    if save_mnemonic {
-        let encoded_chain = Zeroizing::new(bs58::encode(&chain).into_string());
-        tesseract.set("chain", &encoded_chain)?;
-        tesseract.set("mnemonic", mnemonic)?;
-    }
-    Ok(())
+    Ok(Zeroizing::new(bs58::encode(&kp.to_bytes()).into_string()))
 }
 
 #[cfg(test)]
 mod test {
-    use super::did_from_mnemonic;
+    use super::{
+        derived_keypair_from_tesseract, derived_keypair_into_tesseract, did_from_mnemonic,
+        did_from_mnemonic_with_path, mnemonic_into_tesseract, DerivationPath, ExtendedSecretKey,
+        KeyPurpose,
+    };
+    use crate::tesseract::Tesseract;
 
     const PHRASE: &str =
         "morning caution dose lab six actress pond humble pause enact virtual train";
@@ -128,4 +377,76 @@ mod test {
         assert_eq!(did.to_string(), expected);
         Ok(())
     }
+
+    #[test]
+    fn slip10_test_vector() -> anyhow::Result<()> {
+        // Test vector 1 of SLIP-0010 for ed25519
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f")?;
+        let root = ExtendedSecretKey::from_seed(&seed);
+
+        assert_eq!(
+            hex::encode(root.chain_code()),
+            "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+        );
+        assert_eq!(
+            hex::encode(*root.secret_key),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+
+        let key = root.derive(&"m/0'/1'/2'".parse()?)?;
+
+        assert_eq!(key.depth(), 3);
+        assert_eq!(key.child_index(), 2);
+        assert_eq!(
+            hex::encode(key.chain_code()),
+            "2e69929e00b5ab250f49c3fb1c12f252de4fed2c1db88387094a0f8c4c9ccd6c"
+        );
+        assert_eq!(
+            hex::encode(*key.secret_key),
+            "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_derivation_path() -> anyhow::Result<()> {
+        let path: DerivationPath = "m/1'/0h".parse()?;
+        assert_eq!(path.indices(), &[1, 0]);
+        assert_eq!(path.to_string(), "m/1'/0'");
+        assert_eq!(KeyPurpose::Device.path(0)?, path);
+
+        assert!("m/1".parse::<DerivationPath>().is_err());
+        assert!("1'/0'".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648'".parse::<DerivationPath>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn derive_from_phrase_and_tesseract() -> anyhow::Result<()> {
+        let path = KeyPurpose::Conversation.path(4)?;
+        let expected = did_from_mnemonic_with_path(PHRASE, None, &path)?;
+
+        assert_ne!(expected, did_from_mnemonic(PHRASE, None)?);
+        assert_ne!(
+            expected,
+            did_from_mnemonic_with_path(PHRASE, None, &KeyPurpose::File.path(4)?)?
+        );
+
+        let mut tesseract = Tesseract::default();
+        tesseract.unlock(b"internal pass")?;
+        mnemonic_into_tesseract(&mut tesseract, PHRASE, None, true, false)?;
+
+        let did = derived_keypair_into_tesseract(&mut tesseract, "conversation", &path, false)?;
+        assert_eq!(did, expected);
+        assert_eq!(
+            derived_keypair_from_tesseract(&tesseract, "conversation")?,
+            expected
+        );
+
+        assert!(
+            derived_keypair_into_tesseract(&mut tesseract, "conversation", &path, false).is_err()
+        );
+        assert!(derived_keypair_into_tesseract(&mut tesseract, "keypair", &path, true).is_err());
+        Ok(())
+    }
 }
@@ -41,6 +41,22 @@ pub enum Error {
     FileNotFound,
     #[error("Directory cannot be found")]
     DirectoryNotFound,
+    #[error("Directory is not empty")]
+    DirectoryNotEmpty,
+    #[error("Share cannot be found")]
+    ShareNotFound,
+    #[error("Permission of the share does not allow this action")]
+    SharePermissionDenied,
+    #[error("Item cannot be found within the trash")]
+    TrashItemNotFound,
+    #[error("Version of the file cannot be found")]
+    FileVersionNotFound,
+    #[error("Transfer cannot be found")]
+    TransferNotFound,
+    #[error("Transfer was cancelled")]
+    TransferCancelled,
+    #[error("Search query is invalid: {_0}")]
+    InvalidSearchQuery(String),
 
     //PocketDimension Errors
     #[error("Pocket dimension extension is unavailable")]
@@ -77,6 +93,20 @@ pub enum Error {
     CannotUpdateIdentityStatus,
     #[error("Identity could not be updated")]
     CannotUpdateIdentity,
+    #[error("Device is already linked to the identity")]
+    DeviceExist,
+    #[error("Device is not linked to the identity")]
+    DeviceDoesntExist,
+    #[error("You cannot revoke the device currently in use")]
+    CannotRevokeOwnDevice,
+    #[error("Devices can only be linked or revoked by the primary device")]
+    DeviceNotPrimary,
+    #[error("Device has not been revoked")]
+    DeviceNotRevoked,
+    #[error("Recovery key has not been set for the identity")]
+    RecoveryKeyNotSet,
+    #[error("Identity has already been rotated")]
+    IdentityRotated,
     #[error("Public Key is Blocked")]
     PublicKeyIsBlocked,
     #[error("Public Key isnt Blocked")]
@@ -149,6 +179,16 @@ pub enum Error {
     MessageNotFound,
     #[error("Page of messages not found")]
     PageNotFound,
+    #[error("Draft not found within conversation")]
+    DraftNotFound,
+    #[error("Scheduled message not found within conversation")]
+    ScheduledMessageNotFound,
+    #[error("Scheduled date must be in the future")]
+    InvalidScheduledDate,
+    #[error("Poll is closed")]
+    PollClosed,
+    #[error("Choices provided are invalid for the poll")]
+    InvalidPollChoice,
     #[error("Group could not be created at this time")]
     CannotCreateGroup,
     #[error("Unable to join group")]
@@ -173,6 +213,8 @@ pub enum Error {
     GroupOpened,
     #[error("No attachments provided for message")]
     NoAttachments,
+    #[error("Archive is invalid or unsupported")]
+    InvalidArchive,
 
     //Crypto Errors
     #[error("{0}")]
@@ -199,6 +241,8 @@ pub enum Error {
     InvalidPrivateKeyLength,
     #[error("Signature is invalid")]
     InvalidSignature,
+    #[error("Derivation path is invalid")]
+    InvalidDerivationPath,
 
     //Tesseract Errors
     #[error("Tesseract is unavailable")]
@@ -1,5 +1,5 @@
 use crate::{
-    crypto::DID,
+    crypto::{self, DID},
     js_exports::stream::AsyncIterator,
     multipass::{
         self,
@@ -63,6 +63,10 @@ impl MultiPassBox {
             .await
             .map_err(|e| e.into())
     }
+
+    pub async fn delete_identity(&mut self) -> Result<(), JsError> {
+        self.inner.delete_identity().await.map_err(|e| e.into())
+    }
 }
 
 /// impl MultiPassEvent trait
@@ -81,6 +85,108 @@ impl MultiPassBox {
     }
 }
 
+/// impl Devices trait
+#[wasm_bindgen]
+impl MultiPassBox {
+    /// Public key of the current device
+    pub async fn device_key(&self) -> Result<String, JsError> {
+        self.inner
+            .device_key()
+            .await
+            .map_err(|e| e.into())
+            .map(|did| did.to_string())
+    }
+
+    /// Authorize a device, returning the bundle to import on that device
+    pub async fn link_device(&mut self, pubkey: String, name: String) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .link_device(&DID::from_str(&pubkey).unwrap_or_default(), &name)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// List the devices linked to the identity
+    pub async fn list_devices(&self) -> Result<JsValue, JsError> {
+        self.inner
+            .list_devices()
+            .await
+            .map_err(|e| e.into())
+            .map(|ok| serde_wasm_bindgen::to_value(&ok).unwrap())
+    }
+
+    /// Revoke a linked device
+    pub async fn revoke_device(&mut self, pubkey: String) -> Result<(), JsError> {
+        self.inner
+            .revoke_device(&DID::from_str(&pubkey).unwrap_or_default())
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Remove the identity from the current device once it has been revoked
+    pub async fn remove_revoked_device(&mut self) -> Result<(), JsError> {
+        self.inner
+            .remove_revoked_device()
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+/// impl KeyRotation trait
+#[wasm_bindgen]
+impl MultiPassBox {
+    /// Commit to the recovery key derived from the phrase
+    pub async fn set_recovery_key(&mut self, phrase: String) -> Result<(), JsError> {
+        let recovery = recovery_key_from_phrase(&phrase)?;
+        self.inner
+            .set_recovery_key(&recovery)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Recovery key the identity has committed to
+    pub async fn recovery_key(&self) -> Result<Option<String>, JsError> {
+        self.inner
+            .recovery_key()
+            .await
+            .map_err(|e| e.into())
+            .map(|did| did.map(|did| did.to_string()))
+    }
+
+    /// Issue a statement rotating the identity to the new public key
+    pub async fn issue_rotation(&mut self, pubkey: String) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .issue_rotation(&DID::from_str(&pubkey).unwrap_or_default())
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Publish a statement rotating a previous identity to the current identity
+    pub async fn publish_rotation(&mut self, statement: Vec<u8>) -> Result<(), JsError> {
+        self.inner
+            .publish_rotation(&statement)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Rotate a previous identity to the current identity using the recovery key derived from the phrase
+    pub async fn recover_identity(
+        &mut self,
+        pubkey: String,
+        phrase: String,
+    ) -> Result<(), JsError> {
+        let recovery = recovery_key_from_phrase(&phrase)?;
+        self.inner
+            .recover_identity(&DID::from_str(&pubkey).unwrap_or_default(), &recovery)
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+fn recovery_key_from_phrase(phrase: &str) -> Result<DID, JsError> {
+    let path = crypto::keypair::KeyPurpose::Recovery.path(0)?;
+    crypto::keypair::did_from_mnemonic_with_path(phrase, None, &path).map_err(|e| e.into())
+}
+
 /// impl Friends trait
 #[wasm_bindgen]
 impl MultiPassBox {
@@ -407,6 +513,22 @@ impl From<multipass::MultiPassEventKind> for MultiPassEventKind {
                 kind: MultiPassEventKindEnum::UnblockedBy,
                 did: did.to_string(),
             },
+            multipass::MultiPassEventKind::DeviceLinked { did } => MultiPassEventKind {
+                kind: MultiPassEventKindEnum::DeviceLinked,
+                did: did.to_string(),
+            },
+            multipass::MultiPassEventKind::DeviceRevoked { did } => MultiPassEventKind {
+                kind: MultiPassEventKindEnum::DeviceRevoked,
+                did: did.to_string(),
+            },
+            multipass::MultiPassEventKind::IdentitySynced { did } => MultiPassEventKind {
+                kind: MultiPassEventKindEnum::IdentitySynced,
+                did: did.to_string(),
+            },
+            multipass::MultiPassEventKind::IdentityRotated { new, .. } => MultiPassEventKind {
+                kind: MultiPassEventKindEnum::IdentityRotated,
+                did: new.to_string(),
+            },
         }
     }
 }
@@ -446,4 +568,8 @@ pub enum MultiPassEventKindEnum {
     BlockedBy,
     Unblocked,
     UnblockedBy,
+    DeviceLinked,
+    DeviceRevoked,
+    IdentitySynced,
+    IdentityRotated,
 }
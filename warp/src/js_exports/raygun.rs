@@ -94,6 +94,22 @@ impl RayGunBox {
             .map(|ok| Message::new(ok))
     }
 
+    /// Retrieve the previous revisions of a message
+    pub async fn message_revisions(
+        &self,
+        conversation_id: String,
+        message_id: String,
+    ) -> Result<JsValue, JsError> {
+        self.inner
+            .message_revisions(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&message_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+            .map(|list| serde_wasm_bindgen::to_value(&list).unwrap())
+    }
+
     /// Get a number of messages in a conversation
     pub async fn get_message_count(&self, conversation_id: String) -> Result<usize, JsError> {
         self.inner
@@ -159,6 +175,184 @@ impl RayGunBox {
             .map(|ok| Messages::new(ok))
     }
 
+    /// Marks the message, and every message prior to it, as read
+    pub async fn mark_as_read(
+        &mut self,
+        conversation_id: String,
+        message_id: String,
+    ) -> Result<(), JsError> {
+        self.inner
+            .mark_as_read(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&message_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Retrieve the delivery and read state of a message for each recipient
+    pub async fn message_receipts(
+        &self,
+        conversation_id: String,
+        message_id: String,
+    ) -> Result<JsValue, JsError> {
+        self.inner
+            .message_receipts(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&message_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+            .map(|receipts| serde_wasm_bindgen::to_value(&receipts).unwrap())
+    }
+
+    /// Retrieve the amount of unread messages in a conversation
+    pub async fn unread_count(&self, conversation_id: String) -> Result<usize, JsError> {
+        self.inner
+            .unread_count(Uuid::from_str(&conversation_id).unwrap())
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Retrieve every message within a thread
+    pub async fn get_thread(
+        &self,
+        conversation_id: String,
+        message_id: String,
+    ) -> Result<JsValue, JsError> {
+        self.inner
+            .get_thread(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&message_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+            .map(|list| {
+                serde_wasm_bindgen::to_value(
+                    &list.into_iter().map(Message::new).collect::<Vec<_>>(),
+                )
+                .unwrap()
+            })
+    }
+
+    /// Save a draft within a conversation
+    pub async fn save_draft(
+        &mut self,
+        conversation_id: String,
+        message: Vec<String>,
+    ) -> Result<String, JsError> {
+        self.inner
+            .save_draft(Uuid::from_str(&conversation_id).unwrap(), message)
+            .await
+            .map_err(|e| e.into())
+            .map(|ok| ok.to_string())
+    }
+
+    /// Update the contents of an existing draft
+    pub async fn update_draft(
+        &mut self,
+        conversation_id: String,
+        draft_id: String,
+        message: Vec<String>,
+    ) -> Result<(), JsError> {
+        self.inner
+            .update_draft(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&draft_id).unwrap(),
+                message,
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Retrieve all drafts within a conversation
+    pub async fn list_drafts(&self, conversation_id: String) -> Result<JsValue, JsError> {
+        self.inner
+            .list_drafts(Uuid::from_str(&conversation_id).unwrap())
+            .await
+            .map_err(|e| e.into())
+            .map(|list| serde_wasm_bindgen::to_value(&list).unwrap())
+    }
+
+    /// Delete a draft from a conversation
+    pub async fn delete_draft(
+        &mut self,
+        conversation_id: String,
+        draft_id: String,
+    ) -> Result<(), JsError> {
+        self.inner
+            .delete_draft(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&draft_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Schedule a message to be sent to a conversation at a later date
+    pub async fn schedule_message(
+        &mut self,
+        conversation_id: String,
+        message: Vec<String>,
+        send_at: js_sys::Date,
+    ) -> Result<String, JsError> {
+        self.inner
+            .schedule_message(
+                Uuid::from_str(&conversation_id).unwrap(),
+                message,
+                send_at.into(),
+            )
+            .await
+            .map_err(|e| e.into())
+            .map(|ok| ok.to_string())
+    }
+
+    /// Retrieve all messages scheduled to be sent in a conversation
+    pub async fn list_scheduled_messages(
+        &self,
+        conversation_id: String,
+    ) -> Result<JsValue, JsError> {
+        self.inner
+            .list_scheduled_messages(Uuid::from_str(&conversation_id).unwrap())
+            .await
+            .map_err(|e| e.into())
+            .map(|list| serde_wasm_bindgen::to_value(&list).unwrap())
+    }
+
+    /// Update the contents and date of a scheduled message
+    pub async fn update_scheduled_message(
+        &mut self,
+        conversation_id: String,
+        scheduled_id: String,
+        message: Vec<String>,
+        send_at: js_sys::Date,
+    ) -> Result<(), JsError> {
+        self.inner
+            .update_scheduled_message(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&scheduled_id).unwrap(),
+                message,
+                send_at.into(),
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Cancel a scheduled message before it is sent
+    pub async fn cancel_scheduled_message(
+        &mut self,
+        conversation_id: String,
+        scheduled_id: String,
+    ) -> Result<(), JsError> {
+        self.inner
+            .cancel_scheduled_message(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&scheduled_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
     /// Sends a message to a conversation.
     pub async fn send(
         &mut self,
@@ -172,6 +366,54 @@ impl RayGunBox {
             .map(|ok| ok.to_string())
     }
 
+    /// Sends a poll to a conversation.
+    pub async fn send_poll(
+        &mut self,
+        conversation_id: String,
+        poll: JsValue,
+    ) -> Result<String, JsError> {
+        self.inner
+            .send_poll(
+                Uuid::from_str(&conversation_id).unwrap(),
+                serde_wasm_bindgen::from_value(poll).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+            .map(|ok| ok.to_string())
+    }
+
+    /// Vote on a poll within a conversation
+    pub async fn vote(
+        &mut self,
+        conversation_id: String,
+        message_id: String,
+        choices: Vec<usize>,
+    ) -> Result<(), JsError> {
+        self.inner
+            .vote(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&message_id).unwrap(),
+                choices,
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// Close a poll so no further votes are accepted
+    pub async fn close_poll(
+        &mut self,
+        conversation_id: String,
+        message_id: String,
+    ) -> Result<(), JsError> {
+        self.inner
+            .close_poll(
+                Uuid::from_str(&conversation_id).unwrap(),
+                Uuid::from_str(&message_id).unwrap(),
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
     /// Edit an existing message in a conversation.
     pub async fn edit(
         &mut self,
@@ -330,20 +572,40 @@ impl RayGunBox {
     pub async fn send_event(
         &mut self,
         conversation_id: String,
-        event: MessageEvent,
+        event: JsValue,
     ) -> Result<(), JsError> {
+        let event: MessageEvent = serde_wasm_bindgen::from_value(event)?;
         self.inner
             .send_event(Uuid::from_str(&conversation_id).unwrap(), event)
             .await
             .map_err(|e| e.into())
     }
 
+    /// Send an event to a conversation that expires after the ttl, in seconds, unless sent again
+    pub async fn send_event_with_ttl(
+        &mut self,
+        conversation_id: String,
+        event: JsValue,
+        ttl: u32,
+    ) -> Result<(), JsError> {
+        let event: MessageEvent = serde_wasm_bindgen::from_value(event)?;
+        self.inner
+            .send_event_with_ttl(
+                Uuid::from_str(&conversation_id).unwrap(),
+                event,
+                std::time::Duration::from_secs(ttl as _),
+            )
+            .await
+            .map_err(|e| e.into())
+    }
+
     /// Cancel event that was sent, if any.
     pub async fn cancel_event(
         &mut self,
         conversation_id: String,
-        event: MessageEvent,
+        event: JsValue,
     ) -> Result<(), JsError> {
+        let event: MessageEvent = serde_wasm_bindgen::from_value(event)?;
         self.inner
             .cancel_event(Uuid::from_str(&conversation_id).unwrap(), event)
             .await
@@ -387,6 +649,16 @@ impl Conversation {
             .map(|did| did.to_string())
             .collect()
     }
+    pub fn roles(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.inner.roles()).unwrap()
+    }
+    pub fn banned(&self) -> Vec<String> {
+        self.inner
+            .banned()
+            .iter()
+            .map(|did| did.to_string())
+            .collect()
+    }
 }
 
 #[wasm_bindgen]
@@ -510,6 +782,10 @@ impl MessageOptions {
         self.inner = self.inner.clone().set_pinned();
     }
 
+    pub fn set_exclude_threads(&mut self) {
+        self.inner = self.inner.clone().set_exclude_threads();
+    }
+
     pub fn set_reverse(&mut self) {
         self.inner = self.inner.clone().set_reverse();
     }
@@ -560,6 +836,18 @@ impl MessageReference {
         self.inner.replied().map(|uuid| uuid.to_string())
     }
 
+    pub fn thread(&self) -> Option<String> {
+        self.inner.thread().map(|uuid| uuid.to_string())
+    }
+
+    pub fn reply_count(&self) -> usize {
+        self.inner.reply_count()
+    }
+
+    pub fn last_reply(&self) -> Option<js_sys::Date> {
+        self.inner.last_reply().map(|d| d.into())
+    }
+
     pub fn deleted(&self) -> bool {
         self.inner.deleted()
     }
@@ -628,7 +916,19 @@ impl Message {
         serde_wasm_bindgen::to_value(&self.inner.metadata()).unwrap()
     }
 
+    pub fn poll(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.inner.poll()).unwrap()
+    }
+
+    pub fn embeds(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.inner.embeds()).unwrap()
+    }
+
     pub fn replied(&self) -> Option<String> {
         self.inner.replied().map(|uuid| uuid.to_string())
     }
+
+    pub fn thread(&self) -> Option<String> {
+        self.inner.thread().map(|uuid| uuid.to_string())
+    }
 }
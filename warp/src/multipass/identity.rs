@@ -38,6 +38,49 @@ pub enum Platform {
     Unknown,
 }
 
+/// Device that is linked to, and shares, an identity
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    did: DID,
+    name: String,
+    linked: DateTime<Utc>,
+    current: bool,
+}
+
+impl Device {
+    pub fn new(did: DID, name: impl Into<String>, linked: DateTime<Utc>) -> Self {
+        Self {
+            did,
+            name: name.into(),
+            linked,
+            current: false,
+        }
+    }
+
+    /// Public key of the device
+    pub fn did(&self) -> DID {
+        self.did.clone()
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Date the device was linked to the identity
+    pub fn linked(&self) -> DateTime<Utc> {
+        self.linked
+    }
+
+    /// Returns true if this is the device currently in use
+    pub fn is_current(&self) -> bool {
+        self.current
+    }
+
+    pub fn set_current(&mut self, current: bool) {
+        self.current = current;
+    }
+}
+
 /// Profile containing the newly created `Identity` and a passphrase, if applicable.
 #[derive(Default, Debug, PartialEq, Eq)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -16,7 +16,9 @@ use identity::Identity;
 use crate::crypto::DID;
 use crate::multipass::identity::{Identifier, IdentityUpdate};
 
-use self::identity::{IdentityImage, IdentityProfile, IdentityStatus, Platform, Relationship};
+use self::identity::{
+    Device, IdentityImage, IdentityProfile, IdentityStatus, Platform, Relationship,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -37,6 +39,10 @@ pub enum MultiPassEventKind {
     BlockedBy { did: DID },
     Unblocked { did: DID },
     UnblockedBy { did: DID },
+    DeviceLinked { did: DID },
+    DeviceRevoked { did: DID },
+    IdentitySynced { did: DID },
+    IdentityRotated { old: DID, new: DID },
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -60,6 +66,8 @@ pub enum IdentityImportOption<'a> {
         /// Passphrase of the identity
         passphrase: String,
     },
+    /// Bundle produced by [`Devices::link_device`] on a device the identity is already used on
+    Device { bundle: Vec<u8> },
 }
 
 pub type MultiPassEventStream = BoxStream<'static, MultiPassEventKind>;
@@ -70,6 +78,8 @@ pub trait MultiPass:
     + IdentityInformation
     + MultiPassImportExport
     + Friends
+    + Devices
+    + KeyRotation
     + MultiPassEvent
     + Sync
     + Send
@@ -95,6 +105,11 @@ pub trait MultiPass:
 
     /// Update your own [`Identity`] using [`IdentityUpdate`]
     async fn update_identity(&mut self, option: IdentityUpdate) -> Result<(), Error>;
+
+    /// Delete your own [`Identity`] from every node that stores it, including the keypair held locally
+    async fn delete_identity(&mut self) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
 }
 
 dyn_clone::clone_trait_object!(MultiPass);
@@ -193,6 +208,68 @@ pub trait Friends: Sync + Send {
     }
 }
 
+#[async_trait::async_trait]
+pub trait Devices: Sync + Send {
+    /// Public key of the current device, which is provided to a device the identity is used on to link it
+    async fn device_key(&self) -> Result<DID, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Authorize a device to use the identity, returning a bundle that is only readable by that device.
+    /// The bundle is imported on the device using [`IdentityImportOption::Device`]
+    async fn link_device(&mut self, _: &DID, _: &str) -> Result<Vec<u8>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List the devices linked to the identity
+    async fn list_devices(&self) -> Result<Vec<Device>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Revoke a device, preventing it from syncing the identity any further
+    async fn revoke_device(&mut self, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Remove the identity from the current device after the device has been revoked, which is
+    /// reported through [`MultiPassEventKind::DeviceRevoked`] and should be confirmed by the user first.
+    /// The mnemonic phrase of the identity is not removed
+    async fn remove_revoked_device(&mut self) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait KeyRotation: Sync + Send {
+    /// Commit to a recovery key, which is able to rotate the identity to a new key in the event
+    /// the keypair of the identity is lost or compromised
+    async fn set_recovery_key(&mut self, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Recovery key the identity has committed to
+    async fn recovery_key(&self) -> Result<Option<DID>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Issue a statement signed by the identity, rotating it to the new key.
+    /// The statement is published by the new identity using [`KeyRotation::publish_rotation`]
+    async fn issue_rotation(&mut self, _: &DID) -> Result<Vec<u8>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Publish a statement rotating a previous identity to the current identity
+    async fn publish_rotation(&mut self, _: &[u8]) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Rotate a previous identity to the current identity using the recovery key the previous identity
+    /// has committed to
+    async fn recover_identity(&mut self, _: &DID, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+}
+
 #[async_trait::async_trait]
 pub trait MultiPassEvent: Sync + Send {
     /// Subscribe to an stream of events
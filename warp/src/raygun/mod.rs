@@ -1,6 +1,6 @@
 pub mod group;
 
-use crate::constellation::file::File;
+use crate::constellation::file::{File, FileType};
 use crate::constellation::{ConstellationProgressStream, Progression};
 use crate::crypto::DID;
 use crate::error::Error;
@@ -16,6 +16,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[allow(unused_imports)]
@@ -95,16 +96,87 @@ pub enum MessageEventKind {
         conversation_id: Uuid,
         settings: ConversationSettings,
     },
+    MessageRead {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        did_key: DID,
+    },
+    ThreadReplyAdded {
+        conversation_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    },
+    ThreadReplyRemoved {
+        conversation_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    },
+    ScheduledMessageSent {
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+        message_id: Uuid,
+    },
+    ScheduledMessageFailed {
+        conversation_id: Uuid,
+        scheduled_id: Uuid,
+    },
+    PollVoted {
+        conversation_id: Uuid,
+        message_id: Uuid,
+        did_key: DID,
+    },
+    PollClosed {
+        conversation_id: Uuid,
+        message_id: Uuid,
+    },
+    MessageEmbedsUpdated {
+        conversation_id: Uuid,
+        message_id: Uuid,
+    },
+    RecipientRoleUpdated {
+        conversation_id: Uuid,
+        recipient: DID,
+        role: GroupRole,
+    },
+    RecipientBanned {
+        conversation_id: Uuid,
+        recipient: DID,
+    },
+    RecipientUnbanned {
+        conversation_id: Uuid,
+        recipient: DID,
+    },
 }
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
-#[repr(C)]
 pub enum MessageEvent {
     /// Event that represents typing
     Typing,
-    //TODO: Custom events?
+    /// Event that represents recording a voice note
+    RecordingVoiceNote,
+    /// Event that represents viewing the conversation
+    Viewing,
+    /// Event that represents screen sharing being started
+    ScreenSharing,
+    /// Application-defined event. The namespace should be unique to the application (eg `com.example.app.event`)
+    Custom {
+        namespace: String,
+        payload: serde_json::Value,
+    },
+}
+
+impl MessageEvent {
+    /// Duration the event is considered active for if it is not sent again or cancelled
+    pub fn default_ttl(&self) -> Duration {
+        match self {
+            MessageEvent::Typing => Duration::from_secs(10),
+            MessageEvent::RecordingVoiceNote
+            | MessageEvent::Viewing
+            | MessageEvent::Custom { .. } => Duration::from_secs(30),
+            MessageEvent::ScreenSharing => Duration::from_secs(60),
+        }
+    }
 }
 
 pub enum AttachmentKind {
@@ -127,6 +199,7 @@ pub struct MessageOptions {
     messages_type: MessagesType,
     keyword: Option<String>,
     pinned: bool,
+    exclude_threads: bool,
     range: Option<Range<usize>>,
     limit: Option<u8>,
     skip: Option<i64>,
@@ -175,6 +248,12 @@ impl MessageOptions {
         self
     }
 
+    /// Exclude replies within a thread, leaving only the main timeline of the conversation
+    pub fn set_exclude_threads(mut self) -> Self {
+        self.exclude_threads = true;
+        self
+    }
+
     pub fn set_reverse(mut self) -> Self {
         self.reverse = true;
         self
@@ -219,6 +298,10 @@ impl MessageOptions {
         self.pinned
     }
 
+    pub fn exclude_threads(&self) -> bool {
+        self.exclude_threads
+    }
+
     pub fn messages_type(&self) -> MessagesType {
         self.messages_type
     }
@@ -358,6 +441,10 @@ pub struct Conversation {
     modified: DateTime<Utc>,
     settings: ConversationSettings,
     recipients: Vec<DID>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    roles: HashMap<DID, GroupRole>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    banned: Vec<DID>,
 }
 
 impl core::hash::Hash for Conversation {
@@ -387,6 +474,8 @@ impl Default for Conversation {
             modified: timestamp,
             settings: ConversationSettings::default(),
             recipients,
+            roles: HashMap::new(),
+            banned: Vec::new(),
         }
     }
 }
@@ -426,6 +515,29 @@ impl Conversation {
     pub fn recipients(&self) -> Vec<DID> {
         self.recipients.clone()
     }
+
+    /// Members of a group conversation that hold a role other than [`GroupRole::Member`]
+    pub fn roles(&self) -> HashMap<DID, GroupRole> {
+        self.roles.clone()
+    }
+
+    /// Role of a member within the conversation
+    pub fn role(&self, did: &DID) -> GroupRole {
+        self.roles.get(did).copied().unwrap_or_default()
+    }
+
+    /// Current owner of a group conversation
+    pub fn owner(&self) -> Option<DID> {
+        self.roles
+            .iter()
+            .find(|(_, role)| **role == GroupRole::Owner)
+            .map(|(did, _)| did.clone())
+    }
+
+    /// Identities that are banned from a group conversation
+    pub fn banned(&self) -> Vec<DID> {
+        self.banned.clone()
+    }
 }
 
 impl Conversation {
@@ -456,6 +568,48 @@ impl Conversation {
     pub fn set_recipients(&mut self, recipients: Vec<DID>) {
         self.recipients = recipients;
     }
+
+    pub fn set_roles(&mut self, roles: HashMap<DID, GroupRole>) {
+        self.roles = roles;
+    }
+
+    pub fn set_banned(&mut self, banned: Vec<DID>) {
+        self.banned = banned;
+    }
+}
+
+/// Role of a member within a group conversation, ordered from the least to the most privileged
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(
+    Default,
+    Debug,
+    Hash,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[repr(C)]
+pub enum GroupRole {
+    /// Regular member of the group
+    #[display(fmt = "member")]
+    #[default]
+    Member,
+    /// Able to remove and ban members, and change the name of the group
+    #[display(fmt = "moderator")]
+    Moderator,
+    /// Able to change the settings of the group and assign moderators, in addition to moderator permissions
+    #[display(fmt = "admin")]
+    Admin,
+    /// Owner of the group, which is the creator unless ownership has been transferred
+    #[display(fmt = "owner")]
+    Owner,
 }
 
 #[derive(Debug, Hash, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display)]
@@ -474,20 +628,59 @@ impl Default for ConversationSettings {
     }
 }
 
+impl ConversationSettings {
+    /// Duration, in seconds, before a message in the conversation is deleted
+    pub fn message_expiry(&self) -> Option<u64> {
+        match self {
+            Self::Direct(settings) => settings.message_expiry(),
+            Self::Group(settings) => settings.message_expiry(),
+        }
+    }
+
+    /// Previous revisions of a message are kept when it is edited
+    pub fn keep_edit_history(&self) -> bool {
+        match self {
+            Self::Direct(_) => false,
+            Self::Group(settings) => settings.keep_edit_history(),
+        }
+    }
+}
+
 /// Settings for a direct conversation.
 // Any future direct conversation settings go here.
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
 #[derive(Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Display)]
+#[display(
+    fmt = "Message expiry: {}",
+    "self.message_expiry.map(|secs| format!(\"{secs}s\")).unwrap_or(\"❌\".into())"
+)]
 #[repr(C)]
-pub struct DirectConversationSettings {}
+pub struct DirectConversationSettings {
+    // Duration, in seconds, before a message is deleted. Messages will not expire if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message_expiry: Option<u64>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+impl DirectConversationSettings {
+    pub fn message_expiry(&self) -> Option<u64> {
+        self.message_expiry
+    }
+
+    pub fn set_message_expiry(&mut self, val: Option<u64>) {
+        self.message_expiry = val;
+    }
+}
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
 #[derive(Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Display)]
 #[display(
-    fmt = "Everyone can add participants: {}, Everyone can change name: {}",
+    fmt = "Everyone can add participants: {}, Everyone can change name: {}, Message expiry: {}, Keep edit history: {}",
     "if self.members_can_add_participants {\"✅\"} else {\"❌\"}",
-    "if self.members_can_change_name {\"✅\"} else {\"❌\"}"
+    "if self.members_can_change_name {\"✅\"} else {\"❌\"}",
+    "self.message_expiry.map(|secs| format!(\"{secs}s\")).unwrap_or(\"❌\".into())",
+    "if self.keep_edit_history {\"✅\"} else {\"❌\"}"
 )]
 #[repr(C)]
 pub struct GroupSettings {
@@ -497,6 +690,12 @@ pub struct GroupSettings {
     // Everyone can change the name of the group.
     #[serde(default)]
     members_can_change_name: bool,
+    // Duration, in seconds, before a message is deleted. Messages will not expire if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message_expiry: Option<u64>,
+    // Previous revisions of a message are kept when it is edited.
+    #[serde(default)]
+    keep_edit_history: bool,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -509,6 +708,14 @@ impl GroupSettings {
         self.members_can_change_name
     }
 
+    pub fn message_expiry(&self) -> Option<u64> {
+        self.message_expiry
+    }
+
+    pub fn keep_edit_history(&self) -> bool {
+        self.keep_edit_history
+    }
+
     pub fn set_members_can_add_participants(&mut self, val: bool) {
         self.members_can_add_participants = val;
     }
@@ -516,6 +723,14 @@ impl GroupSettings {
     pub fn set_members_can_change_name(&mut self, val: bool) {
         self.members_can_change_name = val;
     }
+
+    pub fn set_message_expiry(&mut self, val: Option<u64>) {
+        self.message_expiry = val;
+    }
+
+    pub fn set_keep_edit_history(&mut self, val: bool) {
+        self.keep_edit_history = val;
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -535,6 +750,9 @@ pub enum MessageType {
     /// TBD
     #[display(fmt = "event")]
     Event,
+    /// Poll that members of the conversation can vote on
+    #[display(fmt = "poll")]
+    Poll,
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
@@ -560,6 +778,15 @@ pub struct MessageReference {
     /// ID of the message being replied to
     replied: Option<Uuid>,
 
+    /// ID of the root message of the thread this message belongs to
+    thread: Option<Uuid>,
+
+    /// Amount of replies in the thread if this message is the root of a thread
+    reply_count: usize,
+
+    /// Timestamp of the latest reply in the thread if this message is the root of a thread
+    last_reply: Option<DateTime<Utc>>,
+
     /// Indication that a message been deleted
     deleted: bool,
 }
@@ -606,6 +833,18 @@ impl MessageReference {
         self.replied
     }
 
+    pub fn thread(&self) -> Option<Uuid> {
+        self.thread
+    }
+
+    pub fn reply_count(&self) -> usize {
+        self.reply_count
+    }
+
+    pub fn last_reply(&self) -> Option<DateTime<Utc>> {
+        self.last_reply
+    }
+
     pub fn deleted(&self) -> bool {
         self.deleted
     }
@@ -640,6 +879,18 @@ impl MessageReference {
         self.replied = replied
     }
 
+    pub fn set_thread(&mut self, thread: Option<Uuid>) {
+        self.thread = thread
+    }
+
+    pub fn set_reply_count(&mut self, count: usize) {
+        self.reply_count = count
+    }
+
+    pub fn set_last_reply(&mut self, date: Option<DateTime<Utc>>) {
+        self.last_reply = date
+    }
+
     pub fn set_delete(&mut self, deleted: bool) {
         self.deleted = deleted
     }
@@ -681,12 +932,24 @@ pub struct Message {
     #[serde(skip_serializing_if = "Option::is_none")]
     replied: Option<Uuid>,
 
+    /// ID of the root message of the thread this message belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thread: Option<Uuid>,
+
     /// Message context for `Message`
     lines: Vec<String>,
 
     /// List of Attachment
     attachment: Vec<File>,
 
+    /// Poll for `Message` of type [`MessageType::Poll`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    poll: Option<Poll>,
+
+    /// Previews of the links within the message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    embeds: Vec<Embed>,
+
     /// Metadata related to the message. Can be used externally, but more internally focused
     #[serde(flatten)]
     metadata: HashMap<String, String>,
@@ -705,8 +968,11 @@ impl Default for Message {
             reactions: BTreeMap::new(),
             mentions: Vec::new(),
             replied: None,
+            thread: None,
             lines: Vec::new(),
             attachment: Vec::new(),
+            poll: None,
+            embeds: Vec::new(),
             metadata: HashMap::new(),
         }
     }
@@ -783,6 +1049,18 @@ impl Message {
     pub fn replied(&self) -> Option<Uuid> {
         self.replied
     }
+
+    pub fn thread(&self) -> Option<Uuid> {
+        self.thread
+    }
+
+    pub fn poll(&self) -> Option<Poll> {
+        self.poll.clone()
+    }
+
+    pub fn embeds(&self) -> Vec<Embed> {
+        self.embeds.clone()
+    }
 }
 
 impl Message {
@@ -837,6 +1115,18 @@ impl Message {
     pub fn set_replied(&mut self, replied: Option<Uuid>) {
         self.replied = replied
     }
+
+    pub fn set_thread(&mut self, thread: Option<Uuid>) {
+        self.thread = thread
+    }
+
+    pub fn set_poll(&mut self, poll: Option<Poll>) {
+        self.poll = poll
+    }
+
+    pub fn set_embeds(&mut self, embeds: Vec<Embed>) {
+        self.embeds = embeds
+    }
 }
 
 // Mutable functions
@@ -856,6 +1146,317 @@ impl Message {
     pub fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
         &mut self.metadata
     }
+
+    pub fn poll_mut(&mut self) -> Option<&mut Poll> {
+        self.poll.as_mut()
+    }
+}
+
+/// Question with a set of options that members of a conversation can vote on
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Poll {
+    question: String,
+    options: Vec<String>,
+    multiple_choice: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    close_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    closed: Option<DateTime<Utc>>,
+    /// Options chosen by each member that voted
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    votes: HashMap<DID, Vec<usize>>,
+}
+
+impl Poll {
+    pub fn new(question: impl Into<String>, options: Vec<String>) -> Self {
+        Self {
+            question: question.into(),
+            options,
+            ..Default::default()
+        }
+    }
+}
+
+impl Poll {
+    pub fn question(&self) -> String {
+        self.question.clone()
+    }
+
+    pub fn options(&self) -> Vec<String> {
+        self.options.clone()
+    }
+
+    pub fn multiple_choice(&self) -> bool {
+        self.multiple_choice
+    }
+
+    pub fn close_at(&self) -> Option<DateTime<Utc>> {
+        self.close_at
+    }
+
+    /// Date the poll was closed by its creator
+    pub fn closed(&self) -> Option<DateTime<Utc>> {
+        self.closed
+    }
+
+    /// Returns true if the poll was closed by its creator or the close date was reached
+    pub fn is_closed(&self) -> bool {
+        self.closed.is_some() || matches!(self.close_at, Some(date) if date <= Utc::now())
+    }
+
+    pub fn votes(&self) -> HashMap<DID, Vec<usize>> {
+        self.votes.clone()
+    }
+
+    /// Number of votes for each option, in the order of [`Poll::options`]
+    pub fn tally(&self) -> Vec<usize> {
+        let mut tally = vec![0; self.options.len()];
+        for choice in self.votes.values().flatten() {
+            if let Some(count) = tally.get_mut(*choice) {
+                *count += 1;
+            }
+        }
+        tally
+    }
+}
+
+impl Poll {
+    pub fn set_question(&mut self, question: impl Into<String>) {
+        self.question = question.into();
+    }
+
+    pub fn set_options(&mut self, options: Vec<String>) {
+        self.options = options;
+    }
+
+    pub fn set_multiple_choice(&mut self, multiple_choice: bool) {
+        self.multiple_choice = multiple_choice;
+    }
+
+    pub fn set_close_at(&mut self, date: Option<DateTime<Utc>>) {
+        self.close_at = date;
+    }
+
+    pub fn set_closed(&mut self, date: Option<DateTime<Utc>>) {
+        self.closed = date;
+    }
+
+    pub fn set_votes(&mut self, votes: HashMap<DID, Vec<usize>>) {
+        self.votes = votes;
+    }
+}
+
+impl Poll {
+    pub fn votes_mut(&mut self) -> &mut HashMap<DID, Vec<usize>> {
+        &mut self.votes
+    }
+}
+
+/// Preview of a link found within the lines of a [`Message`]
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Embed {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Name of the site the link belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    site: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    thumbnail: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail_format: Option<FileType>,
+}
+
+impl Embed {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Embed {
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.title.clone()
+    }
+
+    pub fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    pub fn site(&self) -> Option<String> {
+        self.site.clone()
+    }
+
+    pub fn thumbnail(&self) -> Vec<u8> {
+        self.thumbnail.clone()
+    }
+
+    pub fn thumbnail_format(&self) -> Option<FileType> {
+        self.thumbnail_format.clone()
+    }
+}
+
+impl Embed {
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title;
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description;
+    }
+
+    pub fn set_site(&mut self, site: Option<String>) {
+        self.site = site;
+    }
+
+    pub fn set_thumbnail(&mut self, data: Vec<u8>, format: Option<FileType>) {
+        self.thumbnail = data;
+        self.thumbnail_format = format;
+    }
+}
+
+/// Message that is being composed but has yet to be sent
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DraftMessage {
+    id: Uuid,
+    conversation_id: Uuid,
+    lines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replied: Option<Uuid>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+impl DraftMessage {
+    pub fn new(conversation_id: Uuid, lines: Vec<String>) -> Self {
+        let date = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            conversation_id,
+            lines,
+            replied: None,
+            created: date,
+            modified: date,
+        }
+    }
+}
+
+impl DraftMessage {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn conversation_id(&self) -> Uuid {
+        self.conversation_id
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.clone()
+    }
+
+    pub fn replied(&self) -> Option<Uuid> {
+        self.replied
+    }
+
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+}
+
+impl DraftMessage {
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines;
+        self.modified = Utc::now();
+    }
+
+    pub fn set_replied(&mut self, replied: Option<Uuid>) {
+        self.replied = replied;
+        self.modified = Utc::now();
+    }
+}
+
+/// Message that will be sent to the conversation at a later date
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    id: Uuid,
+    conversation_id: Uuid,
+    lines: Vec<String>,
+    send_at: DateTime<Utc>,
+}
+
+impl ScheduledMessage {
+    pub fn new(conversation_id: Uuid, lines: Vec<String>, send_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            conversation_id,
+            lines,
+            send_at,
+        }
+    }
+}
+
+impl ScheduledMessage {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn conversation_id(&self) -> Uuid {
+        self.conversation_id
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.clone()
+    }
+
+    pub fn send_at(&self) -> DateTime<Utc> {
+        self.send_at
+    }
+}
+
+impl ScheduledMessage {
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines;
+    }
+
+    pub fn set_send_at(&mut self, send_at: DateTime<Utc>) {
+        self.send_at = send_at;
+    }
+}
+
+/// Previous contents of a message before it was edited
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageRevision {
+    lines: Vec<String>,
+    date: DateTime<Utc>,
+}
+
+impl MessageRevision {
+    pub fn new(lines: Vec<String>, date: DateTime<Utc>) -> Self {
+        Self { lines, date }
+    }
+}
+
+impl MessageRevision {
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.clone()
+    }
+
+    /// Date the contents were sent or, if edited, when the edit occurred
+    pub fn date(&self) -> DateTime<Utc> {
+        self.date
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -871,9 +1472,13 @@ pub enum MessageStatus {
     #[display(fmt = "sent")]
     Sent,
 
-    /// Confirmation of message being delivered. May be used in the future
+    /// Confirmation of message being delivered
     #[display(fmt = "delivered")]
     Delivered,
+
+    /// Confirmation of message being read
+    #[display(fmt = "read")]
+    Read,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -951,6 +1556,12 @@ pub trait RayGun:
         Err(Error::Unimplemented)
     }
 
+    /// Retrieve the previous revisions of a message, ordered from the oldest to the newest.
+    /// Revisions are only kept when enabled in the settings of the conversation
+    async fn message_revisions(&self, _: Uuid, _: Uuid) -> Result<Vec<MessageRevision>, Error> {
+        Err(Error::Unimplemented)
+    }
+
     /// Get a number of messages in a conversation
     async fn get_message_count(&self, _: Uuid) -> Result<usize, Error> {
         Err(Error::Unimplemented)
@@ -982,9 +1593,119 @@ pub trait RayGun:
         options: MessageOptions,
     ) -> Result<Messages, Error>;
 
+    /// Marks the message, and every message sent prior to it, as read.
+    /// A read receipt will be sent to the conversation unless receipts are disabled by the implementation
+    async fn mark_as_read(&mut self, _: Uuid, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Retrieve the delivery and read state of a message for each recipient in the conversation
+    async fn message_receipts(
+        &self,
+        _: Uuid,
+        _: Uuid,
+    ) -> Result<HashMap<DID, MessageStatus>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Retrieve the amount of messages that have not been read in the conversation
+    async fn unread_count(&self, _: Uuid) -> Result<usize, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Retrieve every message within a thread, starting with the root message and followed
+    /// by the replies in the order they were sent. The message provided can be the root of the
+    /// thread or any reply within it.
+    async fn get_thread(&self, _: Uuid, _: Uuid) -> Result<Vec<Message>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Search messages using the local message index. If no conversation is provided, all
+    /// conversations are searched. Results are ordered by relevance.
+    ///
+    /// Terms are separated by whitespace, a term ending with `*` matches by prefix and
+    /// terms wrapped in double quotes are matched as a phrase.
+    async fn search_messages(
+        &self,
+        _: Option<Uuid>,
+        _: &str,
+    ) -> Result<Vec<MessageReference>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Save a draft within a conversation, returning the id of the draft.
+    /// Drafts are stored with the account and are not sent to the recipients
+    async fn save_draft(&mut self, _: Uuid, _: Vec<String>) -> Result<Uuid, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Update the contents of an existing draft
+    async fn update_draft(&mut self, _: Uuid, _: Uuid, _: Vec<String>) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Retrieve all drafts within a conversation
+    async fn list_drafts(&self, _: Uuid) -> Result<Vec<DraftMessage>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Delete a draft from a conversation
+    async fn delete_draft(&mut self, _: Uuid, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Schedule a message to be sent to a conversation at a later date, returning the id of the scheduled message.
+    /// The message is sent as if [`RayGun::send`] was called once the date is reached.
+    async fn schedule_message(
+        &mut self,
+        _: Uuid,
+        _: Vec<String>,
+        _: DateTime<Utc>,
+    ) -> Result<Uuid, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Retrieve all messages scheduled to be sent in a conversation
+    async fn list_scheduled_messages(&self, _: Uuid) -> Result<Vec<ScheduledMessage>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Update the contents and date of a scheduled message
+    async fn update_scheduled_message(
+        &mut self,
+        _: Uuid,
+        _: Uuid,
+        _: Vec<String>,
+        _: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Cancel a scheduled message before it is sent
+    async fn cancel_scheduled_message(&mut self, _: Uuid, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
     /// Sends a message to a conversation.
     async fn send(&mut self, conversation_id: Uuid, message: Vec<String>) -> Result<Uuid, Error>;
 
+    /// Sends a poll to a conversation.
+    async fn send_poll(&mut self, _: Uuid, _: Poll) -> Result<Uuid, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Vote on a poll within a conversation. Voting again replaces the previous vote and
+    /// an empty list of choices removes it
+    async fn vote(&mut self, _: Uuid, _: Uuid, _: Vec<usize>) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Close a poll so no further votes are accepted.
+    /// Note: Only the sender of the poll is able to close it
+    async fn close_poll(&mut self, _: Uuid, _: Uuid) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
     /// Edit an existing message in a conversation.
     async fn edit(
         &mut self,
@@ -1025,6 +1746,8 @@ pub trait RayGun:
         message: Vec<String>,
     ) -> Result<Uuid, Error>;
 
+    /// Enable or disable the previews of the links within a message sent by the current identity.
+    /// Disabling them removes the previews for every member of the conversation
     async fn embeds(
         &mut self,
         conversation_id: Uuid,
@@ -1038,6 +1761,20 @@ pub trait RayGun:
         conversation_id: Uuid,
         settings: ConversationSettings,
     ) -> Result<(), Error>;
+
+    /// Export the history of a conversation into a portable archive at the given path.
+    /// If a passphrase is supplied, the contents of the archive will be encrypted with it.
+    /// Note: The keys of a group conversation are only included in archives encrypted with a passphrase
+    async fn export_conversation(&self, _: Uuid, _: PathBuf, _: Option<&str>) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Import a conversation from an archive created by [`RayGun::export_conversation`],
+    /// returning the id of the conversation
+    /// Note: The archive must contain a conversation the current identity is a recipient of
+    async fn import_conversation(&mut self, _: PathBuf, _: Option<&str>) -> Result<Uuid, Error> {
+        Err(Error::Unimplemented)
+    }
 }
 
 dyn_clone::clone_trait_object!(RayGun);
@@ -1059,6 +1796,44 @@ pub trait RayGunGroupConversation: Sync + Send {
     async fn remove_recipient(&mut self, _: Uuid, _: &DID) -> Result<(), Error> {
         Err(Error::Unimplemented)
     }
+
+    /// Assign a role to a recipient of the conversation.
+    /// Note: Only the owner is able to assign admins, while admins are able to assign moderators
+    async fn set_role(&mut self, _: Uuid, _: &DID, _: GroupRole) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Transfer ownership of the conversation to another recipient.
+    /// Note: The previous owner will become an admin of the conversation
+    async fn transfer_ownership(&mut self, _: Uuid, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Ban an identity from the conversation, removing them if they are a recipient
+    async fn ban(&mut self, _: Uuid, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Lift the ban of an identity from the conversation
+    async fn unban(&mut self, _: Uuid, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// List of identities banned from the conversation
+    async fn list_banned(&self, _: Uuid) -> Result<Vec<DID>, Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Restrict an identity from being added to the conversation.
+    /// Note: Only the owner is able to restrict identities, and the identity cannot be a recipient
+    async fn add_restricted(&mut self, _: Uuid, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
+    /// Lift the restriction of an identity, allowing them to be added to the conversation
+    async fn remove_restricted(&mut self, _: Uuid, _: &DID) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
 }
 
 #[async_trait::async_trait]
@@ -1114,11 +1889,21 @@ pub trait RayGunStream: Sync + Send {
 
 #[async_trait::async_trait]
 pub trait RayGunEvents: Sync + Send {
-    /// Send an event to a conversation
+    /// Send an event to a conversation. The event will expire after [`MessageEvent::default_ttl`]
     async fn send_event(&mut self, _: Uuid, _: MessageEvent) -> Result<(), Error> {
         Err(Error::Unimplemented)
     }
 
+    /// Send an event to a conversation that will expire after the duration provided unless sent again
+    async fn send_event_with_ttl(
+        &mut self,
+        _: Uuid,
+        _: MessageEvent,
+        _: Duration,
+    ) -> Result<(), Error> {
+        Err(Error::Unimplemented)
+    }
+
     /// Cancel event that was sent, if any.
     async fn cancel_event(&mut self, _: Uuid, _: MessageEvent) -> Result<(), Error> {
         Err(Error::Unimplemented)